@@ -1,6 +1,8 @@
 use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::solana_sdk::signature::Signature;
 use anchor_client::Cluster;
 use clap::*;
+use serde::{Deserialize, Serialize};
 
 #[derive(Parser, Debug)]
 pub struct ConfigOverride {
@@ -12,7 +14,7 @@ pub struct ConfigOverride {
     pub cluster: Cluster,
     /// Wallet override
     ///
-    /// Example: /path/to/wallet/keypair.json
+    /// Example: /path/to/wallet/keypair.json, env:VAR, base58:<secret>, mnemonic:<phrase>#<derivation path>, https://signer
     /// Default: ~/.config/solana/id.json
     #[clap(
         global = true,
@@ -62,13 +64,25 @@ fn parse_bin_liquidity_distribution(src: &str) -> Result<(i32, f64, f64), Error>
     Ok((delta_id, dist_x, dist_y))
 }
 
-#[derive(Debug, Clone, ValueEnum)]
+#[derive(Debug, Clone, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum SelectiveRounding {
     Up,
     Down,
     None,
 }
 
+#[derive(Debug, Clone, PartialEq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum UnlockAction {
+    /// Claim fees to the fee owner
+    ClaimFee,
+    /// Withdraw all liquidity to the owner
+    Withdraw,
+    /// Withdraw all liquidity and close the position
+    Close,
+}
+
 #[derive(Parser, Debug)]
 pub enum Command {
     /// Create a new liquidity pair.
@@ -197,6 +211,24 @@ pub enum Command {
     ShowPair {
         lb_pair: Pubkey,
     },
+    /// Show oracle observation history and time weighted average price of the given liquidity pair.
+    ShowOracle {
+        lb_pair: Pubkey,
+        /// TWAP window in seconds
+        #[clap(long)]
+        window: Option<u64>,
+    },
+    /// Show reward emission, undistributed and ineligible amounts, estimated APR and runway of the given liquidity pair.
+    ShowRewards {
+        lb_pair: Pubkey,
+        /// Unix timestamp to compute the top up needed to keep the current reward rate until. Funding restarts a
+        /// period of reward_duration, so it is clamped to the end of that period
+        #[clap(long)]
+        target_end: Option<u64>,
+        /// Price of 1 reward token in token Y, by reward index. Not needed for rewards in token X or Y.
+        #[clap(long, value_delimiter = ',')]
+        reward_prices: Vec<f64>,
+    },
     /// Show information of the given position.
     ShowPosition {
         position: Pubkey,
@@ -217,6 +249,11 @@ pub enum Command {
         reward_index: u64,
         funder: Pubkey,
     },
+    /// Withdraw the reward emitted while the active bin had no liquidity. Only the reward funder can withdraw it.
+    WithdrawIneligibleReward {
+        lb_pair: Pubkey,
+        reward_index: u64,
+    },
     /// Close liquidity position.
     ClosePosition {
         /// Address of the position.
@@ -242,6 +279,33 @@ pub enum Command {
 
     ListAllBinStep,
 
+    /// Replay recorded swaps through the fee logic for the preset parameters of a bin step, and hypothetical ones.
+    TuneFeeParameters {
+        /// Bin step of the presets
+        #[clap(long)]
+        bin_step: u16,
+        /// Path to a .csv file with timestamp,start_bin_id,end_bin_id,swap_for_y,amount_in lines, or a .json file of
+        /// recorded swaps
+        #[clap(long, conflicts_with_all = ["swap_signature", "swaps_lb_pair"])]
+        swaps_path: Option<String>,
+        /// Swap transaction whose swap events are replayed. Can be repeated
+        #[clap(long, conflicts_with = "swaps_lb_pair")]
+        swap_signature: Vec<Signature>,
+        /// Replay the swap events of the latest transactions of this pair
+        #[clap(long)]
+        swaps_lb_pair: Option<Pubkey>,
+        /// Number of latest transactions of --swaps-lb-pair to fetch
+        #[clap(long, default_value_t = 1000)]
+        swaps_limit: usize,
+        /// Hypothetical preset as base_factor,filter_period,decay_period,reduction_factor,variable_fee_control,max_volatility_accumulator,protocol_share.
+        /// Can be repeated
+        #[clap(long)]
+        candidate: Vec<String>,
+        /// Only replay the hypothetical presets
+        #[clap(long)]
+        skip_on_chain_presets: bool,
+    },
+
     SimulateSwapDemand {
         lb_pair: Pubkey,
         x_amount: f64, // ex: 10 jup
@@ -284,9 +348,9 @@ pub enum Command {
         /// Address of the pair
         #[clap(long)]
         lb_pair: Pubkey,
-        /// Base position path
-        #[clap(long)]
-        base_position_path: String,
+        /// Base position signer. Keypair path, env:VAR, base58:<secret>, mnemonic:<phrase> or remote signer url
+        #[clap(long, alias = "base-position-path")]
+        base_position_signer: String,
         /// Amount of x
         #[clap(long)]
         amount: u64,
@@ -299,25 +363,115 @@ pub enum Command {
         /// Base pubkey
         #[clap(long)]
         base_pubkey: Pubkey,
-        /// Curvature
-        #[clap(long)]
-        curvature: f64,
-        /// Position owner path
-        #[clap(long)]
-        position_owner_path: String,
+        /// Curvature of the power curve. Same as --distribution power:<curvature>
+        #[clap(long, conflicts_with = "distribution")]
+        curvature: Option<f64>,
+        /// Distribution of the amount between min and max price. power:<curvature>, linear, exponential:<growth>,
+        /// stepped:<price>=<amount>,... or csv:<path> with one price,amount tranche per line
+        #[clap(long)]
+        distribution: Option<String>,
+        /// Position owner signer. Keypair path, env:VAR, base58:<secret>, mnemonic:<phrase> or remote signer url
+        #[clap(long, alias = "position-owner-path")]
+        position_owner_signer: String,
         /// Max retries
         #[clap(long)]
         max_retries: u16,
     },
 
+    /// Compute every position, bin amount and dust deposit of a seed liquidity without sending any transaction
+    PlanSeedLiquidity {
+        /// Address of the pair
+        #[clap(long)]
+        lb_pair: Pubkey,
+        /// Base pubkey
+        #[clap(long)]
+        base_pubkey: Pubkey,
+        /// Position owner
+        #[clap(long)]
+        position_owner: Pubkey,
+        /// Amount of x
+        #[clap(long)]
+        amount: u64,
+        /// Min price
+        #[clap(long)]
+        min_price: f64,
+        /// Max price
+        #[clap(long)]
+        max_price: f64,
+        /// Curvature of the power curve. Same as --distribution power:<curvature>
+        #[clap(long, conflicts_with = "distribution")]
+        curvature: Option<f64>,
+        /// Distribution of the amount between min and max price. power:<curvature>, linear, exponential:<growth>,
+        /// stepped:<price>=<amount>,... or csv:<path> with one price,amount tranche per line
+        #[clap(long)]
+        distribution: Option<String>,
+        /// Output path of the plan
+        #[clap(long)]
+        plan_path: String,
+    },
+
+    /// Execute a seed liquidity plan. Progress is written to a state file, rerun to resume.
+    ExecuteSeedLiquidityPlan {
+        /// Path to the plan file
+        #[clap(long)]
+        plan_path: String,
+        /// Base position signer. Keypair path, env:VAR, base58:<secret>, mnemonic:<phrase> or remote signer url
+        #[clap(long, alias = "base-position-path")]
+        base_position_signer: String,
+        /// Position owner signer. Keypair path, env:VAR, base58:<secret>, mnemonic:<phrase> or remote signer url
+        #[clap(long, alias = "position-owner-path")]
+        position_owner_signer: String,
+        /// State file. Default: <plan_path>.state.json
+        #[clap(long)]
+        state_path: Option<String>,
+    },
+
+    /// Compare on chain bin amounts and position liquidity shares with a seed liquidity plan. Exits with an error on drift
+    VerifySeedLiquidityPlan {
+        /// Path to the plan file
+        #[clap(long)]
+        plan_path: String,
+    },
+
+    /// Read only budget and price schedule of an ILM launch: positions, bin arrays, bitmap extension, rent, price ladder and wallet balances
+    PlanIlmLaunch {
+        /// Address of the pair
+        #[clap(long)]
+        lb_pair: Pubkey,
+        /// Base pubkey
+        #[clap(long)]
+        base_pubkey: Pubkey,
+        /// Amount of x
+        #[clap(long)]
+        amount: u64,
+        /// Min price of a curve seed
+        #[clap(long, requires = "max_price", conflicts_with = "price")]
+        min_price: Option<f64>,
+        /// Max price of a curve seed
+        #[clap(long, requires = "min_price")]
+        max_price: Option<f64>,
+        /// Curvature of a curve seed
+        #[clap(long, conflicts_with = "distribution")]
+        curvature: Option<f64>,
+        /// Distribution of a curve seed. See seed-liquidity --distribution
+        #[clap(long)]
+        distribution: Option<String>,
+        /// Price of a single bin seed
+        #[clap(long, requires = "selective_rounding")]
+        price: Option<f64>,
+        /// Selective rounding of a single bin seed
+        #[clap(long)]
+        selective_rounding: Option<SelectiveRounding>,
+    },
+
     /// Seed liquidity by operator
     SeedLiquidityByOperator {
         /// Address of the pair
         #[clap(long)]
         lb_pair: Pubkey,
-        /// Base position path
-        #[clap(long)]
-        base_position_path: String,
+        /// Base position signer. Keypair path, env:VAR, base58:<secret>, mnemonic:<phrase> or remote signer url
+        #[clap(long, alias = "base-position-path")]
+        base_position_signer: String,
         /// Amount of x
         #[clap(long)]
         amount: u64,
@@ -330,9 +484,13 @@ pub enum Command {
         /// Base pubkey
         #[clap(long)]
         base_pubkey: Pubkey,
-        /// Curvature
+        /// Curvature of the power curve. Same as --distribution power:<curvature>
+        #[clap(long, conflicts_with = "distribution")]
+        curvature: Option<f64>,
+        /// Distribution of the amount between min and max price. power:<curvature>, linear, exponential:<growth>,
+        /// stepped:<price>=<amount>,... or csv:<path> with one price,amount tranche per line
         #[clap(long)]
-        curvature: f64,
+        distribution: Option<String>,
         /// position owner
         #[clap(long)]
         position_owner: Pubkey,
@@ -351,9 +509,9 @@ pub enum Command {
         /// Address of the pair
         #[clap(long)]
         lb_pair: Pubkey,
-        /// Base position path
-        #[clap(long)]
-        base_position_path: String,
+        /// Base position signer. Keypair path, env:VAR, base58:<secret>, mnemonic:<phrase> or remote signer url
+        #[clap(long, alias = "base-position-path")]
+        base_position_signer: String,
         /// Base position pubkey
         #[clap(long)]
         base_pubkey: Pubkey,
@@ -362,9 +520,9 @@ pub enum Command {
         amount: u64,
         #[clap(long)]
         price: f64,
-        /// Position owner
-        #[clap(long)]
-        position_owner_path: String,
+        /// Position owner signer. Keypair path, env:VAR, base58:<secret>, mnemonic:<phrase> or remote signer url
+        #[clap(long, alias = "position-owner-path")]
+        position_owner_signer: String,
         /// Selective rounding
         #[clap(long)]
         selective_rounding: SelectiveRounding,
@@ -374,9 +532,9 @@ pub enum Command {
         /// Address of the pair
         #[clap(long)]
         lb_pair: Pubkey,
-        /// Base position path
-        #[clap(long)]
-        base_position_path: String,
+        /// Base position signer. Keypair path, env:VAR, base58:<secret>, mnemonic:<phrase> or remote signer url
+        #[clap(long, alias = "base-position-path")]
+        base_position_signer: String,
         /// Base position pubkey
         #[clap(long)]
         base_pubkey: Pubkey,
@@ -409,6 +567,126 @@ pub enum Command {
         owner: Pubkey,
     },
 
+    /// Create a position on behalf of an owner, with the provider wallet as the operator.
+    InitializePositionByOperator {
+        /// Address of the pair
+        #[clap(long)]
+        lb_pair: Pubkey,
+        /// Base position signer. Keypair path, env:VAR, base58:<secret>, mnemonic:<phrase> or remote signer url
+        #[clap(long)]
+        base_position_signer: String,
+        /// Lower bin id of the position
+        #[clap(long, allow_negative_numbers = true)]
+        lower_bin_id: i32,
+        /// Number of bins of the position
+        #[clap(long)]
+        width: i32,
+        /// Position owner
+        #[clap(long)]
+        position_owner: Pubkey,
+        /// Fee owner
+        #[clap(long)]
+        fee_owner: Pubkey,
+        /// Point (slot or timestamp) until which the liquidity is locked
+        #[clap(long, default_value_t = 0)]
+        lock_release_point: u64,
+    },
+
+    /// Rotate or clear the operator of a position owned by the provider wallet.
+    UpdatePositionOperator {
+        /// Address of the position
+        #[clap(long)]
+        position: Pubkey,
+        /// New operator. Clear the operator when not set
+        #[clap(long)]
+        operator: Option<Pubkey>,
+    },
+
+    /// List positions where the wallet is the operator or the fee owner, with their lock countdown.
+    ListOperatedPositions {
+        /// Wallet to look up. Default to the provider wallet
+        #[clap(long)]
+        wallet: Option<Pubkey>,
+        /// Only list positions of the pair
+        #[clap(long)]
+        lb_pair: Option<Pubkey>,
+    },
+
+    /// Watch locked positions and run an action on each once its liquidity unlocks. Positions already unlocked when
+    /// first seen are ignored. Safe to restart.
+    WatchPositionUnlocks {
+        /// Owner or operator of the positions. Default to the provider wallet
+        #[clap(long)]
+        wallet: Option<Pubkey>,
+        /// Only watch positions of the pair
+        #[clap(long)]
+        lb_pair: Option<Pubkey>,
+        /// Action to run when a position unlocks
+        #[clap(long, value_enum)]
+        action: UnlockAction,
+        /// File recording the tracked and handled positions
+        #[clap(long, default_value = "watch_position_unlocks.state.json")]
+        state_path: String,
+        /// Seconds between polls
+        #[clap(long, default_value_t = 30)]
+        poll_interval: u64,
+    },
+
+    /// Find positions and bin arrays which still use a legacy account layout
+    FindLegacyAccounts {
+        /// Owner of the positions. Default to the provider wallet when lb_pair is not set
+        #[clap(long)]
+        owner: Option<Pubkey>,
+        /// Only find accounts of the pair
+        #[clap(long)]
+        lb_pair: Option<Pubkey>,
+    },
+
+    /// Migrate the legacy bin arrays and the legacy positions of the provider wallet to the latest layout
+    MigrateLegacyAccounts {
+        /// Only migrate accounts of the pair
+        #[clap(long)]
+        lb_pair: Option<Pubkey>,
+        /// Number of positions migrated per transaction
+        #[clap(long, default_value_t = 2)]
+        position_batch_size: usize,
+        /// Number of bin arrays migrated per transaction
+        #[clap(long, default_value_t = 10)]
+        bin_array_batch_size: usize,
+    },
+
+    /// Show which actions a wallet is allowed to perform on the pair now, and when each of them changes
+    CanIDo {
+        /// Address of the pair
+        #[clap(long)]
+        lb_pair: Pubkey,
+        /// Wallet to check. Default to the provider wallet
+        #[clap(long)]
+        wallet: Option<Pubkey>,
+    },
+    /// Show the launch schedule of a permissioned or customizable pair with wall clock estimates. Optionally check
+    /// planned set-activation-point and set-pre-activation-duration values before submitting them.
+    ShowLaunchSchedule {
+        /// Address of the pair
+        #[clap(long)]
+        lb_pair: Pubkey,
+        /// Planned activation point to check
+        #[clap(long)]
+        new_activation_point: Option<u64>,
+        /// Planned pre-activation duration to check
+        #[clap(long)]
+        new_pre_activation_duration: Option<u64>,
+    },
+
+    /// Run a YAML or JSON plan of steps. Completed steps are checkpointed, rerun the plan to resume after a failure.
+    RunPlan {
+        /// Path to the plan file
+        plan_path: String,
+        /// Checkpoint file. Default: <plan_path>.checkpoint.json
+        #[clap(long)]
+        checkpoint_path: Option<String>,
+    },
+
     #[clap(flatten)]
     Admin(AdminCommand),
 }
@@ -434,8 +712,8 @@ pub enum AdminCommand {
         token_mint_y: Pubkey,
         /// The initial price of the liquidity pair. Eg: 24123.12312412 USDC per 1 BTC.
         initial_price: f64,
-        /// Base keypair path
-        base_keypair_path: String,
+        /// Base keypair signer. Keypair path, env:VAR, base58:<secret>, mnemonic:<phrase> or remote signer url
+        base_keypair_signer: String,
         /// Base fee bps
         base_fee_bps: u16,
         /// Lock duration for bootstrap liquidity position
@@ -540,4 +818,17 @@ pub enum AdminCommand {
         /// Preactivation swap address
         pre_activation_swap_address: Pubkey,
     },
+
+    /// Update protocol share and base factor of the pair. Show the parameter diff and base fee before sending.
+    UpdateFeeParameters {
+        /// Address of the pair
+        lb_pair: Pubkey,
+        /// Portion of swap fees retained by the protocol, in bps
+        protocol_share: u16,
+        /// Base factor for base fee rate
+        base_factor: u16,
+        /// Print the unsigned transaction in base58 instead of sending it
+        #[clap(long)]
+        serialize: bool,
+    },
 }
@@ -0,0 +1,74 @@
+use std::ops::Deref;
+
+use anchor_client::solana_sdk::clock::Clock;
+use anchor_client::{solana_sdk::pubkey::Pubkey, solana_sdk::signer::Signer, Program};
+use anyhow::*;
+use commons::pair_access::{get_pair_action_accesses, ActionAvailability};
+use lb_clmm::pair_action_access::{get_current_point, ActivationType};
+use lb_clmm::state::lb_pair::LbPair;
+
+#[derive(Debug)]
+pub struct CanIDoParameters {
+    pub lb_pair: Pubkey,
+    /// Default to the payer
+    pub wallet: Option<Pubkey>,
+}
+
+pub async fn can_i_do<C: Deref<Target = impl Signer> + Clone>(
+    params: CanIDoParameters,
+    program: &Program<C>,
+) -> Result<()> {
+    let CanIDoParameters { lb_pair, wallet } = params;
+
+    let wallet = wallet.unwrap_or(program.payer());
+    let lb_pair_state: LbPair = program.account(lb_pair).await?;
+
+    let clock = program
+        .rpc()
+        .get_account(&Clock::id())
+        .await
+        .map(|account| {
+            let clock: Clock = bincode::deserialize(account.data.as_ref())?;
+            Ok(clock)
+        })??;
+
+    let current_point = get_current_point(&lb_pair_state, clock.slot, clock.unix_timestamp as u64)?;
+    let unit = match ActivationType::try_from(lb_pair_state.activation_type)? {
+        ActivationType::Slot => "slots",
+        ActivationType::Timestamp => "seconds",
+    };
+
+    println!(
+        "Pair {} wallet {} current point {}",
+        lb_pair, wallet, current_point
+    );
+
+    let accesses = get_pair_action_accesses(
+        &lb_pair_state,
+        wallet,
+        clock.slot,
+        clock.unix_timestamp as u64,
+    )?;
+
+    for access in accesses {
+        let status = match access.availability {
+            ActionAvailability::Allowed { until: None } => "allowed".to_string(),
+            ActionAvailability::Allowed { until: Some(point) } => format!(
+                "allowed until {} (in {} {})",
+                point,
+                point - current_point,
+                unit
+            ),
+            ActionAvailability::Disallowed { from: None } => "not allowed".to_string(),
+            ActionAvailability::Disallowed { from: Some(point) } => format!(
+                "not allowed, allowed from {} (in {} {})",
+                point,
+                point - current_point,
+                unit
+            ),
+        };
+        println!("{:?}: {}", access.action, status);
+    }
+
+    Ok(())
+}
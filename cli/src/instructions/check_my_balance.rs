@@ -3,6 +3,7 @@ use crate::math::price_per_token_to_per_lamport;
 use anchor_client::{solana_sdk::pubkey::Pubkey, solana_sdk::signer::Signer, Program};
 use anchor_spl::token_interface::Mint;
 use anyhow::*;
+use commons::account_layout::{fetch_bin_array, fetch_position};
 use lb_clmm::constants::{MAX_BIN_PER_ARRAY, MAX_BIN_PER_POSITION};
 use lb_clmm::math::safe_math::SafeMath;
 use lb_clmm::math::u128x128_math::Rounding;
@@ -69,8 +70,8 @@ pub async fn check_my_balance<C: Deref<Target = impl Signer> + Clone>(
 
     for i in min_active_id..max_active_id {
         let (position, _bump) = derive_position_pda(lb_pair, base_position_key, i, width);
-        match program.account::<PositionV2>(position).await {
-            Ok(position_state) => {
+        match fetch_position(&program.rpc(), position).await {
+            Ok((_layout, position_state)) => {
                 let lower_bin_array_idx =
                     BinArray::bin_id_to_bin_array_index(position_state.lower_bin_id)?;
                 let upper_bin_array_idx =
@@ -80,7 +81,7 @@ pub async fn check_my_balance<C: Deref<Target = impl Signer> + Clone>(
                 for i in lower_bin_array_idx..=upper_bin_array_idx {
                     let (bin_array, _bump) = derive_bin_array_pda(lb_pair, i.into());
 
-                    match program.account::<BinArray>(bin_array).await {
+                    match fetch_bin_array(&program.rpc(), bin_array).await {
                         Ok(bin_array_state) => bin_arrays.push(bin_array_state),
                         Err(_err) => {}
                     }
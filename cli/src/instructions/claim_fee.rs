@@ -1,4 +1,4 @@
-use super::utils::{get_bin_arrays_for_position, get_or_create_ata};
+use super::utils::{fetch_latest_position, get_bin_arrays_for_position, get_or_create_ata};
 use anchor_client::solana_sdk::compute_budget::ComputeBudgetInstruction;
 use anchor_client::solana_sdk::instruction::Instruction;
 use anchor_client::{
@@ -9,7 +9,6 @@ use anyhow::*;
 use lb_clmm::accounts;
 use lb_clmm::instruction;
 use lb_clmm::state::lb_pair::LbPair;
-use lb_clmm::state::position::PositionV2;
 use lb_clmm::utils::pda::derive_event_authority_pda;
 use std::ops::Deref;
 
@@ -19,7 +18,7 @@ pub async fn claim_fee<C: Deref<Target = impl Signer> + Clone>(
     transaction_config: RpcSendTransactionConfig,
     compute_unit_price: Option<Instruction>,
 ) -> Result<()> {
-    let position_state: PositionV2 = program.account(position).await?;
+    let position_state = fetch_latest_position(program, position).await?;
     let lb_pair_state: LbPair = program.account(position_state.lb_pair).await?;
 
     let position_owner = if position_state.fee_owner == Pubkey::default() {
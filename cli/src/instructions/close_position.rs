@@ -7,11 +7,10 @@ use anchor_lang::prelude::Pubkey;
 use anyhow::*;
 use lb_clmm::accounts;
 use lb_clmm::instruction;
-use lb_clmm::state::position::PositionV2;
 use lb_clmm::utils::pda::derive_event_authority_pda;
 use std::ops::Deref;
 
-use super::utils::get_bin_arrays_for_position;
+use super::utils::{fetch_latest_position, get_bin_arrays_for_position};
 
 pub async fn close_position<C: Deref<Target = impl Signer> + Clone>(
     position: Pubkey,
@@ -19,7 +18,7 @@ pub async fn close_position<C: Deref<Target = impl Signer> + Clone>(
     transaction_config: RpcSendTransactionConfig,
     compute_unit_price: Option<Instruction>,
 ) -> Result<()> {
-    let position_state: PositionV2 = program.account(position).await?;
+    let position_state = fetch_latest_position(program, position).await?;
     let [bin_array_lower, bin_array_upper] = get_bin_arrays_for_position(program, position).await?;
 
     let (event_authority, _bump) = derive_event_authority_pda();
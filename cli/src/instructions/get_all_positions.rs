@@ -2,7 +2,7 @@ use anchor_client::solana_client::rpc_filter::{Memcmp, RpcFilterType};
 use anchor_client::{solana_sdk::signer::Signer, Program};
 use anchor_lang::prelude::Pubkey;
 use anyhow::*;
-use lb_clmm::state::position::PositionV2;
+use lb_clmm::state::position::{Position, PositionV2};
 use std::ops::Deref;
 
 pub async fn get_all_positions<C: Deref<Target = impl Signer> + Clone>(
@@ -10,14 +10,20 @@ pub async fn get_all_positions<C: Deref<Target = impl Signer> + Clone>(
     lb_pair: Pubkey,
     owner: Pubkey,
 ) -> Result<()> {
-    let positions: Vec<(Pubkey, PositionV2)> = program
-        .accounts(vec![
-            RpcFilterType::Memcmp(Memcmp::new_base58_encoded(8, &lb_pair.to_bytes())),
-            RpcFilterType::Memcmp(Memcmp::new_base58_encoded(8 + 32, &owner.to_bytes())),
-        ])
-        .await?;
+    let filters = vec![
+        RpcFilterType::Memcmp(Memcmp::new_base58_encoded(8, &lb_pair.to_bytes())),
+        RpcFilterType::Memcmp(Memcmp::new_base58_encoded(8 + 32, &owner.to_bytes())),
+    ];
+    let positions: Vec<(Pubkey, PositionV2)> = program.accounts(filters.clone()).await?;
     for (key, val) in positions {
         println!("position {} fee owner {}", key, val.fee_owner);
     }
+    let legacy_positions: Vec<(Pubkey, Position)> = program.accounts(filters).await?;
+    for (key, _val) in legacy_positions {
+        println!(
+            "position {} (legacy layout, run migrate-legacy-accounts)",
+            key
+        );
+    }
     Ok(())
 }
@@ -26,6 +26,11 @@ pub async fn initialize_bin_array<C: Deref<Target = impl Signer> + Clone>(
 
     let (bin_array, _bump) = derive_bin_array_pda(lb_pair, bin_array_index);
 
+    if program.rpc().get_account_data(&bin_array).await.is_ok() {
+        println!("Bin array {bin_array} already exists");
+        return Ok(bin_array);
+    }
+
     let accounts = accounts::InitializeBinArray {
         bin_array,
         funder: program.payer(),
@@ -1,11 +1,11 @@
 use std::ops::Deref;
 
 use anchor_client::solana_client::rpc_config::RpcSendTransactionConfig;
-use anchor_client::solana_sdk::signature::Keypair;
 use anchor_client::{solana_sdk::pubkey::Pubkey, solana_sdk::signer::Signer, Program};
 
 use anchor_spl::token::Mint;
 use anyhow::*;
+use commons::signer::SharedSigner;
 use lb_clmm::accounts;
 use lb_clmm::instruction;
 use lb_clmm::instructions::initialize_pool::initialize_permission_lb_pair::InitPermissionPairIx;
@@ -17,14 +17,13 @@ use crate::math::{
     price_per_token_to_per_lamport,
 };
 
-#[derive(Debug)]
 pub struct InitPermissionLbPairParameters {
     pub token_mint_x: Pubkey,
     pub token_mint_y: Pubkey,
     pub bin_step: u16,
     pub initial_price: f64,
     pub base_fee_bps: u16,
-    pub base_keypair: Keypair,
+    pub base_keypair: SharedSigner,
     pub lock_duration: u64,
     pub activation_type: u8,
 }
@@ -105,7 +104,7 @@ pub async fn initialize_permission_lb_pair<C: Deref<Target = impl Signer> + Clon
     let request_builder = program.request();
     let signature = request_builder
         .accounts(accounts)
-        .signer(base_keypair.insecure_clone())
+        .signer(base_keypair.clone())
         .args(ix)
         .send_with_spinner_and_config(transaction_config)
         .await;
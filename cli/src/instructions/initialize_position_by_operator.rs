@@ -0,0 +1,150 @@
+use std::ops::Deref;
+
+use anchor_client::solana_client::rpc_config::RpcSendTransactionConfig;
+use anchor_client::solana_sdk::clock::Clock;
+use anchor_client::solana_sdk::compute_budget::ComputeBudgetInstruction;
+use anchor_client::solana_sdk::instruction::Instruction;
+use anchor_client::{solana_sdk::pubkey::Pubkey, solana_sdk::signer::Signer, Program};
+
+use anchor_lang::{AccountDeserialize, InstructionData, ToAccountMetas};
+use anchor_spl::token::{spl_token, TokenAccount};
+use anyhow::*;
+use commons::signer::SharedSigner;
+use lb_clmm::accounts;
+use lb_clmm::instruction;
+use lb_clmm::state::lb_pair::LbPair;
+use lb_clmm::utils::pda::{derive_event_authority_pda, derive_position_pda};
+use spl_associated_token_account::{
+    get_associated_token_address, instruction::create_associated_token_account,
+};
+
+use crate::instructions::utils::{fetch_latest_position, get_position_lock_status};
+
+pub struct InitPositionByOperatorParameters {
+    pub lb_pair: Pubkey,
+    pub position_base_kp: SharedSigner,
+    pub lower_bin_id: i32,
+    pub width: i32,
+    pub position_owner: Pubkey,
+    pub fee_owner: Pubkey,
+    pub lock_release_point: u64,
+}
+
+pub async fn initialize_position_by_operator<C: Deref<Target = impl Signer> + Clone>(
+    params: InitPositionByOperatorParameters,
+    program: &Program<C>,
+    transaction_config: RpcSendTransactionConfig,
+    compute_unit_price: Option<Instruction>,
+) -> Result<Pubkey> {
+    let InitPositionByOperatorParameters {
+        lb_pair,
+        position_base_kp,
+        lower_bin_id,
+        width,
+        position_owner,
+        fee_owner,
+        lock_release_point,
+    } = params;
+
+    let rpc_client = program.rpc();
+    let lb_pair_state: LbPair = program.account(lb_pair).await?;
+
+    let base_pubkey = position_base_kp.pubkey();
+    let (position, _bump) = derive_position_pda(lb_pair, base_pubkey, lower_bin_id, width);
+    let (event_authority, _bump) = derive_event_authority_pda();
+
+    let mut instructions = vec![ComputeBudgetInstruction::set_compute_unit_limit(1_400_000)];
+
+    if let Some(compute_unit_price) = compute_unit_price {
+        instructions.push(compute_unit_price);
+    }
+
+    let operator_token_x =
+        get_associated_token_address(&program.payer(), &lb_pair_state.token_x_mint);
+    let owner_token_x = get_associated_token_address(&position_owner, &lb_pair_state.token_x_mint);
+
+    // The owner token account must hold token X, which the operator sends 1 lamport to prove ownership
+    let owner_token_x_amount = match rpc_client.get_account(&owner_token_x).await {
+        std::result::Result::Ok(account) => {
+            Some(TokenAccount::try_deserialize(&mut account.data.as_ref())?.amount)
+        }
+        Err(_) => None,
+    };
+
+    if owner_token_x_amount.is_none() {
+        instructions.push(create_associated_token_account(
+            &program.payer(),
+            &position_owner,
+            &lb_pair_state.token_x_mint,
+            &spl_token::ID,
+        ));
+    }
+
+    if owner_token_x_amount.unwrap_or_default() == 0 {
+        instructions.push(spl_token::instruction::transfer(
+            &spl_token::ID,
+            &operator_token_x,
+            &owner_token_x,
+            &program.payer(),
+            &[],
+            1,
+        )?);
+    }
+
+    instructions.push(Instruction {
+        program_id: lb_clmm::ID,
+        accounts: accounts::InitializePositionByOperator {
+            lb_pair,
+            base: base_pubkey,
+            owner: position_owner,
+            operator: program.payer(),
+            payer: program.payer(),
+            position,
+            system_program: anchor_client::solana_sdk::system_program::ID,
+            event_authority,
+            operator_token_x,
+            owner_token_x,
+            program: lb_clmm::ID,
+        }
+        .to_account_metas(None),
+        data: instruction::InitializePositionByOperator {
+            lower_bin_id,
+            width,
+            fee_owner,
+            lock_release_point,
+        }
+        .data(),
+    });
+
+    let mut builder = program.request();
+    builder = builder.signer(position_base_kp.clone());
+    builder = instructions
+        .into_iter()
+        .fold(builder, |builder, ix| builder.instruction(ix));
+
+    let signature = builder
+        .send_with_spinner_and_config(transaction_config)
+        .await;
+
+    println!(
+        "Initialize position {} for owner {}. Signature: {:#?}",
+        position, position_owner, signature
+    );
+
+    signature?;
+
+    let position_state = fetch_latest_position(program, position).await?;
+    let clock = rpc_client.get_account(&Clock::id()).await.map(|account| {
+        let clock: Clock = bincode::deserialize(account.data.as_ref())?;
+        Ok(clock)
+    })??;
+
+    println!(
+        "Operator {} fee owner {} {}",
+        position_state.operator,
+        position_state.fee_owner,
+        get_position_lock_status(&position_state, &lb_pair_state, &clock)?
+    );
+
+    Ok(position)
+}
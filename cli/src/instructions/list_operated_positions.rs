@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+use std::ops::Deref;
+
+use anchor_client::solana_client::rpc_filter::{Memcmp, RpcFilterType};
+use anchor_client::solana_sdk::clock::Clock;
+use anchor_client::{solana_sdk::pubkey::Pubkey, solana_sdk::signer::Signer, Program};
+use anyhow::*;
+use lb_clmm::state::lb_pair::LbPair;
+use lb_clmm::state::position::PositionV2;
+
+use crate::instructions::utils::get_position_lock_status;
+
+#[derive(Debug)]
+pub struct ListOperatedPositionsParameters {
+    pub wallet: Pubkey,
+    pub lb_pair: Option<Pubkey>,
+}
+
+pub async fn get_positions_by_field<C: Deref<Target = impl Signer> + Clone>(
+    program: &Program<C>,
+    offset: usize,
+    wallet: Pubkey,
+    lb_pair: Option<Pubkey>,
+) -> Result<Vec<(Pubkey, PositionV2)>> {
+    let mut filters = vec![RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+        8 + offset,
+        &wallet.to_bytes(),
+    ))];
+    if let Some(lb_pair) = lb_pair {
+        filters.push(RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+            8 + std::mem::offset_of!(PositionV2, lb_pair),
+            &lb_pair.to_bytes(),
+        )));
+    }
+
+    Ok(program.accounts(filters).await?)
+}
+
+pub async fn list_operated_positions<C: Deref<Target = impl Signer> + Clone>(
+    params: ListOperatedPositionsParameters,
+    program: &Program<C>,
+) -> Result<()> {
+    let ListOperatedPositionsParameters { wallet, lb_pair } = params;
+
+    let operated_positions = get_positions_by_field(
+        program,
+        std::mem::offset_of!(PositionV2, operator),
+        wallet,
+        lb_pair,
+    )
+    .await?;
+    let fee_owned_positions = get_positions_by_field(
+        program,
+        std::mem::offset_of!(PositionV2, fee_owner),
+        wallet,
+        lb_pair,
+    )
+    .await?;
+
+    let clock = program
+        .rpc()
+        .get_account(&Clock::id())
+        .await
+        .map(|account| {
+            let clock: Clock = bincode::deserialize(account.data.as_ref())?;
+            Ok(clock)
+        })??;
+
+    let mut lb_pairs: HashMap<Pubkey, LbPair> = HashMap::new();
+
+    for (title, positions) in [
+        ("Positions operated by", operated_positions),
+        ("Positions with fee owner", fee_owned_positions),
+    ] {
+        println!("{} {}: {}", title, wallet, positions.len());
+
+        for (key, position) in positions {
+            if !lb_pairs.contains_key(&position.lb_pair) {
+                let lb_pair_state: LbPair = program.account(position.lb_pair).await?;
+                lb_pairs.insert(position.lb_pair, lb_pair_state);
+            }
+            let lb_pair_state = &lb_pairs[&position.lb_pair];
+
+            println!(
+                "  {} pair {} owner {} operator {} fee owner {} bins {} to {} {}",
+                key,
+                position.lb_pair,
+                position.owner,
+                position.operator,
+                position.fee_owner,
+                position.lower_bin_id,
+                position.upper_bin_id,
+                get_position_lock_status(&position, lb_pair_state, &clock)?
+            );
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,323 @@
+use std::collections::BTreeSet;
+use std::ops::Deref;
+
+use anchor_client::solana_client::rpc_config::RpcSendTransactionConfig;
+use anchor_client::solana_client::rpc_filter::{Memcmp, RpcFilterType};
+use anchor_client::solana_sdk::compute_budget::ComputeBudgetInstruction;
+use anchor_client::solana_sdk::instruction::Instruction;
+use anchor_client::solana_sdk::signature::Keypair;
+use anchor_client::{solana_sdk::pubkey::Pubkey, solana_sdk::signer::Signer, Program};
+
+use anchor_lang::prelude::AccountMeta;
+use anchor_lang::{InstructionData, ToAccountMetas};
+use anyhow::*;
+use lb_clmm::accounts;
+use lb_clmm::instruction;
+use lb_clmm::state::bin::{BinArray, LayoutVersion};
+use lb_clmm::state::position::{Position, PositionV2};
+use lb_clmm::utils::pda::derive_event_authority_pda;
+
+use crate::instructions::utils::get_bin_arrays_for_pair;
+
+/// Accounts which still use a legacy layout
+#[derive(Default)]
+pub struct LegacyAccounts {
+    /// `Position` accounts, which are migrated to `PositionV2`
+    pub positions: Vec<(Pubkey, Position)>,
+    /// `LayoutVersion::V0` bin arrays
+    pub bin_arrays: Vec<(Pubkey, BinArray)>,
+}
+
+fn get_position_filters(owner: Option<Pubkey>, lb_pair: Option<Pubkey>) -> Vec<RpcFilterType> {
+    // lb_pair and owner are at the same offset in both position layouts
+    let mut filters = vec![];
+    if let Some(lb_pair) = lb_pair {
+        filters.push(RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+            8,
+            &lb_pair.to_bytes(),
+        )));
+    }
+    if let Some(owner) = owner {
+        filters.push(RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+            8 + 32,
+            &owner.to_bytes(),
+        )));
+    }
+    filters
+}
+
+async fn get_legacy_bin_arrays<C: Deref<Target = impl Signer> + Clone>(
+    program: &Program<C>,
+    lb_pair: Pubkey,
+) -> Result<Vec<(Pubkey, BinArray)>> {
+    let legacy_version: u8 = LayoutVersion::V0.into();
+    let filters = vec![
+        RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+            8 + std::mem::offset_of!(BinArray, version),
+            &[legacy_version],
+        )),
+        RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+            8 + std::mem::offset_of!(BinArray, lb_pair),
+            &lb_pair.to_bytes(),
+        )),
+    ];
+
+    let mut bin_arrays: Vec<(Pubkey, BinArray)> = program.accounts(filters).await?;
+    bin_arrays.sort_by_key(|(_, bin_array)| bin_array.index);
+
+    Ok(bin_arrays)
+}
+
+/// Find legacy positions of the owner and / or pair, and the legacy bin arrays of the pair, or of the pairs the owner
+/// has positions in.
+pub async fn find_legacy_accounts<C: Deref<Target = impl Signer> + Clone>(
+    program: &Program<C>,
+    owner: Option<Pubkey>,
+    lb_pair: Option<Pubkey>,
+) -> Result<LegacyAccounts> {
+    ensure!(
+        owner.is_some() || lb_pair.is_some(),
+        "Either owner or lb_pair is required"
+    );
+
+    let filters = get_position_filters(owner, lb_pair);
+    let mut positions: Vec<(Pubkey, Position)> = program.accounts(filters.clone()).await?;
+    positions.sort_by_key(|(_, position)| (position.lb_pair, position.lower_bin_id));
+
+    let lb_pairs = match lb_pair {
+        Some(lb_pair) => BTreeSet::from([lb_pair]),
+        None => {
+            let positions_v2: Vec<(Pubkey, PositionV2)> = program.accounts(filters).await?;
+            positions
+                .iter()
+                .map(|(_, position)| position.lb_pair)
+                .chain(positions_v2.iter().map(|(_, position)| position.lb_pair))
+                .collect()
+        }
+    };
+
+    let mut bin_arrays = vec![];
+    for lb_pair in lb_pairs {
+        bin_arrays.extend(get_legacy_bin_arrays(program, lb_pair).await?);
+    }
+
+    Ok(LegacyAccounts {
+        positions,
+        bin_arrays,
+    })
+}
+
+#[derive(Debug)]
+pub struct FindLegacyAccountsParameters {
+    pub owner: Option<Pubkey>,
+    pub lb_pair: Option<Pubkey>,
+}
+
+pub async fn show_legacy_accounts<C: Deref<Target = impl Signer> + Clone>(
+    params: FindLegacyAccountsParameters,
+    program: &Program<C>,
+) -> Result<()> {
+    let FindLegacyAccountsParameters { owner, lb_pair } = params;
+
+    let LegacyAccounts {
+        positions,
+        bin_arrays,
+    } = find_legacy_accounts(program, owner, lb_pair).await?;
+
+    println!("Legacy positions: {}", positions.len());
+    for (key, position) in positions.iter() {
+        println!(
+            "  {} pair {} owner {} bins {} to {}",
+            key, position.lb_pair, position.owner, position.lower_bin_id, position.upper_bin_id
+        );
+    }
+
+    println!("Legacy bin arrays: {}", bin_arrays.len());
+    for (key, bin_array) in bin_arrays.iter() {
+        println!(
+            "  {} pair {} index {}",
+            key, bin_array.lb_pair, bin_array.index
+        );
+    }
+
+    Ok(())
+}
+
+#[derive(Debug)]
+pub struct MigrateLegacyAccountsParameters {
+    /// Only migrate accounts of the pair
+    pub lb_pair: Option<Pubkey>,
+    /// Number of positions migrated per transaction
+    pub position_batch_size: usize,
+    /// Number of bin arrays migrated per transaction
+    pub bin_array_batch_size: usize,
+}
+
+async fn migrate_bin_arrays<C: Deref<Target = impl Signer> + Clone>(
+    bin_arrays: &[(Pubkey, BinArray)],
+    batch_size: usize,
+    program: &Program<C>,
+    transaction_config: RpcSendTransactionConfig,
+    compute_unit_price: Option<Instruction>,
+) -> Result<()> {
+    let lb_pairs = bin_arrays
+        .iter()
+        .map(|(_, bin_array)| bin_array.lb_pair)
+        .collect::<BTreeSet<_>>();
+
+    for lb_pair in lb_pairs {
+        let keys = bin_arrays
+            .iter()
+            .filter(|(_, bin_array)| bin_array.lb_pair == lb_pair)
+            .map(|(key, _)| *key)
+            .collect::<Vec<_>>();
+
+        for batch in keys.chunks(batch_size) {
+            let mut accounts = accounts::MigrateBinArray { lb_pair }.to_account_metas(None);
+            accounts.extend(batch.iter().map(|key| AccountMeta::new(*key, false)));
+
+            let migrate_ix = Instruction {
+                program_id: lb_clmm::ID,
+                accounts,
+                data: instruction::MigrateBinArray {}.data(),
+            };
+
+            let mut builder = program.request();
+            if let Some(compute_unit_price) = compute_unit_price.clone() {
+                builder = builder.instruction(compute_unit_price);
+            }
+
+            let signature = builder
+                .instruction(migrate_ix)
+                .send_with_spinner_and_config(transaction_config)
+                .await;
+
+            println!(
+                "Migrate {} bin arrays of pair {}. Signature: {:#?}",
+                batch.len(),
+                lb_pair,
+                signature
+            );
+
+            signature?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn migrate_positions<C: Deref<Target = impl Signer> + Clone>(
+    positions: &[(Pubkey, Position)],
+    batch_size: usize,
+    program: &Program<C>,
+    transaction_config: RpcSendTransactionConfig,
+    compute_unit_price: Option<Instruction>,
+) -> Result<()> {
+    let (event_authority, _bump) = derive_event_authority_pda();
+
+    for batch in positions.chunks(batch_size) {
+        let mut builder = program
+            .request()
+            .instruction(ComputeBudgetInstruction::set_compute_unit_limit(1_400_000));
+        if let Some(compute_unit_price) = compute_unit_price.clone() {
+            builder = builder.instruction(compute_unit_price);
+        }
+
+        let mut migrated_positions = vec![];
+
+        for (position_v1, position_state) in batch {
+            let position_v2_keypair = Keypair::new();
+            let [bin_array_lower, bin_array_upper] =
+                get_bin_arrays_for_pair(position_state.lb_pair, position_state.lower_bin_id)
+                    .await?;
+
+            let accounts = accounts::MigratePosition {
+                position_v2: position_v2_keypair.pubkey(),
+                position_v1: *position_v1,
+                lb_pair: position_state.lb_pair,
+                bin_array_lower,
+                bin_array_upper,
+                owner: program.payer(),
+                system_program: anchor_client::solana_sdk::system_program::ID,
+                rent_receiver: program.payer(),
+                event_authority,
+                program: lb_clmm::ID,
+            };
+
+            builder = builder
+                .instruction(Instruction {
+                    program_id: lb_clmm::ID,
+                    accounts: accounts.to_account_metas(None),
+                    data: instruction::MigratePosition {}.data(),
+                })
+                .signer(position_v2_keypair.insecure_clone());
+
+            migrated_positions.push((*position_v1, position_v2_keypair.pubkey()));
+        }
+
+        let signature = builder
+            .send_with_spinner_and_config(transaction_config)
+            .await;
+
+        for (position_v1, position_v2) in migrated_positions {
+            println!("Migrate position {} -> {}", position_v1, position_v2);
+        }
+        println!("Signature: {:#?}", signature);
+
+        signature?;
+    }
+
+    Ok(())
+}
+
+/// Migrate the legacy bin arrays, then the legacy positions of the provider wallet. Accounts already migrated are not
+/// found again, so the command can be rerun after a failed batch.
+pub async fn migrate_legacy_accounts<C: Deref<Target = impl Signer> + Clone>(
+    params: MigrateLegacyAccountsParameters,
+    program: &Program<C>,
+    transaction_config: RpcSendTransactionConfig,
+    compute_unit_price: Option<Instruction>,
+) -> Result<()> {
+    let MigrateLegacyAccountsParameters {
+        lb_pair,
+        position_batch_size,
+        bin_array_batch_size,
+    } = params;
+
+    ensure!(
+        position_batch_size > 0 && bin_array_batch_size > 0,
+        "Batch size must be greater than 0"
+    );
+
+    // Only the owner can migrate a position
+    let LegacyAccounts {
+        positions,
+        bin_arrays,
+    } = find_legacy_accounts(program, Some(program.payer()), lb_pair).await?;
+
+    println!(
+        "Found {} legacy positions and {} legacy bin arrays",
+        positions.len(),
+        bin_arrays.len()
+    );
+
+    migrate_bin_arrays(
+        &bin_arrays,
+        bin_array_batch_size,
+        program,
+        transaction_config,
+        compute_unit_price.clone(),
+    )
+    .await?;
+
+    migrate_positions(
+        &positions,
+        position_batch_size,
+        program,
+        transaction_config,
+        compute_unit_price,
+    )
+    .await?;
+
+    Ok(())
+}
@@ -1,5 +1,6 @@
 pub mod add_liquidity;
 pub mod add_liquidity_by_strategy;
+pub mod can_i_do;
 pub mod check_my_balance;
 pub mod claim_fee;
 pub mod claim_reward;
@@ -16,28 +17,43 @@ pub mod initialize_lb_pair;
 pub mod initialize_permission_lb_pair;
 pub mod initialize_position;
 pub mod initialize_position_and_add_liquidity_by_strategy;
+pub mod initialize_position_by_operator;
 pub mod initialize_position_with_price_range;
 pub mod initialize_preset_parameter;
 pub mod initialize_reward;
 pub mod list_all_binstep;
+pub mod list_operated_positions;
+pub mod migrate_legacy_accounts;
+pub mod plan_ilm_launch;
 pub mod remove_all_liquidity;
 pub mod remove_all_liquidity_and_close_position;
 pub mod remove_liquidity;
 pub mod remove_liquidity_by_price_range;
+pub mod run_plan;
+pub mod seed_distribution;
 pub mod seed_liquidity;
 pub mod seed_liquidity_from_operator;
+pub mod seed_liquidity_plan;
 pub mod seed_liquidity_single_bin;
 pub mod seed_liquidity_single_bin_by_operator;
 pub mod set_activation_point;
 pub mod set_pre_activation_duration;
 pub mod set_pre_activation_swap_address;
+pub mod show_launch_schedule;
+pub mod show_oracle;
 pub mod show_pair;
+pub mod show_rewards;
 pub mod simulate_swap_demand;
 pub mod swap_exact_in;
 pub mod swap_exact_out;
 pub mod swap_with_price_impact;
 pub mod toggle_pair_status;
+pub mod tune_fee_parameters;
+pub mod update_fee_parameters;
+pub mod update_position_operator;
 pub mod update_reward_duration;
 pub mod update_reward_funder;
 pub mod utils;
+pub mod watch_position_unlocks;
+pub mod withdraw_ineligible_reward;
 pub mod withdraw_protocol_fee;
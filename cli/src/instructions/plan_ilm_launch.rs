@@ -0,0 +1,534 @@
+use std::collections::BTreeSet;
+use std::ops::Deref;
+
+use crate::args::SelectiveRounding;
+use crate::instructions::seed_distribution::SeedDistribution;
+use crate::instructions::seed_liquidity::{
+    convert_min_max_ui_price_to_min_max_bin_id, get_number_of_position_required_to_cover_range,
+    get_ui_price_from_id, to_wei_amount,
+};
+use crate::math::{
+    find_swappable_min_max_bin_id, get_id_from_price, get_precise_id_from_price,
+    price_per_token_to_per_lamport,
+};
+use anchor_client::solana_sdk::program_pack::Pack;
+use anchor_client::{solana_sdk::pubkey::Pubkey, solana_sdk::signer::Signer, Program};
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::token::{spl_token, Mint};
+use anyhow::*;
+use lb_clmm::constants::MAX_BIN_PER_POSITION;
+use lb_clmm::math::u128x128_math::Rounding;
+use lb_clmm::state::bin::BinArray;
+use lb_clmm::state::bin_array_bitmap_extension::BinArrayBitmapExtension;
+use lb_clmm::state::lb_pair::LbPair;
+use lb_clmm::state::position::PositionV2;
+use lb_clmm::utils::pda::*;
+
+#[derive(Debug)]
+pub enum IlmLaunchShape {
+    /// Seed between min and max price, as `seed_liquidity` and `seed_liquidity_by_operator`
+    Curve {
+        min_price: f64,
+        max_price: f64,
+        distribution: SeedDistribution,
+    },
+    /// Seed a single bin, as `seed_liquidity_single_bin` and `seed_liquidity_single_bin_by_operator`
+    SingleBin {
+        price: f64,
+        selective_rounding: SelectiveRounding,
+    },
+}
+
+#[derive(Debug)]
+pub struct PlanIlmLaunchParameters {
+    pub lb_pair: Pubkey,
+    pub base_pubkey: Pubkey,
+    pub amount: u64,
+    pub shape: IlmLaunchShape,
+}
+
+/// Bin arrays covered by positions starting at the given lower bin ids. Each position covers its lower bin
+/// array and the next one.
+pub fn get_bin_array_indexes_for_positions(lower_bin_ids: &[i32]) -> Result<BTreeSet<i32>> {
+    let mut bin_array_indexes = BTreeSet::new();
+    for lower_bin_id in lower_bin_ids {
+        let lower_bin_array_idx = BinArray::bin_id_to_bin_array_index(*lower_bin_id)?;
+        bin_array_indexes.insert(lower_bin_array_idx);
+        bin_array_indexes.insert(lower_bin_array_idx + 1);
+    }
+    Ok(bin_array_indexes)
+}
+
+/// Seeded bins, as (bin id, amount x), and positions, as (position, lower bin id, width), of a launch.
+pub fn get_ilm_launch_bins_and_positions(
+    lb_pair: Pubkey,
+    base_pubkey: Pubkey,
+    bin_step: u16,
+    base_decimals: u8,
+    quote_decimals: u8,
+    fund_amount: u64,
+    shape: IlmLaunchShape,
+) -> Result<(Vec<(i32, u64)>, Vec<(Pubkey, i32, i32)>)> {
+    let launch = match shape {
+        IlmLaunchShape::Curve {
+            min_price,
+            max_price,
+            distribution,
+        } => {
+            let (min_bin_id, max_bin_id) = convert_min_max_ui_price_to_min_max_bin_id(
+                bin_step,
+                min_price,
+                max_price,
+                base_decimals,
+                quote_decimals,
+            )?;
+            ensure!(min_bin_id < max_bin_id, "Invalid price range");
+
+            let bins_amount = distribution.generate_amount_for_bins(
+                bin_step,
+                min_bin_id,
+                max_bin_id,
+                base_decimals,
+                quote_decimals,
+                fund_amount,
+            )?;
+
+            let width = MAX_BIN_PER_POSITION as i32;
+            let position_number =
+                get_number_of_position_required_to_cover_range(min_bin_id, max_bin_id)?;
+
+            let positions = (0..position_number)
+                .map(|i| {
+                    let lower_bin_id = min_bin_id + width * i;
+                    let (position, _bump) =
+                        derive_position_pda(lb_pair, base_pubkey, lower_bin_id, width);
+                    (position, lower_bin_id, width)
+                })
+                .collect::<Vec<_>>();
+
+            (bins_amount, positions)
+        }
+        IlmLaunchShape::SingleBin {
+            price,
+            selective_rounding,
+        } => {
+            let price = price_per_token_to_per_lamport(price, base_decimals, quote_decimals)
+                .context("price_per_token_per_lamport overflow")?;
+
+            let bin_id = match selective_rounding {
+                SelectiveRounding::None => get_precise_id_from_price(bin_step, &price)
+                    .context("fail to get exact bin id for the price"),
+                SelectiveRounding::Down => get_id_from_price(bin_step, &price, Rounding::Down)
+                    .context("get_id_from_price overflow"),
+                SelectiveRounding::Up => get_id_from_price(bin_step, &price, Rounding::Up)
+                    .context("get_id_from_price overflow"),
+            }?;
+
+            let (position, _bump) = derive_position_pda(lb_pair, base_pubkey, bin_id, 1);
+
+            (vec![(bin_id, fund_amount)], vec![(position, bin_id, 1)])
+        }
+    };
+
+    Ok(launch)
+}
+
+/// Accounts a launch creates and their rent exemption, in lamports.
+#[derive(Debug, Default)]
+pub struct IlmLaunchRent {
+    pub positions_to_create: u64,
+    pub position_rent: u64,
+    pub bin_arrays_to_create: u64,
+    pub bin_array_rent: u64,
+    pub create_bitmap_extension: bool,
+    pub bitmap_extension_rent: u64,
+    pub token_accounts_to_create: u64,
+    pub token_account_rent: u64,
+}
+
+impl IlmLaunchRent {
+    pub fn get_total_rent(&self) -> Result<u64> {
+        self.position_rent
+            .checked_mul(self.positions_to_create)
+            .and_then(|rent| {
+                rent.checked_add(self.bin_array_rent.checked_mul(self.bin_arrays_to_create)?)
+            })
+            .and_then(|rent| {
+                rent.checked_add(if self.create_bitmap_extension {
+                    self.bitmap_extension_rent
+                } else {
+                    0
+                })
+            })
+            .and_then(|rent| {
+                rent.checked_add(
+                    self.token_account_rent
+                        .checked_mul(self.token_accounts_to_create)?,
+                )
+            })
+            .context("total_rent overflow")
+    }
+}
+
+pub async fn plan_ilm_launch<C: Deref<Target = impl Signer> + Clone>(
+    params: PlanIlmLaunchParameters,
+    program: &Program<C>,
+) -> Result<()> {
+    let PlanIlmLaunchParameters {
+        lb_pair,
+        base_pubkey,
+        amount,
+        shape,
+    } = params;
+
+    let rpc_client = program.rpc();
+
+    let lb_pair_state: LbPair = program.account(lb_pair).await?;
+    let bin_step = lb_pair_state.bin_step;
+
+    let token_mint_base: Mint = program.account(lb_pair_state.token_x_mint).await?;
+    let token_mint_quote: Mint = program.account(lb_pair_state.token_y_mint).await?;
+    let base_decimals = token_mint_base.decimals;
+    let quote_decimals = token_mint_quote.decimals;
+
+    let fund_amount = to_wei_amount(amount, base_decimals)?;
+
+    let (bins_amount, positions) = get_ilm_launch_bins_and_positions(
+        lb_pair,
+        base_pubkey,
+        bin_step,
+        base_decimals,
+        quote_decimals,
+        fund_amount,
+        shape,
+    )?;
+
+    let lower_bin_ids = positions
+        .iter()
+        .map(|(_, lower_bin_id, _)| *lower_bin_id)
+        .collect::<Vec<_>>();
+    let bin_array_indexes = get_bin_array_indexes_for_positions(&lower_bin_ids)?;
+
+    let mut positions_to_create = 0u64;
+    println!("Positions: {}", positions.len());
+    for (position, lower_bin_id, width) in positions.iter() {
+        let exists = rpc_client.get_account(position).await.is_ok();
+        if !exists {
+            positions_to_create += 1;
+        }
+        println!(
+            "  {} lower bin id {} upper bin id {} {}",
+            position,
+            lower_bin_id,
+            lower_bin_id + width - 1,
+            if exists { "exists" } else { "to create" }
+        );
+    }
+
+    let mut bin_arrays_to_create = 0u64;
+    println!("Bin arrays: {}", bin_array_indexes.len());
+    for idx in bin_array_indexes.iter() {
+        let (bin_array, _bump) = derive_bin_array_pda(lb_pair, (*idx).into());
+        let exists = rpc_client.get_account(&bin_array).await.is_ok();
+        if !exists {
+            bin_arrays_to_create += 1;
+        }
+        println!(
+            "  {} index {} {}",
+            bin_array,
+            idx,
+            if exists { "exists" } else { "to create" }
+        );
+    }
+
+    let bitmap_extension_required = bin_array_indexes
+        .iter()
+        .any(|idx| lb_pair_state.is_overflow_default_bin_array_bitmap(*idx));
+    let (bitmap_extension, _bump) = derive_bin_array_bitmap_extension(lb_pair);
+    let create_bitmap_extension =
+        bitmap_extension_required && rpc_client.get_account(&bitmap_extension).await.is_err();
+    println!(
+        "Bitmap extension: {}",
+        match (bitmap_extension_required, create_bitmap_extension) {
+            (false, _) => "not required".to_string(),
+            (true, false) => format!("{} exists", bitmap_extension),
+            (true, true) => format!("{} to create", bitmap_extension),
+        }
+    );
+
+    let payer = program.payer();
+    let user_token_x = get_associated_token_address(&payer, &lb_pair_state.token_x_mint);
+    let user_token_y = get_associated_token_address(&payer, &lb_pair_state.token_y_mint);
+
+    let user_token_x_balance = rpc_client.get_token_account_balance(&user_token_x).await;
+    let user_token_y_exists = rpc_client.get_account(&user_token_y).await.is_ok();
+    let token_accounts_to_create =
+        u64::from(user_token_x_balance.is_err()) + u64::from(!user_token_y_exists);
+
+    let position_rent = rpc_client
+        .get_minimum_balance_for_rent_exemption(8 + PositionV2::INIT_SPACE)
+        .await?;
+    let bin_array_rent = rpc_client
+        .get_minimum_balance_for_rent_exemption(8 + BinArray::INIT_SPACE)
+        .await?;
+    let bitmap_extension_rent = rpc_client
+        .get_minimum_balance_for_rent_exemption(8 + BinArrayBitmapExtension::INIT_SPACE)
+        .await?;
+    let token_account_rent = rpc_client
+        .get_minimum_balance_for_rent_exemption(spl_token::state::Account::LEN)
+        .await?;
+
+    let total_rent = IlmLaunchRent {
+        positions_to_create,
+        position_rent,
+        bin_arrays_to_create,
+        bin_array_rent,
+        create_bitmap_extension,
+        bitmap_extension_rent,
+        token_accounts_to_create,
+        token_account_rent,
+    }
+    .get_total_rent()?;
+
+    println!("Rent");
+    println!(
+        "  Positions: {} x {} lamports",
+        positions_to_create, position_rent
+    );
+    println!(
+        "  Bin arrays: {} x {} lamports",
+        bin_arrays_to_create, bin_array_rent
+    );
+    if create_bitmap_extension {
+        println!("  Bitmap extension: {} lamports", bitmap_extension_rent);
+    }
+    println!(
+        "  Token accounts: {} x {} lamports",
+        token_accounts_to_create, token_account_rent
+    );
+    println!("  Total: {} lamports", total_rent);
+
+    println!("Price ladder");
+    let mut cumulative_amount = 0u64;
+    for (bin_id, bin_amount) in bins_amount.iter() {
+        cumulative_amount = cumulative_amount
+            .checked_add(*bin_amount)
+            .context("cumulative_amount overflow")?;
+        println!(
+            "  Bin {} price {} amount {} cumulative {}",
+            bin_id,
+            get_ui_price_from_id(
+                bin_step,
+                *bin_id,
+                base_decimals.into(),
+                quote_decimals.into()
+            ),
+            *bin_amount as f64 / 10f64.powi(base_decimals.into()),
+            cumulative_amount as f64 / 10f64.powi(base_decimals.into()),
+        );
+    }
+
+    let mut warning_count = 0;
+
+    let (min_swappable_bin_id, max_swappable_bin_id) = find_swappable_min_max_bin_id(bin_step)?;
+    for (bin_id, _) in bins_amount.iter() {
+        if *bin_id < min_swappable_bin_id || *bin_id > max_swappable_bin_id {
+            warning_count += 1;
+            println!(
+                "Warning: bin {} is outside of the swappable range {} to {}",
+                bin_id, min_swappable_bin_id, max_swappable_bin_id
+            );
+        }
+    }
+
+    let is_single_bin = positions.iter().all(|(_, _, width)| *width == 1);
+    for (bin_id, _) in bins_amount.iter() {
+        if is_single_bin && *bin_id != lb_pair_state.active_id {
+            warning_count += 1;
+            println!(
+                "Warning: bin {} doesn't match active bin {}",
+                bin_id, lb_pair_state.active_id
+            );
+        } else if !is_single_bin && *bin_id < lb_pair_state.active_id {
+            warning_count += 1;
+            println!(
+                "Warning: bin {} is below active bin {}. Only token Y can be deposited there",
+                bin_id, lb_pair_state.active_id
+            );
+        }
+    }
+
+    let sol_balance = rpc_client.get_balance(&payer).await?;
+    println!("Wallet {} SOL balance {} lamports", payer, sol_balance);
+    if sol_balance < total_rent {
+        warning_count += 1;
+        println!(
+            "Warning: SOL balance is short of {} lamports for rent, excluding transaction fees",
+            total_rent - sol_balance
+        );
+    }
+
+    let token_x_balance = match user_token_x_balance {
+        Result::Ok(balance) => balance.amount.parse::<u64>()?,
+        Result::Err(_) => 0,
+    };
+    println!(
+        "Wallet token X balance {} required {}",
+        token_x_balance, fund_amount
+    );
+    if token_x_balance < fund_amount {
+        warning_count += 1;
+        println!(
+            "Warning: token X balance is short of {}",
+            fund_amount - token_x_balance
+        );
+    }
+
+    println!("Warnings: {}", warning_count);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_client::solana_sdk::rent::Rent;
+
+    #[test]
+    fn test_get_bin_array_indexes_for_positions() {
+        let indexes = |lower_bin_ids: &[i32]| {
+            get_bin_array_indexes_for_positions(lower_bin_ids)
+                .unwrap()
+                .into_iter()
+                .collect::<Vec<_>>()
+        };
+
+        // A position covers its lower bin array and the next one
+        assert_eq!(indexes(&[0]), vec![0, 1]);
+        assert_eq!(indexes(&[69]), vec![0, 1]);
+        assert_eq!(indexes(&[70]), vec![1, 2]);
+        // Negative bin ids round down to the previous bin array
+        assert_eq!(indexes(&[-1]), vec![-1, 0]);
+        assert_eq!(indexes(&[-70]), vec![-1, 0]);
+        assert_eq!(indexes(&[-71]), vec![-2, -1]);
+        // Positions sharing bin arrays across the zero boundary
+        assert_eq!(indexes(&[-140, -70, 0, 70]), vec![-2, -1, 0, 1, 2]);
+        assert_eq!(indexes(&[-35, 35]), vec![-1, 0, 1]);
+    }
+
+    #[test]
+    fn test_plan_curve_launch() {
+        let lb_pair = Pubkey::new_unique();
+        let base_pubkey = Pubkey::new_unique();
+
+        // Bins 0 to 161, from price 1 to 5 with a 1% bin step
+        let (bins_amount, positions) = get_ilm_launch_bins_and_positions(
+            lb_pair,
+            base_pubkey,
+            100,
+            6,
+            6,
+            1_000_000_000,
+            IlmLaunchShape::Curve {
+                min_price: 1.0,
+                max_price: 5.0,
+                distribution: SeedDistribution::Linear,
+            },
+        )
+        .unwrap();
+
+        assert_eq!(bins_amount.first().unwrap().0, 0);
+        assert_eq!(bins_amount.last().unwrap().0, 161);
+        let total: u64 = bins_amount.iter().map(|(_, amount)| amount).sum();
+        assert_eq!(total, 1_000_000_000);
+
+        let width = MAX_BIN_PER_POSITION as i32;
+        assert_eq!(
+            positions,
+            [0, 70, 140]
+                .into_iter()
+                .map(|lower_bin_id| (
+                    derive_position_pda(lb_pair, base_pubkey, lower_bin_id, width).0,
+                    lower_bin_id,
+                    width
+                ))
+                .collect::<Vec<_>>()
+        );
+
+        let lower_bin_ids = positions
+            .iter()
+            .map(|(_, lower_bin_id, _)| *lower_bin_id)
+            .collect::<Vec<_>>();
+        let bin_array_indexes = get_bin_array_indexes_for_positions(&lower_bin_ids).unwrap();
+        assert_eq!(
+            bin_array_indexes.into_iter().collect::<Vec<_>>(),
+            vec![0, 1, 2, 3]
+        );
+
+        // Nothing exists yet: 3 positions, 4 bin arrays and both token accounts
+        let rent = Rent::default();
+        let position_rent = rent.minimum_balance(8 + PositionV2::INIT_SPACE);
+        let bin_array_rent = rent.minimum_balance(8 + BinArray::INIT_SPACE);
+        let token_account_rent = rent.minimum_balance(spl_token::state::Account::LEN);
+        let launch_rent = IlmLaunchRent {
+            positions_to_create: 3,
+            position_rent,
+            bin_arrays_to_create: 4,
+            bin_array_rent,
+            create_bitmap_extension: false,
+            bitmap_extension_rent: rent.minimum_balance(8 + BinArrayBitmapExtension::INIT_SPACE),
+            token_accounts_to_create: 2,
+            token_account_rent,
+        };
+        assert_eq!(
+            launch_rent.get_total_rent().unwrap(),
+            3 * position_rent + 4 * bin_array_rent + 2 * token_account_rent
+        );
+
+        let launch_rent = IlmLaunchRent {
+            create_bitmap_extension: true,
+            ..launch_rent
+        };
+        assert_eq!(
+            launch_rent.get_total_rent().unwrap(),
+            3 * position_rent
+                + 4 * bin_array_rent
+                + launch_rent.bitmap_extension_rent
+                + 2 * token_account_rent
+        );
+    }
+
+    #[test]
+    fn test_plan_single_bin_launch() {
+        let lb_pair = Pubkey::new_unique();
+        let base_pubkey = Pubkey::new_unique();
+
+        // Price 0.5 is between bins -70 and -69 with a 1% bin step
+        let (bins_amount, positions) = get_ilm_launch_bins_and_positions(
+            lb_pair,
+            base_pubkey,
+            100,
+            6,
+            6,
+            1_000_000,
+            IlmLaunchShape::SingleBin {
+                price: 0.5,
+                selective_rounding: SelectiveRounding::Down,
+            },
+        )
+        .unwrap();
+
+        assert_eq!(bins_amount, vec![(-70, 1_000_000)]);
+        assert_eq!(
+            positions,
+            vec![(derive_position_pda(lb_pair, base_pubkey, -70, 1).0, -70, 1)]
+        );
+        assert_eq!(
+            get_bin_array_indexes_for_positions(&[-70])
+                .unwrap()
+                .into_iter()
+                .collect::<Vec<_>>(),
+            vec![-1, 0]
+        );
+    }
+}
@@ -0,0 +1,991 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::ops::Deref;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+
+use anchor_client::solana_client::rpc_config::RpcSendTransactionConfig;
+use anchor_client::solana_sdk::instruction::Instruction;
+use anchor_client::{solana_sdk::pubkey::Pubkey, solana_sdk::signer::Signer, Program};
+use anyhow::*;
+use commons::signer::{load_signer, SignerSource};
+use lb_clmm::state::preset_parameters::PresetParameter;
+use lb_clmm::utils::pda::{
+    derive_customizable_permissionless_lb_pair, derive_lb_pair_pda2, derive_permission_lb_pair_pda,
+};
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::args::SelectiveRounding;
+use crate::instructions::claim_fee::claim_fee;
+use crate::instructions::initialize_bin_array_with_bin_range::{
+    initialize_bin_array_with_bin_range, InitBinArrayWithBinRangeParameters,
+};
+use crate::instructions::initialize_bin_array_with_price_range::{
+    initialize_bin_array_with_price_range, InitBinArrayWithPriceRangeParameters,
+};
+use crate::instructions::initialize_customizable_permissionless_lb_pair::{
+    initialize_customizable_permissionless_lb_pair, InitCustomizablePermissionlessLbPairParameters,
+};
+use crate::instructions::initialize_lb_pair::{initialize_lb_pair, InitLbPairParameters};
+use crate::instructions::initialize_permission_lb_pair::{
+    initialize_permission_lb_pair, InitPermissionLbPairParameters,
+};
+use crate::instructions::seed_distribution::SeedDistribution;
+use crate::instructions::seed_liquidity::{seed_liquidity, SeedLiquidityParameters};
+use crate::instructions::seed_liquidity_from_operator::{
+    seed_liquidity_by_operator, SeedLiquidityByOperatorParameters,
+};
+use crate::instructions::seed_liquidity_single_bin::{
+    seed_liquidity_single_bin, SeedLiquiditySingleBinParameters,
+};
+use crate::instructions::seed_liquidity_single_bin_by_operator::{
+    seed_liquidity_single_bin_by_operator, SeedLiquiditySingleBinByOperatorParameters,
+};
+use crate::instructions::set_activation_point::{set_activation_point, SetActivationPointParam};
+use crate::instructions::set_pre_activation_duration::{
+    set_pre_activation_duration, SetPreactivationDurationParam,
+};
+
+/// Output name of the steps creating a pair.
+pub const LB_PAIR_OUTPUT: &str = "lb_pair";
+
+/// A pubkey in the plan. Either a literal address, or `${step_id.output}` referring to an output of an earlier step.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PubkeyRef {
+    Value(Pubkey),
+    Output { step: String, output: String },
+}
+
+impl FromStr for PubkeyRef {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        if let Some(reference) = s.strip_prefix("${").and_then(|s| s.strip_suffix('}')) {
+            let (step, output) = reference.split_once('.').context(format!(
+                "Invalid reference {}, expected ${{step.output}}",
+                s
+            ))?;
+            return Ok(PubkeyRef::Output {
+                step: step.to_string(),
+                output: output.to_string(),
+            });
+        }
+
+        Ok(PubkeyRef::Value(
+            Pubkey::from_str(s).context(format!("Invalid pubkey {}", s))?,
+        ))
+    }
+}
+
+impl<'de> Deserialize<'de> for PubkeyRef {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        PubkeyRef::from_str(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+impl PubkeyRef {
+    fn resolve(&self, outputs: &HashMap<String, HashMap<String, Pubkey>>) -> Result<Pubkey> {
+        match self {
+            PubkeyRef::Value(pubkey) => Ok(*pubkey),
+            PubkeyRef::Output { step, output } => outputs
+                .get(step)
+                .and_then(|step_outputs| step_outputs.get(output))
+                .copied()
+                .context(format!("Output {}.{} not available", step, output)),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PlanStep {
+    InitializePair {
+        preset_parameter: PubkeyRef,
+        token_mint_x: PubkeyRef,
+        token_mint_y: PubkeyRef,
+        initial_price: f64,
+    },
+    InitializeCustomizablePermissionlessPair {
+        token_mint_x: PubkeyRef,
+        token_mint_y: PubkeyRef,
+        bin_step: u16,
+        initial_price: f64,
+        base_fee_bps: u16,
+        activation_type: u8,
+        has_alpha_vault: bool,
+        selective_rounding: SelectiveRounding,
+        activation_point: Option<u64>,
+    },
+    InitializePermissionPair {
+        token_mint_x: PubkeyRef,
+        token_mint_y: PubkeyRef,
+        bin_step: u16,
+        initial_price: f64,
+        base_keypair_signer: String,
+        base_fee_bps: u16,
+        lock_duration: u64,
+        activation_type: u8,
+    },
+    InitializeBinArrayWithPriceRange {
+        lb_pair: PubkeyRef,
+        lower_price: f64,
+        upper_price: f64,
+    },
+    InitializeBinArrayWithBinRange {
+        lb_pair: PubkeyRef,
+        lower_bin_id: i32,
+        upper_bin_id: i32,
+    },
+    SeedLiquidity {
+        lb_pair: PubkeyRef,
+        base_position_signer: String,
+        position_owner_signer: String,
+        amount: u64,
+        min_price: f64,
+        max_price: f64,
+        curvature: Option<f64>,
+        distribution: Option<String>,
+        /// Attempts before the step fails. Default to a single attempt.
+        max_retries: Option<u16>,
+    },
+    SeedLiquidityByOperator {
+        lb_pair: PubkeyRef,
+        base_position_signer: String,
+        position_owner: PubkeyRef,
+        fee_owner: PubkeyRef,
+        lock_release_point: u64,
+        amount: u64,
+        min_price: f64,
+        max_price: f64,
+        curvature: Option<f64>,
+        distribution: Option<String>,
+        /// Attempts before the step fails. Default to a single attempt.
+        max_retries: Option<u16>,
+    },
+    SeedLiquiditySingleBin {
+        lb_pair: PubkeyRef,
+        base_position_signer: String,
+        position_owner_signer: String,
+        amount: u64,
+        price: f64,
+        selective_rounding: SelectiveRounding,
+    },
+    SeedLiquiditySingleBinByOperator {
+        lb_pair: PubkeyRef,
+        base_position_signer: String,
+        position_owner: PubkeyRef,
+        fee_owner: PubkeyRef,
+        lock_release_point: u64,
+        amount: u64,
+        price: f64,
+        selective_rounding: SelectiveRounding,
+    },
+    SetActivationPoint {
+        lb_pair: PubkeyRef,
+        activation_point: u64,
+    },
+    SetPreActivationDuration {
+        lb_pair: PubkeyRef,
+        pre_activation_duration: u16,
+    },
+    ClaimFee {
+        position: PubkeyRef,
+    },
+}
+
+impl PlanStep {
+    /// Outputs produced by the step, which can be referred by later steps.
+    pub fn outputs(&self) -> &'static [&'static str] {
+        match self {
+            PlanStep::InitializePair { .. }
+            | PlanStep::InitializeCustomizablePermissionlessPair { .. }
+            | PlanStep::InitializePermissionPair { .. } => &[LB_PAIR_OUTPUT],
+            _ => &[],
+        }
+    }
+
+    /// Attempts of the step before it fails.
+    pub fn max_attempts(&self) -> u16 {
+        match self {
+            PlanStep::SeedLiquidity { max_retries, .. }
+            | PlanStep::SeedLiquidityByOperator { max_retries, .. } => {
+                max_retries.unwrap_or(1).max(1)
+            }
+            _ => 1,
+        }
+    }
+
+    fn pubkey_refs(&self) -> Vec<&PubkeyRef> {
+        match self {
+            PlanStep::InitializePair {
+                preset_parameter,
+                token_mint_x,
+                token_mint_y,
+                ..
+            } => vec![preset_parameter, token_mint_x, token_mint_y],
+            PlanStep::InitializeCustomizablePermissionlessPair {
+                token_mint_x,
+                token_mint_y,
+                ..
+            }
+            | PlanStep::InitializePermissionPair {
+                token_mint_x,
+                token_mint_y,
+                ..
+            } => vec![token_mint_x, token_mint_y],
+            PlanStep::InitializeBinArrayWithPriceRange { lb_pair, .. }
+            | PlanStep::InitializeBinArrayWithBinRange { lb_pair, .. }
+            | PlanStep::SeedLiquidity { lb_pair, .. }
+            | PlanStep::SeedLiquiditySingleBin { lb_pair, .. }
+            | PlanStep::SetActivationPoint { lb_pair, .. }
+            | PlanStep::SetPreActivationDuration { lb_pair, .. } => vec![lb_pair],
+            PlanStep::SeedLiquidityByOperator {
+                lb_pair,
+                position_owner,
+                fee_owner,
+                ..
+            }
+            | PlanStep::SeedLiquiditySingleBinByOperator {
+                lb_pair,
+                position_owner,
+                fee_owner,
+                ..
+            } => vec![lb_pair, position_owner, fee_owner],
+            PlanStep::ClaimFee { position } => vec![position],
+        }
+    }
+
+    fn signer_sources(&self) -> Vec<&String> {
+        match self {
+            PlanStep::InitializePermissionPair {
+                base_keypair_signer,
+                ..
+            } => vec![base_keypair_signer],
+            PlanStep::SeedLiquidity {
+                base_position_signer,
+                position_owner_signer,
+                ..
+            }
+            | PlanStep::SeedLiquiditySingleBin {
+                base_position_signer,
+                position_owner_signer,
+                ..
+            } => vec![base_position_signer, position_owner_signer],
+            PlanStep::SeedLiquidityByOperator {
+                base_position_signer,
+                ..
+            }
+            | PlanStep::SeedLiquiditySingleBinByOperator {
+                base_position_signer,
+                ..
+            } => vec![base_position_signer],
+            _ => vec![],
+        }
+    }
+
+    /// Parameter checks which do not require RPC calls.
+    fn validate_parameters(&self) -> Result<()> {
+        match self {
+            PlanStep::InitializePair { initial_price, .. }
+            | PlanStep::InitializeCustomizablePermissionlessPair { initial_price, .. }
+            | PlanStep::InitializePermissionPair { initial_price, .. } => {
+                ensure!(*initial_price > 0.0, "initial_price must be positive");
+            }
+            PlanStep::InitializeBinArrayWithPriceRange {
+                lower_price,
+                upper_price,
+                ..
+            } => {
+                ensure!(
+                    *lower_price > 0.0 && lower_price <= upper_price,
+                    "Invalid price range"
+                );
+            }
+            PlanStep::InitializeBinArrayWithBinRange {
+                lower_bin_id,
+                upper_bin_id,
+                ..
+            } => {
+                ensure!(lower_bin_id <= upper_bin_id, "Invalid bin range");
+            }
+            PlanStep::SeedLiquidity {
+                amount,
+                min_price,
+                max_price,
+                curvature,
+                distribution,
+                ..
+            }
+            | PlanStep::SeedLiquidityByOperator {
+                amount,
+                min_price,
+                max_price,
+                curvature,
+                distribution,
+                ..
+            } => {
+                ensure!(*amount > 0, "amount must be positive");
+                ensure!(
+                    *min_price > 0.0 && min_price < max_price,
+                    "Invalid price range"
+                );
+                SeedDistribution::from_curvature_or_source(*curvature, distribution.as_deref())?;
+            }
+            PlanStep::SeedLiquiditySingleBin { amount, price, .. }
+            | PlanStep::SeedLiquiditySingleBinByOperator { amount, price, .. } => {
+                ensure!(*amount > 0, "amount must be positive");
+                ensure!(*price > 0.0, "price must be positive");
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PlanStepEntry {
+    /// Step id used by references and checkpoints. Required, so that editing the plan does not change the ids of the
+    /// completed steps.
+    pub id: String,
+    #[serde(flatten)]
+    pub step: PlanStep,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Plan {
+    pub steps: Vec<PlanStepEntry>,
+}
+
+impl Plan {
+    pub fn step_ids(&self) -> Vec<String> {
+        self.steps.iter().map(|entry| entry.id.clone()).collect()
+    }
+
+    /// Validate the whole plan before any transaction is sent.
+    pub fn validate(&self) -> Result<()> {
+        ensure!(!self.steps.is_empty(), "Plan has no step");
+
+        let step_ids = self.step_ids();
+        let mut step_outputs: HashMap<&str, &[&str]> = HashMap::new();
+
+        for (idx, (entry, step_id)) in self.steps.iter().zip(step_ids.iter()).enumerate() {
+            ensure!(!step_id.trim().is_empty(), "Step {} has an empty id", idx);
+            // References split on the first dot
+            ensure!(
+                !step_id.contains('.'),
+                "Step id {} must not contain a dot",
+                step_id
+            );
+
+            let context = || format!("Invalid step {}", step_id);
+
+            ensure!(
+                !step_outputs.contains_key(step_id.as_str()),
+                "Duplicated step id {}",
+                step_id
+            );
+
+            entry.step.validate_parameters().with_context(context)?;
+
+            for pubkey_ref in entry.step.pubkey_refs() {
+                if let PubkeyRef::Output { step, output } = pubkey_ref {
+                    let outputs = step_outputs
+                        .get(step.as_str())
+                        .context(format!("Step {} must be defined before {}", step, step_id))
+                        .with_context(context)?;
+                    ensure!(
+                        outputs.contains(&output.as_str()),
+                        "{}: step {} has no output {}",
+                        step_id,
+                        step,
+                        output
+                    );
+                }
+            }
+
+            for source in entry.step.signer_sources() {
+                SignerSource::from_str(source).with_context(context)?;
+            }
+
+            step_outputs.insert(step_id.as_str(), entry.step.outputs());
+        }
+
+        Ok(())
+    }
+}
+
+pub fn read_plan(path: &str) -> Result<Plan> {
+    let file = File::open(path).context(format!("Plan file {} not found", path))?;
+    let reader = BufReader::new(file);
+
+    let is_json = Path::new(path)
+        .extension()
+        .map(|ext| ext.eq_ignore_ascii_case("json"))
+        .unwrap_or(false);
+
+    let plan = if is_json {
+        serde_json::from_reader(reader)?
+    } else {
+        serde_yaml::from_reader(reader)?
+    };
+
+    Ok(plan)
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct CompletedStep {
+    pub id: String,
+    pub outputs: HashMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct PlanCheckpoint {
+    pub completed_steps: Vec<CompletedStep>,
+}
+
+fn read_checkpoint(path: &str) -> Result<PlanCheckpoint> {
+    if !Path::new(path).exists() {
+        return Ok(PlanCheckpoint::default());
+    }
+    let file = File::open(path)?;
+    let reader = BufReader::new(file);
+    Ok(serde_json::from_reader(reader)?)
+}
+
+fn write_checkpoint(path: &str, checkpoint: &PlanCheckpoint) -> Result<()> {
+    let file = File::create(path)?;
+    let mut writer = BufWriter::new(file);
+    serde_json::to_writer_pretty(&mut writer, checkpoint)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Pair of a pair creation step which already exists, such as when the transaction of an earlier run landed but the
+/// checkpoint was not written. None for the other steps.
+async fn get_existing_pair<C: Deref<Target = impl Signer> + Clone>(
+    step: &PlanStep,
+    outputs: &HashMap<String, HashMap<String, Pubkey>>,
+    program: &Program<C>,
+) -> Result<Option<Pubkey>> {
+    let (lb_pair, _bump) = match step {
+        PlanStep::InitializePair {
+            preset_parameter,
+            token_mint_x,
+            token_mint_y,
+            ..
+        } => {
+            let preset_parameter_state = program
+                .account::<PresetParameter>(preset_parameter.resolve(outputs)?)
+                .await?;
+            derive_lb_pair_pda2(
+                token_mint_x.resolve(outputs)?,
+                token_mint_y.resolve(outputs)?,
+                preset_parameter_state.bin_step,
+                preset_parameter_state.base_factor,
+            )
+        }
+        PlanStep::InitializeCustomizablePermissionlessPair {
+            token_mint_x,
+            token_mint_y,
+            ..
+        } => derive_customizable_permissionless_lb_pair(
+            token_mint_x.resolve(outputs)?,
+            token_mint_y.resolve(outputs)?,
+        ),
+        PlanStep::InitializePermissionPair {
+            token_mint_x,
+            token_mint_y,
+            bin_step,
+            base_keypair_signer,
+            ..
+        } => derive_permission_lb_pair_pda(
+            load_signer(base_keypair_signer)?.pubkey(),
+            token_mint_x.resolve(outputs)?,
+            token_mint_y.resolve(outputs)?,
+            *bin_step,
+        ),
+        _ => return Ok(None),
+    };
+
+    if program.rpc().get_account_data(&lb_pair).await.is_ok() {
+        return Ok(Some(lb_pair));
+    }
+    Ok(None)
+}
+
+/// Execute the step. The steps creating accounts at deterministic addresses treat the existing accounts as created, so
+/// that a step whose transaction landed can be run again.
+async fn execute_step<C: Deref<Target = impl Signer> + Clone>(
+    step: &PlanStep,
+    outputs: &HashMap<String, HashMap<String, Pubkey>>,
+    program: &Program<C>,
+    transaction_config: RpcSendTransactionConfig,
+    compute_unit_price: Option<Instruction>,
+) -> Result<HashMap<String, Pubkey>> {
+    let mut step_outputs = HashMap::new();
+
+    if let Some(lb_pair) = get_existing_pair(step, outputs, program).await? {
+        println!("Pair {} already exists", lb_pair);
+        step_outputs.insert(LB_PAIR_OUTPUT.to_string(), lb_pair);
+        return Ok(step_outputs);
+    }
+
+    match step {
+        PlanStep::InitializePair {
+            preset_parameter,
+            token_mint_x,
+            token_mint_y,
+            initial_price,
+        } => {
+            let params = InitLbPairParameters {
+                preset_parameter: preset_parameter.resolve(outputs)?,
+                token_mint_x: token_mint_x.resolve(outputs)?,
+                token_mint_y: token_mint_y.resolve(outputs)?,
+                initial_price: *initial_price,
+            };
+            let lb_pair = initialize_lb_pair(params, program, transaction_config).await?;
+            step_outputs.insert(LB_PAIR_OUTPUT.to_string(), lb_pair);
+        }
+        PlanStep::InitializeCustomizablePermissionlessPair {
+            token_mint_x,
+            token_mint_y,
+            bin_step,
+            initial_price,
+            base_fee_bps,
+            activation_type,
+            has_alpha_vault,
+            selective_rounding,
+            activation_point,
+        } => {
+            let params = InitCustomizablePermissionlessLbPairParameters {
+                token_mint_x: token_mint_x.resolve(outputs)?,
+                token_mint_y: token_mint_y.resolve(outputs)?,
+                bin_step: *bin_step,
+                initial_price: *initial_price,
+                base_fee_bps: *base_fee_bps,
+                activation_type: *activation_type,
+                has_alpha_vault: *has_alpha_vault,
+                activation_point: *activation_point,
+                selective_rounding: selective_rounding.clone(),
+            };
+            let lb_pair = initialize_customizable_permissionless_lb_pair(
+                params,
+                program,
+                transaction_config,
+                compute_unit_price,
+            )
+            .await?;
+            step_outputs.insert(LB_PAIR_OUTPUT.to_string(), lb_pair);
+        }
+        PlanStep::InitializePermissionPair {
+            token_mint_x,
+            token_mint_y,
+            bin_step,
+            initial_price,
+            base_keypair_signer,
+            base_fee_bps,
+            lock_duration,
+            activation_type,
+        } => {
+            let params = InitPermissionLbPairParameters {
+                token_mint_x: token_mint_x.resolve(outputs)?,
+                token_mint_y: token_mint_y.resolve(outputs)?,
+                bin_step: *bin_step,
+                initial_price: *initial_price,
+                base_keypair: load_signer(base_keypair_signer)?,
+                base_fee_bps: *base_fee_bps,
+                lock_duration: *lock_duration,
+                activation_type: *activation_type,
+            };
+            let lb_pair =
+                initialize_permission_lb_pair(params, program, transaction_config).await?;
+            step_outputs.insert(LB_PAIR_OUTPUT.to_string(), lb_pair);
+        }
+        PlanStep::InitializeBinArrayWithPriceRange {
+            lb_pair,
+            lower_price,
+            upper_price,
+        } => {
+            let params = InitBinArrayWithPriceRangeParameters {
+                lb_pair: lb_pair.resolve(outputs)?,
+                lower_price: *lower_price,
+                upper_price: *upper_price,
+            };
+            initialize_bin_array_with_price_range(params, program, transaction_config).await?;
+        }
+        PlanStep::InitializeBinArrayWithBinRange {
+            lb_pair,
+            lower_bin_id,
+            upper_bin_id,
+        } => {
+            let params = InitBinArrayWithBinRangeParameters {
+                lb_pair: lb_pair.resolve(outputs)?,
+                lower_bin_id: *lower_bin_id,
+                upper_bin_id: *upper_bin_id,
+            };
+            initialize_bin_array_with_bin_range(params, program, transaction_config).await?;
+        }
+        PlanStep::SeedLiquidity {
+            lb_pair,
+            base_position_signer,
+            position_owner_signer,
+            amount,
+            min_price,
+            max_price,
+            curvature,
+            distribution,
+            ..
+        } => {
+            let position_base_kp = load_signer(base_position_signer)?;
+            let params = SeedLiquidityParameters {
+                lb_pair: lb_pair.resolve(outputs)?,
+                base_pubkey: position_base_kp.pubkey(),
+                position_base_kp,
+                position_owner_kp: load_signer(position_owner_signer)?,
+                amount: *amount,
+                min_price: *min_price,
+                max_price: *max_price,
+                distribution: SeedDistribution::from_curvature_or_source(
+                    *curvature,
+                    distribution.as_deref(),
+                )?,
+            };
+            seed_liquidity(params, program, transaction_config, compute_unit_price).await?;
+        }
+        PlanStep::SeedLiquidityByOperator {
+            lb_pair,
+            base_position_signer,
+            position_owner,
+            fee_owner,
+            lock_release_point,
+            amount,
+            min_price,
+            max_price,
+            curvature,
+            distribution,
+            ..
+        } => {
+            let position_base_kp = load_signer(base_position_signer)?;
+            let params = SeedLiquidityByOperatorParameters {
+                lb_pair: lb_pair.resolve(outputs)?,
+                base_pubkey: position_base_kp.pubkey(),
+                position_base_kp,
+                position_owner: position_owner.resolve(outputs)?,
+                fee_owner: fee_owner.resolve(outputs)?,
+                lock_release_point: *lock_release_point,
+                amount: *amount,
+                min_price: *min_price,
+                max_price: *max_price,
+                distribution: SeedDistribution::from_curvature_or_source(
+                    *curvature,
+                    distribution.as_deref(),
+                )?,
+            };
+            seed_liquidity_by_operator(params, program, transaction_config, compute_unit_price)
+                .await?;
+        }
+        PlanStep::SeedLiquiditySingleBin {
+            lb_pair,
+            base_position_signer,
+            position_owner_signer,
+            amount,
+            price,
+            selective_rounding,
+        } => {
+            let position_base_kp = load_signer(base_position_signer)?;
+            let params = SeedLiquiditySingleBinParameters {
+                lb_pair: lb_pair.resolve(outputs)?,
+                base_pubkey: position_base_kp.pubkey(),
+                position_base_kp,
+                position_owner_kp: load_signer(position_owner_signer)?,
+                amount: *amount,
+                price: *price,
+                selective_rounding: selective_rounding.clone(),
+            };
+            seed_liquidity_single_bin(params, program, transaction_config, compute_unit_price)
+                .await?;
+        }
+        PlanStep::SeedLiquiditySingleBinByOperator {
+            lb_pair,
+            base_position_signer,
+            position_owner,
+            fee_owner,
+            lock_release_point,
+            amount,
+            price,
+            selective_rounding,
+        } => {
+            let position_base_kp = load_signer(base_position_signer)?;
+            let params = SeedLiquiditySingleBinByOperatorParameters {
+                lb_pair: lb_pair.resolve(outputs)?,
+                base_pubkey: position_base_kp.pubkey(),
+                position_base_kp,
+                position_owner: position_owner.resolve(outputs)?,
+                fee_owner: fee_owner.resolve(outputs)?,
+                lock_release_point: *lock_release_point,
+                amount: *amount,
+                price: *price,
+                selective_rounding: selective_rounding.clone(),
+            };
+            seed_liquidity_single_bin_by_operator(
+                params,
+                program,
+                transaction_config,
+                compute_unit_price,
+            )
+            .await?;
+        }
+        PlanStep::SetActivationPoint {
+            lb_pair,
+            activation_point,
+        } => {
+            let params = SetActivationPointParam {
+                lb_pair: lb_pair.resolve(outputs)?,
+                activation_point: *activation_point,
+            };
+            set_activation_point(params, program, transaction_config).await?;
+        }
+        PlanStep::SetPreActivationDuration {
+            lb_pair,
+            pre_activation_duration,
+        } => {
+            let params = SetPreactivationDurationParam {
+                lb_pair: lb_pair.resolve(outputs)?,
+                pre_activation_duration: *pre_activation_duration,
+            };
+            set_pre_activation_duration(params, program, transaction_config).await?;
+        }
+        PlanStep::ClaimFee { position } => {
+            claim_fee(
+                position.resolve(outputs)?,
+                program,
+                transaction_config,
+                compute_unit_price,
+            )
+            .await?;
+        }
+    }
+
+    Ok(step_outputs)
+}
+
+#[derive(Debug)]
+pub struct RunPlanParameters {
+    pub plan_path: String,
+    pub checkpoint_path: Option<String>,
+}
+
+pub async fn run_plan<C: Deref<Target = impl Signer> + Clone>(
+    params: RunPlanParameters,
+    program: &Program<C>,
+    transaction_config: RpcSendTransactionConfig,
+    compute_unit_price: Option<Instruction>,
+) -> Result<()> {
+    let RunPlanParameters {
+        plan_path,
+        checkpoint_path,
+    } = params;
+
+    let plan = read_plan(&plan_path)?;
+    plan.validate()?;
+
+    let step_ids = plan.step_ids();
+    let checkpoint_path =
+        checkpoint_path.unwrap_or_else(|| format!("{}.checkpoint.json", plan_path));
+    let mut checkpoint = read_checkpoint(&checkpoint_path)?;
+
+    let mut outputs: HashMap<String, HashMap<String, Pubkey>> = HashMap::new();
+    let mut completed_step_ids = HashSet::new();
+
+    for completed_step in checkpoint.completed_steps.iter() {
+        ensure!(
+            step_ids.contains(&completed_step.id),
+            "Checkpoint {} contains step {} which is not in the plan",
+            checkpoint_path,
+            completed_step.id
+        );
+        let mut step_outputs = HashMap::new();
+        for (name, value) in completed_step.outputs.iter() {
+            step_outputs.insert(name.clone(), Pubkey::from_str(value)?);
+        }
+        outputs.insert(completed_step.id.clone(), step_outputs);
+        completed_step_ids.insert(completed_step.id.clone());
+    }
+
+    for (entry, step_id) in plan.steps.iter().zip(step_ids.into_iter()) {
+        if completed_step_ids.contains(&step_id) {
+            println!("Skip completed step {}", step_id);
+            continue;
+        }
+
+        println!("Run step {}", step_id);
+        let max_attempts = entry.step.max_attempts();
+        let mut retry_count = 0;
+        let step_outputs = loop {
+            match execute_step(
+                &entry.step,
+                &outputs,
+                program,
+                transaction_config,
+                compute_unit_price.clone(),
+            )
+            .await
+            {
+                std::result::Result::Ok(step_outputs) => break step_outputs,
+                Err(err) => {
+                    retry_count += 1;
+                    if retry_count >= max_attempts {
+                        return Err(err).context(format!(
+                            "Step {} failed. Rerun the plan to resume from checkpoint {}",
+                            step_id, checkpoint_path
+                        ));
+                    }
+                    println!("Error: {}", err);
+                    println!("Retry step {} ({}/{})", step_id, retry_count, max_attempts);
+                    tokio::time::sleep(Duration::from_secs(16)).await;
+                }
+            }
+        };
+
+        for (name, value) in step_outputs.iter() {
+            println!("{}.{} = {}", step_id, name, value);
+        }
+
+        checkpoint.completed_steps.push(CompletedStep {
+            id: step_id.clone(),
+            outputs: step_outputs
+                .iter()
+                .map(|(name, value)| (name.clone(), value.to_string()))
+                .collect(),
+        });
+        write_checkpoint(&checkpoint_path, &checkpoint)?;
+
+        outputs.insert(step_id, step_outputs);
+    }
+
+    println!("Plan completed");
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_plan(yaml: &str) -> Plan {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn test_validate_plan_with_reference() {
+        let plan = parse_plan(
+            r#"
+steps:
+  - id: pair
+    type: initialize_customizable_permissionless_pair
+    token_mint_x: So11111111111111111111111111111111111111112
+    token_mint_y: EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v
+    bin_step: 80
+    initial_price: 0.003
+    base_fee_bps: 200
+    activation_type: 1
+    has_alpha_vault: false
+    selective_rounding: up
+  - id: bin_arrays
+    type: initialize_bin_array_with_price_range
+    lb_pair: ${pair.lb_pair}
+    lower_price: 0.003
+    upper_price: 0.03
+  - id: seed
+    type: seed_liquidity
+    lb_pair: ${pair.lb_pair}
+    base_position_signer: base.json
+    position_owner_signer: owner.json
+    amount: 1000
+    min_price: 0.003
+    max_price: 0.03
+    curvature: 0.6
+    max_retries: 5
+  - id: activation
+    type: set_activation_point
+    lb_pair: ${pair.lb_pair}
+    activation_point: 1000
+"#,
+        );
+
+        assert!(plan.validate().is_ok());
+        assert_eq!(
+            plan.step_ids(),
+            vec!["pair", "bin_arrays", "seed", "activation"]
+        );
+        assert_eq!(plan.steps[1].step.max_attempts(), 1);
+        assert_eq!(plan.steps[2].step.max_attempts(), 5);
+    }
+
+    #[test]
+    fn test_validate_plan_rejects_invalid_reference() {
+        let forward_reference = parse_plan(
+            r#"
+steps:
+  - id: activation
+    type: set_activation_point
+    lb_pair: ${pair.lb_pair}
+    activation_point: 1000
+  - id: pair
+    type: initialize_pair
+    preset_parameter: So11111111111111111111111111111111111111112
+    token_mint_x: So11111111111111111111111111111111111111112
+    token_mint_y: EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v
+    initial_price: 1.0
+"#,
+        );
+        assert!(forward_reference.validate().is_err());
+
+        let unknown_output = parse_plan(
+            r#"
+steps:
+  - id: bin_arrays
+    type: initialize_bin_array_with_bin_range
+    lb_pair: So11111111111111111111111111111111111111112
+    lower_bin_id: 0
+    upper_bin_id: 10
+  - id: claim_fee
+    type: claim_fee
+    position: ${bin_arrays.lb_pair}
+"#,
+        );
+        assert!(unknown_output.validate().is_err());
+
+        let missing_id = serde_yaml::from_str::<Plan>(
+            r#"
+steps:
+  - type: set_activation_point
+    lb_pair: So11111111111111111111111111111111111111112
+    activation_point: 1000
+"#,
+        );
+        assert!(missing_id.is_err());
+
+        let dotted_id = parse_plan(
+            r#"
+steps:
+  - id: pair.v2
+    type: initialize_customizable_permissionless_pair
+    token_mint_x: So11111111111111111111111111111111111111112
+    token_mint_y: EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v
+    bin_step: 80
+    initial_price: 0.003
+    base_fee_bps: 200
+    activation_type: 1
+    has_alpha_vault: false
+    selective_rounding: up
+"#,
+        );
+        assert!(dotted_id.validate().is_err());
+    }
+}
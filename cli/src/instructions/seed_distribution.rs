@@ -0,0 +1,421 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::str::FromStr;
+
+use crate::instructions::seed_liquidity::{generate_amount_for_bins, get_ui_price_from_id};
+use crate::math::{get_id_from_price, price_per_token_to_per_lamport};
+use anyhow::*;
+use lb_clmm::math::u128x128_math::Rounding;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeedTranche {
+    /// UI price where the tranche starts
+    pub price: f64,
+    /// Weight of the tranche. Normalized against the sum of all tranches
+    pub amount: f64,
+}
+
+/// Shape of the x amount seeded between the min and max price.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SeedDistribution {
+    /// c(p) = amount * ((p - min_price) / (max_price - min_price)) ^ (1 / curvature)
+    Power { curvature: f64 },
+    /// Same amount of x per unit of price. Equivalent to a power curve with curvature 1
+    Linear,
+    /// c(p) = amount * (e^(growth * t) - 1) / (e^growth - 1), where t = (p - min_price) / (max_price - min_price)
+    Exponential { growth: f64 },
+    /// Each tranche covers the bins from its price up to the next tranche price, and its amount is spread
+    /// evenly across those bins. Bins below the first tranche price belong to the first tranche.
+    Tranches(Vec<SeedTranche>),
+}
+
+impl FromStr for SeedDistribution {
+    type Err = Error;
+
+    /// Parse `power:<curvature>`, `linear`, `exponential:<growth>`, `stepped:<price>=<amount>,...` or `csv:<path>`.
+    /// The csv file has one `price,amount` tranche per line.
+    fn from_str(s: &str) -> Result<Self> {
+        let (kind, value) = match s.split_once(':') {
+            Some((kind, value)) => (kind, Some(value)),
+            None => (s, None),
+        };
+
+        let distribution = match (kind, value) {
+            ("linear", None) => SeedDistribution::Linear,
+            ("power", Some(curvature)) => SeedDistribution::Power {
+                curvature: curvature.parse().context("Invalid curvature")?,
+            },
+            ("exponential", Some(growth)) => SeedDistribution::Exponential {
+                growth: growth.parse().context("Invalid growth")?,
+            },
+            ("stepped", Some(tranches)) => {
+                let tranches = tranches
+                    .split(',')
+                    .map(|tranche| {
+                        let (price, amount) = tranche
+                            .split_once('=')
+                            .context(format!("Invalid tranche {}", tranche))?;
+                        parse_tranche(price, amount)
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                SeedDistribution::Tranches(tranches)
+            }
+            ("csv", Some(path)) => SeedDistribution::Tranches(read_tranches_csv(path)?),
+            _ => bail!(
+                "Invalid distribution {}. Expected power:<curvature>, linear, exponential:<growth>, stepped:<price>=<amount>,... or csv:<path>",
+                s
+            ),
+        };
+
+        distribution.validate()?;
+
+        Ok(distribution)
+    }
+}
+
+fn parse_tranche(price: &str, amount: &str) -> Result<SeedTranche> {
+    Ok(SeedTranche {
+        price: price
+            .trim()
+            .parse()
+            .context(format!("Invalid tranche price {}", price))?,
+        amount: amount
+            .trim()
+            .parse()
+            .context(format!("Invalid tranche amount {}", amount))?,
+    })
+}
+
+fn read_tranches_csv(path: &str) -> Result<Vec<SeedTranche>> {
+    let file = File::open(path).context(format!("Distribution file {} not found", path))?;
+    let reader = BufReader::new(file);
+
+    let mut tranches = vec![];
+    for (idx, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (price, amount) = line
+            .split_once(',')
+            .context(format!("Invalid line {}. Expected price,amount", idx + 1))?;
+
+        // Header
+        if idx == 0 && price.trim().parse::<f64>().is_err() {
+            continue;
+        }
+
+        tranches.push(parse_tranche(price, amount)?);
+    }
+
+    Ok(tranches)
+}
+
+impl SeedDistribution {
+    /// Resolve the distribution from the legacy curvature argument or a distribution source. Exactly one must be set.
+    pub fn from_curvature_or_source(
+        curvature: Option<f64>,
+        distribution: Option<&str>,
+    ) -> Result<Self> {
+        match (curvature, distribution) {
+            (Some(curvature), None) => {
+                let distribution = SeedDistribution::Power { curvature };
+                distribution.validate()?;
+                Ok(distribution)
+            }
+            (None, Some(distribution)) => distribution.parse(),
+            (Some(_), Some(_)) => bail!("Provide either curvature or distribution, not both"),
+            (None, None) => bail!("Provide either curvature or distribution"),
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            SeedDistribution::Power { curvature } => {
+                ensure!(
+                    curvature.is_finite() && *curvature > 0.0,
+                    "curvature must be positive"
+                );
+            }
+            SeedDistribution::Linear => {}
+            SeedDistribution::Exponential { growth } => {
+                ensure!(
+                    growth.is_finite() && *growth != 0.0,
+                    "growth must be non zero"
+                );
+            }
+            SeedDistribution::Tranches(tranches) => {
+                ensure!(!tranches.is_empty(), "No tranche");
+                for tranche in tranches.iter() {
+                    ensure!(
+                        tranche.price.is_finite() && tranche.price > 0.0,
+                        "Tranche price must be positive"
+                    );
+                    ensure!(
+                        tranche.amount.is_finite() && tranche.amount > 0.0,
+                        "Tranche amount must be positive"
+                    );
+                }
+                for window in tranches.windows(2) {
+                    ensure!(
+                        window[0].price < window[1].price,
+                        "Tranche prices must be increasing"
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Amount of x for every bin from min_bin_id to max_bin_id - 1. The last bin is excluded the same way as
+    /// `generate_amount_for_bins`, and the amounts sum up to `amount`. Only the power curve may leave a bin empty.
+    pub fn generate_amount_for_bins(
+        &self,
+        bin_step: u16,
+        min_bin_id: i32,
+        max_bin_id: i32,
+        base_token_decimal: u8,
+        quote_token_decimal: u8,
+        amount: u64,
+    ) -> Result<Vec<(i32, u64)>> {
+        ensure!(min_bin_id < max_bin_id, "Invalid price range");
+
+        let min_price = get_ui_price_from_id(
+            bin_step,
+            min_bin_id,
+            base_token_decimal.into(),
+            quote_token_decimal.into(),
+        );
+        let max_price = get_ui_price_from_id(
+            bin_step,
+            max_bin_id,
+            base_token_decimal.into(),
+            quote_token_decimal.into(),
+        );
+
+        let bin_amounts = match self {
+            SeedDistribution::Power { curvature } => generate_amount_for_bins(
+                bin_step,
+                min_bin_id,
+                max_bin_id,
+                min_price,
+                max_price,
+                base_token_decimal,
+                quote_token_decimal,
+                amount,
+                1.0 / curvature,
+            ),
+            SeedDistribution::Linear => generate_amount_for_bins(
+                bin_step,
+                min_bin_id,
+                max_bin_id,
+                min_price,
+                max_price,
+                base_token_decimal,
+                quote_token_decimal,
+                amount,
+                1.0,
+            ),
+            SeedDistribution::Exponential { growth } => {
+                generate_amount_from_cumulative(min_bin_id, max_bin_id, amount, |bin_id| {
+                    let price = get_ui_price_from_id(
+                        bin_step,
+                        bin_id,
+                        base_token_decimal.into(),
+                        quote_token_decimal.into(),
+                    );
+                    let t = (price - min_price) / (max_price - min_price);
+                    (growth * t).exp_m1() / growth.exp_m1()
+                })
+            }
+            SeedDistribution::Tranches(tranches) => {
+                let mut tranche_start_bin_ids = vec![];
+                for (idx, tranche) in tranches.iter().enumerate() {
+                    let price_per_lamport = price_per_token_to_per_lamport(
+                        tranche.price,
+                        base_token_decimal,
+                        quote_token_decimal,
+                    )
+                    .context("price_per_token_to_per_lamport overflow")?;
+                    let start_bin_id =
+                        get_id_from_price(bin_step, &price_per_lamport, Rounding::Up)
+                            .context("get_id_from_price overflow")?;
+
+                    let start_bin_id = if idx == 0 { min_bin_id } else { start_bin_id };
+
+                    ensure!(
+                        start_bin_id < max_bin_id,
+                        "Tranche at price {} starts after max price",
+                        tranche.price
+                    );
+                    if let Some(previous_start_bin_id) = tranche_start_bin_ids.last() {
+                        ensure!(
+                            start_bin_id > *previous_start_bin_id,
+                            "Tranche before price {} covers no bin",
+                            tranche.price
+                        );
+                    }
+
+                    tranche_start_bin_ids.push(start_bin_id);
+                }
+
+                let mut cumulative_weights = vec![0.0f64];
+                let mut cumulative_weight = 0.0f64;
+                for bin_id in min_bin_id..max_bin_id {
+                    let tranche_idx = tranche_start_bin_ids
+                        .iter()
+                        .rposition(|start_bin_id| *start_bin_id <= bin_id)
+                        .context("Bin not covered by any tranche")?;
+                    let tranche_end_bin_id = tranche_start_bin_ids
+                        .get(tranche_idx + 1)
+                        .copied()
+                        .unwrap_or(max_bin_id);
+                    let tranche_bin_count = tranche_end_bin_id - tranche_start_bin_ids[tranche_idx];
+
+                    cumulative_weight += tranches[tranche_idx].amount / tranche_bin_count as f64;
+                    cumulative_weights.push(cumulative_weight);
+                }
+
+                generate_amount_from_cumulative(min_bin_id, max_bin_id, amount, |bin_id| {
+                    cumulative_weights[(bin_id - min_bin_id) as usize] / cumulative_weight
+                })
+            }
+        };
+
+        // The power curve of the curvature argument keeps its original behaviour, where a bin may be left empty
+        let is_power = matches!(self, SeedDistribution::Power { .. });
+        for (bin_id, bin_amount) in bin_amounts.iter().filter(|_| !is_power) {
+            ensure!(
+                *bin_amount > 0,
+                "Distribution leaves bin {} empty. Increase the amount or reduce the price range",
+                bin_id
+            );
+        }
+
+        Ok(bin_amounts)
+    }
+}
+
+/// Amount of each bin is c(bin_id + 1) - c(bin_id), where c(bin_id) = amount * cumulative(bin_id) rounded down.
+/// cumulative must be 0 at min_bin_id and 1 at max_bin_id.
+fn generate_amount_from_cumulative<F: Fn(i32) -> f64>(
+    min_bin_id: i32,
+    max_bin_id: i32,
+    amount: u64,
+    cumulative: F,
+) -> Vec<(i32, u64)> {
+    let get_c = |bin_id: i32| -> u64 {
+        if bin_id >= max_bin_id {
+            amount
+        } else if bin_id <= min_bin_id {
+            0
+        } else {
+            ((amount as f64 * cumulative(bin_id)) as u64).min(amount)
+        }
+    };
+
+    (min_bin_id..max_bin_id)
+        .map(|bin_id| {
+            let c1 = get_c(bin_id + 1);
+            let c0 = get_c(bin_id);
+            (bin_id, c1.saturating_sub(c0))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instructions::seed_liquidity::convert_min_max_ui_price_to_min_max_bin_id;
+
+    fn generate(distribution: &SeedDistribution) -> Result<Vec<(i32, u64)>> {
+        let (min_bin_id, max_bin_id) =
+            convert_min_max_ui_price_to_min_max_bin_id(80, 0.003, 0.03, 6, 6).unwrap();
+        distribution.generate_amount_for_bins(80, min_bin_id, max_bin_id, 6, 6, 100_000_000_000_000)
+    }
+
+    #[test]
+    fn test_parse_distribution() {
+        assert_eq!(
+            "power:0.6".parse::<SeedDistribution>().unwrap(),
+            SeedDistribution::Power { curvature: 0.6 }
+        );
+        assert_eq!(
+            "linear".parse::<SeedDistribution>().unwrap(),
+            SeedDistribution::Linear
+        );
+        assert_eq!(
+            "exponential:2.5".parse::<SeedDistribution>().unwrap(),
+            SeedDistribution::Exponential { growth: 2.5 }
+        );
+        assert_eq!(
+            "stepped:0.003=40,0.01=60"
+                .parse::<SeedDistribution>()
+                .unwrap(),
+            SeedDistribution::Tranches(vec![
+                SeedTranche {
+                    price: 0.003,
+                    amount: 40.0
+                },
+                SeedTranche {
+                    price: 0.01,
+                    amount: 60.0
+                },
+            ])
+        );
+        assert!("stepped:0.01=40,0.003=60"
+            .parse::<SeedDistribution>()
+            .is_err());
+        assert!("power:0".parse::<SeedDistribution>().is_err());
+        assert!("cubic".parse::<SeedDistribution>().is_err());
+    }
+
+    #[test]
+    fn test_generate_amount_for_bins_sum_to_amount() {
+        let distributions = [
+            SeedDistribution::Power { curvature: 0.6 },
+            SeedDistribution::Linear,
+            SeedDistribution::Exponential { growth: 3.0 },
+            SeedDistribution::Exponential { growth: -3.0 },
+            "stepped:0.003=20,0.01=50,0.02=30".parse().unwrap(),
+        ];
+
+        for distribution in distributions.iter() {
+            let bin_amounts = generate(distribution).unwrap();
+            let total: u64 = bin_amounts.iter().map(|(_, amount)| amount).sum();
+            assert_eq!(total, 100_000_000_000_000, "{:?}", distribution);
+        }
+    }
+
+    #[test]
+    fn test_empty_bin_allowed_for_power_curve_only() {
+        let (min_bin_id, max_bin_id) =
+            convert_min_max_ui_price_to_min_max_bin_id(80, 0.003, 0.03, 6, 6).unwrap();
+
+        let bin_amounts = SeedDistribution::from_curvature_or_source(Some(0.6), None)
+            .unwrap()
+            .generate_amount_for_bins(80, min_bin_id, max_bin_id, 6, 6, 10)
+            .unwrap();
+        assert!(bin_amounts.iter().any(|(_, amount)| *amount == 0));
+        let total: u64 = bin_amounts.iter().map(|(_, amount)| amount).sum();
+        assert_eq!(total, 10);
+
+        assert!(SeedDistribution::Linear
+            .generate_amount_for_bins(80, min_bin_id, max_bin_id, 6, 6, 10)
+            .is_err());
+    }
+
+    #[test]
+    fn test_tranche_amount_spread_evenly() {
+        let distribution: SeedDistribution = "stepped:0.003=50,0.01=50".parse().unwrap();
+        let bin_amounts = generate(&distribution).unwrap();
+
+        let (first_bin_id, first_amount) = bin_amounts[0];
+        let (second_bin_id, second_amount) = bin_amounts[1];
+        assert_eq!(first_bin_id + 1, second_bin_id);
+        assert!(first_amount.abs_diff(second_amount) <= 1);
+    }
+}
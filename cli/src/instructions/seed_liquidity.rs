@@ -3,17 +3,18 @@ use std::fs::File;
 use std::io::{BufReader, BufWriter, Write};
 use std::ops::Deref;
 
-use crate::instructions::utils::get_or_create_ata;
+use crate::instructions::seed_distribution::SeedDistribution;
+use crate::instructions::utils::{fetch_latest_position, get_or_create_ata};
 use crate::math::{get_id_from_price, price_per_token_to_per_lamport};
 use anchor_client::solana_client::rpc_config::RpcSendTransactionConfig;
 use anchor_client::solana_sdk::compute_budget::ComputeBudgetInstruction;
 use anchor_client::solana_sdk::instruction::Instruction;
-use anchor_client::solana_sdk::signature::Keypair;
 use anchor_client::{solana_sdk::pubkey::Pubkey, solana_sdk::signer::Signer, Program};
 use anchor_lang::InstructionData;
 use anchor_lang::ToAccountMetas;
 use anchor_spl::token::Mint;
 use anyhow::*;
+use commons::signer::SharedSigner;
 use lb_clmm::accounts;
 use lb_clmm::constants::{BASIS_POINT_MAX, MAX_BIN_PER_POSITION};
 use lb_clmm::instruction;
@@ -103,14 +104,14 @@ pub fn get_number_of_position_required_to_cover_range(
     Ok(position_required)
 }
 
-async fn get_or_create_position<C: Deref<Target = impl Signer> + Clone>(
+pub async fn get_or_create_position<C: Deref<Target = impl Signer> + Clone>(
     program: &Program<C>,
     lb_pair: Pubkey,
-    base_keypair: &Keypair,
+    base_keypair: &SharedSigner,
     lower_bin_id: i32,
     upper_bin_id: i32,
     width: i32,
-    owner: &Keypair,
+    owner: &SharedSigner,
     transaction_config: RpcSendTransactionConfig,
     compute_unit_price_ix: Option<Instruction>,
 ) -> Result<PositionV2> {
@@ -148,8 +149,8 @@ async fn get_or_create_position<C: Deref<Target = impl Signer> + Clone>(
 
         builder = builder
             .instruction(ix)
-            .signer(base_keypair.insecure_clone())
-            .signer(owner.insecure_clone());
+            .signer(base_keypair.clone())
+            .signer(owner.clone());
         let signature = builder
             .send_with_spinner_and_config(transaction_config)
             .await;
@@ -160,13 +161,14 @@ async fn get_or_create_position<C: Deref<Target = impl Signer> + Clone>(
         signature?;
     }
 
-    let position_state: PositionV2 = program.account(position).await?;
+    let position_state = fetch_latest_position(program, position).await?;
 
     Ok(position_state)
 }
 
-pub async fn deposit<C: Deref<Target = impl Signer> + Clone>(
-    program: &Program<C>,
+/// Instructions depositing `deposit_amount_x` of token X into the position bins, sent by `sender`.
+pub fn get_deposit_instructions(
+    sender: Pubkey,
     position: Pubkey,
     position_state: &PositionV2,
     lb_pair_state: &LbPair,
@@ -174,9 +176,8 @@ pub async fn deposit<C: Deref<Target = impl Signer> + Clone>(
     user_token_y: Pubkey,
     deposit_amount_x: u64,
     position_liquidity_distribution: Vec<BinLiquidityDistribution>,
-    transaction_config: RpcSendTransactionConfig,
     compute_unit_price_ix: Option<Instruction>,
-) -> Result<String> {
+) -> Result<Vec<Instruction>> {
     let (event_authority, _bump) = derive_event_authority_pda();
     let mut instructions = if let Some(compute_unit_price_ix) = compute_unit_price_ix {
         vec![
@@ -202,7 +203,7 @@ pub async fn deposit<C: Deref<Target = impl Signer> + Clone>(
             bin_array_bitmap_extension: None,
             bin_array_lower,
             bin_array_upper,
-            sender: program.payer(),
+            sender,
             event_authority,
             program: lb_clmm::ID,
             reserve_x: lb_pair_state.reserve_x,
@@ -225,6 +226,33 @@ pub async fn deposit<C: Deref<Target = impl Signer> + Clone>(
         .data(),
     });
 
+    Ok(instructions)
+}
+
+pub async fn deposit<C: Deref<Target = impl Signer> + Clone>(
+    program: &Program<C>,
+    position: Pubkey,
+    position_state: &PositionV2,
+    lb_pair_state: &LbPair,
+    user_token_x: Pubkey,
+    user_token_y: Pubkey,
+    deposit_amount_x: u64,
+    position_liquidity_distribution: Vec<BinLiquidityDistribution>,
+    transaction_config: RpcSendTransactionConfig,
+    compute_unit_price_ix: Option<Instruction>,
+) -> Result<String> {
+    let instructions = get_deposit_instructions(
+        program.payer(),
+        position,
+        position_state,
+        lb_pair_state,
+        user_token_x,
+        user_token_y,
+        deposit_amount_x,
+        position_liquidity_distribution,
+        compute_unit_price_ix,
+    )?;
+
     let builder = program.request();
     let builder = instructions
         .into_iter()
@@ -433,16 +461,15 @@ pub fn write_dust_deposit_state(path: &str, dust_deposit_state: &DustDepositStat
     Ok(())
 }
 
-#[derive(Debug)]
 pub struct SeedLiquidityParameters {
     pub lb_pair: Pubkey,
-    pub position_base_kp: Keypair,
+    pub position_base_kp: SharedSigner,
     pub amount: u64,
     pub min_price: f64,
     pub max_price: f64,
     pub base_pubkey: Pubkey,
-    pub position_owner_kp: Keypair,
-    pub curvature: f64,
+    pub position_owner_kp: SharedSigner,
+    pub distribution: SeedDistribution,
 }
 
 pub async fn seed_liquidity<C: Deref<Target = impl Signer> + Clone>(
@@ -459,7 +486,7 @@ pub async fn seed_liquidity<C: Deref<Target = impl Signer> + Clone>(
         max_price,
         position_owner_kp,
         base_pubkey,
-        curvature,
+        distribution,
     } = params;
 
     let progress_file_path = format!("{}_progress.json", lb_pair);
@@ -472,8 +499,6 @@ pub async fn seed_liquidity<C: Deref<Target = impl Signer> + Clone>(
         );
     }
 
-    let k = 1.0 / curvature;
-
     // For easier validation during jup launch through .env
     assert_eq!(
         position_base_kp.pubkey(),
@@ -534,17 +559,14 @@ pub async fn seed_liquidity<C: Deref<Target = impl Signer> + Clone>(
     )
     .await?;
 
-    let bins_amount = generate_amount_for_bins(
+    let bins_amount = distribution.generate_amount_for_bins(
         bin_step,
         min_bin_id,
         max_bin_id,
-        actual_min_price,
-        actual_max_price,
         token_mint_base.decimals,
         token_mint_quote.decimals,
         fund_amount,
-        k,
-    );
+    )?;
 
     let bins_amount_map: HashMap<i32, u64> = bins_amount
         .iter()
@@ -643,7 +665,7 @@ pub async fn seed_liquidity<C: Deref<Target = impl Signer> + Clone>(
                 let (position, _bump) =
                     derive_position_pda(lb_pair, position_base_kp.pubkey(), lower_bin_id, width);
 
-                let position_state = program.account::<PositionV2>(position).await?;
+                let position_state = fetch_latest_position(program, position).await?;
                 let position_liquidity_shares = position_state
                     .liquidity_shares
                     .iter()
@@ -684,7 +706,7 @@ pub async fn seed_liquidity<C: Deref<Target = impl Signer> + Clone>(
             let (position, _bump) =
                 derive_position_pda(lb_pair, position_base_kp.pubkey(), lower_bin_id, width);
 
-            let position_state: PositionV2 = program.account(position).await?;
+            let position_state = fetch_latest_position(program, position).await?;
 
             let position_share_snapshot =
                 position_share.get(&position).context("Missing snapshot")?;
@@ -750,7 +772,7 @@ pub async fn seed_liquidity<C: Deref<Target = impl Signer> + Clone>(
         let (position, _bump) =
             derive_position_pda(lb_pair, position_base_kp.pubkey(), lower_bin_id, width);
 
-        let position_state: PositionV2 = program.account(position).await?;
+        let position_state = fetch_latest_position(program, position).await?;
         // Don't deposit to the last bin because c(last_bin + 1) - c(last_bin) will > amount
         let upper_bin_id = std::cmp::min(position_state.upper_bin_id, max_bin_id - 1);
 
@@ -1,22 +1,22 @@
 use std::collections::HashMap;
 use std::ops::Deref;
 
+use crate::instructions::seed_distribution::SeedDistribution;
 use crate::instructions::seed_liquidity::{
     convert_min_max_ui_price_to_min_max_bin_id, create_position_bin_array_if_not_exists, deposit,
-    deposit_amount_to_deposit_parameter, generate_amount_for_bins,
-    generate_redistribute_amount_to_position_based_on_ratio,
+    deposit_amount_to_deposit_parameter, generate_redistribute_amount_to_position_based_on_ratio,
     get_number_of_position_required_to_cover_range, get_on_chain_bins_amount_x,
     get_ui_price_from_id, read_dust_deposit_state, to_wei_amount, write_dust_deposit_state,
 };
-use crate::instructions::utils::get_or_create_ata;
+use crate::instructions::utils::{fetch_latest_position, get_or_create_ata};
 use anchor_client::solana_client::rpc_config::RpcSendTransactionConfig;
 use anchor_client::solana_sdk::instruction::Instruction;
-use anchor_client::solana_sdk::signature::Keypair;
 use anchor_client::{solana_sdk::pubkey::Pubkey, solana_sdk::signer::Signer, Program};
 use anchor_lang::InstructionData;
 use anchor_lang::ToAccountMetas;
 use anchor_spl::token::{spl_token, Mint};
 use anyhow::*;
+use commons::signer::SharedSigner;
 use lb_clmm::accounts;
 use lb_clmm::constants::MAX_BIN_PER_POSITION;
 use lb_clmm::instruction;
@@ -30,7 +30,7 @@ use spl_associated_token_account::instruction::create_associated_token_account;
 async fn get_or_create_position<C: Deref<Target = impl Signer> + Clone>(
     program: &Program<C>,
     lb_pair: Pubkey,
-    base_keypair: &Keypair,
+    base_keypair: &SharedSigner,
     lower_bin_id: i32,
     upper_bin_id: i32,
     width: i32,
@@ -119,9 +119,7 @@ async fn get_or_create_position<C: Deref<Target = impl Signer> + Clone>(
             builder = builder.instruction(compute_unit_price_ix);
         }
 
-        builder = builder
-            .instruction(ix)
-            .signer(base_keypair.insecure_clone());
+        builder = builder.instruction(ix).signer(base_keypair.clone());
         let signature = builder
             .send_with_spinner_and_config(transaction_config)
             .await;
@@ -132,15 +130,14 @@ async fn get_or_create_position<C: Deref<Target = impl Signer> + Clone>(
         signature?;
     }
 
-    let position_state: PositionV2 = program.account(position).await?;
+    let position_state = fetch_latest_position(program, position).await?;
 
     Ok(position_state)
 }
 
-#[derive(Debug)]
 pub struct SeedLiquidityByOperatorParameters {
     pub lb_pair: Pubkey,
-    pub position_base_kp: Keypair,
+    pub position_base_kp: SharedSigner,
     pub amount: u64,
     pub min_price: f64,
     pub max_price: f64,
@@ -148,7 +145,7 @@ pub struct SeedLiquidityByOperatorParameters {
     pub position_owner: Pubkey,
     pub fee_owner: Pubkey,
     pub lock_release_point: u64,
-    pub curvature: f64,
+    pub distribution: SeedDistribution,
 }
 
 pub async fn seed_liquidity_by_operator<C: Deref<Target = impl Signer> + Clone>(
@@ -167,7 +164,7 @@ pub async fn seed_liquidity_by_operator<C: Deref<Target = impl Signer> + Clone>(
         fee_owner,
         lock_release_point,
         base_pubkey,
-        curvature,
+        distribution,
     } = params;
 
     let progress_file_path = format!("{}_progress.json", lb_pair);
@@ -180,8 +177,6 @@ pub async fn seed_liquidity_by_operator<C: Deref<Target = impl Signer> + Clone>(
         );
     }
 
-    let k = 1.0 / curvature;
-
     // For easier validation during jup launch through .env
     assert_eq!(
         position_base_kp.pubkey(),
@@ -242,17 +237,14 @@ pub async fn seed_liquidity_by_operator<C: Deref<Target = impl Signer> + Clone>(
     )
     .await?;
 
-    let bins_amount = generate_amount_for_bins(
+    let bins_amount = distribution.generate_amount_for_bins(
         bin_step,
         min_bin_id,
         max_bin_id,
-        actual_min_price,
-        actual_max_price,
         token_mint_base.decimals,
         token_mint_quote.decimals,
         fund_amount,
-        k,
-    );
+    )?;
 
     let bins_amount_map: HashMap<i32, u64> = bins_amount
         .iter()
@@ -353,7 +345,7 @@ pub async fn seed_liquidity_by_operator<C: Deref<Target = impl Signer> + Clone>(
                 let (position, _bump) =
                     derive_position_pda(lb_pair, position_base_kp.pubkey(), lower_bin_id, width);
 
-                let position_state = program.account::<PositionV2>(position).await?;
+                let position_state = fetch_latest_position(program, position).await?;
                 let position_liquidity_shares = position_state
                     .liquidity_shares
                     .iter()
@@ -394,7 +386,7 @@ pub async fn seed_liquidity_by_operator<C: Deref<Target = impl Signer> + Clone>(
             let (position, _bump) =
                 derive_position_pda(lb_pair, position_base_kp.pubkey(), lower_bin_id, width);
 
-            let position_state: PositionV2 = program.account(position).await?;
+            let position_state = fetch_latest_position(program, position).await?;
 
             let position_share_snapshot =
                 position_share.get(&position).context("Missing snapshot")?;
@@ -461,7 +453,7 @@ pub async fn seed_liquidity_by_operator<C: Deref<Target = impl Signer> + Clone>(
         let (position, _bump) =
             derive_position_pda(lb_pair, position_base_kp.pubkey(), lower_bin_id, width);
 
-        let position_state: PositionV2 = program.account(position).await?;
+        let position_state = fetch_latest_position(program, position).await?;
         // Don't deposit to the last bin because c(last_bin + 1) - c(last_bin) will > amount
         let upper_bin_id = std::cmp::min(position_state.upper_bin_id, max_bin_id - 1);
 
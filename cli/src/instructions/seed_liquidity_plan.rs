@@ -0,0 +1,921 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::ops::Deref;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::instructions::seed_distribution::SeedDistribution;
+use crate::instructions::seed_liquidity::*;
+use crate::instructions::utils::{fetch_latest_position, get_or_create_ata};
+use anchor_client::solana_client::rpc_config::RpcSendTransactionConfig;
+use anchor_client::solana_sdk::hash::Hash;
+use anchor_client::solana_sdk::instruction::Instruction;
+use anchor_client::solana_sdk::signature::Signature;
+use anchor_client::{solana_sdk::pubkey::Pubkey, solana_sdk::signer::Signer, Program};
+use anchor_spl::token::Mint;
+use anyhow::*;
+use commons::account_layout::fetch_position;
+use commons::signer::SharedSigner;
+use lb_clmm::constants::{BASIS_POINT_MAX, MAX_BIN_PER_POSITION};
+use lb_clmm::instructions::deposit::BinLiquidityDistribution;
+use lb_clmm::math::bin_math::get_liquidity;
+use lb_clmm::math::price_math::get_price_from_id;
+use lb_clmm::state::bin::{get_liquidity_share, BinArray};
+use lb_clmm::state::lb_pair::LbPair;
+use lb_clmm::state::position::PositionV2;
+use lb_clmm::utils::pda::*;
+use serde::{Deserialize, Serialize};
+use serde_json_any_key::*;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SeedDepositKind {
+    /// Deposit of the curve amount into the position bins
+    Principal,
+    /// Deposit of the precision loss, based on the bin amount ratio
+    Redistribute,
+    /// Deposit of the remaining dust into the last semi bin
+    Dust,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PlannedPosition {
+    pub position: Pubkey,
+    pub lower_bin_id: i32,
+    pub upper_bin_id: i32,
+    /// Liquidity shares of the position once every deposit landed
+    pub expected_liquidity_shares: Vec<u128>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PlannedDeposit {
+    pub kind: SeedDepositKind,
+    pub position: Pubkey,
+    pub amount_x: u64,
+    /// (bin id, distribution x) pairs
+    pub bin_liquidity_dist: Vec<(i32, u16)>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SeedLiquidityPlan {
+    pub lb_pair: Pubkey,
+    pub base: Pubkey,
+    pub owner: Pubkey,
+    pub bin_step: u16,
+    pub fund_amount: u64,
+    pub min_bin_id: i32,
+    pub max_bin_id: i32,
+    pub positions: Vec<PlannedPosition>,
+    pub deposits: Vec<PlannedDeposit>,
+    /// Amount x of every bin once every deposit landed
+    #[serde(with = "any_key_map")]
+    pub expected_bins_amount_x: HashMap<i32, u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PendingDeposit {
+    pub index: usize,
+    /// Signature of the deposit transaction, saved before it is sent
+    pub signature: String,
+    /// Blockhash of the deposit transaction. Once expired, a deposit which did not land never will.
+    pub recent_blockhash: String,
+}
+
+enum PendingDepositStatus {
+    Landed,
+    Failed(String),
+    Expired,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct SeedExecutionState {
+    pub lb_pair: Pubkey,
+    /// Deposit index -> signature
+    #[serde(with = "any_key_map")]
+    pub confirmed_deposits: HashMap<usize, String>,
+    pub pending_deposit: Option<PendingDeposit>,
+}
+
+#[derive(Default, Clone, Copy)]
+struct SimulatedBin {
+    amount_x: u64,
+    liquidity_supply: u128,
+}
+
+/// Offline replica of the bin and position state changes done by the program for x only deposits.
+struct SeedSimulation {
+    bin_step: u16,
+    bins: HashMap<i32, SimulatedBin>,
+    position_shares: HashMap<Pubkey, (i32, Vec<u128>)>,
+}
+
+impl SeedSimulation {
+    fn new(bin_step: u16) -> Self {
+        Self {
+            bin_step,
+            bins: HashMap::new(),
+            position_shares: HashMap::new(),
+        }
+    }
+
+    fn add_position(&mut self, position: Pubkey, lower_bin_id: i32) {
+        self.position_shares
+            .insert(position, (lower_bin_id, vec![0u128; MAX_BIN_PER_POSITION]));
+    }
+
+    fn deposit(&mut self, deposit: &PlannedDeposit) -> Result<u64> {
+        let (lower_bin_id, shares) = self
+            .position_shares
+            .get_mut(&deposit.position)
+            .context("Deposit to unknown position")?;
+
+        let mut total_deposited = 0u64;
+
+        for (bin_id, distribution_x) in deposit.bin_liquidity_dist.iter() {
+            let amount_into_bin: u64 = u128::from(deposit.amount_x)
+                .checked_mul((*distribution_x).into())
+                .context("amount_into_bin overflow")?
+                .checked_div(BASIS_POINT_MAX as u128)
+                .context("amount_into_bin overflow")?
+                .try_into()
+                .context("amount_into_bin overflow")?;
+
+            if amount_into_bin == 0 {
+                continue;
+            }
+
+            let price = get_price_from_id(*bin_id, self.bin_step)?;
+            let bin = self.bins.entry(*bin_id).or_default();
+
+            let in_liquidity = get_liquidity(amount_into_bin, 0, price)?;
+            let liquidity_share = if bin.liquidity_supply == 0 {
+                in_liquidity
+            } else {
+                let bin_liquidity = get_liquidity(bin.amount_x, 0, price)?;
+                get_liquidity_share(in_liquidity, bin_liquidity, bin.liquidity_supply)?
+            };
+
+            bin.amount_x = bin
+                .amount_x
+                .checked_add(amount_into_bin)
+                .context("amount_x overflow")?;
+            bin.liquidity_supply = bin
+                .liquidity_supply
+                .checked_add(liquidity_share)
+                .context("liquidity_supply overflow")?;
+
+            let idx: usize = bin_id
+                .checked_sub(*lower_bin_id)
+                .context("idx overflow")?
+                .try_into()
+                .context("Bin id out of position range")?;
+            let share = shares
+                .get_mut(idx)
+                .context("Bin id out of position range")?;
+            *share = share
+                .checked_add(liquidity_share)
+                .context("liquidity_shares overflow")?;
+
+            total_deposited = total_deposited
+                .checked_add(amount_into_bin)
+                .context("total_deposited overflow")?;
+        }
+
+        Ok(total_deposited)
+    }
+
+    fn total_amount_x(&self) -> u64 {
+        self.bins.values().map(|bin| bin.amount_x).sum()
+    }
+}
+
+fn to_planned_deposit(
+    kind: SeedDepositKind,
+    position: Pubkey,
+    amount_x: u64,
+    bin_liquidity_dist: Vec<BinLiquidityDistribution>,
+) -> PlannedDeposit {
+    PlannedDeposit {
+        kind,
+        position,
+        amount_x,
+        bin_liquidity_dist: bin_liquidity_dist
+            .into_iter()
+            .map(|dist| (dist.bin_id, dist.distribution_x))
+            .collect(),
+    }
+}
+
+/// Build every position and deposit required to seed `bins_amount` into the pair, including the redistribution
+/// of the precision loss and the final dust deposit, the same way `seed_liquidity` does on chain.
+pub fn build_seed_liquidity_plan(
+    lb_pair: Pubkey,
+    base: Pubkey,
+    owner: Pubkey,
+    bin_step: u16,
+    fund_amount: u64,
+    min_bin_id: i32,
+    max_bin_id: i32,
+    bins_amount: &HashMap<i32, u64>,
+) -> Result<SeedLiquidityPlan> {
+    ensure!(min_bin_id < max_bin_id, "Invalid price range");
+
+    let position_number = get_number_of_position_required_to_cover_range(min_bin_id, max_bin_id)?;
+    let width = MAX_BIN_PER_POSITION as i32;
+
+    let mut simulation = SeedSimulation::new(bin_step);
+    let mut positions = vec![];
+    let mut deposits = vec![];
+
+    for i in 0..position_number {
+        let lower_bin_id = min_bin_id + (width * i);
+        let upper_bin_id = lower_bin_id + width - 1;
+        let (position, _bump) = derive_position_pda(lb_pair, base, lower_bin_id, width);
+
+        simulation.add_position(position, lower_bin_id);
+        positions.push(PlannedPosition {
+            position,
+            lower_bin_id,
+            upper_bin_id,
+            expected_liquidity_shares: vec![],
+        });
+
+        // Don't deposit to the last bin because c(last_bin + 1) - c(last_bin) will > amount
+        let upper_bin_id = std::cmp::min(upper_bin_id, max_bin_id - 1);
+
+        let has_amount = (lower_bin_id..=upper_bin_id)
+            .any(|bin_id| bins_amount.get(&bin_id).copied().unwrap_or_default() > 0);
+
+        if !has_amount {
+            continue;
+        }
+
+        let (position_liquidity_distribution, deposit_amount_x) =
+            deposit_amount_to_deposit_parameter(bins_amount, lower_bin_id, upper_bin_id)?;
+
+        let deposit = to_planned_deposit(
+            SeedDepositKind::Principal,
+            position,
+            deposit_amount_x,
+            position_liquidity_distribution,
+        );
+        simulation.deposit(&deposit)?;
+        deposits.push(deposit);
+    }
+
+    // Redistribute leftover amount a.k.a precision loss back into bins based on bin amount with fund amount ratio
+    let total_amount_in_bins = simulation.total_amount_x();
+    let leftover = fund_amount
+        .checked_sub(total_amount_in_bins)
+        .context("leftover overflow")?;
+
+    if leftover > 0 {
+        let bins_amount_x: HashMap<i32, u64> = (min_bin_id..=max_bin_id)
+            .map(|bin_id| {
+                let amount_x = simulation
+                    .bins
+                    .get(&bin_id)
+                    .map(|bin| bin.amount_x)
+                    .unwrap_or_default();
+                (bin_id, amount_x)
+            })
+            .collect();
+
+        for planned_position in positions.iter() {
+            let lower_bin_id = planned_position.lower_bin_id;
+            let upper_bin_id = std::cmp::min(planned_position.upper_bin_id, max_bin_id - 1);
+
+            // Nothing to redistribute to this position when even its largest bin gets 0
+            let max_bin_amount_x = (lower_bin_id..=upper_bin_id)
+                .filter_map(|bin_id| bins_amount_x.get(&bin_id).copied())
+                .max()
+                .unwrap_or_default();
+            if u128::from(leftover) * u128::from(max_bin_amount_x)
+                < u128::from(total_amount_in_bins)
+            {
+                continue;
+            }
+
+            let (position_liquidity_distribution, position_redistributed_amount) =
+                generate_redistribute_amount_to_position_based_on_ratio(
+                    &bins_amount_x,
+                    total_amount_in_bins.into(),
+                    leftover.into(),
+                    lower_bin_id,
+                    upper_bin_id,
+                )?;
+
+            let deposit = to_planned_deposit(
+                SeedDepositKind::Redistribute,
+                planned_position.position,
+                position_redistributed_amount,
+                position_liquidity_distribution,
+            );
+            simulation.deposit(&deposit)?;
+            deposits.push(deposit);
+        }
+    }
+
+    // Shall be dust after redistribute
+    let leftover = fund_amount
+        .checked_sub(simulation.total_amount_x())
+        .context("leftover overflow")?;
+
+    if leftover > 0 {
+        let last_position = positions.last().context("No position planned")?;
+        let upper_bin_id = std::cmp::min(last_position.upper_bin_id, max_bin_id - 1);
+
+        let deposit = PlannedDeposit {
+            kind: SeedDepositKind::Dust,
+            position: last_position.position,
+            amount_x: leftover,
+            bin_liquidity_dist: vec![(upper_bin_id, BASIS_POINT_MAX as u16)],
+        };
+        simulation.deposit(&deposit)?;
+        deposits.push(deposit);
+    }
+
+    ensure!(
+        simulation.total_amount_x() == fund_amount,
+        "Planned deposits do not sum up to the fund amount"
+    );
+
+    for planned_position in positions.iter_mut() {
+        let (_, shares) = simulation
+            .position_shares
+            .get(&planned_position.position)
+            .context("Missing simulated position")?;
+        planned_position.expected_liquidity_shares = shares.clone();
+    }
+
+    let expected_bins_amount_x = simulation
+        .bins
+        .iter()
+        .filter(|(_, bin)| bin.amount_x > 0)
+        .map(|(bin_id, bin)| (*bin_id, bin.amount_x))
+        .collect();
+
+    Ok(SeedLiquidityPlan {
+        lb_pair,
+        base,
+        owner,
+        bin_step,
+        fund_amount,
+        min_bin_id,
+        max_bin_id,
+        positions,
+        deposits,
+        expected_bins_amount_x,
+    })
+}
+
+pub fn read_seed_liquidity_plan(path: &str) -> Result<SeedLiquidityPlan> {
+    let file = File::open(path).context(format!("Failed to open plan {}", path))?;
+    let reader = BufReader::new(file);
+    let plan = serde_json::from_reader(reader)?;
+    Ok(plan)
+}
+
+pub fn write_seed_liquidity_plan(path: &str, plan: &SeedLiquidityPlan) -> Result<()> {
+    let file = File::create(path)?;
+    let mut writer = BufWriter::new(file);
+    serde_json::to_writer_pretty(&mut writer, plan)?;
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn read_seed_execution_state(path: &str) -> Result<SeedExecutionState> {
+    let file = File::open(path);
+    match file {
+        std::io::Result::Ok(file) => {
+            let reader = BufReader::new(file);
+            let state = serde_json::from_reader(reader)?;
+            Ok(state)
+        }
+        std::io::Result::Err(_) => Ok(SeedExecutionState::default()),
+    }
+}
+
+pub fn write_seed_execution_state(path: &str, state: &SeedExecutionState) -> Result<()> {
+    let file = File::create(path)?;
+    let mut writer = BufWriter::new(file);
+    serde_json::to_writer(&mut writer, state)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Wait until the pending deposit lands, fails, or its blockhash expires.
+async fn get_pending_deposit_status<C: Deref<Target = impl Signer> + Clone>(
+    program: &Program<C>,
+    pending: &PendingDeposit,
+) -> Result<PendingDepositStatus> {
+    let rpc_client = program.rpc();
+    let commitment = rpc_client.commitment();
+    let signature = Signature::from_str(&pending.signature)?;
+    let recent_blockhash = Hash::from_str(&pending.recent_blockhash)?;
+
+    loop {
+        // Checked before the status, so that a deposit landing in between is not taken as expired
+        let blockhash_valid = rpc_client
+            .is_blockhash_valid(&recent_blockhash, commitment)
+            .await?;
+        let status = rpc_client
+            .get_signature_status_with_commitment_and_history(&signature, commitment, true)
+            .await?;
+
+        match status {
+            Some(std::result::Result::Ok(())) => return Ok(PendingDepositStatus::Landed),
+            Some(Err(err)) => return Ok(PendingDepositStatus::Failed(err.to_string())),
+            None if !blockhash_valid => return Ok(PendingDepositStatus::Expired),
+            None => {
+                println!(
+                    "Waiting for deposit #{} {}",
+                    pending.index, pending.signature
+                );
+                tokio::time::sleep(Duration::from_secs(2)).await;
+            }
+        }
+    }
+}
+
+fn get_position_liquidity_shares(position_state: &PositionV2) -> Vec<u128> {
+    position_state
+        .liquidity_shares
+        .iter()
+        .map(|share| share.as_u128())
+        .collect()
+}
+
+pub struct PlanSeedLiquidityParameters {
+    pub lb_pair: Pubkey,
+    pub base_pubkey: Pubkey,
+    pub position_owner: Pubkey,
+    pub amount: u64,
+    pub min_price: f64,
+    pub max_price: f64,
+    pub distribution: SeedDistribution,
+    pub plan_path: String,
+}
+
+pub async fn plan_seed_liquidity<C: Deref<Target = impl Signer> + Clone>(
+    params: PlanSeedLiquidityParameters,
+    program: &Program<C>,
+) -> Result<()> {
+    let PlanSeedLiquidityParameters {
+        lb_pair,
+        base_pubkey,
+        position_owner,
+        amount,
+        min_price,
+        max_price,
+        distribution,
+        plan_path,
+    } = params;
+
+    let lb_pair_state: LbPair = program.account(lb_pair).await?;
+    let bin_step = lb_pair_state.bin_step;
+
+    let token_mint_base: Mint = program.account(lb_pair_state.token_x_mint).await?;
+    let token_mint_quote: Mint = program.account(lb_pair_state.token_y_mint).await?;
+
+    let fund_amount = to_wei_amount(amount, token_mint_base.decimals)?;
+
+    let (min_bin_id, max_bin_id) = convert_min_max_ui_price_to_min_max_bin_id(
+        bin_step,
+        min_price,
+        max_price,
+        token_mint_base.decimals,
+        token_mint_quote.decimals,
+    )?;
+
+    ensure!(min_bin_id < max_bin_id, "Invalid price range");
+
+    let actual_min_price = get_ui_price_from_id(
+        bin_step,
+        min_bin_id,
+        token_mint_base.decimals.into(),
+        token_mint_quote.decimals.into(),
+    );
+    let actual_max_price = get_ui_price_from_id(
+        bin_step,
+        max_bin_id,
+        token_mint_base.decimals.into(),
+        token_mint_quote.decimals.into(),
+    );
+
+    let bins_amount: HashMap<i32, u64> = distribution
+        .generate_amount_for_bins(
+            bin_step,
+            min_bin_id,
+            max_bin_id,
+            token_mint_base.decimals,
+            token_mint_quote.decimals,
+            fund_amount,
+        )?
+        .into_iter()
+        .collect();
+
+    let plan = build_seed_liquidity_plan(
+        lb_pair,
+        base_pubkey,
+        position_owner,
+        bin_step,
+        fund_amount,
+        min_bin_id,
+        max_bin_id,
+        &bins_amount,
+    )?;
+
+    println!(
+        "Seed plan. Actual min price: {} Actual max price: {} Min bin id: {} Max bin id: {} Positions: {} Deposits: {}",
+        actual_min_price,
+        actual_max_price,
+        min_bin_id,
+        max_bin_id,
+        plan.positions.len(),
+        plan.deposits.len()
+    );
+
+    for (index, deposit) in plan.deposits.iter().enumerate() {
+        println!(
+            "#{} {:?} position {} amount x {} bins {}",
+            index,
+            deposit.kind,
+            deposit.position,
+            deposit.amount_x,
+            deposit.bin_liquidity_dist.len()
+        );
+    }
+
+    write_seed_liquidity_plan(&plan_path, &plan)?;
+    println!("Plan written to {}", plan_path);
+
+    Ok(())
+}
+
+pub struct ExecuteSeedLiquidityPlanParameters {
+    pub plan_path: String,
+    pub state_path: Option<String>,
+    pub position_base_kp: SharedSigner,
+    pub position_owner_kp: SharedSigner,
+}
+
+pub async fn execute_seed_liquidity_plan<C: Deref<Target = impl Signer> + Clone>(
+    params: ExecuteSeedLiquidityPlanParameters,
+    program: &Program<C>,
+    transaction_config: RpcSendTransactionConfig,
+    compute_unit_price: Option<Instruction>,
+) -> Result<()> {
+    let ExecuteSeedLiquidityPlanParameters {
+        plan_path,
+        state_path,
+        position_base_kp,
+        position_owner_kp,
+    } = params;
+
+    let plan = read_seed_liquidity_plan(&plan_path)?;
+    let state_path = state_path.unwrap_or(format!("{}.state.json", plan_path));
+
+    let mut state = read_seed_execution_state(&state_path)?;
+    if state.lb_pair == Pubkey::default() {
+        state.lb_pair = plan.lb_pair;
+    }
+    ensure!(
+        state.lb_pair == plan.lb_pair,
+        "Invalid seed execution state file"
+    );
+
+    ensure!(
+        position_base_kp.pubkey() == plan.base,
+        "Invalid position base key"
+    );
+    ensure!(
+        position_owner_kp.pubkey() == plan.owner,
+        "Invalid position owner"
+    );
+
+    let lb_pair = plan.lb_pair;
+    let lb_pair_state: LbPair = program.account(lb_pair).await?;
+
+    let user_token_x = get_or_create_ata(
+        program,
+        transaction_config,
+        lb_pair_state.token_x_mint,
+        program.payer(),
+        compute_unit_price.clone(),
+    )
+    .await?;
+
+    let user_token_y = get_or_create_ata(
+        program,
+        transaction_config,
+        lb_pair_state.token_y_mint,
+        program.payer(),
+        compute_unit_price.clone(),
+    )
+    .await?;
+
+    let width = MAX_BIN_PER_POSITION as i32;
+
+    for planned_position in plan.positions.iter() {
+        create_position_bin_array_if_not_exists(
+            program,
+            lb_pair,
+            planned_position.lower_bin_id,
+            transaction_config,
+            compute_unit_price.clone(),
+        )
+        .await?;
+
+        get_or_create_position(
+            program,
+            lb_pair,
+            &position_base_kp,
+            planned_position.lower_bin_id,
+            planned_position.upper_bin_id,
+            width,
+            &position_owner_kp,
+            transaction_config,
+            compute_unit_price.clone(),
+        )
+        .await?;
+    }
+
+    let rpc_client = program.rpc();
+
+    for (index, deposit_step) in plan.deposits.iter().enumerate() {
+        if state.confirmed_deposits.contains_key(&index) {
+            continue;
+        }
+
+        // The previous run sent this deposit but stopped before it was confirmed. Only resend it once its transaction
+        // failed or can no longer land.
+        if let Some(pending) = state.pending_deposit.clone() {
+            if pending.index == index {
+                match get_pending_deposit_status(program, &pending).await? {
+                    PendingDepositStatus::Landed => {
+                        println!("Deposit #{} already landed on chain", index);
+                        state.confirmed_deposits.insert(index, pending.signature);
+                        state.pending_deposit = None;
+                        write_seed_execution_state(&state_path, &state)?;
+                        continue;
+                    }
+                    PendingDepositStatus::Failed(err) => {
+                        println!("Deposit #{} {} failed: {}", index, pending.signature, err);
+                    }
+                    PendingDepositStatus::Expired => {
+                        println!("Deposit #{} {} expired", index, pending.signature);
+                    }
+                }
+            }
+        }
+
+        let position_state = fetch_latest_position(program, deposit_step.position).await?;
+
+        println!(
+            "Deposit #{} {:?} amount x {}",
+            index, deposit_step.kind, deposit_step.amount_x
+        );
+
+        let position_liquidity_distribution = deposit_step
+            .bin_liquidity_dist
+            .iter()
+            .map(|(bin_id, distribution_x)| BinLiquidityDistribution {
+                bin_id: *bin_id,
+                distribution_x: *distribution_x,
+                distribution_y: 0,
+            })
+            .collect();
+
+        let instructions = get_deposit_instructions(
+            program.payer(),
+            deposit_step.position,
+            &position_state,
+            &lb_pair_state,
+            user_token_x,
+            user_token_y,
+            deposit_step.amount_x,
+            position_liquidity_distribution,
+            compute_unit_price.clone(),
+        )?;
+        let builder = instructions
+            .into_iter()
+            .fold(program.request(), |bld, ix| bld.instruction(ix));
+        let transaction = builder.signed_transaction().await?;
+
+        state.pending_deposit = Some(PendingDeposit {
+            index,
+            signature: transaction.signatures[0].to_string(),
+            recent_blockhash: transaction.message.recent_blockhash.to_string(),
+        });
+        write_seed_execution_state(&state_path, &state)?;
+
+        let signature = rpc_client
+            .send_and_confirm_transaction_with_spinner_and_config(
+                &transaction,
+                rpc_client.commitment(),
+                transaction_config,
+            )
+            .await;
+        println!("Deposit #{}. Sig: {:#?}", index, signature);
+
+        state
+            .confirmed_deposits
+            .insert(index, signature?.to_string());
+        state.pending_deposit = None;
+        write_seed_execution_state(&state_path, &state)?;
+    }
+
+    println!(
+        "All {} deposits confirmed. Run verify to compare on chain state with the plan",
+        plan.deposits.len()
+    );
+
+    Ok(())
+}
+
+pub struct VerifySeedLiquidityPlanParameters {
+    pub plan_path: String,
+}
+
+pub async fn verify_seed_liquidity_plan<C: Deref<Target = impl Signer> + Clone>(
+    params: VerifySeedLiquidityPlanParameters,
+    program: &Program<C>,
+) -> Result<()> {
+    let VerifySeedLiquidityPlanParameters { plan_path } = params;
+
+    let plan = read_seed_liquidity_plan(&plan_path)?;
+    let lb_pair = plan.lb_pair;
+
+    let mut drift_count = 0;
+
+    let start_bin_array_index = BinArray::bin_id_to_bin_array_index(plan.min_bin_id)?;
+    let end_bin_array_index = BinArray::bin_id_to_bin_array_index(plan.max_bin_id)?;
+
+    let mut on_chain_bins_amount_x = HashMap::new();
+    for bin_array_idx in start_bin_array_index..=end_bin_array_index {
+        let (bin_array_pubkey, _bump) = derive_bin_array_pda(lb_pair, bin_array_idx.into());
+        let Result::Ok(bin_array) = program.account::<BinArray>(bin_array_pubkey).await else {
+            println!("Bin array {} not found", bin_array_idx);
+            continue;
+        };
+        let (mut bin_id, _) = BinArray::get_bin_array_lower_upper_bin_id(bin_array_idx)?;
+        for bin in bin_array.bins.iter() {
+            on_chain_bins_amount_x.insert(bin_id, bin.amount_x);
+            bin_id += 1;
+        }
+    }
+
+    let mut total_on_chain_amount_x = 0u64;
+    for bin_id in plan.min_bin_id..=plan.max_bin_id {
+        let expected = plan
+            .expected_bins_amount_x
+            .get(&bin_id)
+            .copied()
+            .unwrap_or_default();
+        let actual = on_chain_bins_amount_x
+            .get(&bin_id)
+            .copied()
+            .unwrap_or_default();
+
+        total_on_chain_amount_x = total_on_chain_amount_x
+            .checked_add(actual)
+            .context("total_on_chain_amount_x overflow")?;
+
+        if expected != actual {
+            drift_count += 1;
+            println!(
+                "Bin {} amount x drift. Expected {} On chain {} Diff {}",
+                bin_id,
+                expected,
+                actual,
+                i128::from(actual) - i128::from(expected)
+            );
+        }
+    }
+
+    for planned_position in plan.positions.iter() {
+        let Result::Ok((_layout, position_state)) =
+            fetch_position(&program.rpc(), planned_position.position).await
+        else {
+            drift_count += 1;
+            println!("Position {} not found", planned_position.position);
+            continue;
+        };
+
+        let liquidity_shares = get_position_liquidity_shares(&position_state);
+        for (i, expected) in planned_position
+            .expected_liquidity_shares
+            .iter()
+            .enumerate()
+        {
+            let actual = liquidity_shares.get(i).copied().unwrap_or_default();
+            if *expected != actual {
+                drift_count += 1;
+                println!(
+                    "Position {} bin {} liquidity share drift. Expected {} On chain {}",
+                    planned_position.position,
+                    planned_position.lower_bin_id + i as i32,
+                    expected,
+                    actual
+                );
+            }
+        }
+    }
+
+    println!(
+        "Fund amount {} On chain amount x {} Drift {}",
+        plan.fund_amount, total_on_chain_amount_x, drift_count
+    );
+
+    ensure!(
+        drift_count == 0,
+        "On chain state drifted from the plan in {} places",
+        drift_count
+    );
+    println!("On chain state matches the plan");
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_seed_liquidity_plan_deposits_fund_amount() {
+        let bin_step = 80;
+        let base_decimals = 6;
+        let quote_decimals = 6;
+        let fund_amount = to_wei_amount(100_000_000, base_decimals).unwrap();
+
+        let (min_bin_id, max_bin_id) = convert_min_max_ui_price_to_min_max_bin_id(
+            bin_step,
+            0.003,
+            0.03,
+            base_decimals,
+            quote_decimals,
+        )
+        .unwrap();
+
+        let min_price = get_ui_price_from_id(
+            bin_step,
+            min_bin_id,
+            base_decimals.into(),
+            quote_decimals.into(),
+        );
+        let max_price = get_ui_price_from_id(
+            bin_step,
+            max_bin_id,
+            base_decimals.into(),
+            quote_decimals.into(),
+        );
+
+        let bins_amount: HashMap<i32, u64> = generate_amount_for_bins(
+            bin_step,
+            min_bin_id,
+            max_bin_id,
+            min_price,
+            max_price,
+            base_decimals,
+            quote_decimals,
+            fund_amount,
+            1.0 / 0.6,
+        )
+        .into_iter()
+        .collect();
+
+        let plan = build_seed_liquidity_plan(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            bin_step,
+            fund_amount,
+            min_bin_id,
+            max_bin_id,
+            &bins_amount,
+        )
+        .unwrap();
+
+        let total_amount_x: u64 = plan.expected_bins_amount_x.values().sum();
+        assert_eq!(total_amount_x, fund_amount);
+        assert!(!plan.expected_bins_amount_x.contains_key(&max_bin_id));
+
+        let position_number =
+            get_number_of_position_required_to_cover_range(min_bin_id, max_bin_id).unwrap();
+        assert_eq!(plan.positions.len(), position_number as usize);
+
+        for deposit in plan.deposits.iter() {
+            let position = plan
+                .positions
+                .iter()
+                .find(|p| p.position == deposit.position)
+                .unwrap();
+            for (bin_id, _) in deposit.bin_liquidity_dist.iter() {
+                assert!(*bin_id >= position.lower_bin_id && *bin_id <= position.upper_bin_id);
+                assert!(*bin_id < max_bin_id);
+            }
+        }
+    }
+}
@@ -4,7 +4,7 @@ use anchor_client::{
     solana_client::rpc_config::RpcSendTransactionConfig,
     solana_sdk::{
         compute_budget::ComputeBudgetInstruction, instruction::Instruction, pubkey::Pubkey,
-        signature::Keypair, signer::Signer,
+        signer::Signer,
     },
     Program,
 };
@@ -12,6 +12,7 @@ use anchor_lang::InstructionData;
 use anchor_lang::ToAccountMetas;
 use anchor_spl::token::{spl_token, Mint};
 use anyhow::{Context, Result};
+use commons::signer::SharedSigner;
 use lb_clmm::{
     accounts, instruction,
     instructions::deposit::{BinLiquidityDistribution, LiquidityParameter},
@@ -28,13 +29,16 @@ use spl_associated_token_account::{
 
 use crate::{
     args::SelectiveRounding,
-    instructions::{seed_liquidity::to_wei_amount, utils::get_or_create_ata},
+    instructions::{
+        seed_liquidity::to_wei_amount,
+        utils::{fetch_latest_position, get_or_create_ata},
+    },
     math::{get_id_from_price, get_precise_id_from_price, price_per_token_to_per_lamport},
 };
 
 pub struct SeedLiquiditySingleBinByOperatorParameters {
     pub lb_pair: Pubkey,
-    pub position_base_kp: Keypair,
+    pub position_base_kp: SharedSigner,
     pub amount: u64,
     pub price: f64,
     pub position_owner: Pubkey,
@@ -117,6 +121,20 @@ pub async fn seed_liquidity_single_bin_by_operator<C: Deref<Target = impl Signer
     let (event_authority, _bump) = derive_event_authority_pda();
     let (position, _bump) = derive_position_pda(lb_pair, base_pubkey, bin_id, 1);
 
+    // The transaction of an earlier run may have landed
+    let position_exists = program.rpc().get_account_data(&position).await.is_ok();
+    if position_exists {
+        let position_state = fetch_latest_position(program, position).await?;
+        if position_state
+            .liquidity_shares
+            .iter()
+            .any(|share| share.as_u128() > 0)
+        {
+            println!("Position {position} is already seeded");
+            return Ok(());
+        }
+    }
+
     let lower_bin_array_index = BinArray::bin_id_to_bin_array_index(bin_id)?;
     let upper_bin_array_index = lower_bin_array_index + 1;
 
@@ -165,7 +183,7 @@ pub async fn seed_liquidity_single_bin_by_operator<C: Deref<Target = impl Signer
         (lower_bin_array, lower_bin_array_index),
         (upper_bin_array, upper_bin_array_index),
     ] {
-        if program.rpc().get_account(&lower_bin_array).await.is_err() {
+        if program.rpc().get_account(&bin_array).await.is_err() {
             let initialize_bin_array_ix = Instruction {
                 program_id: lb_clmm::ID,
                 accounts: accounts::InitializeBinArray {
@@ -249,7 +267,9 @@ pub async fn seed_liquidity_single_bin_by_operator<C: Deref<Target = impl Signer
         .data(),
     };
 
-    instructions.push(initialize_position_ix);
+    if !position_exists {
+        instructions.push(initialize_position_ix);
+    }
 
     let deposit_ix = Instruction {
         program_id: lb_clmm::ID,
@@ -289,7 +309,7 @@ pub async fn seed_liquidity_single_bin_by_operator<C: Deref<Target = impl Signer
     instructions.push(deposit_ix);
 
     let mut builder = program.request();
-    builder = builder.signer(position_base_kp.insecure_clone());
+    builder = builder.signer(position_base_kp.clone());
     builder = instructions
         .into_iter()
         .fold(builder, |builder, ix| builder.instruction(ix));
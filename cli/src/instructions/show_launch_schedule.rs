@@ -0,0 +1,336 @@
+use std::ops::Deref;
+
+use anchor_client::solana_sdk::clock::Clock;
+use anchor_client::{solana_sdk::pubkey::Pubkey, solana_sdk::signer::Signer, Program};
+use anyhow::*;
+use chrono::DateTime;
+use lb_clmm::constants::{FIVE_MINUTES_SLOT_BUFFER, FIVE_MINUTES_TIME_BUFFER};
+use lb_clmm::pair_action_access::{
+    get_current_point, get_lb_pair_type_access_validator_with_current_point, ActivationType,
+};
+use lb_clmm::state::lb_pair::{LbPair, PairType};
+
+/// Used when the cluster doesn't return any performance sample
+const DEFAULT_SECONDS_PER_SLOT: f64 = 0.4;
+
+#[derive(Debug)]
+pub struct ShowLaunchScheduleParameters {
+    pub lb_pair: Pubkey,
+    /// Planned SetActivationPoint value to check
+    pub new_activation_point: Option<u64>,
+    /// Planned SetPreActivationDuration value to check
+    pub new_pre_activation_duration: Option<u64>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct LaunchMilestone {
+    pub point: u64,
+    pub description: String,
+}
+
+/// Launch milestones of the pair sorted by point. Empty for permissionless pairs, or when the activation point is
+/// not set.
+pub fn get_launch_milestones(lb_pair: &LbPair) -> Result<Vec<LaunchMilestone>> {
+    let pair_type = lb_pair.pair_type()?;
+    if pair_type == PairType::Permissionless || lb_pair.activation_point == u64::MAX {
+        return Ok(vec![]);
+    }
+
+    let activation_type = ActivationType::try_from(lb_pair.activation_type)?;
+    let (deposit_close_idle_duration, last_join_buffer) = match activation_type {
+        ActivationType::Slot => (FIVE_MINUTES_SLOT_BUFFER, FIVE_MINUTES_SLOT_BUFFER),
+        ActivationType::Timestamp => (FIVE_MINUTES_TIME_BUFFER, FIVE_MINUTES_TIME_BUFFER),
+    };
+
+    let activation_point = lb_pair.activation_point;
+    let mut milestones = vec![];
+
+    if lb_pair.pre_activation_swap_address != Pubkey::default() {
+        let pre_activation_point = activation_point.saturating_sub(lb_pair.pre_activation_duration);
+
+        milestones.push(LaunchMilestone {
+            point: pre_activation_point,
+            description: format!(
+                "Pre-activation swap address {} can swap",
+                lb_pair.pre_activation_swap_address
+            ),
+        });
+
+        if pair_type == PairType::Permission {
+            let vault_last_join_point =
+                pre_activation_point.saturating_sub(deposit_close_idle_duration);
+            milestones.push(LaunchMilestone {
+                point: vault_last_join_point,
+                description: "Alpha vault deposits close".to_string(),
+            });
+            milestones.push(LaunchMilestone {
+                point: vault_last_join_point.saturating_sub(last_join_buffer),
+                description: "Last join point with buffer. SetActivationPoint and SetPreActivationDuration must keep it in the future".to_string(),
+            });
+            milestones.push(LaunchMilestone {
+                point: pre_activation_point,
+                description: "Pre-activation swap address can no longer be updated".to_string(),
+            });
+        }
+    }
+
+    milestones.push(LaunchMilestone {
+        point: activation_point,
+        description: "Swap opens".to_string(),
+    });
+    milestones.push(LaunchMilestone {
+        point: activation_point,
+        description: "Quote token deposit into the active bin opens".to_string(),
+    });
+    milestones.push(LaunchMilestone {
+        point: activation_point,
+        description: "Position initialization by operator closes".to_string(),
+    });
+
+    match pair_type {
+        PairType::Permission => milestones.push(LaunchMilestone {
+            point: activation_point,
+            description: "Activation point can no longer be updated".to_string(),
+        }),
+        PairType::CustomizablePermissionless => milestones.push(LaunchMilestone {
+            point: activation_point.saturating_add(1),
+            description: "Ask side liquidity withdrawal opens".to_string(),
+        }),
+        PairType::Permissionless => {}
+    }
+
+    milestones.sort_by_key(|milestone| milestone.point);
+
+    Ok(milestones)
+}
+
+fn format_duration(seconds: i64) -> String {
+    let abs_seconds = seconds.unsigned_abs();
+    let duration = format!(
+        "{}d {}h {}m {}s",
+        abs_seconds / 86_400,
+        abs_seconds % 86_400 / 3_600,
+        abs_seconds % 3_600 / 60,
+        abs_seconds % 60
+    );
+    if seconds >= 0 {
+        format!("in {}", duration)
+    } else {
+        format!("{} ago", duration)
+    }
+}
+
+struct WallClock {
+    activation_type: ActivationType,
+    current_slot: u64,
+    current_timestamp: i64,
+    seconds_per_slot: f64,
+}
+
+impl WallClock {
+    /// Estimated unix timestamp of the point
+    fn estimate_timestamp(&self, point: u64) -> i64 {
+        match self.activation_type {
+            ActivationType::Timestamp => point as i64,
+            ActivationType::Slot => {
+                let slot_delta = point as f64 - self.current_slot as f64;
+                self.current_timestamp + (slot_delta * self.seconds_per_slot) as i64
+            }
+        }
+    }
+
+    fn describe(&self, point: u64) -> String {
+        let timestamp = self.estimate_timestamp(point);
+        let date = DateTime::from_timestamp(timestamp, 0)
+            .map(|date| date.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+            .unwrap_or_else(|| "out of range".to_string());
+        let estimate = match self.activation_type {
+            ActivationType::Slot => "~",
+            ActivationType::Timestamp => "",
+        };
+        format!(
+            "{}{} ({})",
+            estimate,
+            date,
+            format_duration(timestamp - self.current_timestamp)
+        )
+    }
+}
+
+fn print_schedule(lb_pair: &LbPair, wall_clock: &WallClock, current_point: u64) -> Result<()> {
+    let milestones = get_launch_milestones(lb_pair)?;
+    if milestones.is_empty() {
+        println!("  No launch schedule. Permissionless pair or activation point not set");
+        return Ok(());
+    }
+
+    for milestone in milestones {
+        let passed = if milestone.point <= current_point {
+            " [passed]"
+        } else {
+            ""
+        };
+        println!(
+            "  {} {}: {}{}",
+            milestone.point,
+            wall_clock.describe(milestone.point),
+            milestone.description,
+            passed
+        );
+    }
+
+    Ok(())
+}
+
+pub async fn show_launch_schedule<C: Deref<Target = impl Signer> + Clone>(
+    params: ShowLaunchScheduleParameters,
+    program: &Program<C>,
+) -> Result<()> {
+    let ShowLaunchScheduleParameters {
+        lb_pair,
+        new_activation_point,
+        new_pre_activation_duration,
+    } = params;
+
+    let rpc_client = program.rpc();
+    let lb_pair_state: LbPair = program.account(lb_pair).await?;
+
+    let clock = rpc_client.get_account(&Clock::id()).await.map(|account| {
+        let clock: Clock = bincode::deserialize(account.data.as_ref())?;
+        Ok(clock)
+    })??;
+
+    let activation_type = ActivationType::try_from(lb_pair_state.activation_type)?;
+
+    let seconds_per_slot = match activation_type {
+        ActivationType::Timestamp => DEFAULT_SECONDS_PER_SLOT,
+        ActivationType::Slot => {
+            let samples = rpc_client
+                .get_recent_performance_samples(Some(60))
+                .await
+                .unwrap_or_default();
+            let total_slots: u64 = samples.iter().map(|sample| sample.num_slots).sum();
+            let total_seconds: u64 = samples
+                .iter()
+                .map(|sample| u64::from(sample.sample_period_secs))
+                .sum();
+            if total_slots > 0 {
+                total_seconds as f64 / total_slots as f64
+            } else {
+                DEFAULT_SECONDS_PER_SLOT
+            }
+        }
+    };
+
+    let wall_clock = WallClock {
+        activation_type,
+        current_slot: clock.slot,
+        current_timestamp: clock.unix_timestamp,
+        seconds_per_slot,
+    };
+
+    let current_point = get_current_point(&lb_pair_state, clock.slot, clock.unix_timestamp as u64)?;
+
+    println!(
+        "Pair {} type {:?} activation type {:?}",
+        lb_pair,
+        lb_pair_state.pair_type()?,
+        activation_type
+    );
+    match activation_type {
+        ActivationType::Slot => println!(
+            "Current slot {}. Estimated {:.3} seconds per slot",
+            clock.slot, seconds_per_slot
+        ),
+        ActivationType::Timestamp => println!("Current timestamp {}", clock.unix_timestamp),
+    }
+    if lb_pair_state.activation_point != u64::MAX {
+        println!(
+            "Activation point {} pre-activation duration {}",
+            lb_pair_state.activation_point, lb_pair_state.pre_activation_duration
+        );
+    }
+
+    println!("Schedule");
+    print_schedule(&lb_pair_state, &wall_clock, current_point)?;
+
+    if new_activation_point.is_none() && new_pre_activation_duration.is_none() {
+        return Ok(());
+    }
+
+    let validator =
+        get_lb_pair_type_access_validator_with_current_point(&lb_pair_state, current_point)?;
+    let mut planned_lb_pair = lb_pair_state;
+
+    if let Some(new_activation_point) = new_activation_point {
+        match validator.validate_update_new_activation_point(new_activation_point) {
+            Result::Ok(()) => println!("SetActivationPoint {}: valid", new_activation_point),
+            Result::Err(err) => println!(
+                "SetActivationPoint {}: rejected. {}",
+                new_activation_point, err
+            ),
+        }
+        planned_lb_pair.activation_point = new_activation_point;
+    }
+
+    if let Some(new_pre_activation_duration) = new_pre_activation_duration {
+        if new_pre_activation_duration > u16::MAX.into() {
+            println!(
+                "SetPreActivationDuration {}: rejected. Exceeds the instruction limit {}",
+                new_pre_activation_duration,
+                u16::MAX
+            );
+        } else {
+            match validator.validate_set_pre_activation_duration(new_pre_activation_duration) {
+                Result::Ok(()) => println!(
+                    "SetPreActivationDuration {}: valid",
+                    new_pre_activation_duration
+                ),
+                Result::Err(err) => println!(
+                    "SetPreActivationDuration {}: rejected. {}",
+                    new_pre_activation_duration, err
+                ),
+            }
+        }
+        planned_lb_pair.pre_activation_duration = new_pre_activation_duration;
+    }
+
+    println!(
+        "Planned schedule. Each planned value is validated against the current on chain state"
+    );
+    print_schedule(&planned_lb_pair, &wall_clock, current_point)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_launch_milestones_permission_pair() {
+        let pre_activation_swap_address = Pubkey::new_unique();
+        let lb_pair = LbPair {
+            pair_type: PairType::Permission.into(),
+            activation_type: ActivationType::Timestamp.into(),
+            activation_point: 100_000,
+            pre_activation_duration: 3_600,
+            pre_activation_swap_address,
+            ..LbPair::default()
+        };
+
+        let milestones = get_launch_milestones(&lb_pair).unwrap();
+        let points = milestones
+            .iter()
+            .map(|milestone| milestone.point)
+            .collect::<Vec<u64>>();
+
+        let pre_activation_point = 100_000 - 3_600;
+        let vault_last_join_point = pre_activation_point - FIVE_MINUTES_TIME_BUFFER;
+        assert_eq!(points[0], vault_last_join_point - FIVE_MINUTES_TIME_BUFFER);
+        assert!(points.contains(&vault_last_join_point));
+        assert!(points.contains(&pre_activation_point));
+        assert_eq!(*points.last().unwrap(), 100_000);
+        assert!(points.windows(2).all(|w| w[0] <= w[1]));
+    }
+}
@@ -0,0 +1,137 @@
+use std::ops::Deref;
+
+use anchor_client::solana_sdk::clock::Clock;
+use anchor_client::{solana_sdk::pubkey::Pubkey, solana_sdk::signer::Signer, Program};
+use anchor_spl::token::Mint;
+use anyhow::*;
+use commons::oracle::{get_required_oracle_length, OracleReader, OracleTwap};
+use lb_clmm::constants::SAMPLE_LIFETIME;
+use lb_clmm::state::lb_pair::LbPair;
+use rust_decimal::prelude::ToPrimitive;
+
+use crate::math::price_per_lamport_to_price_per_token;
+
+#[derive(Debug)]
+pub struct ShowOracleParameters {
+    pub lb_pair: Pubkey,
+    /// TWAP window in seconds
+    pub window: Option<u64>,
+}
+
+fn twap_to_ui_price(
+    twap: &OracleTwap,
+    bin_step: u16,
+    base_decimals: u8,
+    quote_decimals: u8,
+) -> Result<f64> {
+    price_per_lamport_to_price_per_token(
+        twap.price_per_lamport(bin_step),
+        base_decimals,
+        quote_decimals,
+    )
+    .and_then(|price| price.to_f64())
+    .context("price_per_lamport_to_price_per_token overflow")
+}
+
+pub async fn show_oracle<C: Deref<Target = impl Signer> + Clone>(
+    params: ShowOracleParameters,
+    program: &Program<C>,
+) -> Result<()> {
+    let ShowOracleParameters { lb_pair, window } = params;
+
+    let rpc_client = program.rpc();
+    let lb_pair_state: LbPair = program.account(lb_pair).await?;
+
+    let x_mint: Mint = program.account(lb_pair_state.token_x_mint).await?;
+    let y_mint: Mint = program.account(lb_pair_state.token_y_mint).await?;
+
+    let oracle_account = rpc_client.get_account(&lb_pair_state.oracle).await?;
+    let oracle = OracleReader::parse(&oracle_account.data)?;
+
+    let clock = rpc_client.get_account(&Clock::id()).await.map(|account| {
+        let clock: Clock = bincode::deserialize(account.data.as_ref())?;
+        Ok(clock)
+    })??;
+
+    println!(
+        "Oracle {} length {} active size {} latest index {}",
+        lb_pair_state.oracle,
+        oracle.metadata.length,
+        oracle.metadata.active_size,
+        oracle.metadata.idx
+    );
+    println!(
+        "Sample lifetime {} seconds. Full length covers ~{} seconds",
+        SAMPLE_LIFETIME,
+        oracle.metadata.length.saturating_sub(1) * SAMPLE_LIFETIME
+    );
+
+    println!("Observations");
+    for observation in oracle.observations.iter() {
+        println!(
+            "  Created at {} last updated at {} cumulative active bin id {}",
+            observation.created_at,
+            observation.last_updated_at,
+            observation.cumulative_active_bin_id
+        );
+    }
+
+    println!("Average between observations");
+    for twap in oracle.get_sample_twaps() {
+        println!(
+            "  {} to {} bin id {:.2} price {}",
+            twap.start,
+            twap.end,
+            twap.twap_bin_id,
+            twap_to_ui_price(
+                &twap,
+                lb_pair_state.bin_step,
+                x_mint.decimals,
+                y_mint.decimals
+            )?
+        );
+    }
+
+    if let Some(earliest_timestamp) = oracle.earliest_timestamp() {
+        println!(
+            "Samples cover {} seconds",
+            clock.unix_timestamp - earliest_timestamp
+        );
+    }
+
+    let Some(window) = window else {
+        return Ok(());
+    };
+
+    match oracle.get_twap(lb_pair_state.active_id, clock.unix_timestamp, window)? {
+        Some(twap) => {
+            println!(
+                "TWAP over {} seconds: bin id {:.2} price {}",
+                window,
+                twap.twap_bin_id,
+                twap_to_ui_price(
+                    &twap,
+                    lb_pair_state.bin_step,
+                    x_mint.decimals,
+                    y_mint.decimals
+                )?
+            );
+        }
+        None => {
+            println!("Samples don't cover the last {} seconds", window);
+        }
+    }
+
+    let length_to_add = oracle.get_length_to_add(window);
+    if length_to_add > 0 {
+        println!(
+            "Oracle length {} can't cover {} seconds. Required length {}. Run increase-length with length to add {}",
+            oracle.metadata.length,
+            window,
+            get_required_oracle_length(window),
+            length_to_add
+        );
+    }
+
+    Ok(())
+}
@@ -6,6 +6,7 @@ use anchor_client::{solana_sdk::pubkey::Pubkey, solana_sdk::signer::Signer, Prog
 
 use anchor_spl::token::Mint;
 use anyhow::*;
+use commons::account_layout::normalize_bin_array;
 
 use lb_clmm::constants::FEE_PRECISION;
 use lb_clmm::math::price_math::get_price_from_id;
@@ -29,6 +30,9 @@ pub async fn show_pair<C: Deref<Target = impl Signer> + Clone>(
 
     let lb_pair_filter = RpcFilterType::Memcmp(Memcmp::new_base58_encoded(16, &lb_pair.to_bytes()));
     let mut bin_arrays: Vec<(Pubkey, BinArray)> = program.accounts(vec![lb_pair_filter]).await?;
+    for (_, bin_array) in bin_arrays.iter_mut() {
+        normalize_bin_array(bin_array)?;
+    }
     bin_arrays.sort_by(|a, b| a.1.index.cmp(&b.1.index));
 
     println!("{:#?}", lb_pair_state);
@@ -0,0 +1,257 @@
+use std::ops::Deref;
+
+use anchor_client::solana_sdk::clock::Clock;
+use anchor_client::{solana_sdk::pubkey::Pubkey, solana_sdk::signer::Signer, Program};
+use anchor_spl::token_interface::{Mint, TokenAccount};
+use anyhow::*;
+use chrono::DateTime;
+use commons::account_layout::fetch_bin_array;
+use lb_clmm::constants::NUM_REWARDS;
+use lb_clmm::math::price_math::get_price_from_id;
+use lb_clmm::math::u64x64_math::SCALE_OFFSET;
+use lb_clmm::state::bin::BinArray;
+use lb_clmm::state::lb_pair::{LbPair, RewardInfo};
+use lb_clmm::utils::pda::{derive_bin_array_pda, derive_reward_vault_pda};
+use rust_decimal::prelude::ToPrimitive;
+
+use crate::math::{price_per_lamport_to_price_per_token, q64x64_price_to_decimal};
+
+const SECONDS_PER_YEAR: f64 = 365.0 * 24.0 * 60.0 * 60.0;
+
+#[derive(Debug)]
+pub struct ShowRewardsParameters {
+    pub lb_pair: Pubkey,
+    /// Unix timestamp to compute the top up needed to keep the current reward rate until
+    pub target_end: Option<u64>,
+    /// Price of 1 reward token in token Y, by reward index. Rewards in token X or Y are priced from the pair.
+    pub reward_prices: Vec<f64>,
+}
+
+/// Amount of reward emitted over `seconds` at the reward rate, which is a Q64.64 amount per second.
+fn get_emission_amount(reward_info: &RewardInfo, seconds: u64) -> u64 {
+    let amount = reward_info
+        .reward_rate
+        .as_u128()
+        .saturating_mul(seconds.into())
+        >> SCALE_OFFSET;
+    amount.try_into().unwrap_or(u64::MAX)
+}
+
+/// Amount of reward not emitted yet.
+pub fn get_remaining_emission(reward_info: &RewardInfo, current_time: u64) -> u64 {
+    let remaining_seconds = reward_info.reward_duration_end.saturating_sub(current_time);
+    get_emission_amount(reward_info, remaining_seconds)
+}
+
+/// Amount of reward emitted while the active bin had no liquidity. The funder can take it back with
+/// `withdraw_ineligible_reward`.
+pub fn get_ineligible_reward(reward_info: &RewardInfo) -> u64 {
+    get_emission_amount(
+        reward_info,
+        reward_info.cumulative_seconds_with_empty_liquidity_reward,
+    )
+}
+
+/// End of the period `fund_reward` starts at `current_time`, which always lasts `reward_duration`.
+pub fn get_funded_period_end(reward_info: &RewardInfo, current_time: u64) -> u64 {
+    current_time.saturating_add(reward_info.reward_duration)
+}
+
+/// Funding amount which keeps the current reward rate over the period funded at `current_time`, until
+/// `get_funded_period_end`. The leftover of the current period is carried forward by `fund_reward`.
+pub fn get_top_up_amount(reward_info: &RewardInfo, current_time: u64) -> u64 {
+    let required = get_emission_amount(reward_info, reward_info.reward_duration);
+    required.saturating_sub(get_remaining_emission(reward_info, current_time))
+}
+
+fn to_ui_amount(amount: u64, decimals: u8) -> f64 {
+    amount as f64 / 10f64.powi(decimals.into())
+}
+
+fn format_timestamp(timestamp: u64) -> String {
+    i64::try_from(timestamp)
+        .ok()
+        .and_then(|timestamp| DateTime::from_timestamp(timestamp, 0))
+        .map(|time| time.to_rfc3339())
+        .unwrap_or_else(|| timestamp.to_string())
+}
+
+pub async fn show_rewards<C: Deref<Target = impl Signer> + Clone>(
+    params: ShowRewardsParameters,
+    program: &Program<C>,
+) -> Result<()> {
+    let ShowRewardsParameters {
+        lb_pair,
+        target_end,
+        reward_prices,
+    } = params;
+
+    let rpc_client = program.rpc();
+    let lb_pair_state: LbPair = program.account(lb_pair).await?;
+
+    let x_mint: Mint = program.account(lb_pair_state.token_x_mint).await?;
+    let y_mint: Mint = program.account(lb_pair_state.token_y_mint).await?;
+
+    let clock = rpc_client.get_account(&Clock::id()).await.map(|account| {
+        let clock: Clock = bincode::deserialize(account.data.as_ref())?;
+        Ok(clock)
+    })??;
+    let current_time = clock.unix_timestamp as u64;
+
+    let q64x64_price = get_price_from_id(lb_pair_state.active_id, lb_pair_state.bin_step)?;
+    let price_per_lamport = q64x64_price_to_decimal(q64x64_price)
+        .and_then(|price| price.to_f64())
+        .context("q64x64 price to decimal overflow")?;
+    let token_x_price =
+        price_per_lamport_to_price_per_token(price_per_lamport, x_mint.decimals, y_mint.decimals)
+            .and_then(|price| price.to_f64())
+            .context("price_per_lamport_to_price_per_token overflow")?;
+
+    // Rewards are only distributed to the liquidity of the active bin
+    let active_bin_array_idx = BinArray::bin_id_to_bin_array_index(lb_pair_state.active_id)?;
+    let (active_bin_array, _bump) = derive_bin_array_pda(lb_pair, active_bin_array_idx.into());
+    let active_bin_value = match fetch_bin_array(&rpc_client, active_bin_array).await {
+        std::result::Result::Ok(bin_array) => {
+            let bin = bin_array.get_bin(lb_pair_state.active_id)?;
+            to_ui_amount(bin.amount_x, x_mint.decimals) * token_x_price
+                + to_ui_amount(bin.amount_y, y_mint.decimals)
+        }
+        Err(_) => 0.0,
+    };
+
+    println!(
+        "Active bin {} liquidity value {} in token Y",
+        lb_pair_state.active_id, active_bin_value
+    );
+
+    if let Some(target_end) = target_end {
+        ensure!(
+            target_end > current_time,
+            "Target end {} is in the past",
+            target_end
+        );
+    }
+
+    for reward_index in 0..NUM_REWARDS {
+        let reward_info = &lb_pair_state.reward_infos[reward_index];
+        if !reward_info.initialized() {
+            println!("Reward {}: not initialized", reward_index);
+            continue;
+        }
+
+        let reward_mint: Mint = program.account(reward_info.mint).await?;
+        let (reward_vault, _bump) = derive_reward_vault_pda(lb_pair, reward_index as u64);
+        let reward_vault_state: TokenAccount = program.account(reward_vault).await?;
+
+        let remaining_emission = get_remaining_emission(reward_info, current_time);
+        let ineligible_reward = get_ineligible_reward(reward_info);
+        let emission_per_second = reward_info.reward_rate.as_u128() as f64
+            / 2f64.powi(SCALE_OFFSET.into())
+            / 10f64.powi(reward_mint.decimals.into());
+
+        println!("Reward {}", reward_index);
+        println!("  Mint {}", reward_info.mint);
+        println!("  Funder {}", reward_info.funder);
+        println!(
+            "  Vault {} balance {}",
+            reward_vault, reward_vault_state.amount
+        );
+        println!(
+            "  Reward rate {} per second, duration {} seconds",
+            emission_per_second, reward_info.reward_duration
+        );
+        if reward_info.reward_duration_end > current_time {
+            println!(
+                "  Ends at {} ({} seconds remaining)",
+                format_timestamp(reward_info.reward_duration_end),
+                reward_info.reward_duration_end - current_time
+            );
+        } else {
+            println!(
+                "  Ended at {}",
+                format_timestamp(reward_info.reward_duration_end)
+            );
+        }
+        println!("  Remaining undistributed {}", remaining_emission);
+        println!(
+            "  Ineligible (emitted while the active bin was empty) {}",
+            ineligible_reward
+        );
+        if ineligible_reward > 0 && reward_info.reward_duration_end <= current_time {
+            println!("  Withdraw it with withdraw-ineligible-reward");
+        }
+
+        let reward_price = if reward_info.mint == lb_pair_state.token_y_mint {
+            Some(1.0)
+        } else if reward_info.mint == lb_pair_state.token_x_mint {
+            Some(token_x_price)
+        } else {
+            reward_prices.get(reward_index).copied()
+        };
+
+        match reward_price {
+            Some(reward_price)
+                if active_bin_value > 0.0 && reward_info.reward_duration_end > current_time =>
+            {
+                let apr = emission_per_second * SECONDS_PER_YEAR * reward_price / active_bin_value
+                    * 100.0;
+                println!("  Estimated APR of the active bin {}%", apr);
+            }
+            Some(_) => println!("  Estimated APR of the active bin 0%"),
+            None => {
+                println!("  Estimated APR unknown. Provide the reward price with --reward-prices")
+            }
+        }
+
+        if let Some(target_end) = target_end {
+            // Funding restarts a period of reward_duration, so the target is clamped to its end
+            let funded_end = get_funded_period_end(reward_info, current_time);
+            if target_end != funded_end {
+                println!(
+                    "  Target end {} clamped to {}, the end of a period of reward_duration funded now. To reach the target, update the reward duration to {} seconds first, which is allowed once the current period ended",
+                    format_timestamp(target_end),
+                    format_timestamp(funded_end),
+                    target_end - current_time
+                );
+            }
+            let top_up_amount = get_top_up_amount(reward_info, current_time);
+            println!(
+                "  Top up {} to keep the current rate until {}",
+                top_up_amount,
+                format_timestamp(funded_end)
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reward_amounts() {
+        let mut reward_info = RewardInfo::default();
+        // 10 tokens per second
+        reward_info.reward_rate.set(10u128 << SCALE_OFFSET);
+        reward_info.reward_duration = 1_000;
+        reward_info.reward_duration_end = 2_000;
+        reward_info.cumulative_seconds_with_empty_liquidity_reward = 30;
+
+        assert_eq!(get_remaining_emission(&reward_info, 1_500), 5_000);
+        assert_eq!(get_remaining_emission(&reward_info, 2_500), 0);
+        assert_eq!(get_ineligible_reward(&reward_info), 300);
+
+        // Funding restarts a period of 1000 seconds whatever the target
+        assert_eq!(get_funded_period_end(&reward_info, 1_500), 2_500);
+        assert_eq!(get_funded_period_end(&reward_info, 2_500), 3_500);
+        // 1000 seconds at 10 per second, 500 seconds are covered by the leftover
+        assert_eq!(get_top_up_amount(&reward_info, 1_500), 5_000);
+        // The current period ended, the whole period is funded
+        assert_eq!(get_top_up_amount(&reward_info, 2_500), 10_000);
+        // The leftover covers the period
+        reward_info.reward_duration_end = 3_000;
+        assert_eq!(get_top_up_amount(&reward_info, 1_500), 0);
+    }
+}
@@ -0,0 +1,365 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::ops::Deref;
+use std::str::FromStr;
+
+use anchor_client::solana_client::rpc_client::GetConfirmedSignaturesForAddress2Config;
+use anchor_client::solana_client::rpc_config::RpcTransactionConfig;
+use anchor_client::solana_sdk::signature::Signature;
+use anchor_client::{solana_sdk::pubkey::Pubkey, solana_sdk::signer::Signer, Program};
+use anchor_lang::event::EVENT_IX_TAG_LE;
+use anchor_lang::{AnchorDeserialize, Discriminator};
+use anyhow::*;
+use commons::fee_replay::{replay_swaps, FeeReplayReport, RecordedSwap};
+use lb_clmm::constants::FEE_PRECISION;
+use lb_clmm::events::Swap as SwapEvent;
+use lb_clmm::state::preset_parameters::PresetParameter;
+use solana_transaction_status::option_serializer::OptionSerializer;
+use solana_transaction_status::{
+    EncodedConfirmedTransactionWithStatusMeta, UiInstruction, UiTransactionEncoding,
+};
+
+use crate::math::find_swappable_min_max_bin_id;
+
+/// Max number of signatures returned by a getSignaturesForAddress call
+const SIGNATURES_PAGE_SIZE: usize = 1000;
+
+#[derive(Debug)]
+pub enum SwapSource {
+    /// `.csv` file with `timestamp,start_bin_id,end_bin_id,swap_for_y,amount_in` lines, or `.json` file of recorded
+    /// swaps decoded from swap events
+    File(String),
+    /// Swap transactions, whose swap events are decoded
+    Signatures(Vec<Signature>),
+    /// Latest `limit` transactions of the pair, whose swap events on the pair are decoded
+    LbPair { lb_pair: Pubkey, limit: usize },
+}
+
+#[derive(Debug)]
+pub struct TuneFeeParametersParameters {
+    pub bin_step: u16,
+    pub swap_source: SwapSource,
+    /// Hypothetical presets as
+    /// `base_factor,filter_period,decay_period,reduction_factor,variable_fee_control,max_volatility_accumulator,protocol_share`
+    pub candidates: Vec<String>,
+    /// Skip the preset parameters on chain
+    pub skip_on_chain_presets: bool,
+}
+
+fn read_swaps_csv(path: &str) -> Result<Vec<RecordedSwap>> {
+    let file = File::open(path).context(format!("Swap file {} not found", path))?;
+    let reader = BufReader::new(file);
+
+    let mut swaps = vec![];
+    for (idx, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let columns = line.split(',').map(str::trim).collect::<Vec<_>>();
+        ensure!(
+            columns.len() == 5,
+            "Invalid line {}. Expected timestamp,start_bin_id,end_bin_id,swap_for_y,amount_in",
+            idx + 1
+        );
+
+        // Header
+        if idx == 0 && columns[0].parse::<i64>().is_err() {
+            continue;
+        }
+
+        swaps.push(RecordedSwap {
+            timestamp: columns[0].parse()?,
+            start_bin_id: columns[1].parse()?,
+            end_bin_id: columns[2].parse()?,
+            swap_for_y: columns[3].parse()?,
+            amount_in: columns[4].parse()?,
+        });
+    }
+
+    Ok(swaps)
+}
+
+fn read_swaps_file(path: &str) -> Result<Vec<RecordedSwap>> {
+    if path.ends_with(".json") {
+        let file = File::open(path).context(format!("Swap file {} not found", path))?;
+        Ok(serde_json::from_reader(BufReader::new(file))?)
+    } else {
+        read_swaps_csv(path)
+    }
+}
+
+fn parse_swap_event_cpi(ix_data: &[u8]) -> Option<SwapEvent> {
+    if ix_data.len() < 16 || ix_data[..8] != EVENT_IX_TAG_LE {
+        return None;
+    }
+    let event_cpi = &ix_data[8..];
+    if event_cpi[..8] != *SwapEvent::DISCRIMINATOR {
+        return None;
+    }
+    SwapEvent::try_from_slice(&event_cpi[8..]).ok()
+}
+
+/// Swap events emitted by the program in the transaction. Failed transactions have none.
+fn get_swap_events(tx: &EncodedConfirmedTransactionWithStatusMeta) -> Result<Vec<SwapEvent>> {
+    let Some(meta) = &tx.transaction.meta else {
+        return Ok(vec![]);
+    };
+    if meta.err.is_some() {
+        return Ok(vec![]);
+    }
+    let OptionSerializer::Some(inner_instructions) = &meta.inner_instructions else {
+        return Ok(vec![]);
+    };
+
+    let versioned_tx = tx
+        .transaction
+        .transaction
+        .decode()
+        .context("Cannot decode transaction")?;
+    let mut account_keys = versioned_tx.message.static_account_keys().to_vec();
+    if let OptionSerializer::Some(loaded_addresses) = &meta.loaded_addresses {
+        for address in loaded_addresses
+            .writable
+            .iter()
+            .chain(loaded_addresses.readonly.iter())
+        {
+            account_keys.push(Pubkey::from_str(address)?);
+        }
+    }
+
+    let mut events = vec![];
+    for ix in inner_instructions
+        .iter()
+        .flat_map(|ix| ix.instructions.iter())
+    {
+        let UiInstruction::Compiled(compiled_ix) = ix else {
+            continue;
+        };
+        // Events are self CPI of the program. Other programs could emit the same bytes.
+        if account_keys.get(compiled_ix.program_id_index as usize) != Some(&lb_clmm::ID) {
+            continue;
+        }
+        let std::result::Result::Ok(ix_data) = bs58::decode(&compiled_ix.data).into_vec() else {
+            continue;
+        };
+        if let Some(event) = parse_swap_event_cpi(&ix_data) {
+            events.push(event);
+        }
+    }
+
+    Ok(events)
+}
+
+/// Recorded swaps of the transactions, only on `lb_pair` when set.
+async fn fetch_swaps<C: Deref<Target = impl Signer> + Clone>(
+    program: &Program<C>,
+    signatures: &[Signature],
+    lb_pair: Option<Pubkey>,
+) -> Result<Vec<RecordedSwap>> {
+    let rpc_client = program.rpc();
+    let mut swaps = vec![];
+
+    for signature in signatures {
+        let tx = rpc_client
+            .get_transaction_with_config(
+                signature,
+                RpcTransactionConfig {
+                    encoding: Some(UiTransactionEncoding::Base64),
+                    commitment: Some(rpc_client.commitment()),
+                    max_supported_transaction_version: Some(0),
+                },
+            )
+            .await
+            .context(format!("Failed to get transaction {}", signature))?;
+
+        let events = get_swap_events(&tx)?;
+        if events.is_empty() {
+            continue;
+        }
+        let block_time = tx
+            .block_time
+            .context(format!("Transaction {} has no block time", signature))?;
+
+        for event in events.iter() {
+            if lb_pair.is_some_and(|lb_pair| lb_pair != event.lb_pair) {
+                continue;
+            }
+            swaps.push(RecordedSwap::from_swap_event(event, block_time));
+        }
+    }
+
+    Ok(swaps)
+}
+
+/// Latest `limit` successful transactions of the pair, oldest first.
+async fn get_lb_pair_signatures<C: Deref<Target = impl Signer> + Clone>(
+    program: &Program<C>,
+    lb_pair: Pubkey,
+    limit: usize,
+) -> Result<Vec<Signature>> {
+    let rpc_client = program.rpc();
+    let mut signatures = vec![];
+    let mut before = None;
+
+    while signatures.len() < limit {
+        let page = rpc_client
+            .get_signatures_for_address_with_config(
+                &lb_pair,
+                GetConfirmedSignaturesForAddress2Config {
+                    before,
+                    until: None,
+                    limit: Some(SIGNATURES_PAGE_SIZE.min(limit - signatures.len())),
+                    commitment: Some(rpc_client.commitment()),
+                },
+            )
+            .await?;
+        let Some(last) = page.last() else {
+            break;
+        };
+        before = Some(Signature::from_str(&last.signature)?);
+
+        for status in page.iter().filter(|status| status.err.is_none()) {
+            signatures.push(Signature::from_str(&status.signature)?);
+        }
+    }
+
+    signatures.reverse();
+    Ok(signatures)
+}
+
+async fn read_swaps<C: Deref<Target = impl Signer> + Clone>(
+    swap_source: &SwapSource,
+    program: &Program<C>,
+) -> Result<Vec<RecordedSwap>> {
+    let mut swaps = match swap_source {
+        SwapSource::File(path) => read_swaps_file(path)?,
+        SwapSource::Signatures(signatures) => fetch_swaps(program, signatures, None).await?,
+        SwapSource::LbPair { lb_pair, limit } => {
+            let signatures = get_lb_pair_signatures(program, *lb_pair, *limit).await?;
+            println!(
+                "Decoding swap events of {} transactions of {}",
+                signatures.len(),
+                lb_pair
+            );
+            fetch_swaps(program, &signatures, Some(*lb_pair)).await?
+        }
+    };
+    swaps.sort_by_key(|swap: &RecordedSwap| swap.timestamp);
+    Ok(swaps)
+}
+
+fn parse_candidate(bin_step: u16, candidate: &str) -> Result<PresetParameter> {
+    let values = candidate.split(',').map(str::trim).collect::<Vec<_>>();
+    ensure!(
+        values.len() == 7,
+        "Invalid candidate {}. Expected base_factor,filter_period,decay_period,reduction_factor,variable_fee_control,max_volatility_accumulator,protocol_share",
+        candidate
+    );
+
+    let (min_bin_id, max_bin_id) = find_swappable_min_max_bin_id(bin_step)?;
+
+    let preset = PresetParameter {
+        bin_step,
+        base_factor: values[0].parse()?,
+        filter_period: values[1].parse()?,
+        decay_period: values[2].parse()?,
+        reduction_factor: values[3].parse()?,
+        variable_fee_control: values[4].parse()?,
+        max_volatility_accumulator: values[5].parse()?,
+        min_bin_id,
+        max_bin_id,
+        protocol_share: values[6].parse()?,
+    };
+
+    preset
+        .validate()
+        .map_err(|err| anyhow!("Invalid candidate {}. {}", candidate, err))?;
+
+    Ok(preset)
+}
+
+fn fee_rate_to_pct(fee_rate: u128) -> f64 {
+    fee_rate as f64 * 100.0 / FEE_PRECISION as f64
+}
+
+fn print_report(name: &str, preset: &PresetParameter, report: &FeeReplayReport) {
+    println!("{}", name);
+    println!(
+        "  base_factor {} filter_period {} decay_period {} reduction_factor {} variable_fee_control {} max_volatility_accumulator {} protocol_share {}",
+        preset.base_factor,
+        preset.filter_period,
+        preset.decay_period,
+        preset.reduction_factor,
+        preset.variable_fee_control,
+        preset.max_volatility_accumulator,
+        preset.protocol_share
+    );
+    println!(
+        "  Total fee X {} Y {}",
+        report.total_fee_x, report.total_fee_y
+    );
+    println!("  LP fee X {} Y {}", report.lp_fee_x(), report.lp_fee_y());
+    println!(
+        "  Protocol fee X {} Y {}",
+        report.protocol_fee_x, report.protocol_fee_y
+    );
+    println!(
+        "  Fee rate min {}% max {}%",
+        fee_rate_to_pct(report.min_fee_rate),
+        fee_rate_to_pct(report.max_fee_rate)
+    );
+    println!("  Fee rate distribution");
+    for (fee_rate_bps, count) in report.fee_rate_bps_distribution.iter() {
+        println!("    {} bps: {} bins", fee_rate_bps, count);
+    }
+}
+
+pub async fn tune_fee_parameters<C: Deref<Target = impl Signer> + Clone>(
+    params: TuneFeeParametersParameters,
+    program: &Program<C>,
+) -> Result<()> {
+    let TuneFeeParametersParameters {
+        bin_step,
+        swap_source,
+        candidates,
+        skip_on_chain_presets,
+    } = params;
+
+    let swaps = read_swaps(&swap_source, program).await?;
+    println!("Replaying {} swaps", swaps.len());
+
+    let mut presets: Vec<(String, PresetParameter)> = vec![];
+
+    if !skip_on_chain_presets {
+        let mut on_chain_presets = program
+            .accounts::<PresetParameter>(vec![])
+            .await?
+            .into_iter()
+            .filter(|(_, preset)| preset.bin_step == bin_step)
+            .collect::<Vec<(Pubkey, PresetParameter)>>();
+        on_chain_presets.sort_by_key(|(_, preset)| preset.base_factor);
+
+        for (key, preset) in on_chain_presets {
+            presets.push((format!("Preset {}", key), preset));
+        }
+    }
+
+    for (idx, candidate) in candidates.iter().enumerate() {
+        presets.push((
+            format!("Candidate {}", idx),
+            parse_candidate(bin_step, candidate)?,
+        ));
+    }
+
+    ensure!(!presets.is_empty(), "No preset parameter to replay");
+
+    for (name, preset) in presets.iter() {
+        let report = replay_swaps(preset, &swaps)?;
+        print_report(name, preset, &report);
+    }
+
+    Ok(())
+}
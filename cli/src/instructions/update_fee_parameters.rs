@@ -0,0 +1,282 @@
+use std::ops::Deref;
+
+use anchor_client::solana_client::rpc_config::RpcSendTransactionConfig;
+use anchor_client::solana_sdk::clock::Clock;
+use anchor_client::solana_sdk::transaction::Transaction;
+use anchor_client::{solana_sdk::pubkey::Pubkey, solana_sdk::signer::Signer, Program};
+
+use anchor_lang::solana_program::entrypoint::SUCCESS;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::program_stubs::{set_syscalls, SyscallStubs};
+use anchor_lang::{InstructionData, ToAccountMetas};
+use anyhow::*;
+use lb_clmm::constants::{
+    FEE_PRECISION, MAX_BASE_FACTOR_STEP, MAX_FEE_UPDATE_WINDOW, MAX_PROTOCOL_SHARE,
+};
+use lb_clmm::instructions::admin::update_fee_parameters::FeeParameter;
+use lb_clmm::state::lb_pair::LbPair;
+use lb_clmm::state::parameters::StaticParameters;
+use lb_clmm::utils::pda::derive_event_authority_pda;
+
+#[derive(Debug)]
+pub struct UpdateFeeParametersParam {
+    pub lb_pair: Pubkey,
+    pub protocol_share: u16,
+    pub base_factor: u16,
+    /// Print the unsigned transaction instead of sending it
+    pub serialize: bool,
+}
+
+fn base_fee_pct(lb_pair: &LbPair) -> Result<f64> {
+    Ok(lb_pair.get_base_fee()? as f64 * 100.0 / FEE_PRECISION as f64)
+}
+
+fn total_fee_pct(lb_pair: &LbPair) -> Result<f64> {
+    Ok(lb_pair.get_total_fee()? as f64 * 100.0 / FEE_PRECISION as f64)
+}
+
+/// Serve the cluster clock to the program methods reading the Clock sysvar off-chain.
+struct ClockSyscallStubs {
+    clock: Clock,
+}
+
+impl SyscallStubs for ClockSyscallStubs {
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        unsafe {
+            *(var_addr as *mut Clock) = self.clock.clone();
+        }
+        SUCCESS
+    }
+}
+
+/// Apply the fee parameters to copies of the pair with the program's own `update_fee_parameters`, at the clock of
+/// the cluster. Both copies get their volatility references updated as the next swap would, so their fees are the
+/// ones of a swap in the active bin now. Return the copies without and with the new parameters.
+pub fn get_fee_parameters_preview(
+    lb_pair_state: &LbPair,
+    fee_parameter: &FeeParameter,
+    clock: &Clock,
+) -> Result<(LbPair, LbPair)> {
+    let mut new_lb_pair_state = *lb_pair_state;
+    let previous_syscall_stubs = set_syscalls(Box::new(ClockSyscallStubs {
+        clock: clock.clone(),
+    }));
+    let update = new_lb_pair_state.update_fee_parameters(fee_parameter);
+    set_syscalls(previous_syscall_stubs);
+    update.map_err(|e| {
+        anyhow!(
+            "Invalid fee parameters: {}. The base factor change must be at most {} and 100% of the current base factor, the protocol share at most {}, and the last update more than {} seconds ago",
+            e,
+            MAX_BASE_FACTOR_STEP,
+            MAX_PROTOCOL_SHARE,
+            MAX_FEE_UPDATE_WINDOW
+        )
+    })?;
+
+    let mut lb_pair_state = *lb_pair_state;
+    for state in [&mut lb_pair_state, &mut new_lb_pair_state] {
+        state.update_references(clock.unix_timestamp)?;
+        state.update_volatility_accumulator()?;
+    }
+
+    Ok((lb_pair_state, new_lb_pair_state))
+}
+
+fn print_static_parameters_diff(old: &StaticParameters, new: &StaticParameters) {
+    let fields = [
+        (
+            "base_factor",
+            old.base_factor.to_string(),
+            new.base_factor.to_string(),
+        ),
+        (
+            "filter_period",
+            old.filter_period.to_string(),
+            new.filter_period.to_string(),
+        ),
+        (
+            "decay_period",
+            old.decay_period.to_string(),
+            new.decay_period.to_string(),
+        ),
+        (
+            "reduction_factor",
+            old.reduction_factor.to_string(),
+            new.reduction_factor.to_string(),
+        ),
+        (
+            "variable_fee_control",
+            old.variable_fee_control.to_string(),
+            new.variable_fee_control.to_string(),
+        ),
+        (
+            "max_volatility_accumulator",
+            old.max_volatility_accumulator.to_string(),
+            new.max_volatility_accumulator.to_string(),
+        ),
+        (
+            "min_bin_id",
+            old.min_bin_id.to_string(),
+            new.min_bin_id.to_string(),
+        ),
+        (
+            "max_bin_id",
+            old.max_bin_id.to_string(),
+            new.max_bin_id.to_string(),
+        ),
+        (
+            "protocol_share",
+            old.protocol_share.to_string(),
+            new.protocol_share.to_string(),
+        ),
+    ];
+
+    for (name, old_value, new_value) in fields {
+        if old_value == new_value {
+            println!("  {}: {}", name, old_value);
+        } else {
+            println!("- {}: {}", name, old_value);
+            println!("+ {}: {}", name, new_value);
+        }
+    }
+}
+
+pub async fn update_fee_parameters<C: Deref<Target = impl Signer> + Clone>(
+    params: UpdateFeeParametersParam,
+    program: &Program<C>,
+    transaction_config: RpcSendTransactionConfig,
+) -> Result<()> {
+    let UpdateFeeParametersParam {
+        lb_pair,
+        protocol_share,
+        base_factor,
+        serialize,
+    } = params;
+
+    let rpc_client = program.rpc();
+    let lb_pair_state: LbPair = program.account(lb_pair).await?;
+
+    let clock = rpc_client.get_account(&Clock::id()).await.map(|account| {
+        let clock: Clock = bincode::deserialize(account.data.as_ref())?;
+        Ok(clock)
+    })??;
+
+    let fee_parameter = FeeParameter {
+        protocol_share,
+        base_factor,
+    };
+
+    let (lb_pair_state, new_lb_pair_state) =
+        get_fee_parameters_preview(&lb_pair_state, &fee_parameter, &clock)?;
+
+    println!("Static parameters of pair {}", lb_pair);
+    print_static_parameters_diff(&lb_pair_state.parameters, &new_lb_pair_state.parameters);
+    println!(
+        "Base fee {}% -> {}%",
+        base_fee_pct(&lb_pair_state)?,
+        base_fee_pct(&new_lb_pair_state)?
+    );
+    println!(
+        "Fee of a swap in the active bin now {}% -> {}%",
+        total_fee_pct(&lb_pair_state)?,
+        total_fee_pct(&new_lb_pair_state)?
+    );
+
+    let (event_authority, _bump) = derive_event_authority_pda();
+
+    let accounts = lb_clmm::accounts::UpdateFeeParameters {
+        admin: program.payer(),
+        lb_pair,
+        event_authority,
+        program: lb_clmm::ID,
+    }
+    .to_account_metas(None);
+
+    let ix_data = lb_clmm::instruction::UpdateFeeParameters { fee_parameter }.data();
+
+    let update_fee_parameters_ix = Instruction {
+        accounts,
+        data: ix_data,
+        program_id: lb_clmm::ID,
+    };
+
+    if serialize {
+        let mut transaction =
+            Transaction::new_with_payer(&[update_fee_parameters_ix], Some(&program.payer()));
+        transaction.message.recent_blockhash = rpc_client.get_latest_blockhash().await?;
+
+        let serialized_transaction = bincode::serialize(&transaction)?;
+        println!(
+            "Unsigned transaction (base58): {}",
+            bs58::encode(serialized_transaction).into_string()
+        );
+
+        return Ok(());
+    }
+
+    let request_builder = program.request();
+    let signature = request_builder
+        .instruction(update_fee_parameters_ix)
+        .send_with_spinner_and_config(transaction_config)
+        .await;
+
+    println!("Update fee parameters. Signature: {:#?}", signature);
+
+    signature?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fee_parameters_preview() {
+        let mut lb_pair_state = LbPair::default();
+        lb_pair_state.bin_step = 10;
+        lb_pair_state.parameters.base_factor = 10_000;
+        lb_pair_state.parameters.protocol_share = 1_000;
+        lb_pair_state.parameters.filter_period = 30;
+        lb_pair_state.parameters.decay_period = 600;
+        lb_pair_state.parameters.reduction_factor = 5_000;
+        lb_pair_state.parameters.variable_fee_control = 40_000;
+        lb_pair_state.parameters.max_volatility_accumulator = 350_000;
+        lb_pair_state.v_parameters.volatility_accumulator = 100_000;
+        lb_pair_state.v_parameters.last_update_timestamp = 1_000;
+        lb_pair_state.last_updated_at = 1_000;
+
+        let clock = Clock {
+            unix_timestamp: 1_000 + MAX_FEE_UPDATE_WINDOW + 100,
+            ..Clock::default()
+        };
+        let fee_parameter = FeeParameter {
+            protocol_share: 2_000,
+            base_factor: 10_000 + MAX_BASE_FACTOR_STEP,
+        };
+        let (old, new) =
+            get_fee_parameters_preview(&lb_pair_state, &fee_parameter, &clock).unwrap();
+
+        assert_eq!(new.parameters.base_factor, 10_000 + MAX_BASE_FACTOR_STEP);
+        assert_eq!(new.parameters.protocol_share, 2_000);
+        assert_eq!(new.last_updated_at, clock.unix_timestamp);
+        assert_eq!(old.parameters.base_factor, 10_000);
+        assert_eq!(old.parameters.protocol_share, 1_000);
+        // Within the decay period, the volatility decays by the reduction factor for the next swap
+        assert_eq!(old.v_parameters.volatility_reference, 50_000);
+        assert_eq!(new.v_parameters.volatility_reference, 50_000);
+        assert!(new.get_total_fee().unwrap() > old.get_total_fee().unwrap());
+
+        // The program refuses an update within the update window and excessive steps
+        let recent_clock = Clock {
+            unix_timestamp: 1_000 + MAX_FEE_UPDATE_WINDOW,
+            ..Clock::default()
+        };
+        assert!(get_fee_parameters_preview(&lb_pair_state, &fee_parameter, &recent_clock).is_err());
+        let fee_parameter = FeeParameter {
+            protocol_share: MAX_PROTOCOL_SHARE + 1,
+            base_factor: 10_000,
+        };
+        assert!(get_fee_parameters_preview(&lb_pair_state, &fee_parameter, &clock).is_err());
+    }
+}
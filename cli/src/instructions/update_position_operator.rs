@@ -0,0 +1,60 @@
+use std::ops::Deref;
+
+use anchor_client::solana_client::rpc_config::RpcSendTransactionConfig;
+use anchor_client::{solana_sdk::pubkey::Pubkey, solana_sdk::signer::Signer, Program};
+
+use crate::instructions::utils::fetch_latest_position;
+use anyhow::*;
+use lb_clmm::accounts;
+use lb_clmm::instruction;
+use lb_clmm::utils::pda::derive_event_authority_pda;
+
+#[derive(Debug)]
+pub struct UpdatePositionOperatorParameters {
+    pub position: Pubkey,
+    /// New operator. None to clear the operator.
+    pub operator: Option<Pubkey>,
+}
+
+pub async fn update_position_operator<C: Deref<Target = impl Signer> + Clone>(
+    params: UpdatePositionOperatorParameters,
+    program: &Program<C>,
+    transaction_config: RpcSendTransactionConfig,
+) -> Result<()> {
+    let UpdatePositionOperatorParameters { position, operator } = params;
+
+    let position_state = fetch_latest_position(program, position).await?;
+    ensure!(
+        position_state.owner == program.payer(),
+        "Only the position owner {} can update the operator",
+        position_state.owner
+    );
+
+    let operator = operator.unwrap_or_default();
+    let (event_authority, _bump) = derive_event_authority_pda();
+
+    let accounts = accounts::UpdatePositionOperator {
+        position,
+        owner: program.payer(),
+        event_authority,
+        program: lb_clmm::ID,
+    };
+
+    let ix = instruction::UpdatePositionOperator { operator };
+
+    let request_builder = program.request();
+    let signature = request_builder
+        .accounts(accounts)
+        .args(ix)
+        .send_with_spinner_and_config(transaction_config)
+        .await;
+
+    println!(
+        "Update position {} operator {} -> {}. Signature: {:#?}",
+        position, position_state.operator, operator, signature
+    );
+
+    signature?;
+
+    Ok(())
+}
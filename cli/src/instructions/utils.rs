@@ -1,7 +1,11 @@
+use anchor_client::solana_sdk::clock::Clock;
 use anchor_client::solana_sdk::instruction::Instruction;
 use anchor_client::solana_sdk::signer::Signer;
 use anchor_client::Program;
+use commons::account_layout::{fetch_position, PositionLayout};
+use lb_clmm::pair_action_access::{get_current_point, ActivationType};
 use lb_clmm::state::bin::BinArray;
+use lb_clmm::state::lb_pair::LbPair;
 use lb_clmm::state::position::PositionV2;
 use lb_clmm::utils::pda::derive_bin_array_pda;
 use spl_associated_token_account::instruction::create_associated_token_account;
@@ -52,7 +56,7 @@ pub async fn get_bin_arrays_for_position<C: Deref<Target = impl Signer> + Clone>
     program: &Program<C>,
     position_address: Pubkey,
 ) -> Result<[Pubkey; 2]> {
-    let position: PositionV2 = program.account(position_address).await?;
+    let (_layout, position) = fetch_position(&program.rpc(), position_address).await?;
 
     let lower_bin_array_idx = BinArray::bin_id_to_bin_array_index(position.lower_bin_id)?;
     let upper_bin_array_idx = lower_bin_array_idx.checked_add(1).context("MathOverflow")?;
@@ -65,6 +69,21 @@ pub async fn get_bin_arrays_for_position<C: Deref<Target = impl Signer> + Clone>
     Ok([lower_bin_array, upper_bin_array])
 }
 
+/// Position of either layout. Instructions only accept the latest layout, so a legacy position has to be migrated with
+/// `migrate-legacy-accounts` first.
+pub async fn fetch_latest_position<C: Deref<Target = impl Signer> + Clone>(
+    program: &Program<C>,
+    position: Pubkey,
+) -> Result<PositionV2> {
+    let (layout, position_state) = fetch_position(&program.rpc(), position).await?;
+    ensure!(
+        layout == PositionLayout::V2,
+        "Position {} uses the legacy layout. Migrate it with migrate-legacy-accounts first",
+        position
+    );
+    Ok(position_state)
+}
+
 pub async fn get_bin_arrays_for_pair(lb_pair: Pubkey, lower_bin_id: i32) -> Result<[Pubkey; 2]> {
     let lower_bin_array_idx = BinArray::bin_id_to_bin_array_index(lower_bin_id)?;
     let upper_bin_array_idx = lower_bin_array_idx.checked_add(1).context("MathOverflow")?;
@@ -74,3 +93,27 @@ pub async fn get_bin_arrays_for_pair(lb_pair: Pubkey, lower_bin_id: i32) -> Resu
 
     Ok([lower_bin_array, upper_bin_array])
 }
+
+/// Lock status of the position, counted down in slots or seconds based on the activation type of the pair
+pub fn get_position_lock_status(
+    position: &PositionV2,
+    lb_pair: &LbPair,
+    clock: &Clock,
+) -> Result<String> {
+    let current_point = get_current_point(lb_pair, clock.slot, clock.unix_timestamp as u64)?;
+    if !position.is_liquidity_locked(current_point) {
+        return Ok("unlocked".to_string());
+    }
+
+    let unit = match ActivationType::try_from(lb_pair.activation_type)? {
+        ActivationType::Slot => "slots",
+        ActivationType::Timestamp => "seconds",
+    };
+
+    Ok(format!(
+        "locked until {}, {} {} remaining",
+        position.lock_release_point,
+        position.lock_release_point - current_point,
+        unit
+    ))
+}
@@ -0,0 +1,320 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Write};
+use std::ops::Deref;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+
+use anchor_client::solana_client::rpc_config::RpcSendTransactionConfig;
+use anchor_client::solana_sdk::clock::Clock;
+use anchor_client::solana_sdk::instruction::Instruction;
+use anchor_client::{solana_sdk::pubkey::Pubkey, solana_sdk::signer::Signer, Program};
+use anyhow::*;
+use chrono::DateTime;
+use lb_clmm::pair_action_access::get_current_point;
+use lb_clmm::state::lb_pair::LbPair;
+use lb_clmm::state::position::PositionV2;
+use serde::{Deserialize, Serialize};
+
+use crate::args::UnlockAction;
+use crate::instructions::claim_fee::claim_fee;
+use crate::instructions::list_operated_positions::get_positions_by_field;
+use crate::instructions::remove_all_liquidity::{
+    remove_all_liquidity, RemoveAllLiquidityParameters,
+};
+use crate::instructions::remove_all_liquidity_and_close_position::{
+    remove_all_liquidity_and_close_position, RemoveAllLiquidityAndClosePositionParameters,
+};
+
+#[derive(Debug)]
+pub struct WatchPositionUnlocksParameters {
+    /// Owner or operator of the positions to watch
+    pub wallet: Pubkey,
+    pub lb_pair: Option<Pubkey>,
+    pub action: UnlockAction,
+    /// State file recording the handled positions
+    pub state_path: String,
+    /// Seconds between polls
+    pub poll_interval: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct HandledPosition {
+    pub position: String,
+    pub action: UnlockAction,
+    pub unix_timestamp: i64,
+    pub slot: u64,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct UnlockWatcherState {
+    /// Positions first seen while still locked. Only these are handled once unlocked.
+    #[serde(default)]
+    pub tracked_positions: Vec<String>,
+    pub handled_positions: Vec<HandledPosition>,
+}
+
+fn read_state(path: &str) -> Result<UnlockWatcherState> {
+    if !Path::new(path).exists() {
+        return Ok(UnlockWatcherState::default());
+    }
+    let file = File::open(path)?;
+    let reader = BufReader::new(file);
+    Ok(serde_json::from_reader(reader)?)
+}
+
+/// Write to a temporary file renamed over the state, so that a crash never leaves a partial state.
+fn write_state(path: &str, state: &UnlockWatcherState) -> Result<()> {
+    let tmp_path = format!("{}.tmp", path);
+    let file = File::create(&tmp_path)?;
+    let mut writer = BufWriter::new(file);
+    serde_json::to_writer_pretty(&mut writer, state)?;
+    writer.flush()?;
+    writer.get_ref().sync_all()?;
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+async fn get_clock<C: Deref<Target = impl Signer> + Clone>(program: &Program<C>) -> Result<Clock> {
+    let account = program.rpc().get_account(&Clock::id()).await?;
+    Ok(bincode::deserialize(account.data.as_ref())?)
+}
+
+fn format_timestamp(unix_timestamp: i64) -> String {
+    DateTime::from_timestamp(unix_timestamp, 0)
+        .map(|time| time.to_rfc3339())
+        .unwrap_or_else(|| unix_timestamp.to_string())
+}
+
+async fn get_watched_positions<C: Deref<Target = impl Signer> + Clone>(
+    program: &Program<C>,
+    wallet: Pubkey,
+    lb_pair: Option<Pubkey>,
+) -> Result<BTreeMap<Pubkey, PositionV2>> {
+    let mut positions = BTreeMap::new();
+
+    for offset in [
+        std::mem::offset_of!(PositionV2, owner),
+        std::mem::offset_of!(PositionV2, operator),
+    ] {
+        for (key, position) in get_positions_by_field(program, offset, wallet, lb_pair).await? {
+            // Positions without a lock are not launch positions. Whether the lock is still in the future is checked
+            // against the clock of the pair by the caller.
+            if position.lock_release_point > 0 {
+                positions.insert(key, position);
+            }
+        }
+    }
+
+    Ok(positions)
+}
+
+async fn run_unlock_action<C: Deref<Target = impl Signer> + Clone>(
+    action: &UnlockAction,
+    position: Pubkey,
+    lb_pair: Pubkey,
+    program: &Program<C>,
+    transaction_config: RpcSendTransactionConfig,
+    compute_unit_price: Option<Instruction>,
+) -> Result<()> {
+    match action {
+        UnlockAction::ClaimFee => {
+            claim_fee(position, program, transaction_config, compute_unit_price).await
+        }
+        UnlockAction::Withdraw => {
+            let params = RemoveAllLiquidityParameters { lb_pair, position };
+            remove_all_liquidity(params, program, transaction_config, compute_unit_price).await
+        }
+        UnlockAction::Close => {
+            let params = RemoveAllLiquidityAndClosePositionParameters { lb_pair, position };
+            remove_all_liquidity_and_close_position(
+                params,
+                program,
+                transaction_config,
+                compute_unit_price,
+            )
+            .await
+        }
+    }
+}
+
+pub async fn watch_position_unlocks<C: Deref<Target = impl Signer> + Clone>(
+    params: WatchPositionUnlocksParameters,
+    program: &Program<C>,
+    transaction_config: RpcSendTransactionConfig,
+    compute_unit_price: Option<Instruction>,
+) -> Result<()> {
+    let WatchPositionUnlocksParameters {
+        wallet,
+        lb_pair,
+        action,
+        state_path,
+        poll_interval,
+    } = params;
+
+    ensure!(poll_interval > 0, "Poll interval must be greater than 0");
+
+    let mut state = read_state(&state_path)?;
+    let mut handled_positions = state
+        .handled_positions
+        .iter()
+        .map(|handled| Pubkey::from_str(&handled.position))
+        .collect::<std::result::Result<HashSet<_>, _>>()?;
+    let mut tracked_positions = state
+        .tracked_positions
+        .iter()
+        .map(|position| Pubkey::from_str(position))
+        .collect::<std::result::Result<HashSet<_>, _>>()?;
+
+    println!(
+        "Watching positions of {} for unlock. Action {:?}. {} positions tracked and {} already handled in {}",
+        wallet,
+        action,
+        tracked_positions.len(),
+        handled_positions.len(),
+        state_path
+    );
+
+    let mut ignored_positions = HashSet::new();
+    let mut interval = tokio::time::interval(Duration::from_secs(poll_interval));
+
+    loop {
+        interval.tick().await;
+
+        // RPC errors are transient, retried on the next poll
+        let clock = match get_clock(program).await {
+            std::result::Result::Ok(clock) => clock,
+            Err(err) => {
+                println!("Failed to get clock. {}", err);
+                continue;
+            }
+        };
+
+        let positions = match get_watched_positions(program, wallet, lb_pair).await {
+            std::result::Result::Ok(positions) => positions,
+            Err(err) => {
+                println!(
+                    "[{}] Failed to get positions. {}",
+                    format_timestamp(clock.unix_timestamp),
+                    err
+                );
+                continue;
+            }
+        };
+        let mut lb_pairs: HashMap<Pubkey, LbPair> = HashMap::new();
+        let mut locked_count = 0;
+
+        for (key, position) in positions {
+            if handled_positions.contains(&key) || ignored_positions.contains(&key) {
+                continue;
+            }
+
+            // Liquidity is withdrawn to the sender, so only the owner can withdraw or close
+            if action != UnlockAction::ClaimFee && position.owner != program.payer() {
+                println!(
+                    "Ignore position {}. Only the owner {} can {:?} it",
+                    key, position.owner, action
+                );
+                ignored_positions.insert(key);
+                continue;
+            }
+
+            if !lb_pairs.contains_key(&position.lb_pair) {
+                match program.account::<LbPair>(position.lb_pair).await {
+                    std::result::Result::Ok(lb_pair_state) => {
+                        lb_pairs.insert(position.lb_pair, lb_pair_state);
+                    }
+                    Err(err) => {
+                        println!(
+                            "[{}] Failed to get pair {}. {}",
+                            format_timestamp(clock.unix_timestamp),
+                            position.lb_pair,
+                            err
+                        );
+                        continue;
+                    }
+                }
+            }
+            let lb_pair_state = &lb_pairs[&position.lb_pair];
+
+            let current_point =
+                get_current_point(lb_pair_state, clock.slot, clock.unix_timestamp as u64)?;
+
+            if position.is_liquidity_locked(current_point) {
+                if tracked_positions.insert(key) {
+                    println!(
+                        "Track position {} locked until {}",
+                        key, position.lock_release_point
+                    );
+                    state.tracked_positions.push(key.to_string());
+                    write_state(&state_path, &state)?;
+                }
+                locked_count += 1;
+                continue;
+            }
+
+            // Unlocked before the watcher first saw it, not a lock the watcher is waiting for
+            if !tracked_positions.contains(&key) {
+                println!(
+                    "Ignore position {}. Already unlocked at {} when first seen",
+                    key, position.lock_release_point
+                );
+                ignored_positions.insert(key);
+                continue;
+            }
+
+            println!(
+                "[{}] Position {} unlocked at {}. Running {:?}",
+                format_timestamp(clock.unix_timestamp),
+                key,
+                position.lock_release_point,
+                action
+            );
+
+            let result = run_unlock_action(
+                &action,
+                key,
+                position.lb_pair,
+                program,
+                transaction_config,
+                compute_unit_price.clone(),
+            )
+            .await;
+
+            if let Err(err) = result {
+                // Retried on the next poll
+                println!(
+                    "[{}] {:?} position {} failed. {}",
+                    format_timestamp(clock.unix_timestamp),
+                    action,
+                    key,
+                    err
+                );
+                continue;
+            }
+
+            println!(
+                "[{}] {:?} position {} done",
+                format_timestamp(clock.unix_timestamp),
+                action,
+                key
+            );
+
+            state.handled_positions.push(HandledPosition {
+                position: key.to_string(),
+                action: action.clone(),
+                unix_timestamp: clock.unix_timestamp,
+                slot: clock.slot,
+            });
+            write_state(&state_path, &state)?;
+            handled_positions.insert(key);
+        }
+
+        println!(
+            "[{}] {} positions still locked",
+            format_timestamp(clock.unix_timestamp),
+            locked_count
+        );
+    }
+}
@@ -0,0 +1,85 @@
+use crate::instructions::utils::get_or_create_ata;
+use anchor_client::solana_client::rpc_config::RpcSendTransactionConfig;
+use anchor_client::solana_sdk::instruction::Instruction;
+use anchor_client::{solana_sdk::pubkey::Pubkey, solana_sdk::signer::Signer, Program};
+use anyhow::*;
+use lb_clmm::accounts;
+use lb_clmm::instruction;
+use lb_clmm::state::bin::BinArray;
+use lb_clmm::state::lb_pair::LbPair;
+use lb_clmm::utils::pda::*;
+use std::ops::Deref;
+
+#[derive(Debug)]
+pub struct WithdrawIneligibleRewardParams {
+    pub lb_pair: Pubkey,
+    pub reward_index: u64,
+}
+
+pub async fn withdraw_ineligible_reward<C: Deref<Target = impl Signer> + Clone>(
+    params: WithdrawIneligibleRewardParams,
+    program: &Program<C>,
+    transaction_config: RpcSendTransactionConfig,
+    compute_unit_price: Option<Instruction>,
+) -> Result<()> {
+    let WithdrawIneligibleRewardParams {
+        lb_pair,
+        reward_index,
+    } = params;
+
+    let (reward_vault, _bump) = derive_reward_vault_pda(lb_pair, reward_index);
+    let lb_pair_state: LbPair = program.account(lb_pair).await?;
+    let reward_info = lb_pair_state.reward_infos[reward_index as usize];
+    let reward_mint = reward_info.mint;
+
+    ensure!(
+        reward_info.funder == program.payer(),
+        "Only the reward funder {} can withdraw the ineligible reward",
+        reward_info.funder
+    );
+
+    let funder_token_account = get_or_create_ata(
+        program,
+        transaction_config,
+        reward_mint,
+        program.payer(),
+        compute_unit_price.clone(),
+    )
+    .await?;
+
+    let active_bin_array_idx = BinArray::bin_id_to_bin_array_index(lb_pair_state.active_id)?;
+    let (bin_array, _bump) = derive_bin_array_pda(lb_pair, active_bin_array_idx as i64);
+
+    let (event_authority, _bump) = derive_event_authority_pda();
+
+    let accounts = accounts::WithdrawIneligibleReward {
+        lb_pair,
+        reward_vault,
+        reward_mint,
+        funder: program.payer(),
+        funder_token_account,
+        bin_array,
+        token_program: anchor_spl::token::ID,
+        event_authority,
+        program: lb_clmm::ID,
+    };
+
+    let ix = instruction::WithdrawIneligibleReward { reward_index };
+
+    let mut request_builder = program.request();
+    if let Some(compute_unit_price) = compute_unit_price {
+        request_builder = request_builder.instruction(compute_unit_price);
+    }
+
+    let signature = request_builder
+        .accounts(accounts)
+        .args(ix)
+        .send_with_spinner_and_config(transaction_config)
+        .await;
+
+    println!("Withdraw ineligible reward. Signature: {:#?}", signature);
+
+    signature?;
+
+    Ok(())
+}
@@ -6,13 +6,12 @@ use anchor_client::solana_sdk::instruction::Instruction;
 use anchor_client::Client;
 use anchor_client::{
     solana_client::rpc_config::RpcSendTransactionConfig,
-    solana_sdk::{
-        commitment_config::CommitmentConfig,
-        signer::{keypair::*, Signer},
-    },
+    solana_sdk::{commitment_config::CommitmentConfig, signer::Signer},
 };
 use anyhow::*;
 use clap::*;
+use commons::account_layout::fetch_position;
+use commons::signer::load_signer;
 
 mod args;
 mod instructions;
@@ -44,6 +43,7 @@ use crate::{
     args::Command,
     instructions::{
         add_liquidity::{add_liquidity, AddLiquidityParam},
+        can_i_do::*,
         check_my_balance::{check_my_balance, CheckMyBalanceParameters},
         claim_fee::claim_fee,
         claim_reward::*,
@@ -60,27 +60,42 @@ use crate::{
             initialize_permission_lb_pair, InitPermissionLbPairParameters,
         },
         initialize_position::{initialize_position, InitPositionParameters},
+        initialize_position_by_operator::*,
         initialize_preset_parameter::initialize_preset_parameter,
         initialize_reward::*,
         list_all_binstep::list_all_binstep,
+        list_operated_positions::*,
+        migrate_legacy_accounts::*,
+        plan_ilm_launch::*,
         remove_liquidity::{remove_liquidity, RemoveLiquidityParameters},
         remove_liquidity_by_price_range::{
             remove_liquidity_by_price_range, RemoveLiquidityByPriceRangeParameters,
         },
+        run_plan::{run_plan, RunPlanParameters},
+        seed_distribution::SeedDistribution,
         seed_liquidity::{seed_liquidity, SeedLiquidityParameters},
+        seed_liquidity_plan::*,
         set_activation_point::*,
         set_pre_activation_duration::{set_pre_activation_duration, SetPreactivationDurationParam},
         set_pre_activation_swap_address::{
             set_pre_activation_swap_address, SetPreactivationSwapAddressParam,
         },
+        show_launch_schedule::*,
+        show_oracle::*,
         show_pair::show_pair,
+        show_rewards::*,
         simulate_swap_demand::{simulate_swap_demand, SimulateSwapDemandParameters},
         swap_exact_in::{swap, SwapExactInParameters},
         swap_exact_out::{swap_exact_out, SwapExactOutParameters},
         swap_with_price_impact::{swap_with_price_impact, SwapWithPriceImpactParameters},
         toggle_pair_status::toggle_pool_status,
+        tune_fee_parameters::*,
+        update_fee_parameters::*,
+        update_position_operator::*,
         update_reward_duration::*,
         update_reward_funder::*,
+        watch_position_unlocks::*,
+        withdraw_ineligible_reward::*,
         withdraw_protocol_fee::{withdraw_protocol_fee, WithdrawProtocolFeeParams},
     },
 };
@@ -99,15 +114,14 @@ fn get_set_compute_unit_price_ix(micro_lamports: u64) -> Option<Instruction> {
 async fn main() -> Result<()> {
     let cli = Cli::parse();
 
-    let payer =
-        read_keypair_file(cli.config_override.wallet).expect("Wallet keypair file not found");
+    let payer = load_signer(&cli.config_override.wallet).expect("Wallet signer not found");
 
     println!("Wallet {:#?}", payer.pubkey());
 
     let commitment_config = CommitmentConfig::confirmed();
     let client = Client::new_with_options(
         cli.config_override.cluster,
-        Rc::new(Keypair::from_bytes(&payer.to_bytes())?),
+        Rc::new(payer),
         commitment_config,
     );
 
@@ -256,9 +270,25 @@ async fn main() -> Result<()> {
         Command::ShowPair { lb_pair } => {
             show_pair(lb_pair, &amm_program).await?;
         }
+        Command::ShowOracle { lb_pair, window } => {
+            let params = ShowOracleParameters { lb_pair, window };
+            show_oracle(params, &amm_program).await?;
+        }
+        Command::ShowRewards {
+            lb_pair,
+            target_end,
+            reward_prices,
+        } => {
+            let params = ShowRewardsParameters {
+                lb_pair,
+                target_end,
+                reward_prices,
+            };
+            show_rewards(params, &amm_program).await?;
+        }
         Command::ShowPosition { position } => {
-            let position: lb_clmm::state::position::Position =
-                amm_program.account(position).await?;
+            let (layout, position) = fetch_position(&amm_program.rpc(), position).await?;
+            println!("Layout {:?}", layout);
             println!("{:#?}", position);
         }
 
@@ -304,6 +334,22 @@ async fn main() -> Result<()> {
             };
             update_reward_funder(params, &amm_program, transaction_config).await?;
         }
+        Command::WithdrawIneligibleReward {
+            lb_pair,
+            reward_index,
+        } => {
+            let params = WithdrawIneligibleRewardParams {
+                lb_pair,
+                reward_index,
+            };
+            withdraw_ineligible_reward(
+                params,
+                &amm_program,
+                transaction_config,
+                compute_unit_price_ix,
+            )
+            .await?;
+        }
         Command::ClosePosition { position } => {
             close_position(position, &amm_program, transaction_config, None).await?;
         }
@@ -335,6 +381,35 @@ async fn main() -> Result<()> {
         Command::ListAllBinStep => {
             list_all_binstep(&amm_program).await?;
         }
+        Command::TuneFeeParameters {
+            bin_step,
+            swaps_path,
+            swap_signature,
+            swaps_lb_pair,
+            swaps_limit,
+            candidate,
+            skip_on_chain_presets,
+        } => {
+            let swap_source = if let Some(path) = swaps_path {
+                SwapSource::File(path)
+            } else if let Some(lb_pair) = swaps_lb_pair {
+                SwapSource::LbPair {
+                    lb_pair,
+                    limit: swaps_limit,
+                }
+            } else if !swap_signature.is_empty() {
+                SwapSource::Signatures(swap_signature)
+            } else {
+                bail!("Missing swaps. Set --swaps-path, --swap-signature or --swaps-lb-pair");
+            };
+            let params = TuneFeeParametersParameters {
+                bin_step,
+                swap_source,
+                candidates: candidate,
+                skip_on_chain_presets,
+            };
+            tune_fee_parameters(params, &amm_program).await?;
+        }
         Command::SimulateSwapDemand {
             lb_pair,
             x_amount,
@@ -413,22 +488,25 @@ async fn main() -> Result<()> {
         }
         Command::SeedLiquidity {
             lb_pair,
-            base_position_path,
+            base_position_signer,
             amount,
             min_price,
             max_price,
             base_pubkey,
             curvature,
-            position_owner_path,
+            distribution,
+            position_owner_signer,
             max_retries,
         } => {
+            let distribution =
+                SeedDistribution::from_curvature_or_source(curvature, distribution.as_deref())?;
             let mut retry_count = 0;
             loop {
-                let position_base_kp = read_keypair_file(base_position_path.clone())
-                    .expect("position base keypair file not found");
+                let position_base_kp =
+                    load_signer(&base_position_signer).expect("position base signer not found");
 
-                let position_owner_kp = read_keypair_file(position_owner_path.clone())
-                    .expect("position owner keypair file not found");
+                let position_owner_kp =
+                    load_signer(&position_owner_signer).expect("position owner signer not found");
 
                 let params = SeedLiquidityParameters {
                     lb_pair,
@@ -438,7 +516,7 @@ async fn main() -> Result<()> {
                     max_price,
                     base_pubkey,
                     position_owner_kp,
-                    curvature,
+                    distribution: distribution.clone(),
                 };
                 if let Err(err) = seed_liquidity(
                     params,
@@ -462,21 +540,24 @@ async fn main() -> Result<()> {
         }
         Command::SeedLiquidityByOperator {
             lb_pair,
-            base_position_path,
+            base_position_signer,
             amount,
             min_price,
             max_price,
             base_pubkey,
             curvature,
+            distribution,
             position_owner,
             fee_owner,
             lock_release_point,
             max_retries,
         } => {
+            let distribution =
+                SeedDistribution::from_curvature_or_source(curvature, distribution.as_deref())?;
             let mut retry_count = 0;
             loop {
-                let position_base_kp = read_keypair_file(base_position_path.clone())
-                    .expect("position base keypair file not found");
+                let position_base_kp =
+                    load_signer(&base_position_signer).expect("position base signer not found");
 
                 let params = SeedLiquidityByOperatorParameters {
                     lb_pair,
@@ -488,7 +569,7 @@ async fn main() -> Result<()> {
                     position_owner,
                     fee_owner,
                     lock_release_point,
-                    curvature,
+                    distribution: distribution.clone(),
                 };
                 if let Err(err) = seed_liquidity_by_operator(
                     params,
@@ -512,18 +593,18 @@ async fn main() -> Result<()> {
         }
         Command::SeedLiquiditySingleBin {
             lb_pair,
-            base_position_path,
+            base_position_signer,
             base_pubkey,
             amount,
             price,
-            position_owner_path,
+            position_owner_signer,
             selective_rounding,
         } => {
-            let position_base_kp = read_keypair_file(base_position_path)
-                .expect("position base keypair file not found");
+            let position_base_kp =
+                load_signer(&base_position_signer).expect("position base signer not found");
 
-            let position_owner_kp = read_keypair_file(position_owner_path)
-                .expect("position owner keypair file not found");
+            let position_owner_kp =
+                load_signer(&position_owner_signer).expect("position owner signer not found");
 
             let params = SeedLiquiditySingleBinParameters {
                 lb_pair,
@@ -544,7 +625,7 @@ async fn main() -> Result<()> {
         }
         Command::SeedLiquiditySingleBinByOperator {
             lb_pair,
-            base_position_path,
+            base_position_signer,
             base_pubkey,
             amount,
             price,
@@ -553,8 +634,8 @@ async fn main() -> Result<()> {
             lock_release_point,
             selective_rounding,
         } => {
-            let position_base_kp = read_keypair_file(base_position_path)
-                .expect("position base keypair file not found");
+            let position_base_kp =
+                load_signer(&base_position_signer).expect("position base signer not found");
 
             let params = SeedLiquiditySingleBinByOperatorParameters {
                 lb_pair,
@@ -578,19 +659,227 @@ async fn main() -> Result<()> {
         Command::GetAllPositionsForAnOwner { lb_pair, owner } => {
             get_all_positions(&amm_program, lb_pair, owner).await?;
         }
+        Command::InitializePositionByOperator {
+            lb_pair,
+            base_position_signer,
+            lower_bin_id,
+            width,
+            position_owner,
+            fee_owner,
+            lock_release_point,
+        } => {
+            let position_base_kp =
+                load_signer(&base_position_signer).expect("position base signer not found");
+
+            let params = InitPositionByOperatorParameters {
+                lb_pair,
+                position_base_kp,
+                lower_bin_id,
+                width,
+                position_owner,
+                fee_owner,
+                lock_release_point,
+            };
+            initialize_position_by_operator(
+                params,
+                &amm_program,
+                transaction_config,
+                compute_unit_price_ix,
+            )
+            .await?;
+        }
+        Command::UpdatePositionOperator { position, operator } => {
+            let params = UpdatePositionOperatorParameters { position, operator };
+            update_position_operator(params, &amm_program, transaction_config).await?;
+        }
+        Command::ListOperatedPositions { wallet, lb_pair } => {
+            let params = ListOperatedPositionsParameters {
+                wallet: wallet.unwrap_or(amm_program.payer()),
+                lb_pair,
+            };
+            list_operated_positions(params, &amm_program).await?;
+        }
+        Command::FindLegacyAccounts { owner, lb_pair } => {
+            let params = FindLegacyAccountsParameters {
+                owner: owner.or_else(|| lb_pair.is_none().then(|| amm_program.payer())),
+                lb_pair,
+            };
+            show_legacy_accounts(params, &amm_program).await?;
+        }
+        Command::MigrateLegacyAccounts {
+            lb_pair,
+            position_batch_size,
+            bin_array_batch_size,
+        } => {
+            let params = MigrateLegacyAccountsParameters {
+                lb_pair,
+                position_batch_size,
+                bin_array_batch_size,
+            };
+            migrate_legacy_accounts(
+                params,
+                &amm_program,
+                transaction_config,
+                compute_unit_price_ix,
+            )
+            .await?;
+        }
+        Command::WatchPositionUnlocks {
+            wallet,
+            lb_pair,
+            action,
+            state_path,
+            poll_interval,
+        } => {
+            let params = WatchPositionUnlocksParameters {
+                wallet: wallet.unwrap_or(amm_program.payer()),
+                lb_pair,
+                action,
+                state_path,
+                poll_interval,
+            };
+            watch_position_unlocks(
+                params,
+                &amm_program,
+                transaction_config,
+                compute_unit_price_ix,
+            )
+            .await?;
+        }
+        Command::PlanIlmLaunch {
+            lb_pair,
+            base_pubkey,
+            amount,
+            min_price,
+            max_price,
+            curvature,
+            distribution,
+            price,
+            selective_rounding,
+        } => {
+            let shape = match (min_price, max_price, price, selective_rounding) {
+                (Some(min_price), Some(max_price), None, _) => IlmLaunchShape::Curve {
+                    min_price,
+                    max_price,
+                    distribution: SeedDistribution::from_curvature_or_source(
+                        curvature,
+                        distribution.as_deref(),
+                    )?,
+                },
+                (None, None, Some(price), Some(selective_rounding)) => IlmLaunchShape::SingleBin {
+                    price,
+                    selective_rounding,
+                },
+                _ => bail!("Provide either --min-price and --max-price, or --price and --selective-rounding"),
+            };
+            let params = PlanIlmLaunchParameters {
+                lb_pair,
+                base_pubkey,
+                amount,
+                shape,
+            };
+            plan_ilm_launch(params, &amm_program).await?;
+        }
+        Command::PlanSeedLiquidity {
+            lb_pair,
+            base_pubkey,
+            position_owner,
+            amount,
+            min_price,
+            max_price,
+            curvature,
+            distribution,
+            plan_path,
+        } => {
+            let params = PlanSeedLiquidityParameters {
+                lb_pair,
+                base_pubkey,
+                position_owner,
+                amount,
+                min_price,
+                max_price,
+                distribution: SeedDistribution::from_curvature_or_source(
+                    curvature,
+                    distribution.as_deref(),
+                )?,
+                plan_path,
+            };
+            plan_seed_liquidity(params, &amm_program).await?;
+        }
+        Command::ExecuteSeedLiquidityPlan {
+            plan_path,
+            base_position_signer,
+            position_owner_signer,
+            state_path,
+        } => {
+            let position_base_kp =
+                load_signer(&base_position_signer).expect("position base signer not found");
+            let position_owner_kp =
+                load_signer(&position_owner_signer).expect("position owner signer not found");
+
+            let params = ExecuteSeedLiquidityPlanParameters {
+                plan_path,
+                state_path,
+                position_base_kp,
+                position_owner_kp,
+            };
+            execute_seed_liquidity_plan(
+                params,
+                &amm_program,
+                transaction_config,
+                compute_unit_price_ix,
+            )
+            .await?;
+        }
+        Command::VerifySeedLiquidityPlan { plan_path } => {
+            let params = VerifySeedLiquidityPlanParameters { plan_path };
+            verify_seed_liquidity_plan(params, &amm_program).await?;
+        }
+        Command::CanIDo { lb_pair, wallet } => {
+            let params = CanIDoParameters { lb_pair, wallet };
+            can_i_do(params, &amm_program).await?;
+        }
+        Command::ShowLaunchSchedule {
+            lb_pair,
+            new_activation_point,
+            new_pre_activation_duration,
+        } => {
+            let params = ShowLaunchScheduleParameters {
+                lb_pair,
+                new_activation_point,
+                new_pre_activation_duration,
+            };
+            show_launch_schedule(params, &amm_program).await?;
+        }
+        Command::RunPlan {
+            plan_path,
+            checkpoint_path,
+        } => {
+            let params = RunPlanParameters {
+                plan_path,
+                checkpoint_path,
+            };
+            run_plan(
+                params,
+                &amm_program,
+                transaction_config,
+                compute_unit_price_ix,
+            )
+            .await?;
+        }
         Command::Admin(admin_command) => match admin_command {
             AdminCommand::InitializePermissionPair {
                 bin_step,
                 token_mint_x,
                 token_mint_y,
                 initial_price,
-                base_keypair_path,
+                base_keypair_signer,
                 base_fee_bps,
                 lock_duration,
                 activation_type,
             } => {
                 let base_keypair =
-                    read_keypair_file(base_keypair_path).expect("base keypair file not found");
+                    load_signer(&base_keypair_signer).expect("base keypair signer not found");
                 let params = InitPermissionLbPairParameters {
                     base_keypair,
                     bin_step,
@@ -746,6 +1035,20 @@ async fn main() -> Result<()> {
                 };
                 set_pre_activation_duration(params, &amm_program, transaction_config).await?;
             }
+            AdminCommand::UpdateFeeParameters {
+                lb_pair,
+                protocol_share,
+                base_factor,
+                serialize,
+            } => {
+                let params = UpdateFeeParametersParam {
+                    lb_pair,
+                    protocol_share,
+                    base_factor,
+                    serialize,
+                };
+                update_fee_parameters(params, &amm_program, transaction_config).await?;
+            }
         },
     };
 
@@ -0,0 +1,134 @@
+use std::cell::RefCell;
+
+use anchor_client::anchor_lang::{AccountDeserialize, Discriminator};
+use anchor_client::solana_client::nonblocking::rpc_client::RpcClient;
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anyhow::*;
+use lb_clmm::state::bin::{BinArray, LayoutVersion};
+use lb_clmm::state::position::{Position, PositionV2};
+
+/// Account layout of a position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PositionLayout {
+    /// Legacy `Position` with u64 liquidity shares, without operator, lock and fee owner
+    V1,
+    V2,
+}
+
+/// Decode a position account of either layout. Legacy positions are converted the same way as `migrate_position`
+/// does, so the liquidity shares are comparable with the bins of migrated bin arrays.
+pub fn decode_position(data: &[u8]) -> Result<(PositionLayout, PositionV2)> {
+    ensure!(data.len() >= 8, "Position account data too short");
+
+    if data.starts_with(PositionV2::DISCRIMINATOR) {
+        let position = PositionV2::try_deserialize(&mut &data[..])?;
+        return Ok((PositionLayout::V2, position));
+    }
+
+    ensure!(
+        data.starts_with(Position::DISCRIMINATOR),
+        "Account is not a position"
+    );
+
+    let position = convert_legacy_position(Position::try_deserialize(&mut &data[..])?)?;
+
+    Ok((PositionLayout::V1, position))
+}
+
+/// Convert a legacy position to the latest layout, the same way as `migrate_position` does.
+pub fn convert_legacy_position(position_v1: Position) -> Result<PositionV2> {
+    let position_v1 = RefCell::new(position_v1);
+    let mut position = PositionV2::default();
+    position
+        .migrate_from_v1(position_v1.borrow())
+        .map_err(|err| anyhow!("Failed to convert legacy position. {}", err))?;
+    Ok(position)
+}
+
+/// Convert a bin array of any layout to the latest one in place, scaling the liquidity supply of `V0` bin arrays as
+/// `migrate_bin_array` does. Return the layout the bin array was stored with.
+pub fn normalize_bin_array(bin_array: &mut BinArray) -> Result<LayoutVersion> {
+    let version = LayoutVersion::try_from(bin_array.version)
+        .map_err(|_| anyhow!("Unknown bin array layout version {}", bin_array.version))?;
+
+    if version == LayoutVersion::V0 {
+        bin_array
+            .migrate_to_v2()
+            .map_err(|err| anyhow!("Failed to convert legacy bin array. {}", err))?;
+    }
+
+    Ok(version)
+}
+
+/// Decode a bin array account of either layout.
+pub fn decode_bin_array(data: &[u8]) -> Result<(LayoutVersion, BinArray)> {
+    let mut bin_array = BinArray::try_deserialize(&mut &data[..])?;
+    let version = normalize_bin_array(&mut bin_array)?;
+    Ok((version, bin_array))
+}
+
+pub async fn fetch_position(
+    rpc_client: &RpcClient,
+    position: Pubkey,
+) -> Result<(PositionLayout, PositionV2)> {
+    let account = rpc_client.get_account(&position).await?;
+    decode_position(&account.data).context(format!("Failed to decode position {}", position))
+}
+
+pub async fn fetch_bin_array(rpc_client: &RpcClient, bin_array: Pubkey) -> Result<BinArray> {
+    let account = rpc_client.get_account(&bin_array).await?;
+    let (_version, bin_array_state) = decode_bin_array(&account.data)
+        .context(format!("Failed to decode bin array {}", bin_array))?;
+    Ok(bin_array_state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_client::anchor_lang::__private::bytemuck;
+    use lb_clmm::constants::MAX_BIN_PER_POSITION;
+    use lb_clmm::math::u64x64_math::SCALE_OFFSET;
+    use lb_clmm::state::position::{FeeInfo, UserRewardInfo};
+
+    #[test]
+    fn test_decode_legacy_position() {
+        let mut liquidity_shares = [0u64; MAX_BIN_PER_POSITION];
+        liquidity_shares[0] = 1_000;
+        liquidity_shares[1] = 2_000;
+
+        let position_v1 = Position {
+            lb_pair: Pubkey::new_unique(),
+            owner: Pubkey::new_unique(),
+            liquidity_shares,
+            reward_infos: [UserRewardInfo::default(); MAX_BIN_PER_POSITION],
+            fee_infos: [FeeInfo::default(); MAX_BIN_PER_POSITION],
+            lower_bin_id: -10,
+            upper_bin_id: 59,
+            last_updated_at: 100,
+            total_claimed_fee_x_amount: 5,
+            total_claimed_fee_y_amount: 6,
+            total_claimed_rewards: [0u64; 2],
+            _reserved: [0u8; 160],
+        };
+
+        let mut data = Position::DISCRIMINATOR.to_vec();
+        data.extend_from_slice(bytemuck::bytes_of(&position_v1));
+
+        let (layout, position) = decode_position(&data).unwrap();
+
+        assert_eq!(layout, PositionLayout::V1);
+        assert_eq!(position.lb_pair, position_v1.lb_pair);
+        assert_eq!(position.owner, position_v1.owner);
+        assert_eq!(position.lower_bin_id, -10);
+        assert_eq!(position.upper_bin_id, 59);
+        assert_eq!(position.total_claimed_fee_x_amount, 5);
+        assert_eq!(position.operator, Pubkey::default());
+        assert_eq!(position.lock_release_point, 0);
+        assert_eq!(
+            position.liquidity_shares[1].as_u128(),
+            2_000u128 << SCALE_OFFSET
+        );
+
+        assert!(decode_position(&[0u8; 8]).is_err());
+    }
+}
@@ -0,0 +1,177 @@
+use anyhow::{ensure, Result};
+use lb_clmm::state::lb_pair::LbPair;
+
+/// Fee rates of the pair at a point of time. Fee rates are in FEE_PRECISION unit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeeRateSnapshot {
+    pub timestamp: i64,
+    pub active_id: i32,
+    pub volatility_accumulator: u32,
+    pub volatility_reference: u32,
+    pub base_fee_rate: u128,
+    pub variable_fee_rate: u128,
+    pub total_fee_rate: u128,
+}
+
+impl FeeRateSnapshot {
+    fn new(lb_pair: &LbPair, timestamp: i64) -> Result<Self> {
+        Ok(Self {
+            timestamp,
+            active_id: lb_pair.active_id,
+            volatility_accumulator: lb_pair.v_parameters.volatility_accumulator,
+            volatility_reference: lb_pair.v_parameters.volatility_reference,
+            base_fee_rate: lb_pair.get_base_fee()?,
+            variable_fee_rate: lb_pair.get_variable_fee()?,
+            total_fee_rate: lb_pair.get_total_fee()?,
+        })
+    }
+}
+
+/// A swap which moves the active bin by `bin_delta` at `timestamp`. Negative delta is swap for Y, where the active
+/// bin moves to the left.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HypotheticalSwap {
+    pub timestamp: i64,
+    pub bin_delta: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SwapFeeForecast {
+    pub swap: HypotheticalSwap,
+    /// Fee rate charged in each bin the swap goes through, from the starting active bin to the ending active bin
+    pub bin_fee_rates: Vec<FeeRateSnapshot>,
+}
+
+impl SwapFeeForecast {
+    pub fn max_total_fee_rate(&self) -> u128 {
+        self.bin_fee_rates
+            .iter()
+            .map(|snapshot| snapshot.total_fee_rate)
+            .max()
+            .unwrap_or_default()
+    }
+}
+
+/// Fee rate a swap, which doesn't move the active bin, would be charged at `timestamp` if there's no trade until
+/// then.
+pub fn get_fee_rate_at(lb_pair: &LbPair, timestamp: i64) -> Result<FeeRateSnapshot> {
+    let mut lb_pair = *lb_pair;
+    lb_pair.update_references(timestamp)?;
+    lb_pair.update_volatility_accumulator()?;
+    FeeRateSnapshot::new(&lb_pair, timestamp)
+}
+
+/// Project how the fee decays from `current_timestamp` over the next `duration` seconds with no trades, sampled every
+/// `interval` seconds.
+pub fn forecast_fee_decay(
+    lb_pair: &LbPair,
+    current_timestamp: i64,
+    duration: u64,
+    interval: u64,
+) -> Result<Vec<FeeRateSnapshot>> {
+    ensure!(interval > 0, "Interval must be greater than 0");
+
+    let mut snapshots = vec![];
+    for elapsed in (0..=duration).step_by(interval.try_into()?) {
+        let timestamp = current_timestamp.saturating_add(elapsed.try_into()?);
+        snapshots.push(get_fee_rate_at(lb_pair, timestamp)?);
+    }
+
+    Ok(snapshots)
+}
+
+/// Project the fee path of a sequence of swaps, in the same way the program update the volatility parameters
+/// during swap.
+pub fn forecast_fee_for_swaps(
+    lb_pair: &LbPair,
+    swaps: &[HypotheticalSwap],
+) -> Result<Vec<SwapFeeForecast>> {
+    let mut lb_pair = *lb_pair;
+    let mut forecasts = vec![];
+
+    for swap in swaps {
+        ensure!(
+            swap.timestamp >= lb_pair.v_parameters.last_update_timestamp,
+            "Swap at {} is before the last update {}",
+            swap.timestamp,
+            lb_pair.v_parameters.last_update_timestamp
+        );
+
+        lb_pair.update_references(swap.timestamp)?;
+
+        let swap_for_y = swap.bin_delta < 0;
+        let mut bin_fee_rates = vec![];
+        for i in 0..=swap.bin_delta.unsigned_abs() {
+            if i > 0 {
+                lb_pair.advance_active_bin(swap_for_y)?;
+            }
+            lb_pair.update_volatility_accumulator()?;
+            bin_fee_rates.push(FeeRateSnapshot::new(&lb_pair, swap.timestamp)?);
+        }
+
+        lb_pair.v_parameters.last_update_timestamp = swap.timestamp;
+
+        forecasts.push(SwapFeeForecast {
+            swap: *swap,
+            bin_fee_rates,
+        });
+    }
+
+    Ok(forecasts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fee_decay_after_swaps() {
+        let lb_pair = LbPair {
+            bin_step: 10,
+            ..LbPair::default()
+        };
+        let filter_period = i64::from(lb_pair.parameters.filter_period);
+        let decay_period = i64::from(lb_pair.parameters.decay_period);
+
+        let forecasts = forecast_fee_for_swaps(
+            &lb_pair,
+            &[
+                HypotheticalSwap {
+                    timestamp: 1_000,
+                    bin_delta: 5,
+                },
+                HypotheticalSwap {
+                    timestamp: 1_001,
+                    bin_delta: -2,
+                },
+            ],
+        )
+        .unwrap();
+
+        let first = &forecasts[0];
+        assert_eq!(first.bin_fee_rates.len(), 6);
+        assert_eq!(first.bin_fee_rates[0].variable_fee_rate, 0);
+        assert_eq!(first.bin_fee_rates[5].volatility_accumulator, 50_000);
+
+        // Within filter period, the reference bin stays at the first swap starting bin
+        let second = &forecasts[1];
+        assert_eq!(second.bin_fee_rates[2].active_id, 3);
+        assert_eq!(second.bin_fee_rates[2].volatility_accumulator, 30_000);
+
+        let mut lb_pair = lb_pair;
+        lb_pair.active_id = 3;
+        lb_pair.v_parameters.volatility_accumulator = 50_000;
+        lb_pair.v_parameters.index_reference = 0;
+        lb_pair.v_parameters.last_update_timestamp = 1_001;
+
+        let decay = forecast_fee_decay(&lb_pair, 1_001, decay_period as u64, 1).unwrap();
+        assert!(decay
+            .windows(2)
+            .all(|w| w[1].total_fee_rate <= w[0].total_fee_rate));
+        assert_eq!(
+            decay[filter_period as usize].volatility_accumulator,
+            50_000 * u32::from(lb_pair.parameters.reduction_factor) / 10_000
+        );
+        assert_eq!(decay.last().unwrap().variable_fee_rate, 0);
+    }
+}
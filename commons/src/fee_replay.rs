@@ -0,0 +1,203 @@
+use std::collections::BTreeMap;
+
+use anyhow::{ensure, Context, Result};
+use lb_clmm::{
+    constants::{BASIS_POINT_MAX, FEE_PRECISION},
+    events::Swap as SwapEvent,
+    state::{lb_pair::LbPair, preset_parameters::PresetParameter},
+};
+use serde::{Deserialize, Serialize};
+
+/// A swap recorded from the chain. `amount_in` includes fee.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedSwap {
+    pub timestamp: i64,
+    pub start_bin_id: i32,
+    pub end_bin_id: i32,
+    pub swap_for_y: bool,
+    pub amount_in: u64,
+}
+
+impl RecordedSwap {
+    /// Swap event doesn't carry the time, which has to come from the block time of the transaction.
+    pub fn from_swap_event(event: &SwapEvent, block_time: i64) -> Self {
+        Self {
+            timestamp: block_time,
+            start_bin_id: event.start_bin_id,
+            end_bin_id: event.end_bin_id,
+            swap_for_y: event.swap_for_y,
+            amount_in: event.amount_in,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct FeeReplayReport {
+    /// Fee in token X, including protocol fee
+    pub total_fee_x: u64,
+    /// Fee in token Y, including protocol fee
+    pub total_fee_y: u64,
+    pub protocol_fee_x: u64,
+    pub protocol_fee_y: u64,
+    /// Number of bins swapped by total fee rate in basis point
+    pub fee_rate_bps_distribution: BTreeMap<u64, u64>,
+    /// Min total fee rate charged, in FEE_PRECISION unit
+    pub min_fee_rate: u128,
+    /// Max total fee rate charged, in FEE_PRECISION unit
+    pub max_fee_rate: u128,
+}
+
+impl FeeReplayReport {
+    pub fn lp_fee_x(&self) -> u64 {
+        self.total_fee_x - self.protocol_fee_x
+    }
+
+    pub fn lp_fee_y(&self) -> u64 {
+        self.total_fee_y - self.protocol_fee_y
+    }
+
+    fn record(
+        &mut self,
+        swap_for_y: bool,
+        fee: u64,
+        protocol_fee: u64,
+        fee_rate: u128,
+    ) -> Result<()> {
+        let (total_fee, total_protocol_fee) = if swap_for_y {
+            (&mut self.total_fee_x, &mut self.protocol_fee_x)
+        } else {
+            (&mut self.total_fee_y, &mut self.protocol_fee_y)
+        };
+        *total_fee = total_fee.checked_add(fee).context("MathOverflow")?;
+        *total_protocol_fee = total_protocol_fee
+            .checked_add(protocol_fee)
+            .context("MathOverflow")?;
+
+        if self.fee_rate_bps_distribution.is_empty() {
+            self.min_fee_rate = fee_rate;
+            self.max_fee_rate = fee_rate;
+        } else {
+            self.min_fee_rate = self.min_fee_rate.min(fee_rate);
+            self.max_fee_rate = self.max_fee_rate.max(fee_rate);
+        }
+
+        let fee_rate_bps =
+            u64::try_from(fee_rate * BASIS_POINT_MAX as u128 / FEE_PRECISION as u128)?;
+        *self
+            .fee_rate_bps_distribution
+            .entry(fee_rate_bps)
+            .or_default() += 1;
+
+        Ok(())
+    }
+}
+
+/// Replay the recorded swaps through the fee logic of a pair configured with the preset parameter. Bins the swap
+/// crossed are not recorded, so the amount in is split evenly across the bins from start to end bin.
+pub fn replay_swaps(preset: &PresetParameter, swaps: &[RecordedSwap]) -> Result<FeeReplayReport> {
+    let mut lb_pair = LbPair {
+        bin_step: preset.bin_step,
+        parameters: preset.to_static_parameters(),
+        ..LbPair::default()
+    };
+
+    let mut report = FeeReplayReport::default();
+
+    for swap in swaps {
+        ensure!(
+            swap.timestamp >= lb_pair.v_parameters.last_update_timestamp,
+            "Swaps must be sorted by timestamp"
+        );
+
+        // Active bin might be moved by swaps not in the record
+        lb_pair.active_id = swap.start_bin_id;
+        lb_pair.update_references(swap.timestamp)?;
+
+        let move_left = swap.end_bin_id < swap.start_bin_id;
+        let bin_count = u64::from(swap.end_bin_id.abs_diff(swap.start_bin_id)) + 1;
+        let amount_per_bin = swap.amount_in / bin_count;
+
+        for i in 0..bin_count {
+            if i > 0 {
+                lb_pair.advance_active_bin(move_left)?;
+            }
+            lb_pair.update_volatility_accumulator()?;
+
+            let amount_in = if i == bin_count - 1 {
+                swap.amount_in - amount_per_bin * (bin_count - 1)
+            } else {
+                amount_per_bin
+            };
+
+            let fee = lb_pair.compute_fee_from_amount(amount_in)?;
+            let protocol_fee = lb_pair.compute_protocol_fee(fee)?;
+
+            report.record(swap.swap_for_y, fee, protocol_fee, lb_pair.get_total_fee()?)?;
+        }
+
+        lb_pair.v_parameters.last_update_timestamp = swap.timestamp;
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lb_clmm::state::parameters::StaticParameters;
+
+    fn preset(variable_fee_control: u32) -> PresetParameter {
+        let default = StaticParameters::default();
+        PresetParameter {
+            bin_step: 10,
+            base_factor: 10_000,
+            filter_period: default.filter_period,
+            decay_period: default.decay_period,
+            reduction_factor: default.reduction_factor,
+            variable_fee_control,
+            max_volatility_accumulator: default.max_volatility_accumulator,
+            min_bin_id: default.min_bin_id,
+            max_bin_id: default.max_bin_id,
+            protocol_share: 1_000,
+        }
+    }
+
+    #[test]
+    fn test_replay_swaps() {
+        let swaps = [
+            RecordedSwap {
+                timestamp: 1_000,
+                start_bin_id: 0,
+                end_bin_id: -4,
+                swap_for_y: true,
+                amount_in: 1_000_000,
+            },
+            RecordedSwap {
+                timestamp: 1_005,
+                start_bin_id: -4,
+                end_bin_id: 0,
+                swap_for_y: false,
+                amount_in: 1_000_000,
+            },
+        ];
+
+        let static_fee_report = replay_swaps(&preset(0), &swaps).unwrap();
+        // Base fee rate = 10_000 * 10 = 10 bps
+        assert_eq!(static_fee_report.total_fee_x, 1_000);
+        assert_eq!(static_fee_report.total_fee_y, 1_000);
+        assert_eq!(static_fee_report.protocol_fee_x, 100);
+        assert_eq!(static_fee_report.lp_fee_x(), 900);
+        assert_eq!(
+            static_fee_report.fee_rate_bps_distribution,
+            BTreeMap::from([(10, 10)])
+        );
+
+        let dynamic_fee_report = replay_swaps(&preset(40_000), &swaps).unwrap();
+        assert_eq!(
+            dynamic_fee_report.min_fee_rate,
+            static_fee_report.min_fee_rate
+        );
+        assert!(dynamic_fee_report.total_fee_x > static_fee_report.total_fee_x);
+        assert!(dynamic_fee_report.max_fee_rate > static_fee_report.max_fee_rate);
+    }
+}
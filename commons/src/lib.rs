@@ -1 +1,7 @@
+pub mod account_layout;
+pub mod fee_forecast;
+pub mod fee_replay;
+pub mod oracle;
+pub mod pair_access;
 pub mod quote;
+pub mod signer;
@@ -0,0 +1,210 @@
+use anchor_client::anchor_lang::AccountDeserialize;
+use anyhow::{ensure, Context, Result};
+use lb_clmm::{
+    constants::{BASIS_POINT_MAX, SAMPLE_LIFETIME},
+    state::oracle::{Observation, Oracle},
+};
+
+const OBSERVATION_SIZE: usize = std::mem::size_of::<Observation>();
+
+/// Time weighted average active bin id between `start` and `end` timestamps.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OracleTwap {
+    pub start: i64,
+    pub end: i64,
+    pub twap_bin_id: f64,
+}
+
+impl OracleTwap {
+    /// Price per lamport of the time weighted average bin id
+    pub fn price_per_lamport(&self, bin_step: u16) -> f64 {
+        (1.0 + f64::from(bin_step) / f64::from(BASIS_POINT_MAX)).powf(self.twap_bin_id)
+    }
+}
+
+/// Oracle account read off chain, without going through `DynamicOracle`.
+#[derive(Debug)]
+pub struct OracleReader {
+    pub metadata: Oracle,
+    /// Initialized observations, from the earliest to the latest
+    pub observations: Vec<Observation>,
+}
+
+fn parse_observation(data: &[u8]) -> Result<Observation> {
+    Ok(Observation {
+        cumulative_active_bin_id: i128::from_le_bytes(data[0..16].try_into()?),
+        created_at: i64::from_le_bytes(data[16..24].try_into()?),
+        last_updated_at: i64::from_le_bytes(data[24..32].try_into()?),
+    })
+}
+
+/// Number of observations needed for the samples to cover `window` seconds.
+pub fn get_required_oracle_length(window: u64) -> u64 {
+    // The latest sample might just be created, so one more sample is required
+    window.div_ceil(SAMPLE_LIFETIME) + 1
+}
+
+impl OracleReader {
+    /// Parse raw oracle account data
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let metadata = Oracle::try_deserialize(&mut &data[..])?;
+
+        let observations_data = data
+            .get(Oracle::metadata_len()..)
+            .context("Oracle account data is too short")?;
+        let length = metadata.length as usize;
+        ensure!(
+            observations_data.len() >= length * OBSERVATION_SIZE,
+            "Oracle account data is shorter than the oracle length {}",
+            length
+        );
+
+        let mut observations = vec![];
+        for chunk in observations_data
+            .chunks_exact(OBSERVATION_SIZE)
+            .take(length)
+        {
+            let observation = parse_observation(chunk)?;
+            if observation.initialized() {
+                observations.push(observation);
+            }
+        }
+        // The ring buffer wraps around, and IncreaseLength appends empty slots behind the latest index
+        observations.sort_by_key(|observation| observation.last_updated_at);
+
+        Ok(Self {
+            metadata,
+            observations,
+        })
+    }
+
+    /// Earliest timestamp the samples can answer for. The cumulative active bin id is only known at the last
+    /// update of each sample.
+    pub fn earliest_timestamp(&self) -> Option<i64> {
+        self.observations
+            .first()
+            .map(|observation| observation.last_updated_at)
+    }
+
+    /// Cumulative active bin id at `timestamp`. Interpolated between samples, and extrapolated with the current
+    /// active bin id after the latest sample.
+    fn get_cumulative_active_bin_id(&self, timestamp: i64, active_id: i32) -> Option<f64> {
+        let latest = self.observations.last()?;
+        if timestamp >= latest.last_updated_at {
+            let elapsed = timestamp - latest.last_updated_at;
+            return Some(
+                latest.cumulative_active_bin_id as f64 + f64::from(active_id) * elapsed as f64,
+            );
+        }
+
+        for window in self.observations.windows(2) {
+            let (left, right) = (&window[0], &window[1]);
+            if timestamp >= left.last_updated_at && timestamp <= right.last_updated_at {
+                let duration = right.last_updated_at - left.last_updated_at;
+                if duration == 0 {
+                    return Some(left.cumulative_active_bin_id as f64);
+                }
+                let delta = (right.cumulative_active_bin_id - left.cumulative_active_bin_id) as f64;
+                let elapsed = (timestamp - left.last_updated_at) as f64;
+                return Some(
+                    left.cumulative_active_bin_id as f64 + delta * elapsed / duration as f64,
+                );
+            }
+        }
+
+        None
+    }
+
+    /// Time weighted average active bin id over the last `window` seconds. Return None when the samples don't cover
+    /// the window.
+    pub fn get_twap(
+        &self,
+        active_id: i32,
+        current_timestamp: i64,
+        window: u64,
+    ) -> Result<Option<OracleTwap>> {
+        ensure!(window > 0, "Window must be greater than 0");
+        let start = current_timestamp
+            .checked_sub(window.try_into()?)
+            .context("window overflow")?;
+
+        let Some(earliest_timestamp) = self.earliest_timestamp() else {
+            return Ok(None);
+        };
+        if start < earliest_timestamp {
+            return Ok(None);
+        }
+
+        let start_cumulative = self
+            .get_cumulative_active_bin_id(start, active_id)
+            .context("Fail to get cumulative active bin id at window start")?;
+        let end_cumulative = self
+            .get_cumulative_active_bin_id(current_timestamp, active_id)
+            .context("Fail to get cumulative active bin id at window end")?;
+
+        Ok(Some(OracleTwap {
+            start,
+            end: current_timestamp,
+            twap_bin_id: (end_cumulative - start_cumulative) / window as f64,
+        }))
+    }
+
+    /// Time weighted average active bin id between each consecutive samples
+    pub fn get_sample_twaps(&self) -> Vec<OracleTwap> {
+        self.observations
+            .windows(2)
+            .filter(|window| window[1].last_updated_at > window[0].last_updated_at)
+            .map(|window| {
+                let (left, right) = (&window[0], &window[1]);
+                let duration = right.last_updated_at - left.last_updated_at;
+                let delta = right.cumulative_active_bin_id - left.cumulative_active_bin_id;
+                OracleTwap {
+                    start: left.last_updated_at,
+                    end: right.last_updated_at,
+                    twap_bin_id: delta as f64 / duration as f64,
+                }
+            })
+            .collect()
+    }
+
+    /// Length to add through IncreaseLength for the oracle to be able to cover `window` seconds. 0 when the
+    /// current length is enough, even if the samples haven't been filled up yet.
+    pub fn get_length_to_add(&self, window: u64) -> u64 {
+        get_required_oracle_length(window).saturating_sub(self.metadata.length)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn observation(cumulative_active_bin_id: i128, timestamp: i64) -> Observation {
+        Observation {
+            cumulative_active_bin_id,
+            created_at: timestamp,
+            last_updated_at: timestamp,
+        }
+    }
+
+    #[test]
+    fn test_twap() {
+        let reader = OracleReader {
+            metadata: Oracle {
+                idx: 1,
+                active_size: 2,
+                length: 2,
+            },
+            // Active bin 10 between 1_000 and 1_120
+            observations: vec![observation(0, 1_000), observation(1_200, 1_120)],
+        };
+
+        // Active bin 20 after the latest sample
+        let twap = reader.get_twap(20, 1_180, 120).unwrap().unwrap();
+        assert_eq!(twap.start, 1_060);
+        assert_eq!(twap.twap_bin_id, 15.0);
+
+        assert!(reader.get_twap(20, 1_180, 181).unwrap().is_none());
+        assert_eq!(reader.get_length_to_add(120), 0);
+        assert_eq!(reader.get_length_to_add(600), 4);
+    }
+}
@@ -0,0 +1,226 @@
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anyhow::{ensure, Result};
+use lb_clmm::{
+    pair_action_access::{get_current_point, get_lb_pair_type_access_validator_with_current_point},
+    state::lb_pair::LbPair,
+};
+
+/// Actions gated by the pair type action access rules of the program.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PairAction {
+    Swap,
+    AddLiquidity,
+    DepositQuoteTokenInActiveBin,
+    RemoveAskSideLiquidity,
+    RemoveBidSideLiquidity,
+    InitializePosition,
+    InitializePositionByOperator,
+    InitializeBinArray,
+    SetPreActivationSwapAddress,
+}
+
+impl PairAction {
+    pub const ALL: [PairAction; 9] = [
+        PairAction::Swap,
+        PairAction::AddLiquidity,
+        PairAction::DepositQuoteTokenInActiveBin,
+        PairAction::RemoveAskSideLiquidity,
+        PairAction::RemoveBidSideLiquidity,
+        PairAction::InitializePosition,
+        PairAction::InitializePositionByOperator,
+        PairAction::InitializeBinArray,
+        PairAction::SetPreActivationSwapAddress,
+    ];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActionAvailability {
+    /// Allowed now. `until` is the point from which it is no longer allowed, if any.
+    Allowed { until: Option<u64> },
+    /// Not allowed now. `from` is the point from which it becomes allowed without any change to the pair, if any.
+    Disallowed { from: Option<u64> },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PairActionAccess {
+    pub action: PairAction,
+    pub availability: ActionAvailability,
+}
+
+/// Whether the wallet is allowed to perform the action on the pair at `current_point`. `current_point` is a slot
+/// or a timestamp depending on the activation type of the pair.
+pub fn is_action_allowed(
+    lb_pair: &LbPair,
+    wallet: Pubkey,
+    action: PairAction,
+    current_point: u64,
+) -> Result<bool> {
+    let validator = get_lb_pair_type_access_validator_with_current_point(lb_pair, current_point)?;
+
+    let allowed = match action {
+        PairAction::Swap => validator.validate_swap_access(wallet),
+        PairAction::AddLiquidity => validator.validate_add_liquidity_access(),
+        PairAction::DepositQuoteTokenInActiveBin => {
+            validator.validate_deposit_quote_token_in_active_bin()
+        }
+        PairAction::RemoveAskSideLiquidity => validator.validate_remove_liquidity_access(true)?,
+        PairAction::RemoveBidSideLiquidity => validator.validate_remove_liquidity_access(false)?,
+        PairAction::InitializePosition => validator.validate_initialize_position(),
+        PairAction::InitializePositionByOperator => {
+            validator.validate_initialize_position_by_operator()
+        }
+        PairAction::InitializeBinArray => validator.validate_initialize_bin_array(),
+        PairAction::SetPreActivationSwapAddress => {
+            validator.validate_set_pre_activation_swap_address().is_ok()
+        }
+    };
+
+    Ok(allowed)
+}
+
+/// Points after `current_point` where the access rules can flip, without any change to the pair.
+fn get_transition_points(lb_pair: &LbPair, current_point: u64) -> Vec<u64> {
+    let mut points = vec![
+        lb_pair
+            .activation_point
+            .saturating_sub(lb_pair.pre_activation_duration),
+        lb_pair.activation_point,
+        lb_pair.activation_point.saturating_add(1),
+    ];
+    points.retain(|point| *point > current_point && *point != u64::MAX);
+    points.sort_unstable();
+    points.dedup();
+    points
+}
+
+/// Actions the wallet is allowed to perform on the pair now, and the point where each of them changes.
+pub fn get_pair_action_accesses(
+    lb_pair: &LbPair,
+    wallet: Pubkey,
+    current_slot: u64,
+    current_timestamp: u64,
+) -> Result<Vec<PairActionAccess>> {
+    let current_point = get_current_point(lb_pair, current_slot, current_timestamp)?;
+    let transition_points = get_transition_points(lb_pair, current_point);
+
+    let mut accesses = vec![];
+    for action in PairAction::ALL {
+        let allowed = is_action_allowed(lb_pair, wallet, action, current_point)?;
+
+        let mut change_point = None;
+        for point in transition_points.iter() {
+            if is_action_allowed(lb_pair, wallet, action, *point)? != allowed {
+                change_point = Some(*point);
+                break;
+            }
+        }
+
+        let availability = if allowed {
+            ActionAvailability::Allowed {
+                until: change_point,
+            }
+        } else {
+            ActionAvailability::Disallowed { from: change_point }
+        };
+
+        accesses.push(PairActionAccess {
+            action,
+            availability,
+        });
+    }
+
+    Ok(accesses)
+}
+
+/// Validate whether a swap is allowed by the pair. Swaps without a known sender are checked against the public
+/// activation point, as the default pubkey can never be a signer.
+pub fn validate_swap_access(
+    lb_pair: &LbPair,
+    sender: Option<Pubkey>,
+    current_timestamp: u64,
+    current_slot: u64,
+) -> Result<()> {
+    let current_point = get_current_point(lb_pair, current_slot, current_timestamp)?;
+    ensure!(
+        is_action_allowed(
+            lb_pair,
+            sender.unwrap_or_default(),
+            PairAction::Swap,
+            current_point
+        )?,
+        "Pair is disabled or not activated"
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lb_clmm::pair_action_access::ActivationType;
+    use lb_clmm::state::lb_pair::PairType;
+
+    fn permission_pair(activation_point: u64, pre_activation_duration: u64) -> LbPair {
+        LbPair {
+            pair_type: PairType::Permission.into(),
+            activation_type: ActivationType::Timestamp.into(),
+            activation_point,
+            pre_activation_duration,
+            pre_activation_swap_address: Pubkey::new_unique(),
+            ..LbPair::default()
+        }
+    }
+
+    fn get_availability(accesses: &[PairActionAccess], action: PairAction) -> ActionAvailability {
+        accesses
+            .iter()
+            .find(|access| access.action == action)
+            .unwrap()
+            .availability
+    }
+
+    #[test]
+    fn test_swap_access_before_activation() {
+        let lb_pair = permission_pair(1_000, 100);
+        let wallet = Pubkey::new_unique();
+
+        let accesses = get_pair_action_accesses(&lb_pair, wallet, 0, 500).unwrap();
+        assert_eq!(
+            get_availability(&accesses, PairAction::Swap),
+            ActionAvailability::Disallowed { from: Some(1_000) }
+        );
+        assert_eq!(
+            get_availability(&accesses, PairAction::InitializePositionByOperator),
+            ActionAvailability::Allowed { until: Some(1_000) }
+        );
+        assert_eq!(
+            get_availability(&accesses, PairAction::AddLiquidity),
+            ActionAvailability::Allowed { until: None }
+        );
+
+        let accesses =
+            get_pair_action_accesses(&lb_pair, lb_pair.pre_activation_swap_address, 0, 500)
+                .unwrap();
+        assert_eq!(
+            get_availability(&accesses, PairAction::Swap),
+            ActionAvailability::Disallowed { from: Some(900) }
+        );
+    }
+
+    #[test]
+    fn test_validate_swap_access() {
+        let lb_pair = permission_pair(1_000, 100);
+
+        assert!(validate_swap_access(&lb_pair, None, 999, 0).is_err());
+        assert!(validate_swap_access(&lb_pair, None, 1_000, 0).is_ok());
+        assert!(
+            validate_swap_access(&lb_pair, Some(lb_pair.pre_activation_swap_address), 900, 0)
+                .is_ok()
+        );
+
+        let disabled_pair = LbPair {
+            status: 1,
+            ..LbPair::default()
+        };
+        assert!(validate_swap_access(&disabled_pair, None, 0, 0).is_err());
+    }
+}
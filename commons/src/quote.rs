@@ -1,16 +1,17 @@
 use anchor_client::solana_sdk::pubkey::Pubkey;
-use anyhow::{ensure, Context, Result};
+use anyhow::{Context, Result};
 use lb_clmm::{
-    pair_action_access::ActivationType,
     state::{
         bin::{Bin, BinArray, SwapResult},
         bin_array_bitmap_extension::BinArrayBitmapExtension,
-        lb_pair::{LbPair, PairStatus, PairType},
+        lb_pair::LbPair,
     },
     utils::pda::derive_bin_array_pda,
 };
 use std::collections::HashMap;
 
+use crate::pair_access::validate_swap_access;
+
 #[derive(Debug)]
 pub struct SwapExactInQuote {
     pub amount_out: u64,
@@ -23,33 +24,6 @@ pub struct SwapExactOutQuote {
     pub fee: u64,
 }
 
-fn validate_swap_activation(
-    lb_pair: &LbPair,
-    current_timestamp: u64,
-    current_slot: u64,
-) -> Result<()> {
-    ensure!(
-        lb_pair.status()?.eq(&PairStatus::Enabled),
-        "Pair is disabled"
-    );
-
-    let pair_type = lb_pair.pair_type()?;
-    if pair_type.eq(&PairType::Permission) {
-        let activation_type = ActivationType::try_from(lb_pair.activation_type)?;
-        let current_point = match activation_type {
-            ActivationType::Slot => current_slot,
-            ActivationType::Timestamp => current_timestamp,
-        };
-
-        ensure!(
-            current_point >= lb_pair.activation_point,
-            "Pair is disabled"
-        );
-    }
-
-    Ok(())
-}
-
 pub fn quote_exact_out(
     lb_pair_pubkey: Pubkey,
     lb_pair: &LbPair,
@@ -60,7 +34,7 @@ pub fn quote_exact_out(
     current_timestamp: u64,
     current_slot: u64,
 ) -> Result<SwapExactOutQuote> {
-    validate_swap_activation(lb_pair, current_timestamp, current_slot)?;
+    validate_swap_access(lb_pair, None, current_timestamp, current_slot)?;
 
     let mut lb_pair = *lb_pair;
     lb_pair.update_references(current_timestamp as i64)?;
@@ -149,7 +123,7 @@ pub fn quote_exact_in(
     current_timestamp: u64,
     current_slot: u64,
 ) -> Result<SwapExactInQuote> {
-    validate_swap_activation(lb_pair, current_timestamp, current_slot)?;
+    validate_swap_access(lb_pair, None, current_timestamp, current_slot)?;
 
     let mut lb_pair = *lb_pair;
     lb_pair.update_references(current_timestamp as i64)?;
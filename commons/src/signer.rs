@@ -0,0 +1,399 @@
+use anchor_client::solana_sdk::{
+    derivation_path::DerivationPath,
+    pubkey::Pubkey,
+    signature::Signature,
+    signer::{
+        keypair::{
+            generate_seed_from_seed_phrase_and_passphrase, keypair_from_seed_and_derivation_path,
+            read_keypair, read_keypair_file, Keypair,
+        },
+        Signer, SignerError,
+    },
+};
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{str::FromStr, sync::Arc};
+
+/// Signer shared between the anchor client and the instruction builders.
+pub type SharedSigner = Arc<dyn Signer + Send + Sync>;
+
+/// Solana CLI default derivation path. Same as `solana-keygen recover prompt://`.
+pub const DEFAULT_DERIVATION_PATH: &str = "m/44'/501'/0'/0'";
+
+/// Where to load a signer from.
+///
+/// Accepted formats:
+/// - `/path/to/keypair.json` or `file:/path/to/keypair.json`, a leading `~` is the home directory
+/// - `env:VAR_NAME`, the variable holds a JSON byte array or a base58 secret key
+/// - `base58:<secret key>`
+/// - `mnemonic:<seed phrase>` or `mnemonic:<seed phrase>#<derivation path>`
+/// - `http://host/path` or `https://host/path`, a remote signing service
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignerSource {
+    File(String),
+    Env(String),
+    Base58(String),
+    Mnemonic {
+        phrase: String,
+        derivation_path: String,
+    },
+    Remote(String),
+}
+
+impl FromStr for SignerSource {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+
+        if let Some(var) = s.strip_prefix("env:") {
+            return Ok(SignerSource::Env(var.to_string()));
+        }
+
+        if let Some(secret) = s.strip_prefix("base58:") {
+            return Ok(SignerSource::Base58(secret.to_string()));
+        }
+
+        if let Some(mnemonic) = s.strip_prefix("mnemonic:") {
+            let (phrase, derivation_path) = match mnemonic.split_once('#') {
+                Some((phrase, derivation_path)) => (phrase, derivation_path),
+                None => (mnemonic, DEFAULT_DERIVATION_PATH),
+            };
+            return Ok(SignerSource::Mnemonic {
+                phrase: phrase.split_whitespace().collect::<Vec<_>>().join(" "),
+                derivation_path: derivation_path.to_string(),
+            });
+        }
+
+        if s.starts_with("http://") || s.starts_with("https://") {
+            return Ok(SignerSource::Remote(s.to_string()));
+        }
+
+        let path = s.strip_prefix("file:").unwrap_or(s);
+        if path.is_empty() {
+            return Err(anyhow!("Empty signer source"));
+        }
+
+        Ok(SignerSource::File(shellexpand::tilde(path).to_string()))
+    }
+}
+
+impl SignerSource {
+    /// Load the signer. Remote signers are queried for their public key once here.
+    pub fn load(&self) -> Result<SharedSigner> {
+        let signer: SharedSigner = match self {
+            SignerSource::File(path) => {
+                let path = shellexpand::tilde(path);
+                Arc::new(
+                    read_keypair_file(path.as_ref())
+                        .map_err(|e| anyhow!("Failed to read keypair file {}: {}", path, e))?,
+                )
+            }
+            SignerSource::Env(var) => {
+                let value = std::env::var(var)
+                    .with_context(|| format!("Environment variable {} not set", var))?;
+                Arc::new(keypair_from_env_value(&value)?)
+            }
+            SignerSource::Base58(secret) => Arc::new(keypair_from_base58(secret)?),
+            SignerSource::Mnemonic {
+                phrase,
+                derivation_path,
+            } => Arc::new(keypair_from_mnemonic(phrase, derivation_path)?),
+            SignerSource::Remote(endpoint) => Arc::new(RemoteSigner::new(Box::new(
+                HttpSigningService::new(endpoint.clone()),
+            ))?),
+        };
+
+        Ok(signer)
+    }
+}
+
+/// Parse and load a signer in one go.
+pub fn load_signer(source: &str) -> Result<SharedSigner> {
+    SignerSource::from_str(source)?.load()
+}
+
+fn keypair_from_base58(secret: &str) -> Result<Keypair> {
+    let bytes = bs58::decode(secret.trim())
+        .into_vec()
+        .context("Invalid base58 secret key")?;
+    Keypair::from_bytes(&bytes).map_err(|e| anyhow!("Invalid secret key: {}", e))
+}
+
+fn keypair_from_env_value(value: &str) -> Result<Keypair> {
+    let value = value.trim();
+    if value.starts_with('[') {
+        read_keypair(&mut value.as_bytes()).map_err(|e| anyhow!("Invalid keypair bytes: {}", e))
+    } else {
+        keypair_from_base58(value)
+    }
+}
+
+fn keypair_from_mnemonic(phrase: &str, derivation_path: &str) -> Result<Keypair> {
+    let derivation_path = DerivationPath::from_absolute_path_str(derivation_path)
+        .map_err(|e| anyhow!("Invalid derivation path {}: {}", derivation_path, e))?;
+    let seed = generate_seed_from_seed_phrase_and_passphrase(phrase, "");
+    keypair_from_seed_and_derivation_path(&seed, Some(derivation_path))
+        .map_err(|e| anyhow!("Failed to derive keypair from mnemonic: {}", e))
+}
+
+/// A service holding the private key and signing messages on our behalf. Eg: HSM, KMS.
+pub trait RemoteSigningService: Send + Sync {
+    fn get_pubkey(&self) -> Result<Pubkey>;
+    fn sign_message(&self, pubkey: &Pubkey, message: &[u8]) -> Result<Signature>;
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RemotePubkeyResponse {
+    pub pubkey: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RemoteSignRequest {
+    pub pubkey: String,
+    /// Base58 encoded message
+    pub message: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RemoteSignResponse {
+    /// Base58 encoded signature
+    pub signature: String,
+}
+
+/// JSON over HTTP signing service.
+///
+/// `GET {endpoint}/pubkey` returns `RemotePubkeyResponse`
+/// `POST {endpoint}/sign` with `RemoteSignRequest` returns `RemoteSignResponse`
+pub struct HttpSigningService {
+    endpoint: String,
+    agent: ureq::Agent,
+}
+
+impl HttpSigningService {
+    pub fn new(endpoint: String) -> Self {
+        Self {
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            agent: ureq::AgentBuilder::new()
+                .timeout(std::time::Duration::from_secs(30))
+                .build(),
+        }
+    }
+}
+
+impl RemoteSigningService for HttpSigningService {
+    fn get_pubkey(&self) -> Result<Pubkey> {
+        let response: RemotePubkeyResponse = self
+            .agent
+            .get(&format!("{}/pubkey", self.endpoint))
+            .call()?
+            .into_json()?;
+        Pubkey::from_str(&response.pubkey).context("Invalid pubkey from remote signer")
+    }
+
+    fn sign_message(&self, pubkey: &Pubkey, message: &[u8]) -> Result<Signature> {
+        let response: RemoteSignResponse = self
+            .agent
+            .post(&format!("{}/sign", self.endpoint))
+            .send_json(RemoteSignRequest {
+                pubkey: pubkey.to_string(),
+                message: bs58::encode(message).into_string(),
+            })?
+            .into_json()?;
+        Signature::from_str(&response.signature).context("Invalid signature from remote signer")
+    }
+}
+
+/// Signer backed by a `RemoteSigningService`. Signatures are verified before being returned.
+pub struct RemoteSigner {
+    service: Box<dyn RemoteSigningService>,
+    pubkey: Pubkey,
+}
+
+impl RemoteSigner {
+    pub fn new(service: Box<dyn RemoteSigningService>) -> Result<Self> {
+        let pubkey = service.get_pubkey()?;
+        Ok(Self { service, pubkey })
+    }
+}
+
+impl Signer for RemoteSigner {
+    fn try_pubkey(&self) -> Result<Pubkey, SignerError> {
+        Ok(self.pubkey)
+    }
+
+    fn try_sign_message(&self, message: &[u8]) -> Result<Signature, SignerError> {
+        let signature = self
+            .service
+            .sign_message(&self.pubkey, message)
+            .map_err(|e| SignerError::Connection(e.to_string()))?;
+
+        if !signature.verify(self.pubkey.as_ref(), message) {
+            return Err(SignerError::Custom(
+                "Remote signer returned an invalid signature".to_string(),
+            ));
+        }
+
+        Ok(signature)
+    }
+
+    fn is_interactive(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+
+    /// Minimal HTTP server implementing the remote signer protocol with a local keypair.
+    fn spawn_mock_signing_server(keypair: Keypair) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+
+                let mut content_length = 0;
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).unwrap();
+                    if header.trim().is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = header.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            content_length = value.trim().parse().unwrap();
+                        }
+                    }
+                }
+
+                let mut body = vec![0u8; content_length];
+                reader.read_exact(&mut body).unwrap();
+
+                let response_body = if request_line.starts_with("GET /pubkey") {
+                    serde_json::to_string(&RemotePubkeyResponse {
+                        pubkey: keypair.pubkey().to_string(),
+                    })
+                    .unwrap()
+                } else {
+                    let request: RemoteSignRequest = serde_json::from_slice(&body).unwrap();
+                    assert_eq!(request.pubkey, keypair.pubkey().to_string());
+                    let message = bs58::decode(request.message).into_vec().unwrap();
+                    serde_json::to_string(&RemoteSignResponse {
+                        signature: keypair.sign_message(&message).to_string(),
+                    })
+                    .unwrap()
+                };
+
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    response_body.len(),
+                    response_body
+                )
+                .unwrap();
+            }
+        });
+
+        endpoint
+    }
+
+    #[test]
+    fn test_parse_signer_source() {
+        for source in ["~/keypair.json", "file:~/keypair.json"] {
+            let SignerSource::File(path) = SignerSource::from_str(source).unwrap() else {
+                panic!("{source} is not a file source");
+            };
+            assert!(!path.starts_with('~') && path.ends_with("/keypair.json"));
+        }
+        assert_eq!(
+            SignerSource::from_str("file:/tmp/keypair.json").unwrap(),
+            SignerSource::File("/tmp/keypair.json".to_string())
+        );
+        assert_eq!(
+            SignerSource::from_str("env:DLMM_WALLET").unwrap(),
+            SignerSource::Env("DLMM_WALLET".to_string())
+        );
+        assert_eq!(
+            SignerSource::from_str("https://signer.local/v1").unwrap(),
+            SignerSource::Remote("https://signer.local/v1".to_string())
+        );
+        assert_eq!(
+            SignerSource::from_str("mnemonic:a  b c#m/44'/501'/1'/0'").unwrap(),
+            SignerSource::Mnemonic {
+                phrase: "a b c".to_string(),
+                derivation_path: "m/44'/501'/1'/0'".to_string(),
+            }
+        );
+        assert!(SignerSource::from_str("").is_err());
+    }
+
+    #[test]
+    fn test_load_base58_and_env_signer() {
+        let keypair = Keypair::new();
+
+        let signer = load_signer(&format!("base58:{}", keypair.to_base58_string())).unwrap();
+        assert_eq!(signer.pubkey(), keypair.pubkey());
+
+        std::env::set_var(
+            "DLMM_TEST_SIGNER_BYTES",
+            format!("{:?}", keypair.to_bytes().to_vec()),
+        );
+        let signer = load_signer("env:DLMM_TEST_SIGNER_BYTES").unwrap();
+        assert_eq!(signer.pubkey(), keypair.pubkey());
+
+        let home = std::env::temp_dir().join("dlmm_test_signer_home");
+        std::fs::create_dir_all(&home).unwrap();
+        std::fs::write(
+            home.join("keypair.json"),
+            format!("{:?}", keypair.to_bytes().to_vec()),
+        )
+        .unwrap();
+        std::env::set_var("HOME", &home);
+        let signer = SignerSource::File("~/keypair.json".to_string())
+            .load()
+            .unwrap();
+        assert_eq!(signer.pubkey(), keypair.pubkey());
+
+        assert!(load_signer("env:DLMM_TEST_SIGNER_NOT_SET").is_err());
+    }
+
+    #[test]
+    fn test_load_mnemonic_signer() {
+        let phrase = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+        let default_account = load_signer(&format!("mnemonic:{}", phrase)).unwrap();
+        let second_account = load_signer(&format!("mnemonic:{}#m/44'/501'/1'/0'", phrase)).unwrap();
+
+        // Deterministic, and the derivation path matters
+        assert_eq!(
+            default_account.pubkey(),
+            load_signer(&format!("mnemonic:{}", phrase))
+                .unwrap()
+                .pubkey()
+        );
+        assert_ne!(default_account.pubkey(), second_account.pubkey());
+    }
+
+    #[test]
+    fn test_remote_signer() {
+        let keypair = Keypair::new();
+        let expected_pubkey = keypair.pubkey();
+        let message = b"dlmm remote signer";
+        let expected_signature = keypair.sign_message(message);
+
+        let endpoint = spawn_mock_signing_server(keypair);
+        let signer = load_signer(&endpoint).unwrap();
+
+        assert_eq!(signer.pubkey(), expected_pubkey);
+        assert_eq!(signer.sign_message(message), expected_signature);
+    }
+}
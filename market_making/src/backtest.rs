@@ -0,0 +1,878 @@
+use crate::core::get_amount_within_price_impact;
+use crate::pair_config::PairConfig;
+use crate::paper::{get_empty_bin_array, get_value, PaperDeposit, PaperTrading};
+use crate::state::SinglePosition;
+use crate::strategy::{Strategy, StrategyAction, StrategySnapshot};
+use anchor_client::solana_account_decoder::UiAccount;
+use anchor_client::solana_sdk::account::Account;
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::AccountDeserialize;
+use anyhow::*;
+use commons::account_layout::decode_bin_array;
+use commons::quote::{get_bin_array_pubkeys_for_swap, SwapExactInQuote};
+use lb_clmm::constants::BIN_ARRAY_BITMAP_SIZE;
+use lb_clmm::math::price_math::get_price_from_id;
+use lb_clmm::state::bin::{Bin, BinArray, SwapResult};
+use lb_clmm::state::bin_array_bitmap_extension::BinArrayBitmapExtension;
+use lb_clmm::state::lb_pair::LbPair;
+use lb_clmm::utils::pda;
+use serde::Deserialize;
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Recorded event of a pair, one JSON object per line of a backtest file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BacktestEvent {
+    /// Accounts of the pair, its bin arrays and its bitmap extension, as returned by the RPC with base64 encoding.
+    /// They replace the recorded accounts of the previous states, and the virtual positions keep their liquidity in the
+    /// new bins.
+    PoolState {
+        timestamp: u64,
+        accounts: Vec<UiAccount>,
+    },
+    /// Swap of the recorded flow, replayed against the bins
+    Swap {
+        timestamp: u64,
+        amount_in: u64,
+        swap_for_y: bool,
+    },
+}
+
+impl BacktestEvent {
+    pub fn get_timestamp(&self) -> u64 {
+        match self {
+            BacktestEvent::PoolState { timestamp, .. } => *timestamp,
+            BacktestEvent::Swap { timestamp, .. } => *timestamp,
+        }
+    }
+}
+
+pub fn get_events_from_file(path: &str) -> Result<Vec<BacktestEvent>> {
+    let file = File::open(path)?;
+    let mut events = vec![];
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let event: BacktestEvent =
+            serde_json::from_str(&line).context(format!("Invalid event at line {}", i + 1))?;
+        events.push(event);
+    }
+    Ok(events)
+}
+
+#[derive(Default, PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct BacktestReport {
+    pub start_timestamp: u64,
+    pub end_timestamp: u64,
+    /// Number of recorded swaps replayed
+    pub swap_count: usize,
+    pub rebalance_time: u64,
+    /// Fees earned, claimed and pending
+    pub fee_x: u64,
+    pub fee_y: u64,
+    /// Fees paid to all the liquidity providers by the replayed swaps, recorded and of the strategy
+    pub lp_fee_x: u64,
+    pub lp_fee_y: u64,
+    /// Value of the fees earned at the final price, in token y
+    pub fee_value: u64,
+    /// Value of the initial balances at the initial price, in token y
+    pub initial_value: u64,
+    /// Value of the initial balances at the final price, in token y
+    pub hold_value: u64,
+    /// Value of the wallet and the positions at the final price, in token y
+    pub value: u64,
+    /// Loss of the value without fees against holding the initial balances, as a ratio of the hold value
+    pub impermanent_loss: f64,
+    /// Value against the initial value, as a ratio of the initial value
+    pub total_return: f64,
+}
+
+/// Replay recorded events of a pair through a strategy. The clock follows the events: the strategy runs when the
+/// active bin moves to an edge position or out of the range, and at least every refresh interval, like the bot.
+/// Actions are applied to a virtual portfolio as in paper mode, except that swaps move the replayed bins and the
+/// deposits and withdraws add and remove their liquidity from them.
+pub struct Backtester {
+    pub config: PairConfig,
+    pub strategy: Arc<dyn Strategy>,
+    /// Seconds between strategy runs without trigger
+    pub refresh_interval: u64,
+    pub state: SinglePosition,
+    pub bitmap_extension: Option<BinArrayBitmapExtension>,
+    pub paper: PaperTrading,
+    pub timestamp: u64,
+    pub swap_count: usize,
+    pub lp_fee_x: u64,
+    pub lp_fee_y: u64,
+    start_timestamp: Option<u64>,
+    last_run_timestamp: u64,
+}
+
+impl Backtester {
+    pub fn new(config: PairConfig, strategy: Arc<dyn Strategy>, refresh_interval: u64) -> Self {
+        let lb_pair = Pubkey::from_str(&config.pair_address).unwrap();
+        let paper = PaperTrading::new(&vec![config.clone()]);
+        Backtester {
+            config,
+            strategy,
+            refresh_interval,
+            state: SinglePosition::new(lb_pair),
+            bitmap_extension: None,
+            paper,
+            timestamp: 0,
+            swap_count: 0,
+            lp_fee_x: 0,
+            lp_fee_y: 0,
+            start_timestamp: None,
+            last_run_timestamp: 0,
+        }
+    }
+
+    pub fn run(&mut self, events: &[BacktestEvent]) -> Result<BacktestReport> {
+        for event in events.iter() {
+            let started = self.start_timestamp.is_some();
+            let previous_active_id = self.state.lb_pair_state.active_id;
+            self.apply_event(event)?;
+
+            // The strategy also runs on the first pool state
+            let moved = !started
+                || (self.state.lb_pair_state.active_id != previous_active_id
+                    && self.state.is_active_bin_at_edge());
+            let refresh = self.timestamp >= self.last_run_timestamp + self.refresh_interval;
+            if moved || refresh {
+                // Like the bot, a failed run does not stop the following ones
+                if let Err(err) = self.run_strategy() {
+                    warn!("run strategy at {} err {}", self.timestamp, err);
+                }
+                self.last_run_timestamp = self.timestamp;
+            }
+        }
+        self.get_report()
+    }
+
+    fn apply_event(&mut self, event: &BacktestEvent) -> Result<()> {
+        ensure!(
+            event.get_timestamp() >= self.timestamp,
+            "Events are not ordered by timestamp"
+        );
+        self.timestamp = event.get_timestamp();
+        self.state.last_update_timestamp = self.timestamp;
+
+        match event {
+            BacktestEvent::PoolState { accounts, .. } => {
+                for account in accounts.iter() {
+                    let account = account
+                        .decode::<Account>()
+                        .context("Cannot decode recorded account")?;
+                    self.apply_account(&account.data)?;
+                }
+                self.sync_bin_array_bitmap()?;
+                if self.start_timestamp.is_none() {
+                    self.paper
+                        .start(self.state.lb_pair, &self.state.lb_pair_state)?;
+                    self.start_timestamp = Some(self.timestamp);
+                    self.last_run_timestamp = self.timestamp;
+                }
+            }
+            BacktestEvent::Swap {
+                amount_in,
+                swap_for_y,
+                ..
+            } => {
+                ensure!(
+                    self.start_timestamp.is_some(),
+                    "A pool state must come before the swaps"
+                );
+                self.replay_swap(*amount_in, *swap_for_y)?;
+                self.swap_count += 1;
+            }
+        }
+        Ok(())
+    }
+
+    fn apply_account(&mut self, data: &[u8]) -> Result<()> {
+        if let std::result::Result::Ok(lb_pair_state) = LbPair::try_deserialize(&mut &data[..]) {
+            self.state.lb_pair_state = lb_pair_state;
+            return Ok(());
+        }
+        if let std::result::Result::Ok(bitmap_extension) =
+            BinArrayBitmapExtension::try_deserialize(&mut &data[..])
+        {
+            self.bitmap_extension = Some(bitmap_extension);
+            return Ok(());
+        }
+        let (_version, mut bin_array) = decode_bin_array(data)?;
+        let (bin_array_pk, _bump) = pda::derive_bin_array_pda(self.state.lb_pair, bin_array.index);
+        if let Some(previous_bin_array) = self.state.bin_arrays.get(&bin_array_pk) {
+            // The recorded bins do not hold the liquidity of the virtual positions, move it to them
+            for (bin_id, share) in self.get_liquidity_shares(&self.state.position_pks)? {
+                if bin_array.is_bin_id_within_range(bin_id).is_err() {
+                    continue;
+                }
+                let (amount_x, amount_y) = previous_bin_array
+                    .get_bin(bin_id)?
+                    .calculate_out_amount(share)?;
+                bin_array
+                    .get_bin_mut(bin_id)?
+                    .deposit(amount_x, amount_y, share)?;
+            }
+        }
+        self.state.bin_arrays.insert(bin_array_pk, bin_array);
+        Ok(())
+    }
+
+    /// Liquidity shares of the virtual positions, as (bin_id, share).
+    fn get_liquidity_shares(&self, positions: &[Pubkey]) -> Result<Vec<(i32, u128)>> {
+        let mut shares = vec![];
+        for (position_pk, position) in self
+            .state
+            .position_pks
+            .iter()
+            .zip(self.state.positions.iter())
+        {
+            if !positions.contains(position_pk) {
+                continue;
+            }
+            for (i, share) in position.liquidity_shares.iter().enumerate() {
+                let share = share.as_u128();
+                if share > 0 {
+                    shares.push((position.from_idx_to_bin_id(i)?, share));
+                }
+            }
+        }
+        Ok(shares)
+    }
+
+    fn get_bin_mut(&mut self, bin_id: i32) -> Result<&mut Bin> {
+        let index = BinArray::bin_id_to_bin_array_index(bin_id)?;
+        let (bin_array_pk, _bump) = pda::derive_bin_array_pda(self.state.lb_pair, index.into());
+        let bin_array = self
+            .state
+            .bin_arrays
+            .get_mut(&bin_array_pk)
+            .context("Cannot get bin array")?;
+        Ok(bin_array.get_bin_mut(bin_id)?)
+    }
+
+    /// Add the liquidity of new virtual positions to the replayed bins, like the program does on a deposit.
+    fn add_liquidity(&mut self, deposits: &[PaperDeposit]) -> Result<()> {
+        for deposit in deposits.iter() {
+            for &(bin_id, amount_x, amount_y, share) in deposit.bins.iter() {
+                self.get_bin_mut(bin_id)?
+                    .deposit(amount_x, amount_y, share)?;
+            }
+        }
+        self.sync_bin_array_bitmap()
+    }
+
+    /// Remove the liquidity of withdrawn virtual positions from the replayed bins. It must run before the positions
+    /// are synced out of the state.
+    fn remove_liquidity(&mut self, positions: &[Pubkey]) -> Result<()> {
+        for (bin_id, share) in self.get_liquidity_shares(positions)? {
+            self.get_bin_mut(bin_id)?.withdraw(share)?;
+        }
+        self.sync_bin_array_bitmap()
+    }
+
+    /// Set the bit of each bin array in the bitmap to whether it holds liquidity, like the program does when the
+    /// liquidity of a bin array becomes or stops being zero, so that the swaps find the bins of the virtual positions.
+    fn sync_bin_array_bitmap(&mut self) -> Result<()> {
+        for bin_array in self.state.bin_arrays.values() {
+            let index = bin_array.index as i32;
+            let has_liquidity = !bin_array.is_zero_liquidity();
+            if self
+                .state
+                .lb_pair_state
+                .is_overflow_default_bin_array_bitmap(index)
+            {
+                let Some(bitmap_extension) = self.bitmap_extension.as_mut() else {
+                    ensure!(
+                        !has_liquidity,
+                        "Bitmap extension not found for bin array {index}"
+                    );
+                    continue;
+                };
+                if bitmap_extension.bit(index)? != has_liquidity {
+                    bitmap_extension.flip_bin_array_bit(index)?;
+                }
+            } else {
+                let offset = (index + BIN_ARRAY_BITMAP_SIZE) as usize;
+                let bit =
+                    self.state.lb_pair_state.bin_array_bitmap[offset / 64] >> (offset % 64) & 1;
+                if (bit == 1) != has_liquidity {
+                    self.state.lb_pair_state.flip_bin_array_bit(&None, index)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Swap against the replayed bins, counting the fees paid to the liquidity providers.
+    fn replay_swap(&mut self, amount_in: u64, swap_for_y: bool) -> Result<SwapExactInQuote> {
+        let protocol_fee_x = self.state.lb_pair_state.protocol_fee.amount_x;
+        let protocol_fee_y = self.state.lb_pair_state.protocol_fee.amount_y;
+        let quote = apply_swap(
+            &mut self.state,
+            self.bitmap_extension.as_ref(),
+            amount_in,
+            swap_for_y,
+            self.timestamp,
+        )?;
+
+        // The fee is paid in the token in
+        if swap_for_y {
+            let protocol_fee = self.state.lb_pair_state.protocol_fee.amount_x - protocol_fee_x;
+            self.lp_fee_x = self.lp_fee_x.saturating_add(quote.fee - protocol_fee);
+        } else {
+            let protocol_fee = self.state.lb_pair_state.protocol_fee.amount_y - protocol_fee_y;
+            self.lp_fee_y = self.lp_fee_y.saturating_add(quote.fee - protocol_fee);
+        }
+        Ok(quote)
+    }
+
+    fn run_strategy(&mut self) -> Result<()> {
+        let (balance_x, balance_y) = self.paper.get_balances(self.state.lb_pair)?;
+        let snapshot = StrategySnapshot {
+            config: &self.config,
+            state: &self.state,
+            position: self.state.get_positions()?,
+            balance_x,
+            balance_y,
+            timestamp: self.timestamp,
+        };
+
+        let actions = self.strategy.decide(&snapshot)?;
+        if actions.is_empty() {
+            return Ok(());
+        }
+
+        self.execute_actions(actions)?;
+        self.state.inc_rebalance_time();
+        Ok(())
+    }
+
+    fn execute_actions(&mut self, actions: Vec<StrategyAction>) -> Result<()> {
+        let lb_pair = self.state.lb_pair;
+        // Output of the swaps, added to the next deposit
+        let mut swap_out_x = 0u64;
+        let mut swap_out_y = 0u64;
+
+        for action in actions {
+            match action {
+                StrategyAction::Withdraw => {
+                    let positions = self.state.position_pks.clone();
+                    self.paper.withdraw(&self.state, &positions)?;
+                    self.remove_liquidity(&positions)?;
+                }
+                StrategyAction::WithdrawPositions { positions } => {
+                    self.paper.withdraw(&self.state, &positions)?;
+                    self.remove_liquidity(&positions)?;
+                }
+                StrategyAction::ClaimFee => {
+                    self.paper.claim_fee(&self.state)?;
+                }
+                StrategyAction::Swap {
+                    amount_in,
+                    swap_for_y,
+                } => {
+                    let Some(quote) = self.swap(amount_in, swap_for_y)? else {
+                        continue;
+                    };
+                    if swap_for_y {
+                        swap_out_y = swap_out_y.saturating_add(quote.amount_out);
+                    } else {
+                        swap_out_x = swap_out_x.saturating_add(quote.amount_out);
+                    }
+                }
+                StrategyAction::Deposit {
+                    amount_x,
+                    amount_y,
+                    min_bin_id,
+                    max_bin_id,
+                    strategy_type,
+                } => {
+                    let (balance_x, balance_y) = self.paper.get_balances(lb_pair)?;
+                    let amount_x = amount_x.saturating_add(swap_out_x).min(balance_x);
+                    let amount_y = amount_y.saturating_add(swap_out_y).min(balance_y);
+                    swap_out_x = 0;
+                    swap_out_y = 0;
+
+                    self.insert_bin_arrays(min_bin_id, max_bin_id)?;
+                    let deposits = self.paper.deposit(
+                        &self.state,
+                        amount_x,
+                        amount_y,
+                        min_bin_id,
+                        max_bin_id,
+                        strategy_type,
+                    )?;
+                    self.add_liquidity(&deposits)?;
+                }
+            }
+        }
+
+        self.sync_positions()
+    }
+
+    /// Swap of the strategy, within the price impact limit of the pair. It moves the replayed bins like a recorded
+    /// swap, and its output goes to the virtual wallet.
+    fn swap(&mut self, amount_in: u64, swap_for_y: bool) -> Result<Option<SwapExactInQuote>> {
+        let lb_pair = self.state.lb_pair;
+        let (balance_x, balance_y) = self.paper.get_balances(lb_pair)?;
+        let amount_in = amount_in.min(if swap_for_y { balance_x } else { balance_y });
+        let price = get_price_from_id(
+            self.state.lb_pair_state.active_id,
+            self.state.lb_pair_state.bin_step,
+        )?;
+
+        let swap = get_amount_within_price_impact(
+            amount_in,
+            price,
+            swap_for_y,
+            self.config.get_max_price_impact_bps(),
+            |amount_in| {
+                let mut state = self.state.clone();
+                apply_swap(
+                    &mut state,
+                    self.bitmap_extension.as_ref(),
+                    amount_in,
+                    swap_for_y,
+                    self.timestamp,
+                )
+            },
+        )?;
+        let Some((amount_in, _min_amount_out, _quote)) = swap else {
+            return Ok(None);
+        };
+
+        let quote = self.replay_swap(amount_in, swap_for_y)?;
+        self.paper
+            .swap(lb_pair, amount_in, quote.amount_out, swap_for_y)?;
+        Ok(Some(quote))
+    }
+
+    /// Add empty bin arrays for the bin range and the active bin where none was recorded.
+    fn insert_bin_arrays(&mut self, min_bin_id: i32, max_bin_id: i32) -> Result<()> {
+        let lower_bin_array_idx = BinArray::bin_id_to_bin_array_index(min_bin_id)?;
+        // A position may end in the bin array after the one of its upper bin
+        let upper_bin_array_idx = BinArray::bin_id_to_bin_array_index(max_bin_id)?
+            .checked_add(1)
+            .context("MathOverflow")?;
+        let active_bin_array_idx =
+            BinArray::bin_id_to_bin_array_index(self.state.lb_pair_state.active_id)?;
+
+        for index in (lower_bin_array_idx..=upper_bin_array_idx).chain([active_bin_array_idx]) {
+            let (bin_array_pk, _bump) = pda::derive_bin_array_pda(self.state.lb_pair, index.into());
+            if !self.state.bin_arrays.contains_key(&bin_array_pk) {
+                self.state.bin_arrays.insert(
+                    bin_array_pk,
+                    get_empty_bin_array(self.state.lb_pair, index)?,
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Put the virtual positions in the state, sorted by bin id, like a refresh of the bot.
+    fn sync_positions(&mut self) -> Result<()> {
+        let mut positions = self.paper.get_positions(self.state.lb_pair)?;
+        positions.sort_by_key(|(_, position)| position.lower_bin_id);
+
+        self.state.min_bin_id = positions
+            .first()
+            .map(|(_, position)| position.lower_bin_id)
+            .unwrap_or(0);
+        self.state.max_bin_id = positions
+            .iter()
+            .map(|(_, position)| position.upper_bin_id)
+            .max()
+            .unwrap_or(0);
+        self.state.position_pks = positions.iter().map(|(pk, _)| *pk).collect();
+        self.state.positions = positions
+            .into_iter()
+            .map(|(_, position)| position)
+            .collect();
+        Ok(())
+    }
+
+    pub fn get_report(&self) -> Result<BacktestReport> {
+        let performance = self
+            .paper
+            .get_performance(&self.state, &self.state.get_positions()?)?;
+        let price = get_price_from_id(
+            self.state.lb_pair_state.active_id,
+            self.state.lb_pair_state.bin_step,
+        )?;
+        let fee_value = get_value(performance.fee_x, performance.fee_y, price)?;
+
+        let ratio = |value: u64, reference: u64| -> f64 {
+            if reference == 0 {
+                return 0.0;
+            }
+            (value as f64 - reference as f64) / reference as f64
+        };
+
+        Ok(BacktestReport {
+            start_timestamp: self.start_timestamp.unwrap_or_default(),
+            end_timestamp: self.timestamp,
+            swap_count: self.swap_count,
+            rebalance_time: self.state.rebalance_time,
+            fee_x: performance.fee_x,
+            fee_y: performance.fee_y,
+            lp_fee_x: self.lp_fee_x,
+            lp_fee_y: self.lp_fee_y,
+            fee_value,
+            initial_value: performance.initial_value,
+            hold_value: performance.hold_value,
+            value: performance.value,
+            impermanent_loss: -ratio(
+                performance.value.saturating_sub(fee_value),
+                performance.hold_value,
+            ),
+            total_return: ratio(performance.value, performance.initial_value),
+        })
+    }
+}
+
+/// Swap against the bins of the state with the math of the program: the fee of each bin, minus the protocol share,
+/// goes to its liquidity providers through the fee per token stored. Return the amount out and the fee.
+pub fn apply_swap(
+    state: &mut SinglePosition,
+    bitmap_extension: Option<&BinArrayBitmapExtension>,
+    mut amount_in: u64,
+    swap_for_y: bool,
+    current_timestamp: u64,
+) -> Result<SwapExactInQuote> {
+    let SinglePosition {
+        lb_pair,
+        lb_pair_state,
+        bin_arrays,
+        ..
+    } = state;
+    lb_pair_state.update_references(current_timestamp as i64)?;
+
+    let mut total_amount_out: u64 = 0;
+    let mut total_fee: u64 = 0;
+
+    while amount_in > 0 {
+        let active_bin_array_pubkey = get_bin_array_pubkeys_for_swap(
+            *lb_pair,
+            lb_pair_state,
+            bitmap_extension,
+            swap_for_y,
+            1,
+        )?
+        .pop()
+        .context("Pool out of liquidity")?;
+
+        let active_bin_array = bin_arrays
+            .get_mut(&active_bin_array_pubkey)
+            .context("Active bin array not found")?;
+
+        loop {
+            if active_bin_array
+                .is_bin_id_within_range(lb_pair_state.active_id)
+                .is_err()
+                || amount_in == 0
+            {
+                break;
+            }
+
+            lb_pair_state.update_volatility_accumulator()?;
+
+            let active_bin = active_bin_array.get_bin_mut(lb_pair_state.active_id)?;
+            let price = active_bin
+                .get_or_store_bin_price(lb_pair_state.active_id, lb_pair_state.bin_step)?;
+
+            if !active_bin.is_empty(!swap_for_y) {
+                let SwapResult {
+                    amount_in_with_fees,
+                    amount_out,
+                    fee,
+                    protocol_fee_after_host_fee,
+                    ..
+                } = active_bin.swap(amount_in, price, swap_for_y, lb_pair_state, None)?;
+
+                let lp_fee = fee
+                    .checked_sub(protocol_fee_after_host_fee)
+                    .context("MathOverflow")?;
+                active_bin.update_fee_per_token_stored(lp_fee, swap_for_y)?;
+                if swap_for_y {
+                    lb_pair_state.accumulate_protocol_fees(protocol_fee_after_host_fee, 0)?;
+                } else {
+                    lb_pair_state.accumulate_protocol_fees(0, protocol_fee_after_host_fee)?;
+                }
+
+                amount_in = amount_in
+                    .checked_sub(amount_in_with_fees)
+                    .context("MathOverflow")?;
+                total_amount_out = total_amount_out
+                    .checked_add(amount_out)
+                    .context("MathOverflow")?;
+                total_fee = total_fee.checked_add(fee).context("MathOverflow")?;
+            }
+
+            if amount_in > 0 {
+                lb_pair_state.advance_active_bin(swap_for_y)?;
+            }
+        }
+    }
+
+    Ok(SwapExactInQuote {
+        amount_out: total_amount_out,
+        fee: total_fee,
+    })
+}
+
+#[cfg(test)]
+mod backtest_test {
+    use super::*;
+    use crate::paper::get_bin;
+    use crate::strategy::{
+        get_force_rebalance_actions, StrategyRegistry, SHIFT_BOTH_STRATEGY, SHIFT_LEFT_STRATEGY,
+        VIEW_STRATEGY,
+    };
+    use anchor_client::anchor_lang::__private::bytemuck;
+    use anchor_client::solana_account_decoder::UiAccountEncoding;
+    use anchor_lang::Discriminator;
+    use lb_clmm::instructions::deposit::StrategyType;
+    use lb_clmm::math::bin_math::get_liquidity;
+
+    fn to_ui_account(discriminator: &[u8], data: &[u8]) -> UiAccount {
+        let mut account_data = discriminator.to_vec();
+        account_data.extend_from_slice(data);
+        let account = Account {
+            lamports: 1,
+            data: account_data,
+            owner: lb_clmm::ID,
+            executable: false,
+            rent_epoch: 0,
+        };
+        UiAccount::encode(
+            &Pubkey::default(),
+            &account,
+            UiAccountEncoding::Base64,
+            None,
+            None,
+        )
+    }
+
+    /// Pair at bin 0 with 1 token x in each bin above, 1 token y in each bin below and both in the active bin
+    fn get_pool_state(lb_pair: Pubkey) -> BacktestEvent {
+        let mut lb_pair_state = LbPair::default();
+        lb_pair_state.bin_step = 10;
+        lb_pair_state.parameters.base_factor = 10_000;
+        lb_pair_state.flip_bin_array_bit(&None, -1).unwrap();
+        lb_pair_state.flip_bin_array_bit(&None, 0).unwrap();
+
+        let mut accounts = vec![to_ui_account(
+            LbPair::DISCRIMINATOR,
+            bytemuck::bytes_of(&lb_pair_state),
+        )];
+        for index in -1..=0 {
+            let mut bin_array = get_empty_bin_array(lb_pair, index).unwrap();
+            for bin_id in -20..=20 {
+                if BinArray::bin_id_to_bin_array_index(bin_id).unwrap() != index {
+                    continue;
+                }
+                let amount_x = if bin_id >= 0 { 1_000_000 } else { 0 };
+                let amount_y = if bin_id <= 0 { 1_000_000 } else { 0 };
+                let price = get_price_from_id(bin_id, 10).unwrap();
+                let bin = bin_array.get_bin_mut(bin_id).unwrap();
+                bin.amount_x = amount_x;
+                bin.amount_y = amount_y;
+                bin.liquidity_supply
+                    .set(get_liquidity(amount_x, amount_y, price).unwrap());
+            }
+            accounts.push(to_ui_account(
+                BinArray::DISCRIMINATOR,
+                bytemuck::bytes_of(&bin_array),
+            ));
+        }
+
+        BacktestEvent::PoolState {
+            timestamp: 1_000,
+            accounts,
+        }
+    }
+
+    #[test]
+    fn test_backtest_shift_both() {
+        let lb_pair = Pubkey::new_unique();
+        let config = PairConfig {
+            pair_address: lb_pair.to_string(),
+            x_amount: 100_000,
+            y_amount: 100_000,
+            range_width: Some(5),
+            strategy: Some(SHIFT_BOTH_STRATEGY.to_string()),
+            ..Default::default()
+        };
+        let strategy = StrategyRegistry::default()
+            .get(SHIFT_BOTH_STRATEGY)
+            .unwrap();
+        let mut backtester = Backtester::new(config, strategy, 3_600);
+
+        let events = vec![
+            get_pool_state(lb_pair),
+            // Buys the token x of bins 0 to 4, the price leaves the empty range to the right
+            BacktestEvent::Swap {
+                timestamp: 1_010,
+                amount_in: 5_500_000,
+                swap_for_y: false,
+            },
+            // Sells through the new range, the price leaves it to the left
+            BacktestEvent::Swap {
+                timestamp: 1_020,
+                amount_in: 3_000_000,
+                swap_for_y: true,
+            },
+        ];
+        let report = backtester.run(&events).unwrap();
+
+        assert_eq!(report.start_timestamp, 1_000);
+        assert_eq!(report.end_timestamp, 1_020);
+        assert_eq!(report.swap_count, 2);
+        // Deposit once the price moved, then move the range back
+        assert_eq!(report.rebalance_time, 2);
+        assert!(!backtester.state.position_pks.is_empty());
+        // The second swap paid fees in token x to the range, the positions earn at most the fees paid to all the
+        // liquidity providers
+        assert!(report.fee_x > 0);
+        assert!(report.fee_x <= report.lp_fee_x);
+        assert!(report.fee_y <= report.lp_fee_y);
+        assert_eq!(report.initial_value, 200_000);
+
+        // Withdrawing everything gives back the value of the report
+        backtester
+            .execute_actions(vec![StrategyAction::Withdraw])
+            .unwrap();
+        assert!(backtester.state.position_pks.is_empty());
+        let (balance_x, balance_y) = backtester.paper.get_balances(lb_pair).unwrap();
+        let price = get_price_from_id(backtester.state.lb_pair_state.active_id, 10).unwrap();
+        let value = get_value(balance_x, balance_y, price).unwrap();
+        assert_eq!(value, report.value);
+    }
+
+    #[test]
+    fn test_backtest_fee_conservation() {
+        let lb_pair = Pubkey::new_unique();
+        let config = PairConfig {
+            pair_address: lb_pair.to_string(),
+            x_amount: 10_000_000,
+            y_amount: 10_000_000,
+            strategy: Some(VIEW_STRATEGY.to_string()),
+            ..Default::default()
+        };
+        let strategy = StrategyRegistry::default().get(VIEW_STRATEGY).unwrap();
+        let mut backtester = Backtester::new(config, strategy, 3_600);
+        backtester.run(&[get_pool_state(lb_pair)]).unwrap();
+
+        // The positions hold most of the liquidity of the active bin
+        backtester
+            .execute_actions(vec![StrategyAction::Deposit {
+                amount_x: 10_000_000,
+                amount_y: 10_000_000,
+                min_bin_id: -2,
+                max_bin_id: 2,
+                strategy_type: StrategyType::SpotBalanced,
+            }])
+            .unwrap();
+        let active_bin = get_bin(&backtester.state, 0).unwrap();
+        assert!(active_bin.amount_x > 2_500_000 && active_bin.amount_y > 2_500_000);
+
+        let report = backtester
+            .run(&[BacktestEvent::Swap {
+                timestamp: 1_010,
+                amount_in: 500_000,
+                swap_for_y: true,
+            }])
+            .unwrap();
+        assert_eq!(backtester.state.lb_pair_state.active_id, 0);
+        assert!(report.lp_fee_x > 0);
+        assert_eq!(report.lp_fee_y, 0);
+        // The positions earn their share of the fees, never more than the fees paid
+        assert!(report.fee_x <= report.lp_fee_x);
+        assert!(report.fee_x * 2 > report.lp_fee_x);
+        assert_eq!(report.fee_y, 0);
+    }
+
+    fn force_rebalance(backtester: &mut Backtester) {
+        let (balance_x, balance_y) = backtester
+            .paper
+            .get_balances(backtester.state.lb_pair)
+            .unwrap();
+        let snapshot = StrategySnapshot {
+            config: &backtester.config,
+            state: &backtester.state,
+            position: backtester.state.get_positions().unwrap(),
+            balance_x,
+            balance_y,
+            timestamp: backtester.timestamp,
+        };
+        let actions = get_force_rebalance_actions(&snapshot).unwrap();
+        backtester.execute_actions(actions).unwrap();
+    }
+
+    #[test]
+    fn test_force_rebalance() {
+        let lb_pair = Pubkey::new_unique();
+        let config = PairConfig {
+            pair_address: lb_pair.to_string(),
+            x_amount: 100_000,
+            y_amount: 100_000,
+            range_width: Some(5),
+            strategy: Some(SHIFT_LEFT_STRATEGY.to_string()),
+            ..Default::default()
+        };
+        let strategy = StrategyRegistry::default()
+            .get(SHIFT_LEFT_STRATEGY)
+            .unwrap();
+        let mut backtester = Backtester::new(config, strategy, 3_600);
+
+        // The price goes up, which a left only strategy without positions ignores
+        let events = vec![
+            get_pool_state(lb_pair),
+            BacktestEvent::Swap {
+                timestamp: 1_010,
+                amount_in: 2_500_000,
+                swap_for_y: false,
+            },
+        ];
+        backtester.run(&events).unwrap();
+        let active_id = backtester.state.lb_pair_state.active_id;
+        assert!(active_id > 0);
+        assert!(backtester.state.position_pks.is_empty());
+
+        // Without positions, the configured amounts are deposited around the active bin
+        force_rebalance(&mut backtester);
+        assert!(!backtester.state.position_pks.is_empty());
+        assert_eq!(backtester.state.min_bin_id, active_id - 2);
+        assert_eq!(backtester.state.max_bin_id, active_id + 2);
+
+        // The price moves within the range, a forced rebalance centers the range on it again with the withdrawn
+        // amounts
+        let events = vec![BacktestEvent::Swap {
+            timestamp: 1_020,
+            amount_in: 1_000_000,
+            swap_for_y: false,
+        }];
+        backtester.run(&events).unwrap();
+        let active_id = backtester.state.lb_pair_state.active_id;
+        let position = backtester.state.get_positions().unwrap();
+        let (balance_x, balance_y) = backtester.paper.get_balances(lb_pair).unwrap();
+        force_rebalance(&mut backtester);
+        assert_eq!(backtester.state.min_bin_id, active_id - 2);
+        assert_eq!(backtester.state.max_bin_id, active_id + 2);
+        // The wallet balances are not deposited
+        let (new_balance_x, new_balance_y) = backtester.paper.get_balances(lb_pair).unwrap();
+        assert!(new_balance_x >= balance_x && new_balance_y >= balance_y);
+        assert!(position.amount_x + position.amount_y > 0);
+        let new_position = backtester.state.get_positions().unwrap();
+        assert!(new_position.amount_x > 0 && new_position.amount_y > 0);
+    }
+}
@@ -1,20 +1,31 @@
+use crate::journal::{
+    get_active_price, HistoryRaw, Journal, JournalAction, JournalEntry, PairHistory,
+};
+use crate::metrics::{Metrics, CLAIM_FEE_ACTION, DEPOSIT_ACTION, SWAP_ACTION, WITHDRAW_ACTION};
 use crate::pair_config::get_pair_config;
+use crate::pair_config::merge_reloaded_config;
+use crate::pair_config::should_market_making;
 use crate::pair_config::PairConfig;
+use crate::pair_config::BASIS_POINT_MAX;
+use crate::paper::{get_empty_bin_array, PaperTrading};
+use crate::risk::{get_limit_breach, get_snapshot_value, KillSwitch, RiskState};
 use crate::state::get_decimals;
 use crate::state::AllPosition;
 use crate::state::PositionInfo;
 use crate::state::SinglePosition;
+use crate::state::{get_position_deposit_amounts, get_position_ranges};
+use crate::strategy::{
+    get_force_rebalance_actions, StrategyAction, StrategyRegistry, StrategySnapshot, VIEW_STRATEGY,
+};
 use crate::utils::parse_swap_event;
 use crate::utils::send_tx;
 use crate::utils::simulate_transaction;
 use crate::utils::{create_program, get_epoch_sec, get_or_create_ata};
-use crate::MarketMakingMode;
-use anchor_client::anchor_lang::Space;
 use anchor_client::solana_client::rpc_filter::{Memcmp, RpcFilterType};
 use anchor_client::solana_sdk::compute_budget::ComputeBudgetInstruction;
 use anchor_client::solana_sdk::instruction::Instruction;
+use anchor_client::solana_sdk::signature::Keypair;
 use anchor_client::solana_sdk::signature::Signer;
-use anchor_client::solana_sdk::signature::{read_keypair_file, Keypair};
 use anchor_client::{solana_sdk::pubkey::Pubkey, Cluster, Program};
 use anchor_lang::prelude::AccountMeta;
 use anchor_lang::AccountDeserialize;
@@ -26,29 +37,195 @@ use anchor_spl::token::Mint;
 use anchor_spl::token::TokenAccount;
 use anyhow::Ok;
 use anyhow::*;
+use commons::account_layout::{convert_legacy_position, decode_bin_array};
+use commons::quote::{get_bin_array_pubkeys_for_swap, quote_exact_in, SwapExactInQuote};
+use commons::signer::SharedSigner;
 use lb_clmm::accounts;
-use lb_clmm::constants::MAX_BIN_PER_ARRAY;
-use lb_clmm::constants::MAX_BIN_PER_POSITION;
 use lb_clmm::events::Swap as SwapEvent;
 use lb_clmm::instruction;
 use lb_clmm::instructions::deposit::*;
-use lb_clmm::math::safe_math::SafeMath;
-use lb_clmm::state::{bin::BinArray, lb_pair::LbPair, position::PositionV2};
+use lb_clmm::math::price_math::get_price_from_id;
+use lb_clmm::state::bin_array_bitmap_extension::BinArrayBitmapExtension;
+use lb_clmm::state::{
+    bin::{Bin, BinArray},
+    lb_pair::LbPair,
+    position::{Position, PositionV2},
+};
 use lb_clmm::utils::pda;
 use lb_clmm::utils::pda::*;
 use std::collections::HashMap;
+use std::future::Future;
 use std::str::FromStr;
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::time::Instant;
+
+/// Maximum number of accounts of a getMultipleAccounts call
+const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+/// Number of times the swap amount is halved to get within the price impact limit
+const MAX_PRICE_IMPACT_TRIES: usize = 5;
+
+/// Largest amount up to `amount_in` whose quoted price impact against the price of the active bin, including fees, is
+/// within the limit. The amount is halved on each try. Return the amount in, the min amount out enforcing the limit,
+/// and the quote.
+pub fn get_amount_within_price_impact<F>(
+    amount_in: u64,
+    price: u128,
+    swap_for_y: bool,
+    max_price_impact_bps: u64,
+    mut quote: F,
+) -> Result<Option<(u64, u64, SwapExactInQuote)>>
+where
+    F: FnMut(u64) -> Result<SwapExactInQuote>,
+{
+    let mut amount_in = amount_in;
+    for _ in 0..MAX_PRICE_IMPACT_TRIES {
+        if amount_in == 0 {
+            break;
+        }
+        let spot_amount_out = Bin::get_amount_out(amount_in, price, swap_for_y)?;
+        let min_amount_out = (spot_amount_out as u128
+            * (BASIS_POINT_MAX - max_price_impact_bps) as u128
+            / BASIS_POINT_MAX as u128) as u64;
+
+        match quote(amount_in) {
+            std::result::Result::Ok(quote) if quote.amount_out >= min_amount_out => {
+                return Ok(Some((amount_in, min_amount_out, quote)));
+            }
+            std::result::Result::Ok(quote) => {
+                info!(
+                    "swap {amount_in} {swap_for_y} quoted {} below {min_amount_out}",
+                    quote.amount_out
+                );
+            }
+            Err(err) => {
+                info!("cannot quote swap {amount_in} {swap_for_y} {err}");
+            }
+        }
+        amount_in /= 2;
+    }
+
+    Ok(None)
+}
+
+fn get_swap_action(swap_event: &SwapEvent) -> JournalAction {
+    JournalAction::Swap {
+        amount_in: swap_event.amount_in,
+        amount_out: swap_event.amount_out,
+        swap_for_y: swap_event.swap_for_y,
+        start_bin_id: swap_event.start_bin_id,
+        end_bin_id: swap_event.end_bin_id,
+        fee: swap_event.fee,
+        protocol_fee: swap_event.protocol_fee,
+    }
+}
+
 pub struct Core {
     pub provider: Cluster,
-    pub wallet: Option<String>,
+    pub wallet: Option<SharedSigner>,
     pub owner: Pubkey,
-    pub config: Vec<PairConfig>,
+    /// Effective config, changed by the control API and by config reloads
+    pub config: Arc<Mutex<Vec<PairConfig>>>,
+    /// Config last read from the config file, to tell the changes of a reload from the changes of the control API
+    pub file_config: Arc<Mutex<Vec<PairConfig>>>,
     pub state: Arc<Mutex<AllPosition>>,
+    pub strategies: StrategyRegistry,
+    /// Virtual portfolios the actions are applied to in paper mode, instead of sending transactions
+    pub paper: Option<Arc<Mutex<PaperTrading>>>,
+    /// Transaction counts and RPC latencies served on `/metrics`
+    pub metrics: Arc<Mutex<Metrics>>,
+    /// Held while actions of the strategies or of the control API are sent, so that they do not interleave
+    pub execution_lock: tokio::sync::Mutex<()>,
+    /// Actions of the bot, persisted to the journal file
+    pub journal: Arc<Mutex<Journal>>,
+    /// Starting values of the risk limits, and the kill switch once triggered
+    pub risk: Arc<Mutex<RiskState>>,
 }
 
 impl Core {
+    fn get_payer(&self) -> Result<SharedSigner> {
+        self.wallet
+            .clone()
+            .ok_or_else(|| Error::msg("Requires a wallet signer"))
+    }
+
+    /// Await an RPC call and record its latency in the metrics.
+    async fn observe_rpc<T>(&self, method: &'static str, call: impl Future<Output = T>) -> T {
+        let start = Instant::now();
+        let result = call.await;
+        self.metrics
+            .lock()
+            .unwrap()
+            .observe_rpc_latency(method, start.elapsed());
+        result
+    }
+
+    /// Record an action of the pair in the journal. A failed write is only logged, as the action is done.
+    fn record_action(
+        &self,
+        lb_pair: Pubkey,
+        lb_pair_state: &LbPair,
+        signature: Option<String>,
+        action: JournalAction,
+    ) {
+        let entry = get_active_price(lb_pair_state).map(|price| JournalEntry {
+            timestamp: get_epoch_sec(),
+            pair_address: lb_pair.to_string(),
+            signature,
+            price,
+            action,
+        });
+        if let Err(err) = entry.and_then(|entry| self.journal.lock().unwrap().record(entry)) {
+            error!("record action of {lb_pair} err {err}");
+        }
+    }
+
+    /// Record a change of the risk state in the journal. These entries carry no price.
+    fn record_risk_action(&self, timestamp: u64, pair_address: String, action: JournalAction) {
+        let entry = JournalEntry {
+            timestamp,
+            pair_address,
+            signature: None,
+            price: 0.0,
+            action,
+        };
+        if let Err(err) = self.journal.lock().unwrap().record(entry) {
+            error!("record risk action err {err}");
+        }
+    }
+
+    /// Journal action of the withdraw of a position, with its amounts and pending fees in the state.
+    fn get_withdraw_action(state: &SinglePosition, position: Pubkey) -> Result<JournalAction> {
+        let i = state
+            .position_pks
+            .iter()
+            .position(|&position_pk| position_pk == position)
+            .ok_or_else(|| Error::msg(format!("Unknown position {}", position)))?;
+        let (amount_x, amount_y, fee_x, fee_y) = state.get_position_amounts(&state.positions[i])?;
+        Ok(JournalAction::Withdraw {
+            position: position.to_string(),
+            amount_x,
+            amount_y,
+            fee_x,
+            fee_y,
+        })
+    }
+
+    /// Count the transaction of an action of the pair in the metrics, as a success or a failure.
+    fn record_transaction<T>(
+        &self,
+        lb_pair: Pubkey,
+        action: &'static str,
+        result: Result<T>,
+    ) -> Result<T> {
+        self.metrics
+            .lock()
+            .unwrap()
+            .inc_transaction(lb_pair, action, result.is_ok());
+        result
+    }
+
     pub async fn refresh_state(&self) -> Result<()> {
         let program: Program<Arc<Keypair>> = create_program(
             self.provider.to_string(),
@@ -57,25 +234,44 @@ impl Core {
             Arc::new(Keypair::new()),
         )?;
 
-        for pair in self.config.iter() {
+        for pair in self.get_config().iter() {
             let pair_address = Pubkey::from_str(&pair.pair_address).unwrap();
-            let lb_pair_state: LbPair = program.account(pair_address).await?;
+            let lb_pair_state: LbPair = self
+                .observe_rpc("getAccountInfo", program.account(pair_address))
+                .await?;
             // let token_x: Mint = program.account(lb_pair_state.token_x_mint).await?;
             // let token_y: Mint = program.account(lb_pair_state.token_y_mint).await?;
-            // get all position with an user
-            let mut position_states = program
-                .accounts::<PositionV2>(vec![
-                    RpcFilterType::DataSize((8 + PositionV2::INIT_SPACE) as u64),
-                    RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
-                        8 + 32,
-                        self.owner.to_bytes().to_vec(),
-                    )),
-                    RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
-                        8,
-                        pair_address.to_bytes().to_vec(),
-                    )),
-                ])
-                .await?;
+            // get all position with an user, or the virtual positions in paper mode
+            let mut position_states = match &self.paper {
+                Some(paper) => {
+                    let mut paper = paper.lock().unwrap();
+                    paper.start(pair_address, &lb_pair_state)?;
+                    paper.get_positions(pair_address)?
+                }
+                None => {
+                    // lb_pair and owner are at the same offset in both position layouts
+                    let filters = vec![
+                        RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+                            8 + 32,
+                            self.owner.to_bytes().to_vec(),
+                        )),
+                        RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+                            8,
+                            pair_address.to_bytes().to_vec(),
+                        )),
+                    ];
+                    let call = program.accounts::<PositionV2>(filters.clone());
+                    let mut positions = self.observe_rpc("getProgramAccounts", call).await?;
+                    let call = program.accounts::<Position>(filters);
+                    for (position, legacy_position) in
+                        self.observe_rpc("getProgramAccounts", call).await?
+                    {
+                        warn!("position {position} of {pair_address} uses the legacy layout, migrate it to withdraw");
+                        positions.push((position, convert_legacy_position(legacy_position)?));
+                    }
+                    positions
+                }
+            };
             let mut position_pks = vec![];
             let mut positions = vec![];
             let mut min_bin_id = 0;
@@ -87,7 +283,11 @@ impl Core {
                     .sort_by(|a, b| a.1.lower_bin_id.partial_cmp(&b.1.lower_bin_id).unwrap());
 
                 min_bin_id = position_states[0].1.lower_bin_id;
-                max_bin_id = position_states[position_states.len() - 1].1.upper_bin_id;
+                max_bin_id = position_states
+                    .iter()
+                    .map(|(_, position)| position.upper_bin_id)
+                    .max()
+                    .unwrap();
                 for position in position_states.iter() {
                     position_pks.push(position.0);
                     positions.push(position.1);
@@ -100,15 +300,22 @@ impl Core {
                             continue;
                         }
                         bin_arrays_indexes.push(bin_array_index);
-
-                        let (bin_array_pk, _bump) =
-                            pda::derive_bin_array_pda(pair_address, bin_array_index.into());
-
-                        let bin_array_state: BinArray = program.account(bin_array_pk).await?;
-
-                        bin_arrays.insert(bin_array_pk, bin_array_state);
+                    }
+                    // Amounts of the virtual positions are read from the bin array after the lower one too
+                    if self.paper.is_some() {
+                        let bin_array_index =
+                            BinArray::bin_id_to_bin_array_index(position.lower_bin_id)?
+                                .checked_add(1)
+                                .context("MathOverflow")?;
+                        if !bin_arrays_indexes.contains(&bin_array_index) {
+                            bin_arrays_indexes.push(bin_array_index);
+                        }
                     }
                 }
+
+                bin_arrays = self
+                    .get_bin_arrays(&program, pair_address, &bin_arrays_indexes)
+                    .await?;
             }
 
             let mut all_state = self.state.lock().unwrap();
@@ -122,11 +329,88 @@ impl Core {
             // state.token_x = token_x;
             // state.token_y = token_y;
             state.last_update_timestamp = get_epoch_sec();
+            self.metrics
+                .lock()
+                .unwrap()
+                .set_last_refresh_timestamp(pair_address, state.last_update_timestamp);
         }
 
         Ok(())
     }
 
+    /// Fetch bin arrays of the pair with batched getMultipleAccounts calls. In paper mode, the bin arrays which do not
+    /// exist on chain are empty.
+    async fn get_bin_arrays(
+        &self,
+        program: &Program<Arc<Keypair>>,
+        lb_pair: Pubkey,
+        bin_arrays_indexes: &[i32],
+    ) -> Result<HashMap<Pubkey, BinArray>> {
+        let mut bin_arrays = HashMap::new();
+        for chunk in bin_arrays_indexes.chunks(MAX_MULTIPLE_ACCOUNTS) {
+            let bin_array_pks = chunk
+                .iter()
+                .map(|&index| pda::derive_bin_array_pda(lb_pair, index.into()).0)
+                .collect::<Vec<_>>();
+            let accounts = self
+                .observe_rpc(
+                    "getMultipleAccounts",
+                    program.rpc().get_multiple_accounts(&bin_array_pks),
+                )
+                .await?;
+            for ((account, &bin_array_pk), &index) in
+                accounts.into_iter().zip(bin_array_pks.iter()).zip(chunk)
+            {
+                let bin_array_state = match account {
+                    Some(account) => decode_bin_array(&account.data)?.1,
+                    None if self.paper.is_some() => get_empty_bin_array(lb_pair, index)?,
+                    None => {
+                        return Err(Error::msg(format!("Cannot get binarray {}", bin_array_pk)))
+                    }
+                };
+                bin_arrays.insert(bin_array_pk, bin_array_state);
+            }
+        }
+        Ok(bin_arrays)
+    }
+
+    /// Copy of the state with the bin arrays of a bin range and of the active bin, for the paper deposits.
+    async fn get_state_with_bin_arrays(
+        &self,
+        state: &SinglePosition,
+        min_bin_id: i32,
+        max_bin_id: i32,
+    ) -> Result<SinglePosition> {
+        let program: Program<Arc<Keypair>> = create_program(
+            self.provider.to_string(),
+            self.provider.to_string(),
+            lb_clmm::ID,
+            Arc::new(Keypair::new()),
+        )?;
+        let lower_bin_array_idx = BinArray::bin_id_to_bin_array_index(min_bin_id)?;
+        // A position may end in the bin array after the one of its upper bin
+        let upper_bin_array_idx = BinArray::bin_id_to_bin_array_index(max_bin_id)?
+            .checked_add(1)
+            .context("MathOverflow")?;
+        let active_bin_array_idx =
+            BinArray::bin_id_to_bin_array_index(state.lb_pair_state.active_id)?;
+
+        let missing_indexes = (lower_bin_array_idx..=upper_bin_array_idx)
+            .chain(std::iter::once(active_bin_array_idx))
+            .filter(|&index| {
+                let (bin_array_pk, _bump) = pda::derive_bin_array_pda(state.lb_pair, index.into());
+                !state.bin_arrays.contains_key(&bin_array_pk)
+            })
+            .collect::<Vec<_>>();
+
+        let mut state = state.clone();
+        let bin_arrays = self
+            .get_bin_arrays(&program, state.lb_pair, &missing_indexes)
+            .await?;
+        state.bin_arrays.extend(bin_arrays);
+        Ok(state)
+    }
+
     pub async fn fetch_token_info(&self) -> Result<()> {
         let token_mints = self.get_all_token_mints();
         let program: Program<Arc<Keypair>> = create_program(
@@ -135,7 +419,12 @@ impl Core {
             lb_clmm::ID,
             Arc::new(Keypair::new()),
         )?;
-        let accounts = program.rpc().get_multiple_accounts(&token_mints).await?;
+        let accounts = self
+            .observe_rpc(
+                "getMultipleAccounts",
+                program.rpc().get_multiple_accounts(&token_mints),
+            )
+            .await?;
 
         let mut tokens = HashMap::new();
         for (i, &token_pk) in token_mints.iter().enumerate() {
@@ -166,9 +455,82 @@ impl Core {
         position.clone()
     }
 
+    /// Pairs and bin arrays the state is made of, which are watched for updates.
+    pub fn get_watched_accounts(&self) -> Vec<Pubkey> {
+        let state = self.state.lock().unwrap();
+        let mut accounts = vec![];
+        for (&lb_pair, position) in state.all_positions.iter() {
+            accounts.push(lb_pair);
+            accounts.extend(position.bin_arrays.keys());
+        }
+        accounts.sort_unstable();
+        accounts.dedup();
+        accounts
+    }
+
+    /// Apply the new data of a watched pair or bin array to the state. Return the pair when its active bin moved to
+    /// an edge position or out of the range, where the strategies act.
+    pub fn apply_account_update(&self, pubkey: Pubkey, data: &[u8]) -> Result<Option<Pubkey>> {
+        let mut all_state = self.state.lock().unwrap();
+
+        if let Some(state) = all_state.all_positions.get_mut(&pubkey) {
+            let lb_pair_state = LbPair::try_deserialize(&mut data.as_ref())?;
+            let previous_active_id = state.lb_pair_state.active_id;
+            state.lb_pair_state = lb_pair_state;
+            state.last_update_timestamp = get_epoch_sec();
+
+            if lb_pair_state.active_id != previous_active_id && state.is_active_bin_at_edge() {
+                return Ok(Some(pubkey));
+            }
+            return Ok(None);
+        }
+
+        for state in all_state.all_positions.values_mut() {
+            if let Some(bin_array) = state.bin_arrays.get_mut(&pubkey) {
+                let (_version, bin_array_state) = decode_bin_array(data)?;
+                *bin_array = bin_array_state;
+                state.last_update_timestamp = get_epoch_sec();
+                break;
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Fetch the watched accounts with batched getMultipleAccounts calls, for when the subscriptions are down.
+    /// Return the pairs whose active bin moved to an edge position or out of the range.
+    pub async fn poll_accounts(&self) -> Result<Vec<Pubkey>> {
+        let program: Program<Arc<Keypair>> = create_program(
+            self.provider.to_string(),
+            self.provider.to_string(),
+            lb_clmm::ID,
+            Arc::new(Keypair::new()),
+        )?;
+
+        let watched_accounts = self.get_watched_accounts();
+        let mut lb_pairs = vec![];
+        for chunk in watched_accounts.chunks(MAX_MULTIPLE_ACCOUNTS) {
+            let accounts = self
+                .observe_rpc(
+                    "getMultipleAccounts",
+                    program.rpc().get_multiple_accounts(chunk),
+                )
+                .await?;
+            for (account, &pubkey) in accounts.into_iter().zip(chunk.iter()) {
+                let Some(account) = account else {
+                    continue;
+                };
+                if let Some(lb_pair) = self.apply_account_update(pubkey, &account.data)? {
+                    lb_pairs.push(lb_pair);
+                }
+            }
+        }
+
+        Ok(lb_pairs)
+    }
+
     pub async fn init_user_ata(&self) -> Result<()> {
-        let payer = read_keypair_file(self.wallet.clone().unwrap())
-            .map_err(|_| Error::msg("Requires a keypair file"))?;
+        let payer = self.get_payer()?;
         let program: Program<Arc<Keypair>> = create_program(
             self.provider.to_string(),
             self.provider.to_string(),
@@ -184,14 +546,34 @@ impl Core {
 
     // withdraw all positions
     pub async fn withdraw(&self, state: &SinglePosition, is_simulation: bool) -> Result<()> {
-        // let state = self.get_state();
-        if state.position_pks.len() == 0 {
+        self.withdraw_positions(state, &state.position_pks, is_simulation)
+            .await
+    }
+
+    // withdraw and close some positions of the pair
+    pub async fn withdraw_positions(
+        &self,
+        state: &SinglePosition,
+        positions: &[Pubkey],
+        is_simulation: bool,
+    ) -> Result<()> {
+        if positions.len() == 0 {
+            return Ok(());
+        }
+        if let Some(paper) = &self.paper {
+            let actions = positions
+                .iter()
+                .map(|&position| Self::get_withdraw_action(state, position))
+                .collect::<Result<Vec<_>>>()?;
+            paper.lock().unwrap().withdraw(state, positions)?;
+            for action in actions {
+                self.record_action(state.lb_pair, &state.lb_pair_state, None, action);
+            }
             return Ok(());
         }
         let (event_authority, _bump) = derive_event_authority_pda();
         let lb_pair = state.lb_pair;
-        let payer = read_keypair_file(self.wallet.clone().unwrap())
-            .map_err(|_| Error::msg("Requires a keypair file"))?;
+        let payer = self.get_payer()?;
         let program: Program<Arc<Keypair>> = create_program(
             self.provider.to_string(),
             self.provider.to_string(),
@@ -199,8 +581,14 @@ impl Core {
             Arc::new(Keypair::new()),
         )?;
         let lb_pair_state = state.lb_pair_state;
-        for (i, &position) in state.position_pks.iter().enumerate() {
+        for &position in positions.iter() {
+            let i = state
+                .position_pks
+                .iter()
+                .position(|&position_pk| position_pk == position)
+                .ok_or_else(|| Error::msg(format!("Unknown position {}", position)))?;
             let position_state = state.positions[i];
+            let action = Self::get_withdraw_action(state, position)?;
             let lower_bin_array_idx =
                 BinArray::bin_id_to_bin_array_index(position_state.lower_bin_id)?;
             let upper_bin_array_idx = lower_bin_array_idx.checked_add(1).context("MathOverflow")?;
@@ -288,50 +676,270 @@ impl Core {
                     simulate_transaction(vec![&payer], payer.pubkey(), &program, &builder).await?;
                 println!("{:?}", response);
             } else {
-                let signature = send_tx(vec![&payer], payer.pubkey(), &program, &builder).await?;
+                let signature = self.record_transaction(
+                    state.lb_pair,
+                    WITHDRAW_ACTION,
+                    send_tx(vec![&payer], payer.pubkey(), &program, &builder).await,
+                )?;
                 info!("close popsition {position} {signature}");
+                self.record_action(lb_pair, &lb_pair_state, Some(signature.to_string()), action);
+            }
+        }
+
+        Ok(())
+    }
+
+    // claim fee of all positions
+    pub async fn claim_fee(&self, state: &SinglePosition, is_simulation: bool) -> Result<()> {
+        if state.position_pks.len() == 0 {
+            return Ok(());
+        }
+        if let Some(paper) = &self.paper {
+            let mut actions = vec![];
+            for (&position, position_state) in state.position_pks.iter().zip(state.positions.iter())
+            {
+                let (_amount_x, _amount_y, fee_x, fee_y) =
+                    state.get_position_amounts(position_state)?;
+                actions.push(JournalAction::ClaimFee {
+                    position: position.to_string(),
+                    fee_x,
+                    fee_y,
+                });
+            }
+            paper.lock().unwrap().claim_fee(state)?;
+            for action in actions {
+                self.record_action(state.lb_pair, &state.lb_pair_state, None, action);
+            }
+            return Ok(());
+        }
+        let (event_authority, _bump) = derive_event_authority_pda();
+        let lb_pair = state.lb_pair;
+        let payer = self.get_payer()?;
+        let program: Program<Arc<Keypair>> = create_program(
+            self.provider.to_string(),
+            self.provider.to_string(),
+            lb_clmm::ID,
+            Arc::new(Keypair::new()),
+        )?;
+        let lb_pair_state = state.lb_pair_state;
+        let user_token_x =
+            get_associated_token_address(&payer.pubkey(), &lb_pair_state.token_x_mint);
+        let user_token_y =
+            get_associated_token_address(&payer.pubkey(), &lb_pair_state.token_y_mint);
+
+        for (i, &position) in state.position_pks.iter().enumerate() {
+            let position_state = state.positions[i];
+            let (_amount_x, _amount_y, fee_x, fee_y) =
+                state.get_position_amounts(&position_state)?;
+            let lower_bin_array_idx =
+                BinArray::bin_id_to_bin_array_index(position_state.lower_bin_id)?;
+            let upper_bin_array_idx = lower_bin_array_idx.checked_add(1).context("MathOverflow")?;
+
+            let (bin_array_lower, _bump) =
+                derive_bin_array_pda(lb_pair, lower_bin_array_idx.into());
+            let (bin_array_upper, _bump) =
+                derive_bin_array_pda(lb_pair, upper_bin_array_idx.into());
+
+            let instructions = vec![
+                ComputeBudgetInstruction::set_compute_unit_limit(1_400_000),
+                Instruction {
+                    program_id: lb_clmm::ID,
+                    accounts: accounts::ClaimFee {
+                        bin_array_lower,
+                        bin_array_upper,
+                        lb_pair,
+                        sender: payer.pubkey(),
+                        position,
+                        reserve_x: lb_pair_state.reserve_x,
+                        reserve_y: lb_pair_state.reserve_y,
+                        token_program: anchor_spl::token::ID,
+                        token_x_mint: lb_pair_state.token_x_mint,
+                        token_y_mint: lb_pair_state.token_y_mint,
+                        user_token_x,
+                        user_token_y,
+                        event_authority,
+                        program: lb_clmm::ID,
+                    }
+                    .to_account_metas(None),
+                    data: instruction::ClaimFee {}.data(),
+                },
+            ];
+
+            let builder = program.request();
+            let builder = instructions
+                .into_iter()
+                .fold(builder, |bld, ix| bld.instruction(ix));
+
+            if is_simulation {
+                let response =
+                    simulate_transaction(vec![&payer], payer.pubkey(), &program, &builder).await?;
+                println!("{:?}", response);
+            } else {
+                let signature = self.record_transaction(
+                    state.lb_pair,
+                    CLAIM_FEE_ACTION,
+                    send_tx(vec![&payer], payer.pubkey(), &program, &builder).await,
+                )?;
+                info!("claim fee {position} {signature}");
+                self.record_action(
+                    lb_pair,
+                    &lb_pair_state,
+                    Some(signature.to_string()),
+                    JournalAction::ClaimFee {
+                        position: position.to_string(),
+                        fee_x,
+                        fee_y,
+                    },
+                );
             }
         }
 
         Ok(())
     }
 
+    /// Quote the swap against the live bin arrays with `get_amount_within_price_impact`. Return the amount in, the
+    /// min amount out enforcing the limit on chain, the quote, and the bin arrays of the swap.
+    async fn get_swap_amount_within_price_impact(
+        &self,
+        program: &Program<Arc<Keypair>>,
+        state: &SinglePosition,
+        amount_in: u64,
+        swap_for_y: bool,
+        max_price_impact_bps: u64,
+    ) -> Result<Option<(u64, u64, SwapExactInQuote, Vec<Pubkey>)>> {
+        let lb_pair = state.lb_pair;
+        let lb_pair_state = state.lb_pair_state;
+
+        let (bitmap_extension_key, _bump) = derive_bin_array_bitmap_extension(lb_pair);
+        let bitmap_extension = program
+            .account::<BinArrayBitmapExtension>(bitmap_extension_key)
+            .await
+            .ok();
+
+        let bin_arrays_for_swap = get_bin_array_pubkeys_for_swap(
+            lb_pair,
+            &lb_pair_state,
+            bitmap_extension.as_ref(),
+            swap_for_y,
+            3,
+        )?;
+        let accounts = program
+            .rpc()
+            .get_multiple_accounts(&bin_arrays_for_swap)
+            .await?;
+        let mut bin_arrays = HashMap::new();
+        for (account, &key) in accounts.into_iter().zip(bin_arrays_for_swap.iter()) {
+            let account = account.context("Bin array not found")?;
+            let (_version, bin_array) = decode_bin_array(&account.data)?;
+            bin_arrays.insert(key, bin_array);
+        }
+
+        let current_slot = program.rpc().get_slot().await?;
+        let price = get_price_from_id(lb_pair_state.active_id, lb_pair_state.bin_step)?;
+
+        let swap = get_amount_within_price_impact(
+            amount_in,
+            price,
+            swap_for_y,
+            max_price_impact_bps,
+            |amount_in| {
+                quote_exact_in(
+                    lb_pair,
+                    &lb_pair_state,
+                    amount_in,
+                    swap_for_y,
+                    bin_arrays.clone(),
+                    bitmap_extension.as_ref(),
+                    get_epoch_sec(),
+                    current_slot,
+                )
+            },
+        )?;
+
+        Ok(swap.map(|(amount_in, min_amount_out, quote)| {
+            (amount_in, min_amount_out, quote, bin_arrays_for_swap)
+        }))
+    }
+
     // TODO implement jupiter swap swap
+    // Return None when no amount can be swapped within the price impact limit
     async fn swap(
         &self,
         state: &SinglePosition,
         amount_in: u64,
         swap_for_y: bool,
+        max_price_impact_bps: u64,
         is_simulation: bool,
-    ) -> Result<SwapEvent> {
+    ) -> Result<Option<SwapEvent>> {
         // let state = self.get_state();
         let lb_pair_state = state.lb_pair_state;
         let lb_pair = state.lb_pair;
-        let active_bin_array_idx = BinArray::bin_id_to_bin_array_index(lb_pair_state.active_id)?;
 
-        let payer = read_keypair_file(self.wallet.clone().unwrap())
-            .map_err(|_| Error::msg("Requires a keypair file"))?;
         let program: Program<Arc<Keypair>> = create_program(
             self.provider.to_string(),
             self.provider.to_string(),
             lb_clmm::ID,
             Arc::new(Keypair::new()),
         )?;
-        let (bin_array_0, _bump) = derive_bin_array_pda(lb_pair, active_bin_array_idx as i64);
 
-        let (user_token_in, user_token_out, bin_array_1, bin_array_2) = if swap_for_y {
+        // The strategy may ask for more than the virtual wallet holds
+        let amount_in = match &self.paper {
+            Some(paper) => {
+                let (balance_x, balance_y) = paper.lock().unwrap().get_balances(lb_pair)?;
+                amount_in.min(if swap_for_y { balance_x } else { balance_y })
+            }
+            None => amount_in,
+        };
+
+        let Some((amount_in, min_amount_out, quote, bin_arrays_for_swap)) = self
+            .get_swap_amount_within_price_impact(
+                &program,
+                state,
+                amount_in,
+                swap_for_y,
+                max_price_impact_bps,
+            )
+            .await?
+        else {
+            info!(
+                "skip swap {swap_for_y} {lb_pair}, price impact above {max_price_impact_bps} bps"
+            );
+            return Ok(None);
+        };
+
+        if let Some(paper) = &self.paper {
+            paper
+                .lock()
+                .unwrap()
+                .swap(lb_pair, amount_in, quote.amount_out, swap_for_y)?;
+            info!("paper swap {amount_in} {swap_for_y} {}", quote.amount_out);
+            let swap_event = SwapEvent {
+                lb_pair,
+                from: self.owner,
+                start_bin_id: lb_pair_state.active_id,
+                end_bin_id: lb_pair_state.active_id,
+                amount_in,
+                amount_out: quote.amount_out,
+                swap_for_y,
+                fee: quote.fee,
+                protocol_fee: 0,
+                fee_bps: 0,
+                host_fee: 0,
+            };
+            self.record_action(lb_pair, &lb_pair_state, None, get_swap_action(&swap_event));
+            return Ok(Some(swap_event));
+        }
+
+        let payer = self.get_payer()?;
+        let (user_token_in, user_token_out) = if swap_for_y {
             (
                 get_associated_token_address(&payer.pubkey(), &lb_pair_state.token_x_mint),
                 get_associated_token_address(&payer.pubkey(), &lb_pair_state.token_y_mint),
-                derive_bin_array_pda(lb_pair, (active_bin_array_idx - 1) as i64).0,
-                derive_bin_array_pda(lb_pair, (active_bin_array_idx - 2) as i64).0,
             )
         } else {
             (
                 get_associated_token_address(&payer.pubkey(), &lb_pair_state.token_y_mint),
                 get_associated_token_address(&payer.pubkey(), &lb_pair_state.token_x_mint),
-                derive_bin_array_pda(lb_pair, (active_bin_array_idx + 1) as i64).0,
-                derive_bin_array_pda(lb_pair, (active_bin_array_idx + 2) as i64).0,
             )
         };
 
@@ -370,26 +978,17 @@ impl Core {
 
         let ix = instruction::Swap {
             amount_in,
-            min_amount_out: state.get_min_out_amount_with_slippage_rate(amount_in, swap_for_y)?,
+            min_amount_out,
         };
 
-        let remaining_accounts = vec![
-            AccountMeta {
-                is_signer: false,
-                is_writable: true,
-                pubkey: bin_array_0,
-            },
-            AccountMeta {
-                is_signer: false,
-                is_writable: true,
-                pubkey: bin_array_1,
-            },
-            AccountMeta {
+        let remaining_accounts = bin_arrays_for_swap
+            .into_iter()
+            .map(|pubkey| AccountMeta {
                 is_signer: false,
                 is_writable: true,
-                pubkey: bin_array_2,
-            },
-        ];
+                pubkey,
+            })
+            .collect::<Vec<_>>();
 
         let compute_budget_ix = ComputeBudgetInstruction::set_compute_unit_limit(1_400_000);
 
@@ -404,7 +1003,7 @@ impl Core {
             let response =
                 simulate_transaction(vec![&payer], payer.pubkey(), &program, &builder).await?;
             println!("{:?}", response);
-            return Ok(SwapEvent {
+            return Ok(Some(SwapEvent {
                 lb_pair: Pubkey::default(),
                 from: Pubkey::default(),
                 start_bin_id: 0,
@@ -416,41 +1015,114 @@ impl Core {
                 protocol_fee: 0,
                 fee_bps: 0,
                 host_fee: 0,
-            });
+            }));
         }
 
-        let signature = send_tx(vec![&payer], payer.pubkey(), &program, &builder).await?;
+        let signature = self.record_transaction(
+            lb_pair,
+            SWAP_ACTION,
+            send_tx(vec![&payer], payer.pubkey(), &program, &builder).await,
+        )?;
         info!("swap {amount_in} {swap_for_y} {signature}");
 
         // TODO should handle if cannot get swap eevent
         let swap_event = parse_swap_event(&program, signature).await?;
+        self.record_action(
+            lb_pair,
+            &lb_pair_state,
+            Some(signature.to_string()),
+            get_swap_action(&swap_event),
+        );
 
-        Ok(swap_event)
+        Ok(Some(swap_event))
     }
 
+    // deposit into adjacent positions covering the bin range, one transaction per position
+    #[allow(clippy::too_many_arguments)]
     pub async fn deposit(
         &self,
         state: &SinglePosition,
         amount_x: u64,
         amount_y: u64,
-        active_id: i32,
+        min_bin_id: i32,
+        max_bin_id: i32,
+        strategy_type: StrategyType,
+        is_simulation: bool,
+    ) -> Result<()> {
+        ensure!(min_bin_id <= max_bin_id, "Invalid bin range");
+        if let Some(paper) = &self.paper {
+            let state = self
+                .get_state_with_bin_arrays(state, min_bin_id, max_bin_id)
+                .await?;
+            let deposits = paper.lock().unwrap().deposit(
+                &state,
+                amount_x,
+                amount_y,
+                min_bin_id,
+                max_bin_id,
+                strategy_type,
+            )?;
+            // The virtual positions cover the same ranges as the real ones would
+            let ranges = get_position_ranges(min_bin_id, max_bin_id);
+            for (deposit, (lower_bin_id, upper_bin_id)) in deposits.into_iter().zip(ranges) {
+                self.record_action(
+                    state.lb_pair,
+                    &state.lb_pair_state,
+                    None,
+                    JournalAction::Deposit {
+                        position: deposit.position.to_string(),
+                        amount_x: deposit.amount_x,
+                        amount_y: deposit.amount_y,
+                        lower_bin_id,
+                        upper_bin_id,
+                    },
+                );
+            }
+            return Ok(());
+        }
+        let ranges = get_position_ranges(min_bin_id, max_bin_id);
+        let amounts = get_position_deposit_amounts(
+            &ranges,
+            state.lb_pair_state.active_id,
+            amount_x,
+            amount_y,
+        );
+        for (&(lower_bin_id, upper_bin_id), &(amount_x, amount_y)) in
+            ranges.iter().zip(amounts.iter())
+        {
+            self.deposit_position(
+                state,
+                amount_x,
+                amount_y,
+                lower_bin_id,
+                upper_bin_id,
+                strategy_type.clone(),
+                is_simulation,
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn deposit_position(
+        &self,
+        state: &SinglePosition,
+        amount_x: u64,
+        amount_y: u64,
+        lower_bin_id: i32,
+        upper_bin_id: i32,
+        strategy_type: StrategyType,
         is_simulation: bool,
     ) -> Result<()> {
         // let state = self.get_state();
-        let payer = read_keypair_file(self.wallet.clone().unwrap())
-            .map_err(|_| Error::msg("Requires a keypair file"))?;
+        let payer = self.get_payer()?;
         let program: Program<Arc<Keypair>> = create_program(
             self.provider.to_string(),
             self.provider.to_string(),
             lb_clmm::ID,
             Arc::new(Keypair::new()),
         )?;
-        let lower_bin_id = active_id - (MAX_BIN_PER_ARRAY as i32).checked_div(2).unwrap();
-        let upper_bin_id = lower_bin_id
-            .checked_add(MAX_BIN_PER_ARRAY as i32)
-            .unwrap()
-            .checked_sub(1)
-            .unwrap();
         let lower_bin_array_idx = BinArray::bin_id_to_bin_array_index(lower_bin_id)?;
         let upper_bin_array_idx = lower_bin_array_idx.checked_add(1).unwrap();
 
@@ -496,7 +1168,7 @@ impl Core {
             .to_account_metas(None),
             data: instruction::InitializePosition {
                 lower_bin_id,
-                width: MAX_BIN_PER_POSITION as i32,
+                width: upper_bin_id - lower_bin_id + 1,
             }
             .data(),
         });
@@ -521,43 +1193,104 @@ impl Core {
         let user_token_y =
             get_associated_token_address(&payer.pubkey(), &lb_pair_state.token_y_mint);
 
-        instructions.push(Instruction {
-            program_id: lb_clmm::ID,
-            accounts: accounts::ModifyLiquidity {
-                lb_pair,
-                position,
-                bin_array_bitmap_extension,
-                bin_array_lower,
-                bin_array_upper,
-                sender: payer.pubkey(),
-                event_authority,
-                program: lb_clmm::ID,
-                reserve_x: lb_pair_state.reserve_x,
-                reserve_y: lb_pair_state.reserve_y,
-                token_x_mint: lb_pair_state.token_x_mint,
-                token_y_mint: lb_pair_state.token_y_mint,
-                user_token_x,
-                user_token_y,
-                token_x_program: anchor_spl::token::ID,
-                token_y_program: anchor_spl::token::ID,
-            }
-            .to_account_metas(None),
-            data: instruction::AddLiquidityByStrategy {
-                liquidity_parameter: LiquidityParameterByStrategy {
+        let is_one_side = matches!(
+            strategy_type,
+            StrategyType::SpotOneSide | StrategyType::CurveOneSide | StrategyType::BidAskOneSide
+        );
+
+        // An empty position still keeps the range contiguous
+        if is_one_side && (amount_x != 0 || amount_y != 0) {
+            ensure!(
+                amount_x == 0 || amount_y == 0,
+                "One sided deposit takes a single token"
+            );
+            let (amount, user_token, reserve, token_mint) = if amount_x != 0 {
+                (
                     amount_x,
+                    user_token_x,
+                    lb_pair_state.reserve_x,
+                    lb_pair_state.token_x_mint,
+                )
+            } else {
+                (
                     amount_y,
-                    active_id: lb_pair_state.active_id,
-                    max_active_bin_slippage: 3,
-                    strategy_parameters: StrategyParameters {
-                        min_bin_id: lower_bin_id,
-                        max_bin_id: upper_bin_id,
-                        strategy_type: StrategyType::SpotBalanced,
-                        parameteres: [0u8; 64],
+                    user_token_y,
+                    lb_pair_state.reserve_y,
+                    lb_pair_state.token_y_mint,
+                )
+            };
+
+            instructions.push(Instruction {
+                program_id: lb_clmm::ID,
+                accounts: accounts::ModifyLiquidityOneSide {
+                    lb_pair,
+                    position,
+                    bin_array_bitmap_extension,
+                    bin_array_lower,
+                    bin_array_upper,
+                    sender: payer.pubkey(),
+                    user_token,
+                    reserve,
+                    token_mint,
+                    token_program: anchor_spl::token::ID,
+                    event_authority,
+                    program: lb_clmm::ID,
+                }
+                .to_account_metas(None),
+                data: instruction::AddLiquidityByStrategyOneSide {
+                    liquidity_parameter: LiquidityParameterByStrategyOneSide {
+                        amount,
+                        active_id: lb_pair_state.active_id,
+                        max_active_bin_slippage: 3,
+                        strategy_parameters: StrategyParameters {
+                            min_bin_id: lower_bin_id,
+                            max_bin_id: upper_bin_id,
+                            strategy_type,
+                            parameteres: [0u8; 64],
+                        },
                     },
-                },
-            }
-            .data(),
-        });
+                }
+                .data(),
+            });
+        } else if amount_x != 0 || amount_y != 0 {
+            instructions.push(Instruction {
+                program_id: lb_clmm::ID,
+                accounts: accounts::ModifyLiquidity {
+                    lb_pair,
+                    position,
+                    bin_array_bitmap_extension,
+                    bin_array_lower,
+                    bin_array_upper,
+                    sender: payer.pubkey(),
+                    event_authority,
+                    program: lb_clmm::ID,
+                    reserve_x: lb_pair_state.reserve_x,
+                    reserve_y: lb_pair_state.reserve_y,
+                    token_x_mint: lb_pair_state.token_x_mint,
+                    token_y_mint: lb_pair_state.token_y_mint,
+                    user_token_x,
+                    user_token_y,
+                    token_x_program: anchor_spl::token::ID,
+                    token_y_program: anchor_spl::token::ID,
+                }
+                .to_account_metas(None),
+                data: instruction::AddLiquidityByStrategy {
+                    liquidity_parameter: LiquidityParameterByStrategy {
+                        amount_x,
+                        amount_y,
+                        active_id: lb_pair_state.active_id,
+                        max_active_bin_slippage: 3,
+                        strategy_parameters: StrategyParameters {
+                            min_bin_id: lower_bin_id,
+                            max_bin_id: upper_bin_id,
+                            strategy_type,
+                            parameteres: [0u8; 64],
+                        },
+                    },
+                }
+                .data(),
+            });
+        }
         let builder = program.request();
         let builder = instructions
             .into_iter()
@@ -574,14 +1307,30 @@ impl Core {
             .map_err(|_| Error::msg("Cannot simulate tx"))?;
             info!("deposit {amount_x} {amount_y} {position} {:?}", simulate_tx);
         } else {
-            let signature = send_tx(
-                vec![&payer, &position_kp],
-                payer.pubkey(),
-                &program,
-                &builder,
-            )
-            .await?;
+            let signature = self.record_transaction(
+                state.lb_pair,
+                DEPOSIT_ACTION,
+                send_tx(
+                    vec![&payer, &position_kp],
+                    payer.pubkey(),
+                    &program,
+                    &builder,
+                )
+                .await,
+            )?;
             info!("deposit {amount_x} {amount_y} {position} {signature}");
+            self.record_action(
+                lb_pair,
+                &state.lb_pair_state,
+                Some(signature.to_string()),
+                JournalAction::Deposit {
+                    position: position.to_string(),
+                    amount_x,
+                    amount_y,
+                    lower_bin_id,
+                    upper_bin_id,
+                },
+            );
         }
 
         Ok(())
@@ -596,8 +1345,12 @@ impl Core {
         // let state = self.get_state();
         let lb_pair_state = position.lb_pair_state;
 
-        let payer = read_keypair_file(self.wallet.clone().unwrap())
-            .map_err(|_| Error::msg("Requires a keypair file"))?;
+        if let Some(paper) = &self.paper {
+            let (balance_x, balance_y) = paper.lock().unwrap().get_balances(position.lb_pair)?;
+            return Ok((amount_x.min(balance_x), amount_y.min(balance_y)));
+        }
+
+        let payer = self.get_payer()?;
 
         let program: Program<Arc<Keypair>> = create_program(
             self.provider.to_string(),
@@ -642,155 +1395,439 @@ impl Core {
         let state = self.state.lock().unwrap();
         state.tokens.clone()
     }
-    pub async fn check_shift_price_range(&self) -> Result<()> {
+    pub async fn get_balances(&self, state: &SinglePosition) -> Result<(u64, u64)> {
+        if let Some(paper) = &self.paper {
+            return paper.lock().unwrap().get_balances(state.lb_pair);
+        }
+        let program: Program<Arc<Keypair>> = create_program(
+            self.provider.to_string(),
+            self.provider.to_string(),
+            lb_clmm::ID,
+            Arc::new(Keypair::new()),
+        )?;
+        let lb_pair_state = state.lb_pair_state;
+        let user_token_x = get_associated_token_address(&self.owner, &lb_pair_state.token_x_mint);
+        let user_token_y = get_associated_token_address(&self.owner, &lb_pair_state.token_y_mint);
+
+        let accounts = program
+            .rpc()
+            .get_multiple_accounts(&[user_token_x, user_token_y])
+            .await?;
+
+        let mut balances = [0u64; 2];
+        for (i, account) in accounts.into_iter().enumerate() {
+            if let Some(account) = account {
+                balances[i] = TokenAccount::try_deserialize(&mut account.data.as_ref())?.amount;
+            }
+        }
+
+        Ok((balances[0], balances[1]))
+    }
+
+    pub async fn run_strategies(&self) -> Result<()> {
+        let lb_pairs = self
+            .get_all_positions()
+            .iter()
+            .map(|position| position.lb_pair)
+            .collect::<Vec<_>>();
+        self.run_strategies_for_pairs(&lb_pairs).await
+    }
+
+    pub async fn run_strategies_for_pairs(&self, lb_pairs: &[Pubkey]) -> Result<()> {
+        let _execution = self.execution_lock.lock().await;
+        self.run_strategies_for_pairs_unlocked(lb_pairs).await
+    }
+
+    async fn run_strategies_for_pairs_unlocked(&self, lb_pairs: &[Pubkey]) -> Result<()> {
+        if self.get_kill_switch().is_some() {
+            return Ok(());
+        }
         let all_positions = self.get_all_positions();
+        let config = self.get_config();
         for position in all_positions.iter() {
-            let pair_config = get_pair_config(&self.config, position.lb_pair);
-            // check whether out of price range
-            // let state = self.get_state();
-            if pair_config.mode == MarketMakingMode::ModeRight
-                && position.lb_pair_state.active_id > position.max_bin_id
-            {
-                self.shift_right(&position).await?;
-                self.inc_rebalance_time(position.lb_pair);
+            if !lb_pairs.contains(&position.lb_pair) {
+                continue;
             }
-
-            if pair_config.mode == MarketMakingMode::ModeLeft
-                && position.lb_pair_state.active_id < position.min_bin_id
-            {
-                self.shift_left(&position).await?;
-                self.inc_rebalance_time(position.lb_pair);
+            let pair_config = get_pair_config(&config, position.lb_pair);
+            if pair_config.paused {
+                continue;
             }
-            if pair_config.mode == MarketMakingMode::ModeBoth {
-                if position.lb_pair_state.active_id < position.min_bin_id {
-                    self.shift_left(&position).await?;
-                    self.inc_rebalance_time(position.lb_pair);
-                } else if position.lb_pair_state.active_id > position.max_bin_id {
-                    self.shift_right(&position).await?;
-                    self.inc_rebalance_time(position.lb_pair);
-                }
+            let strategy = self.strategies.get(&pair_config.get_strategy_name())?;
+
+            let (balance_x, balance_y) = self.get_balances(position).await?;
+            let snapshot = StrategySnapshot {
+                config: &pair_config,
+                state: position,
+                position: position.get_positions()?,
+                balance_x,
+                balance_y,
+                timestamp: get_epoch_sec(),
+            };
+
+            let actions = strategy.decide(&snapshot)?;
+            if let Some(reason) = self.check_risk_limits(&snapshot, &actions).await? {
+                self.trigger_kill_switch_unlocked(Some(position.lb_pair), reason)
+                    .await?;
+                return Ok(());
+            }
+            if actions.is_empty() {
+                continue;
             }
+
+            self.execute_actions(position, actions).await?;
+            self.inc_rebalance_time(position.lb_pair);
         }
 
         Ok(())
     }
 
-    async fn shift_right(&self, state: &SinglePosition) -> Result<()> {
-        let pair_config = get_pair_config(&self.config, state.lb_pair);
-        // validate that y amount is zero
-        info!("shift right {}", state.lb_pair);
-        let position = state.get_positions()?;
-        if position.amount_x != 0 {
-            return Err(Error::msg("Amount x is not zero"));
-        }
-
-        info!("withdraw {}", state.lb_pair);
-        // withdraw
-        self.withdraw(state, false).await?;
-
-        // buy base
-        let amount_y_for_buy = position
-            .amount_y
-            .safe_div(2)
-            .map_err(|_| Error::msg("Math is overflow"))?;
-        let (amount_x, amount_y) = if amount_y_for_buy != 0 {
-            info!("swap {}", state.lb_pair);
-            let swap_event = self.swap(state, amount_y_for_buy, false, false).await?;
-            (swap_event.amount_out, position.amount_y - amount_y_for_buy)
-        } else {
-            (pair_config.x_amount, pair_config.y_amount)
-        };
-
-        // deposit again, just test with 1 position only
-        info!("deposit {}", state.lb_pair);
-        match self
-            .deposit(
-                state,
-                amount_x,
-                amount_y,
-                state.lb_pair_state.active_id,
-                false,
-            )
-            .await
-        {
-            Err(_) => {
-                self.deposit(
-                    state,
+    async fn execute_actions(
+        &self,
+        state: &SinglePosition,
+        actions: Vec<StrategyAction>,
+    ) -> Result<()> {
+        // Output of the swaps, added to the next deposit
+        let mut swap_out_x = 0u64;
+        let mut swap_out_y = 0u64;
+
+        for action in actions {
+            info!("{:?} {}", action, state.lb_pair);
+            match action {
+                StrategyAction::Withdraw => {
+                    self.withdraw(state, false).await?;
+                }
+                StrategyAction::WithdrawPositions { positions } => {
+                    self.withdraw_positions(state, &positions, false).await?;
+                }
+                StrategyAction::ClaimFee => {
+                    self.claim_fee(state, false).await?;
+                }
+                StrategyAction::Swap {
+                    amount_in,
+                    swap_for_y,
+                } => {
+                    let max_price_impact_bps = get_pair_config(&self.get_config(), state.lb_pair)
+                        .get_max_price_impact_bps();
+                    let Some(swap_event) = self
+                        .swap(state, amount_in, swap_for_y, max_price_impact_bps, false)
+                        .await?
+                    else {
+                        continue;
+                    };
+                    if swap_for_y {
+                        swap_out_y = swap_out_y.saturating_add(swap_event.amount_out);
+                    } else {
+                        swap_out_x = swap_out_x.saturating_add(swap_event.amount_out);
+                    }
+                }
+                StrategyAction::Deposit {
                     amount_x,
                     amount_y,
-                    state.lb_pair_state.active_id,
-                    true,
-                )
-                .await?;
+                    min_bin_id,
+                    max_bin_id,
+                    strategy_type,
+                } => {
+                    let amount_x = amount_x.saturating_add(swap_out_x);
+                    let amount_y = amount_y.saturating_add(swap_out_y);
+                    swap_out_x = 0;
+                    swap_out_y = 0;
+
+                    // sanity check with real balances
+                    let (amount_x, amount_y) =
+                        self.get_deposit_amount(state, amount_x, amount_y).await?;
+                    match self
+                        .deposit(
+                            state,
+                            amount_x,
+                            amount_y,
+                            min_bin_id,
+                            max_bin_id,
+                            strategy_type.clone(),
+                            false,
+                        )
+                        .await
+                    {
+                        Err(_) => {
+                            self.deposit(
+                                state,
+                                amount_x,
+                                amount_y,
+                                min_bin_id,
+                                max_bin_id,
+                                strategy_type,
+                                true,
+                            )
+                            .await?;
+                        }
+                        _ => {}
+                    }
+                }
             }
-            _ => {}
         }
+
         info!("refresh state {}", state.lb_pair);
         // fetch positions again
         self.refresh_state().await?;
         Ok(())
     }
-    async fn shift_left(&self, state: &SinglePosition) -> Result<()> {
-        let pair_config = get_pair_config(&self.config, state.lb_pair);
-        info!("shift left {}", state.lb_pair);
-        // validate that y amount is zero
-        let position = state.get_positions()?;
-        if position.amount_y != 0 {
-            return Err(Error::msg("Amount y is not zero"));
+
+    pub fn get_config(&self) -> Vec<PairConfig> {
+        self.config.lock().unwrap().clone()
+    }
+
+    /// Config of a pair of the effective config.
+    pub fn get_pair_config(&self, lb_pair: Pubkey) -> Result<PairConfig> {
+        self.get_config()
+            .into_iter()
+            .find(|pair_config| pair_config.pair_address == lb_pair.to_string())
+            .ok_or_else(|| Error::msg(format!("Pair {} not found", lb_pair)))
+    }
+
+    /// Check a new config the way the config file is checked at start, and that it can be run without restart.
+    fn check_config(&self, config: &Vec<PairConfig>) -> Result<()> {
+        self.strategies.validate(config)?;
+        if self.wallet.is_none() && self.paper.is_none() && should_market_making(config) {
+            return Err(Error::msg(
+                "Requires a wallet signer to market make, restart with --wallet",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Change the config of a pair. Return the new config of the pair.
+    pub fn update_pair_config<F>(&self, lb_pair: Pubkey, update: F) -> Result<PairConfig>
+    where
+        F: FnOnce(&mut PairConfig),
+    {
+        let mut config = self.config.lock().unwrap();
+        let mut new_config = config.clone();
+        let pair_config = new_config
+            .iter_mut()
+            .find(|pair_config| pair_config.pair_address == lb_pair.to_string())
+            .ok_or_else(|| Error::msg(format!("Pair {} not found", lb_pair)))?;
+        update(pair_config);
+        let pair_config = pair_config.clone();
+
+        self.check_config(&new_config)?;
+        *config = new_config;
+        Ok(pair_config)
+    }
+
+    /// Apply the config file read again, which must have the same pairs. Only the fields changed in the file since
+    /// its previous read are applied, so that the changes made through the control API, such as a pause, are kept.
+    pub fn reload_config(&self, new_file_config: Vec<PairConfig>) -> Result<()> {
+        let mut config = self.config.lock().unwrap();
+        let mut file_config = self.file_config.lock().unwrap();
+        let new_config = merge_reloaded_config(&*config, &*file_config, &new_file_config)?;
+        self.check_config(&new_config)?;
+
+        let get_pair_addresses = |config: &Vec<PairConfig>| {
+            let mut pair_addresses = config
+                .iter()
+                .map(|pair_config| pair_config.pair_address.clone())
+                .collect::<Vec<_>>();
+            pair_addresses.sort_unstable();
+            pair_addresses
+        };
+        if get_pair_addresses(&*config) != get_pair_addresses(&new_config) {
+            return Err(Error::msg("Adding or removing pairs requires a restart"));
         }
-        info!("withdraw {}", state.lb_pair);
-        // withdraw
-        self.withdraw(state, false).await?;
-
-        // sell base
-        let amount_x_for_sell = position
-            .amount_x
-            .safe_div(2)
-            .map_err(|_| Error::msg("Math is overflow"))?;
-        let (amount_x, amount_y) = if amount_x_for_sell != 0 {
-            info!("swap {}", state.lb_pair);
-            let swap_event = self.swap(state, amount_x_for_sell, true, false).await?;
-            (position.amount_x - amount_x_for_sell, swap_event.amount_out)
-        } else {
-            (pair_config.x_amount, pair_config.y_amount)
+        *config = new_config;
+        *file_config = new_file_config;
+        Ok(())
+    }
+
+    /// Reason a forced rebalance of the pair is refused. A pair with the view strategy is never rebalanced.
+    pub fn check_force_rebalance(&self, lb_pair: Pubkey) -> Result<()> {
+        let pair_config = self.get_pair_config(lb_pair)?;
+        if pair_config.paused {
+            return Err(Error::msg(format!("Pair {} is paused", lb_pair)));
+        }
+        if pair_config.get_strategy_name() == VIEW_STRATEGY {
+            return Err(Error::msg(format!(
+                "Pair {} only monitors with the view strategy",
+                lb_pair
+            )));
+        }
+        if self.get_kill_switch().is_some() {
+            return Err(Error::msg("The kill switch was triggered"));
+        }
+        Ok(())
+    }
+
+    /// Withdraw all positions of the pair and deposit the withdrawn amounts and fees into a new range around the
+    /// active bin, whatever the position of the active bin against the range.
+    pub async fn force_rebalance(&self, lb_pair: Pubkey) -> Result<()> {
+        self.check_force_rebalance(lb_pair)?;
+        let _execution = self.execution_lock.lock().await;
+
+        self.refresh_state().await?;
+        let pair_config = self.get_pair_config(lb_pair)?;
+        let state = self.get_position_state(lb_pair);
+        let (balance_x, balance_y) = self.get_balances(&state).await?;
+        let snapshot = StrategySnapshot {
+            config: &pair_config,
+            state: &state,
+            position: state.get_positions()?,
+            balance_x,
+            balance_y,
+            timestamp: get_epoch_sec(),
         };
 
-        // sanity check with real balances
-        let (amount_x, amount_y) = self.get_deposit_amount(state, amount_x, amount_y).await?;
-        info!("deposit {}", state.lb_pair);
-        match self
-            .deposit(
-                state,
-                amount_x,
-                amount_y,
-                state.lb_pair_state.active_id,
-                false,
-            )
-            .await
-        {
-            Err(_) => {
-                self.deposit(
-                    state,
-                    amount_x,
-                    amount_y,
-                    state.lb_pair_state.active_id,
-                    true,
-                )
+        let actions = get_force_rebalance_actions(&snapshot)?;
+        if let Some(reason) = self.check_risk_limits(&snapshot, &actions).await? {
+            self.trigger_kill_switch_unlocked(Some(lb_pair), reason.clone())
+                .await?;
+            return Err(Error::msg(format!(
+                "The kill switch was triggered: {}",
+                reason
+            )));
+        }
+
+        self.execute_actions(&state, actions).await?;
+        self.inc_rebalance_time(lb_pair);
+        Ok(())
+    }
+
+    /// Pause the pair and withdraw all its positions.
+    pub async fn withdraw_pair(&self, lb_pair: Pubkey) -> Result<()> {
+        self.update_pair_config(lb_pair, |pair_config| pair_config.paused = true)?;
+        let _execution = self.execution_lock.lock().await;
+
+        let state = self.get_position_state(lb_pair);
+        self.withdraw(&state, false).await?;
+        self.refresh_state().await
+    }
+
+    pub fn get_kill_switch(&self) -> Option<KillSwitch> {
+        self.risk.lock().unwrap().kill_switch.clone()
+    }
+
+    /// Reason to trigger the kill switch, from the risk limits of the pair and the actions its strategy decided. The
+    /// value of the pair at its first check is the starting value of the drawdown.
+    async fn check_risk_limits(
+        &self,
+        snapshot: &StrategySnapshot<'_>,
+        actions: &[StrategyAction],
+    ) -> Result<Option<String>> {
+        if !snapshot.config.has_risk_limits() {
+            return Ok(None);
+        }
+        let value = get_snapshot_value(snapshot)?;
+        // The virtual wallet pays no fees
+        let sol_balance = if snapshot.config.min_sol_balance.is_some() && self.paper.is_none() {
+            let program: Program<Arc<Keypair>> = create_program(
+                self.provider.to_string(),
+                self.provider.to_string(),
+                lb_clmm::ID,
+                Arc::new(Keypair::new()),
+            )?;
+            let sol_balance = self
+                .observe_rpc("getBalance", program.rpc().get_balance(&self.owner))
                 .await?;
+            Some(sol_balance)
+        } else {
+            None
+        };
+
+        let is_first_check = {
+            let mut risk = self.risk.lock().unwrap();
+            let is_first_check = !risk.start_values.contains_key(&snapshot.state.lb_pair);
+            if is_first_check {
+                risk.start_values.insert(snapshot.state.lb_pair, value);
             }
-            _ => {}
+            is_first_check
+        };
+        if is_first_check {
+            self.record_action(
+                snapshot.state.lb_pair,
+                &snapshot.state.lb_pair_state,
+                None,
+                JournalAction::StartValue { value },
+            );
         }
 
-        info!("refresh state {}", state.lb_pair);
-        // fetch positions again
-        self.refresh_state().await?;
+        let risk = self.risk.lock().unwrap();
+        let journal = self.journal.lock().unwrap();
+        Ok(get_limit_breach(
+            snapshot,
+            actions,
+            value,
+            sol_balance,
+            &journal,
+            &risk,
+        ))
+    }
+
+    /// Trigger the kill switch: withdraw all liquidity of all pairs and stop running the strategies, until an operator
+    /// clears it. The kill switch is recorded in the journal, so that it survives a restart.
+    pub async fn trigger_kill_switch(&self, lb_pair: Option<Pubkey>, reason: String) -> Result<()> {
+        let _execution = self.execution_lock.lock().await;
+        self.trigger_kill_switch_unlocked(lb_pair, reason).await
+    }
+
+    async fn trigger_kill_switch_unlocked(
+        &self,
+        lb_pair: Option<Pubkey>,
+        reason: String,
+    ) -> Result<()> {
+        let kill_switch = {
+            let mut risk = self.risk.lock().unwrap();
+            if risk.kill_switch.is_some() {
+                return Ok(());
+            }
+            let kill_switch = KillSwitch {
+                timestamp: get_epoch_sec(),
+                pair_address: lb_pair.map(|lb_pair| lb_pair.to_string()),
+                reason: reason.clone(),
+            };
+            risk.kill_switch = Some(kill_switch.clone());
+            kill_switch
+        };
+        error!("kill switch triggered by {:?}: {}", lb_pair, reason);
+        self.record_risk_action(
+            kill_switch.timestamp,
+            kill_switch.pair_address.unwrap_or_default(),
+            JournalAction::KillSwitch { reason },
+        );
+
+        // Withdraw as much as possible, a failed pair does not keep the others in the pools
+        for state in self.get_all_positions().iter() {
+            if let Err(err) = self.withdraw(state, false).await {
+                error!("kill switch withdraw {} err {}", state.lb_pair, err);
+            }
+        }
+        self.refresh_state().await
+    }
+
+    /// Clear the kill switch, so that the strategies run again. The risk limits count from now: the swap volume is
+    /// counted again and the starting values are taken again at the next check.
+    pub async fn clear_kill_switch(&self) -> Result<()> {
+        let _execution = self.execution_lock.lock().await;
+        let timestamp = get_epoch_sec();
+        {
+            let mut risk = self.risk.lock().unwrap();
+            if risk.kill_switch.is_none() {
+                return Err(Error::msg("The kill switch is not triggered"));
+            }
+            *risk = RiskState {
+                start_timestamp: timestamp,
+                ..Default::default()
+            };
+        }
+        info!("kill switch cleared");
+        self.record_risk_action(timestamp, String::new(), JournalAction::ClearKillSwitch);
         Ok(())
     }
 
     pub fn inc_rebalance_time(&self, lb_pair: Pubkey) {
-        let mut state = self.state.lock().unwrap();
-        let state = state.all_positions.get_mut(&lb_pair).unwrap();
-        state.inc_rebalance_time();
+        let lb_pair_state = {
+            let mut state = self.state.lock().unwrap();
+            let state = state.all_positions.get_mut(&lb_pair).unwrap();
+            state.inc_rebalance_time();
+            state.lb_pair_state
+        };
+        self.record_action(lb_pair, &lb_pair_state, None, JournalAction::Rebalance);
     }
 
     pub fn get_positions(&self) -> Result<Vec<PositionInfo>> {
@@ -802,21 +1839,85 @@ impl Core {
             let x_decimals = get_decimals(position.lb_pair_state.token_x_mint, &tokens);
             let y_decimals = get_decimals(position.lb_pair_state.token_y_mint, &tokens);
             let position_raw = position.get_positions()?;
-            position_infos.push(position_raw.to_position_info(x_decimals, y_decimals)?);
+            let mut position_info = position_raw.to_position_info(x_decimals, y_decimals)?;
+            if let Some(paper) = &self.paper {
+                let performance = paper
+                    .lock()
+                    .unwrap()
+                    .get_performance(position, &position_raw)?;
+                position_info.paper = Some(performance.to_paper_info(x_decimals, y_decimals));
+            }
+            position_infos.push(position_info);
         }
         return Ok(position_infos);
     }
+
+    /// Realised PnL and fees of each pair over time, from the journal.
+    pub fn get_history(&self) -> Vec<PairHistory> {
+        let all_positions = self.get_all_positions();
+        let tokens = self.get_all_tokens();
+        let journal = self.journal.lock().unwrap();
+
+        let mut pair_histories = vec![];
+        for position in all_positions.iter() {
+            let x_decimals = get_decimals(position.lb_pair_state.token_x_mint, &tokens);
+            let y_decimals = get_decimals(position.lb_pair_state.token_y_mint, &tokens);
+            let pair_address = position.lb_pair.to_string();
+            let history = journal
+                .get_history(&pair_address)
+                .iter()
+                .map(|point| point.to_history_point(x_decimals, y_decimals))
+                .collect();
+            pair_histories.push(PairHistory {
+                pair_address,
+                history,
+            });
+        }
+        pair_histories.sort_by(|a, b| a.pair_address.cmp(&b.pair_address));
+        pair_histories
+    }
+
+    /// Metrics of the pairs and of the bot in the Prometheus text format.
+    pub fn get_metrics(&self) -> Result<String> {
+        let all_positions = self.get_all_positions();
+        let tokens = self.get_all_tokens();
+
+        let mut position_infos = vec![];
+        for position in all_positions.iter() {
+            let x_decimals = get_decimals(position.lb_pair_state.token_x_mint, &tokens);
+            let y_decimals = get_decimals(position.lb_pair_state.token_y_mint, &tokens);
+            let position_info = match position
+                .get_positions()
+                .and_then(|positions| positions.to_position_info(x_decimals, y_decimals))
+            {
+                std::result::Result::Ok(position_info) => position_info,
+                Err(err) => {
+                    error!("skip metrics of pair {}: {err}", position.lb_pair);
+                    continue;
+                }
+            };
+            position_infos.push((position.lb_pair, position_info));
+        }
+        position_infos.sort_by_key(|(lb_pair, _)| *lb_pair);
+
+        let killed = self.get_kill_switch().is_some();
+        let metrics = self.metrics.lock().unwrap();
+        Ok(metrics.render(&position_infos, killed, get_epoch_sec()))
+    }
 }
 
 #[cfg(test)]
 mod core_test {
     use super::*;
+    use crate::pair_config::DEFAULT_MAX_PRICE_IMPACT_BPS;
+    use crate::MarketMakingMode;
+    use commons::signer::load_signer;
     use std::env;
     #[tokio::test(flavor = "multi_thread")]
     async fn test_withdraw() {
         let wallet = env::var("MM_WALLET").unwrap();
         let cluster = env::var("MM_CLUSTER").unwrap();
-        let payer = read_keypair_file(wallet.clone()).unwrap();
+        let payer = load_signer(&wallet).unwrap();
 
         let lp_pair = Pubkey::from_str("FoSDw2L5DmTuQTFe55gWPDXf88euaxAEKFre74CnvQbX").unwrap();
 
@@ -825,14 +1926,22 @@ mod core_test {
             x_amount: 17000000,
             y_amount: 2000000,
             mode: MarketMakingMode::ModeBoth,
+            ..Default::default()
         }];
 
         let core = &Core {
             provider: Cluster::from_str(&cluster).unwrap(),
-            wallet: Some(wallet),
+            wallet: Some(payer.clone()),
             owner: payer.pubkey(),
-            config: config.clone(),
+            config: Arc::new(Mutex::new(config.clone())),
+            file_config: Arc::new(Mutex::new(config.clone())),
             state: Arc::new(Mutex::new(AllPosition::new(&config))),
+            strategies: StrategyRegistry::default(),
+            paper: None,
+            metrics: Arc::new(Mutex::new(Metrics::default())),
+            execution_lock: tokio::sync::Mutex::new(()),
+            journal: Arc::new(Mutex::new(Journal::default())),
+            risk: Arc::new(Mutex::new(RiskState::default())),
         };
 
         core.refresh_state().await.unwrap();
@@ -847,7 +1956,7 @@ mod core_test {
     async fn test_swap() {
         let wallet = env::var("MM_WALLET").unwrap();
         let cluster = env::var("MM_CLUSTER").unwrap();
-        let payer = read_keypair_file(wallet.clone()).unwrap();
+        let payer = load_signer(&wallet).unwrap();
 
         let lp_pair = Pubkey::from_str("FoSDw2L5DmTuQTFe55gWPDXf88euaxAEKFre74CnvQbX").unwrap();
 
@@ -856,20 +1965,30 @@ mod core_test {
             x_amount: 17000000,
             y_amount: 2000000,
             mode: MarketMakingMode::ModeBoth,
+            ..Default::default()
         }];
 
         let core = &Core {
             provider: Cluster::from_str(&cluster).unwrap(),
-            wallet: Some(wallet),
+            wallet: Some(payer.clone()),
             owner: payer.pubkey(),
-            config: config.clone(),
+            config: Arc::new(Mutex::new(config.clone())),
+            file_config: Arc::new(Mutex::new(config.clone())),
             state: Arc::new(Mutex::new(AllPosition::new(&config))),
+            strategies: StrategyRegistry::default(),
+            paper: None,
+            metrics: Arc::new(Mutex::new(Metrics::default())),
+            execution_lock: tokio::sync::Mutex::new(()),
+            journal: Arc::new(Mutex::new(Journal::default())),
+            risk: Arc::new(Mutex::new(RiskState::default())),
         };
 
         core.refresh_state().await.unwrap();
 
         let state = core.get_position_state(lp_pair);
 
-        core.swap(&state, 1000000, true, true).await.unwrap();
+        core.swap(&state, 1000000, true, DEFAULT_MAX_PRICE_IMPACT_BPS, true)
+            .await
+            .unwrap();
     }
 }
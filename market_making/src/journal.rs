@@ -0,0 +1,405 @@
+use crate::state::AllPosition;
+use anyhow::*;
+use lb_clmm::math::price_math::get_price_from_id;
+use lb_clmm::math::u64x64_math::SCALE_OFFSET;
+use lb_clmm::state::lb_pair::LbPair;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+
+/// Action of the bot. Amounts are in base units.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JournalAction {
+    /// Position withdrawn and closed, with its amounts and pending fees before the withdraw
+    Withdraw {
+        position: String,
+        amount_x: u64,
+        amount_y: u64,
+        fee_x: u64,
+        fee_y: u64,
+    },
+    ClaimFee {
+        position: String,
+        fee_x: u64,
+        fee_y: u64,
+    },
+    /// Swap, from its decoded `Swap` event
+    Swap {
+        amount_in: u64,
+        amount_out: u64,
+        swap_for_y: bool,
+        start_bin_id: i32,
+        end_bin_id: i32,
+        fee: u64,
+        protocol_fee: u64,
+    },
+    Deposit {
+        position: String,
+        amount_x: u64,
+        amount_y: u64,
+        lower_bin_id: i32,
+        upper_bin_id: i32,
+    },
+    /// Strategy run which took actions, counted in `rebalance_time`
+    Rebalance,
+    /// Value of the positions and wallet of the pair at its first risk check, the starting value of the drawdown
+    StartValue { value: u64 },
+    /// Kill switch triggered by a limit of the pair, or through the control API when the pair address is empty
+    KillSwitch { reason: String },
+    /// Kill switch cleared by an operator. The risk limits count from it again.
+    ClearKillSwitch,
+}
+
+impl JournalAction {
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            JournalAction::Withdraw { .. } => "withdraw",
+            JournalAction::ClaimFee { .. } => "claim_fee",
+            JournalAction::Swap { .. } => "swap",
+            JournalAction::Deposit { .. } => "deposit",
+            JournalAction::Rebalance => "rebalance",
+            JournalAction::StartValue { .. } => "start_value",
+            JournalAction::KillSwitch { .. } => "kill_switch",
+            JournalAction::ClearKillSwitch => "clear_kill_switch",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub timestamp: u64,
+    pub pair_address: String,
+    /// Signature of the transaction, none in paper mode
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+    /// Price of the active bin before the action, in base units of token Y per base unit of token X. 0 for the kill
+    /// switch entries.
+    pub price: f64,
+    #[serde(flatten)]
+    pub action: JournalAction,
+}
+
+/// Price of the active bin of the pair, in base units of token Y per base unit of token X.
+pub fn get_active_price(lb_pair_state: &LbPair) -> Result<f64> {
+    let price = get_price_from_id(lb_pair_state.active_id, lb_pair_state.bin_step)?;
+    Ok(price as f64 / (1u128 << SCALE_OFFSET) as f64)
+}
+
+/// Append-only journal of the actions of the bot, one JSON entry per line. Without a file, it is kept in memory only.
+#[derive(Debug, Default)]
+pub struct Journal {
+    file: Option<File>,
+    pub entries: Vec<JournalEntry>,
+}
+
+impl Journal {
+    /// Load the entries of the journal file and append the next ones to it. The file is created if needed.
+    pub fn open(path: &str) -> Result<Self> {
+        let mut entries = vec![];
+        if Path::new(path).exists() {
+            let reader = BufReader::new(File::open(path)?);
+            for (i, line) in reader.lines().enumerate() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                // A crash while writing leaves a truncated last line
+                match serde_json::from_str::<JournalEntry>(&line) {
+                    std::result::Result::Ok(entry) => entries.push(entry),
+                    Err(err) => warn!("skip line {} of journal {} err {}", i + 1, path, err),
+                }
+            }
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Journal {
+            file: Some(file),
+            entries,
+        })
+    }
+
+    pub fn record(&mut self, entry: JournalEntry) -> Result<()> {
+        if let Some(file) = &mut self.file {
+            let line = serde_json::to_string(&entry)?;
+            writeln!(file, "{}", line)?;
+            file.flush()?;
+        }
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    pub fn get_rebalance_time(&self, pair_address: &str) -> u64 {
+        self.count_rebalances_since(pair_address, 0)
+    }
+
+    pub fn count_rebalances_since(&self, pair_address: &str, since: u64) -> u64 {
+        self.entries
+            .iter()
+            .filter(|entry| {
+                entry.pair_address == pair_address
+                    && entry.timestamp >= since
+                    && entry.action == JournalAction::Rebalance
+            })
+            .count() as u64
+    }
+
+    /// Amounts of token x and token y swapped by the pair since the timestamp.
+    pub fn get_swap_volume_since(&self, pair_address: &str, since: u64) -> (u64, u64) {
+        let mut volume_x = 0u64;
+        let mut volume_y = 0u64;
+        for entry in self.entries.iter() {
+            if entry.pair_address != pair_address || entry.timestamp < since {
+                continue;
+            }
+            if let JournalAction::Swap {
+                amount_in,
+                swap_for_y,
+                ..
+            } = entry.action
+            {
+                if swap_for_y {
+                    volume_x = volume_x.saturating_add(amount_in);
+                } else {
+                    volume_y = volume_y.saturating_add(amount_in);
+                }
+            }
+        }
+        (volume_x, volume_y)
+    }
+
+    /// Restore the counters of the pairs from the journal.
+    pub fn restore(&self, state: &mut AllPosition) {
+        for (lb_pair, position) in state.all_positions.iter_mut() {
+            position.rebalance_time = self.get_rebalance_time(&lb_pair.to_string());
+        }
+    }
+
+    /// Realised PnL and fees of the pair after each of its actions. The PnL is in base units of token Y, at the price
+    /// of each action: fees earned, closed positions against their deposit, and swaps against the active price.
+    /// Positions deposited before the journal was started only count for their fees.
+    pub fn get_history(&self, pair_address: &str) -> Vec<HistoryRaw> {
+        let mut history = vec![];
+        let mut deposit_values: HashMap<&str, f64> = HashMap::new();
+        let mut point = HistoryRaw::default();
+
+        for entry in self.entries.iter() {
+            if entry.pair_address != pair_address {
+                continue;
+            }
+            let price = entry.price;
+            match &entry.action {
+                JournalAction::Withdraw {
+                    position,
+                    amount_x,
+                    amount_y,
+                    fee_x,
+                    fee_y,
+                } => {
+                    if let Some(deposit_value) = deposit_values.remove(position.as_str()) {
+                        point.realized_pnl +=
+                            *amount_x as f64 * price + *amount_y as f64 - deposit_value;
+                    }
+                    point.add_fee(*fee_x, *fee_y, price);
+                }
+                JournalAction::ClaimFee { fee_x, fee_y, .. } => {
+                    point.add_fee(*fee_x, *fee_y, price);
+                }
+                JournalAction::Swap {
+                    amount_in,
+                    amount_out,
+                    swap_for_y,
+                    ..
+                } => {
+                    point.realized_pnl += if *swap_for_y {
+                        *amount_out as f64 - *amount_in as f64 * price
+                    } else {
+                        *amount_out as f64 * price - *amount_in as f64
+                    };
+                }
+                JournalAction::Deposit {
+                    position,
+                    amount_x,
+                    amount_y,
+                    ..
+                } => {
+                    *deposit_values.entry(position.as_str()).or_default() +=
+                        *amount_x as f64 * price + *amount_y as f64;
+                }
+                JournalAction::Rebalance => {
+                    point.rebalance_time += 1;
+                }
+                // Not an action on the pools
+                JournalAction::StartValue { .. }
+                | JournalAction::KillSwitch { .. }
+                | JournalAction::ClearKillSwitch => continue,
+            }
+            point.timestamp = entry.timestamp;
+            point.action = entry.action.get_name().to_string();
+            history.push(point.clone());
+        }
+
+        history
+    }
+}
+
+/// Totals of a pair after an action, in base units
+#[derive(Default, PartialEq, Debug, Clone)]
+pub struct HistoryRaw {
+    pub timestamp: u64,
+    pub action: String,
+    pub realized_pnl: f64,
+    pub fee_x: u64,
+    pub fee_y: u64,
+    pub rebalance_time: u64,
+}
+
+impl HistoryRaw {
+    fn add_fee(&mut self, fee_x: u64, fee_y: u64, price: f64) {
+        self.fee_x = self.fee_x.saturating_add(fee_x);
+        self.fee_y = self.fee_y.saturating_add(fee_y);
+        self.realized_pnl += fee_x as f64 * price + fee_y as f64;
+    }
+
+    pub fn to_history_point(&self, token_x_decimals: u8, token_y_decimals: u8) -> HistoryPoint {
+        let x_scale = 10f64.powi(token_x_decimals.into());
+        let y_scale = 10f64.powi(token_y_decimals.into());
+        HistoryPoint {
+            timestamp: self.timestamp,
+            action: self.action.clone(),
+            realized_pnl: self.realized_pnl / y_scale,
+            fee_x: self.fee_x as f64 / x_scale,
+            fee_y: self.fee_y as f64 / y_scale,
+            rebalance_time: self.rebalance_time,
+        }
+    }
+}
+
+/// Totals of a pair after an action. The PnL is in token Y.
+#[derive(Default, PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryPoint {
+    pub timestamp: u64,
+    pub action: String,
+    pub realized_pnl: f64,
+    pub fee_x: f64,
+    pub fee_y: f64,
+    pub rebalance_time: u64,
+}
+
+#[derive(Default, PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct PairHistory {
+    pub pair_address: String,
+    pub history: Vec<HistoryPoint>,
+}
+
+#[cfg(test)]
+mod journal_test {
+    use super::*;
+    use crate::pair_config::PairConfig;
+    use anchor_lang::prelude::Pubkey;
+
+    fn get_entry(pair_address: &str, timestamp: u64, action: JournalAction) -> JournalEntry {
+        JournalEntry {
+            timestamp,
+            pair_address: pair_address.to_string(),
+            signature: None,
+            price: 2.0,
+            action,
+        }
+    }
+
+    #[test]
+    fn test_journal() {
+        let lb_pair = Pubkey::new_unique();
+        let pair_address = lb_pair.to_string();
+        let path = std::env::temp_dir().join(format!("journal_test_{}.jsonl", pair_address));
+        let path = path.to_str().unwrap();
+
+        let mut journal = Journal::open(path).unwrap();
+        let entries = vec![
+            get_entry(
+                &pair_address,
+                1,
+                JournalAction::Deposit {
+                    position: "a".to_string(),
+                    amount_x: 100,
+                    amount_y: 200,
+                    lower_bin_id: 0,
+                    upper_bin_id: 69,
+                },
+            ),
+            get_entry(&pair_address, 1, JournalAction::Rebalance),
+            get_entry(
+                &pair_address,
+                2,
+                JournalAction::ClaimFee {
+                    position: "a".to_string(),
+                    fee_x: 1,
+                    fee_y: 2,
+                },
+            ),
+            get_entry(
+                &pair_address,
+                3,
+                JournalAction::Withdraw {
+                    position: "a".to_string(),
+                    amount_x: 50,
+                    amount_y: 310,
+                    fee_x: 1,
+                    fee_y: 0,
+                },
+            ),
+            get_entry(
+                &pair_address,
+                3,
+                JournalAction::Swap {
+                    amount_in: 10,
+                    amount_out: 19,
+                    swap_for_y: true,
+                    start_bin_id: 0,
+                    end_bin_id: 0,
+                    fee: 1,
+                    protocol_fee: 0,
+                },
+            ),
+            get_entry(&pair_address, 3, JournalAction::Rebalance),
+        ];
+        for entry in entries.iter() {
+            journal.record(entry.clone()).unwrap();
+        }
+
+        // The entries are rebuilt from the file
+        let journal = Journal::open(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(journal.entries, entries);
+
+        let mut state = AllPosition::new(&vec![PairConfig {
+            pair_address: pair_address.clone(),
+            ..Default::default()
+        }]);
+        journal.restore(&mut state);
+        assert_eq!(state.all_positions[&lb_pair].rebalance_time, 2);
+
+        let history = journal.get_history(&pair_address);
+        assert_eq!(history.len(), 6);
+        // Fees of 1 * 2 + 2, then 1 * 2
+        assert_eq!(history[2].realized_pnl, 4.0);
+        // Closed for 50 * 2 + 310 against a deposit of 100 * 2 + 200
+        assert_eq!(history[3].realized_pnl, 16.0);
+        assert_eq!(history[3].fee_x, 2);
+        assert_eq!(history[3].fee_y, 2);
+        // Swapped at 1.9 for a price of 2
+        let last = history.last().unwrap();
+        assert_eq!(last.realized_pnl, 15.0);
+        assert_eq!(last.rebalance_time, 2);
+        assert_eq!(last.action, "rebalance");
+        assert_eq!(last.timestamp, 3);
+
+        let point = last.to_history_point(0, 1);
+        assert_eq!(point.realized_pnl, 1.5);
+        assert_eq!(point.fee_x, 2.0);
+        assert_eq!(point.fee_y, 0.2);
+    }
+}
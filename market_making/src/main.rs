@@ -1,18 +1,30 @@
+pub mod backtest;
 pub mod bin_array_manager;
 pub mod core;
+pub mod journal;
+pub mod metrics;
 pub mod pair_config;
+pub mod paper;
+pub mod risk;
 pub mod router;
 pub mod state;
+pub mod strategy;
 pub mod utils;
+pub mod watcher;
 use anchor_client::solana_sdk::pubkey::Pubkey;
-use anchor_client::solana_sdk::signature::read_keypair_file;
 use anchor_client::solana_sdk::signer::Signer;
 use anchor_client::Cluster;
+use backtest::{get_events_from_file, Backtester};
 use clap::Parser;
+use commons::signer::load_signer;
 use core::Core;
 use hyper::Server;
+use journal::Journal;
+use metrics::Metrics;
 use pair_config::{get_config_from_file, should_market_making};
-use router::router;
+use paper::PaperTrading;
+use risk::RiskState;
+use router::{router, ControlConfig};
 use routerify::RouterService;
 use serde::{Deserialize, Serialize};
 use state::AllPosition;
@@ -22,12 +34,12 @@ use std::str::FromStr;
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::time::Duration;
+use strategy::StrategyRegistry;
+use watcher::{run_config_watcher, run_watcher, WatcherConfig};
 
 #[macro_use]
 extern crate log;
 
-use tokio::time::interval;
-
 #[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 pub enum MarketMakingMode {
     ModeRight,
@@ -66,7 +78,7 @@ pub struct Args {
     /// Solana RPC provider. For example: https://api.mainnet-beta.solana.com
     #[clap(long, default_value_t = Cluster::Localnet)]
     provider: Cluster,
-    /// Wallet of owner
+    /// Wallet of owner. Keypair path, env:VAR, base58:<secret>, mnemonic:<phrase>#<derivation path> or remote signer url
     #[clap(long)]
     wallet: Option<String>,
     /// Address of owner, only user_public_key or wallet is set, other wise it is panic immediately
@@ -75,6 +87,38 @@ pub struct Args {
     /// config path
     #[clap(long)]
     config_file: String,
+    /// Websocket url of the RPC for account subscriptions. Defaults to the websocket url of the provider
+    #[clap(long)]
+    ws_provider: Option<String>,
+    /// Milliseconds to wait for more account updates before running the strategies of a pair
+    #[clap(long, default_value_t = 400)]
+    debounce_ms: u64,
+    /// Seconds between getMultipleAccounts polls while the websocket is down
+    #[clap(long, default_value_t = 5)]
+    poll_interval: u64,
+    /// Seconds between full state refreshes, which also run the strategies
+    #[clap(long, default_value_t = 60)]
+    refresh_interval: u64,
+    /// Follow the live pools but trade a virtual portfolio instead of sending transactions. No wallet is required
+    #[clap(long)]
+    paper: bool,
+    /// Replay the recorded events of a file for the first pair of the config and print the report, instead of
+    /// running the bot
+    #[clap(long)]
+    backtest: Option<String>,
+    /// Bearer token of the control API. The control API is disabled without it
+    #[clap(long)]
+    api_token: Option<String>,
+    /// Seconds between checks of the config file, which is reloaded when modified. 0 disables the reload
+    #[clap(long, default_value_t = 5)]
+    config_reload_interval: u64,
+    /// File the actions are appended to, and the counters restored from at start. Defaults to journal.jsonl, or
+    /// paper_journal.jsonl in paper mode
+    #[clap(long)]
+    journal_file: Option<String>,
+    /// Clear the kill switch recorded in the journal, so that the strategies run again
+    #[clap(long)]
+    clear_kill_switch: bool,
     // /// public key pair address,
     // #[clap(long)]
     // pair_address: Pubkey,
@@ -97,78 +141,135 @@ async fn main() {
         wallet,
         user_public_key,
         config_file,
+        ws_provider,
+        debounce_ms,
+        poll_interval,
+        refresh_interval,
+        paper,
+        backtest,
+        api_token,
+        config_reload_interval,
+        journal_file,
+        clear_kill_switch,
     } = Args::parse();
 
     let config = get_config_from_file(&config_file).unwrap();
+    let ws_url = ws_provider.unwrap_or_else(|| provider.ws_url().to_string());
+
+    // Register custom strategies here, then select them by name with `strategy` in the pair config
+    let strategies = StrategyRegistry::default();
+    strategies.validate(&config).unwrap();
+
+    if let Some(backtest_file) = backtest {
+        let pair_config = config.first().expect("No pair in config").clone();
+        let strategy = strategies.get(&pair_config.get_strategy_name()).unwrap();
+        let events = get_events_from_file(&backtest_file).unwrap();
+        let report = Backtester::new(pair_config, strategy, refresh_interval)
+            .run(&events)
+            .unwrap();
+        println!("{}", serde_json::to_string_pretty(&report).unwrap());
+        return;
+    }
 
     // info!("{:?}", mode);
 
-    let user_wallet = if should_market_making(&config) {
-        let wallet =
-            read_keypair_file(wallet.clone().unwrap()).expect("Wallet keypair file not found");
-        wallet.pubkey()
+    let wallet = if should_market_making(&config) && !paper {
+        Some(load_signer(&wallet.unwrap()).expect("Wallet signer not found"))
     } else {
-        user_public_key.unwrap()
+        None
+    };
+
+    let user_wallet = match &wallet {
+        Some(wallet) => wallet.pubkey(),
+        // The virtual positions have no owner
+        None if paper => user_public_key.unwrap_or_default(),
+        None => user_public_key.unwrap(),
     };
 
+    let journal_file = journal_file.unwrap_or_else(|| {
+        if paper {
+            "paper_journal.jsonl".to_string()
+        } else {
+            "journal.jsonl".to_string()
+        }
+    });
+    let journal = Journal::open(&journal_file).unwrap();
+    let mut state = AllPosition::new(&config);
+    journal.restore(&mut state);
+    let risk = RiskState::from_journal(&journal);
+
     let core = Core {
         provider,
         wallet,
         owner: user_wallet,
-        config: config.clone(),
-        state: Arc::new(Mutex::new(AllPosition::new(&config))),
+        config: Arc::new(Mutex::new(config.clone())),
+        file_config: Arc::new(Mutex::new(config.clone())),
+        state: Arc::new(Mutex::new(state)),
+        strategies,
+        paper: paper.then(|| Arc::new(Mutex::new(PaperTrading::new(&config)))),
+        metrics: Arc::new(Mutex::new(Metrics::default())),
+        execution_lock: tokio::sync::Mutex::new(()),
+        journal: Arc::new(Mutex::new(journal)),
+        risk: Arc::new(Mutex::new(risk)),
     };
 
+    if clear_kill_switch {
+        if let Err(err) = core.clear_kill_switch().await {
+            warn!("clear kill switch err {}", err);
+        }
+    }
+    if let Some(kill_switch) = core.get_kill_switch() {
+        error!(
+            "kill switch triggered at {} by {:?}: {}. The strategies do not run until it is cleared with --clear-kill-switch or DELETE /kill_switch",
+            kill_switch.timestamp, kill_switch.pair_address, kill_switch.reason
+        );
+    }
+
     // init some state
     core.refresh_state().await.unwrap();
     core.fetch_token_info().await.unwrap();
     let core = Arc::new(core);
     let mut handles = vec![];
+
+    // Paper portfolios can switch to a trading strategy at runtime
+    let run_strategies = should_market_making(&config) || paper;
+    if run_strategies && !paper {
+        // init user ata
+        core.init_user_ata().await.unwrap();
+    }
+
     {
-        // crawl epoch down
+        let watcher_config = WatcherConfig {
+            ws_url,
+            debounce: Duration::from_millis(debounce_ms),
+            poll_interval: Duration::from_secs(poll_interval),
+            refresh_interval: Duration::from_secs(refresh_interval),
+            run_strategies,
+        };
         let core = core.clone();
-        let handle = tokio::spawn(async move {
-            let duration = 60; // 1 min
-            let mut interval = interval(Duration::from_secs(duration));
-            loop {
-                interval.tick().await;
-                info!("refresh state");
-                match core.refresh_state().await {
-                    Ok(_) => {}
-                    Err(err) => error!("refresh_state err {}", err),
-                }
-            }
-        });
+        let handle = tokio::spawn(run_watcher(core, watcher_config));
         handles.push(handle);
     }
 
-    if should_market_making(&config) {
-        {
-            // crawl epoch down
-            let core = core.clone();
-
-            // init user ata
-            core.init_user_ata().await.unwrap();
-
-            let handle = tokio::spawn(async move {
-                let duration = 60; // 1 min
-                let mut interval = interval(Duration::from_secs(duration));
-                loop {
-                    interval.tick().await;
-                    info!("check shift price range");
-                    match core.check_shift_price_range().await {
-                        Ok(_) => {}
-                        Err(err) => error!("check shift price err {}", err),
-                    }
-                }
-            });
-            handles.push(handle);
-        }
+    if config_reload_interval > 0 {
+        let core = core.clone();
+        let handle = tokio::spawn(run_config_watcher(
+            core,
+            config_file.clone(),
+            Duration::from_secs(config_reload_interval),
+        ));
+        handles.push(handle);
     }
 
     // let mut handles = vec![];
 
-    let router = router(core);
+    let router = router(
+        core,
+        ControlConfig {
+            api_token,
+            config_file,
+        },
+    );
 
     let service = RouterService::new(router).unwrap();
 
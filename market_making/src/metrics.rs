@@ -0,0 +1,243 @@
+use crate::state::PositionInfo;
+use anchor_lang::prelude::Pubkey;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::time::Duration;
+
+pub const WITHDRAW_ACTION: &str = "withdraw";
+pub const CLAIM_FEE_ACTION: &str = "claim_fee";
+pub const SWAP_ACTION: &str = "swap";
+pub const DEPOSIT_ACTION: &str = "deposit";
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct RpcLatency {
+    pub count: u64,
+    pub sum: Duration,
+    pub last: Duration,
+}
+
+/// Counters of the bot, rendered with the state of the pairs in the Prometheus text format.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    /// Transactions sent per pair, action and success
+    pub transactions: BTreeMap<(Pubkey, &'static str, bool), u64>,
+    /// Latency of the RPC calls per method
+    pub rpc_latencies: BTreeMap<&'static str, RpcLatency>,
+    /// Timestamp of the last successful full refresh per pair
+    pub last_refresh_timestamps: BTreeMap<Pubkey, u64>,
+}
+
+impl Metrics {
+    pub fn inc_transaction(&mut self, lb_pair: Pubkey, action: &'static str, success: bool) {
+        *self
+            .transactions
+            .entry((lb_pair, action, success))
+            .or_default() += 1;
+    }
+
+    pub fn observe_rpc_latency(&mut self, method: &'static str, elapsed: Duration) {
+        let latency = self.rpc_latencies.entry(method).or_default();
+        latency.count += 1;
+        latency.sum += elapsed;
+        latency.last = elapsed;
+    }
+
+    pub fn set_last_refresh_timestamp(&mut self, lb_pair: Pubkey, timestamp: u64) {
+        self.last_refresh_timestamps.insert(lb_pair, timestamp);
+    }
+
+    /// Render the metrics and the position info of the pairs at `now`, in seconds since the epoch.
+    pub fn render(&self, positions: &[(Pubkey, PositionInfo)], killed: bool, now: u64) -> String {
+        let mut out = String::new();
+
+        write_header(
+            &mut out,
+            "killed",
+            "Whether the kill switch was triggered, 1 or 0",
+            "gauge",
+        );
+        let _ = writeln!(out, "market_making_killed {}", killed as u8);
+
+        let pair_gauges: [(&str, &str, fn(&PositionInfo) -> f64); 8] = [
+            ("active_id", "Active bin of the pair", |info| {
+                info.active_id as f64
+            }),
+            (
+                "in_range",
+                "Whether the active bin is in a position, 1 or 0",
+                |info| info.active_position_index.is_some() as u8 as f64,
+            ),
+            ("amount_x", "Amount of token x in the positions", |info| {
+                info.amount_x
+            }),
+            ("amount_y", "Amount of token y in the positions", |info| {
+                info.amount_y
+            }),
+            ("fee_x", "Pending fees of token x", |info| info.fee_x),
+            ("fee_y", "Pending fees of token y", |info| info.fee_y),
+            ("position_count", "Number of positions", |info| {
+                info.position_len as f64
+            }),
+            ("rebalance_time", "Number of rebalances", |info| {
+                info.rebalance_time as f64
+            }),
+        ];
+        for (name, help, value) in pair_gauges {
+            write_header(&mut out, name, help, "gauge");
+            for (lb_pair, info) in positions.iter() {
+                let _ = writeln!(
+                    out,
+                    "market_making_{name}{{pair=\"{lb_pair}\"}} {}",
+                    value(info)
+                );
+            }
+        }
+
+        write_header(
+            &mut out,
+            "last_update_age_seconds",
+            "Seconds since the state of the pair was last updated, by a refresh or an account update",
+            "gauge",
+        );
+        for (lb_pair, info) in positions.iter() {
+            let _ = writeln!(
+                out,
+                "market_making_last_update_age_seconds{{pair=\"{lb_pair}\"}} {}",
+                now.saturating_sub(info.last_update_timestamp)
+            );
+        }
+
+        write_header(
+            &mut out,
+            "last_refresh_age_seconds",
+            "Seconds since the last successful full refresh of the pair",
+            "gauge",
+        );
+        for (lb_pair, _info) in positions.iter() {
+            // Pairs never refreshed are as old as the epoch, which alerts fire on
+            let last_refresh_timestamp = self
+                .last_refresh_timestamps
+                .get(lb_pair)
+                .copied()
+                .unwrap_or_default();
+            let _ = writeln!(
+                out,
+                "market_making_last_refresh_age_seconds{{pair=\"{lb_pair}\"}} {}",
+                now.saturating_sub(last_refresh_timestamp)
+            );
+        }
+
+        write_header(
+            &mut out,
+            "transactions_total",
+            "Transactions sent per pair, action and status",
+            "counter",
+        );
+        for ((lb_pair, action, success), count) in self.transactions.iter() {
+            let status = if *success { "success" } else { "failure" };
+            let _ = writeln!(
+                out,
+                "market_making_transactions_total{{pair=\"{lb_pair}\",action=\"{action}\",status=\"{status}\"}} {count}"
+            );
+        }
+
+        write_header(
+            &mut out,
+            "rpc_latency_seconds",
+            "Latency of the RPC calls per method",
+            "summary",
+        );
+        for (method, latency) in self.rpc_latencies.iter() {
+            let _ = writeln!(
+                out,
+                "market_making_rpc_latency_seconds_sum{{method=\"{method}\"}} {}",
+                latency.sum.as_secs_f64()
+            );
+            let _ = writeln!(
+                out,
+                "market_making_rpc_latency_seconds_count{{method=\"{method}\"}} {}",
+                latency.count
+            );
+        }
+        write_header(
+            &mut out,
+            "rpc_last_latency_seconds",
+            "Latency of the last RPC call per method",
+            "gauge",
+        );
+        for (method, latency) in self.rpc_latencies.iter() {
+            let _ = writeln!(
+                out,
+                "market_making_rpc_last_latency_seconds{{method=\"{method}\"}} {}",
+                latency.last.as_secs_f64()
+            );
+        }
+
+        out
+    }
+}
+
+fn write_header(out: &mut String, name: &str, help: &str, metric_type: &str) {
+    let _ = writeln!(out, "# HELP market_making_{name} {help}");
+    let _ = writeln!(out, "# TYPE market_making_{name} {metric_type}");
+}
+
+#[cfg(test)]
+mod metrics_test {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let lb_pair = Pubkey::new_unique();
+        let mut metrics = Metrics::default();
+        metrics.inc_transaction(lb_pair, SWAP_ACTION, true);
+        metrics.inc_transaction(lb_pair, SWAP_ACTION, true);
+        metrics.inc_transaction(lb_pair, DEPOSIT_ACTION, false);
+        metrics.observe_rpc_latency("getMultipleAccounts", Duration::from_millis(100));
+        metrics.observe_rpc_latency("getMultipleAccounts", Duration::from_millis(300));
+        metrics.set_last_refresh_timestamp(lb_pair, 1_000);
+
+        let info = PositionInfo {
+            active_id: -5,
+            active_position_index: Some(0),
+            rebalance_time: 3,
+            amount_x: 1.5,
+            last_update_timestamp: 1_050,
+            ..Default::default()
+        };
+        let out = metrics.render(&[(lb_pair, info)], false, 1_060);
+
+        assert!(out.contains(&format!(
+            "market_making_active_id{{pair=\"{lb_pair}\"}} -5\n"
+        )));
+        assert!(out.contains("market_making_killed 0\n"));
+        assert!(out.contains(&format!("market_making_in_range{{pair=\"{lb_pair}\"}} 1\n")));
+        assert!(out.contains(&format!(
+            "market_making_amount_x{{pair=\"{lb_pair}\"}} 1.5\n"
+        )));
+        assert!(out.contains(&format!(
+            "market_making_rebalance_time{{pair=\"{lb_pair}\"}} 3\n"
+        )));
+        assert!(out.contains(&format!(
+            "market_making_last_update_age_seconds{{pair=\"{lb_pair}\"}} 10\n"
+        )));
+        assert!(out.contains(&format!(
+            "market_making_last_refresh_age_seconds{{pair=\"{lb_pair}\"}} 60\n"
+        )));
+        assert!(out.contains(&format!(
+            "market_making_transactions_total{{pair=\"{lb_pair}\",action=\"swap\",status=\"success\"}} 2\n"
+        )));
+        assert!(out.contains(&format!(
+            "market_making_transactions_total{{pair=\"{lb_pair}\",action=\"deposit\",status=\"failure\"}} 1\n"
+        )));
+        assert!(out.contains(
+            "market_making_rpc_latency_seconds_sum{method=\"getMultipleAccounts\"} 0.4\n"
+        ));
+        assert!(out.contains(
+            "market_making_rpc_latency_seconds_count{method=\"getMultipleAccounts\"} 2\n"
+        ));
+        assert!(out.contains(
+            "market_making_rpc_last_latency_seconds{method=\"getMultipleAccounts\"} 0.3\n"
+        ));
+    }
+}
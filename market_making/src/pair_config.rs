@@ -1,22 +1,158 @@
+use crate::strategy::{get_strategy_name_from_mode, VIEW_STRATEGY};
 use crate::MarketMakingMode;
 use anchor_lang::prelude::Pubkey;
 use anyhow::*;
-use serde::Deserialize;
+use lb_clmm::constants::MAX_BIN_PER_POSITION;
+use serde::{Deserialize, Serialize};
 use std::fs::File;
 use std::io::Read;
 
-#[derive(Debug, Deserialize, Clone, Default)]
+pub const DEFAULT_MAX_PRICE_IMPACT_BPS: u64 = 300; // 3%
+pub const BASIS_POINT_MAX: u64 = 10_000;
+
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
 #[serde(rename_all = "snake_case")]
 pub struct PairConfig {
     pub pair_address: String,
     pub x_amount: u64,
     pub y_amount: u64,
+    /// Number of bins of the range provided with liquidity. Ranges wider than one position are split into adjacent
+    /// positions. Defaults to one position.
+    #[serde(default)]
+    pub range_width: Option<u32>,
+    /// Legacy way to select a built-in strategy. Ignored when strategy is set.
+    #[serde(default)]
     pub mode: MarketMakingMode,
+    /// Share of the inventory value held in token x after a rebalance swap, between 0 and 1. Defaults to 0.5.
+    #[serde(default)]
+    pub target_inventory_ratio: Option<f64>,
+    /// Maximum amount of token x swapped per rebalance
+    #[serde(default)]
+    pub max_swap_amount_x: Option<u64>,
+    /// Maximum amount of token y swapped per rebalance
+    #[serde(default)]
+    pub max_swap_amount_y: Option<u64>,
+    /// Maximum price impact of a swap, including fees, in basis points. Swaps above it are reduced, or skipped.
+    #[serde(default)]
+    pub max_price_impact_bps: Option<u64>,
+    /// Deposit the withdrawn token on its side of the active bin instead of swapping
+    #[serde(default)]
+    pub one_sided_rebalance: bool,
+    /// Initial virtual balance of token x in paper mode. Defaults to x_amount.
+    #[serde(default)]
+    pub paper_balance_x: Option<u64>,
+    /// Initial virtual balance of token y in paper mode. Defaults to y_amount.
+    #[serde(default)]
+    pub paper_balance_y: Option<u64>,
+    /// Name of the strategy in the strategy registry
+    #[serde(default)]
+    pub strategy: Option<String>,
+    /// Parameters of the strategy, parsed by the strategy itself
+    #[serde(default)]
+    pub strategy_parameters: serde_json::Value,
+    /// Skip the strategy of the pair. The state is still refreshed.
+    #[serde(default)]
+    pub paused: bool,
+    /// Maximum number of rebalances in the last hour. Above it, the kill switch is triggered.
+    #[serde(default)]
+    pub max_rebalances_per_hour: Option<u64>,
+    /// Maximum number of rebalances in the last day. Above it, the kill switch is triggered.
+    #[serde(default)]
+    pub max_rebalances_per_day: Option<u64>,
+    /// Maximum amount of token x swapped, over the journal since the kill switch was last cleared. Above it, the kill switch is triggered.
+    #[serde(default)]
+    pub max_swap_volume_x: Option<u64>,
+    /// Maximum amount of token y swapped, over the journal since the kill switch was last cleared. Above it, the kill switch is triggered.
+    #[serde(default)]
+    pub max_swap_volume_y: Option<u64>,
+    /// Maximum loss of value of the positions and wallet against their value at the first check, in basis points.
+    /// Above it, the kill switch is triggered.
+    #[serde(default)]
+    pub max_drawdown_bps: Option<u64>,
+    /// Minimum SOL balance of the wallet for fees, in lamports. Below it, the kill switch is triggered.
+    #[serde(default)]
+    pub min_sol_balance: Option<u64>,
+}
+
+/// Change of the config of a pair through the control API. Fields which are not set are kept.
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(rename_all = "snake_case")]
+pub struct PairConfigUpdate {
+    /// Legacy mode of the pair. Unless strategy is set too, it also clears the strategy, so that the mode applies.
+    #[serde(default)]
+    pub mode: Option<MarketMakingMode>,
+    #[serde(default)]
+    pub strategy: Option<String>,
+    #[serde(default)]
+    pub x_amount: Option<u64>,
+    #[serde(default)]
+    pub y_amount: Option<u64>,
+}
+
+impl PairConfigUpdate {
+    pub fn apply(&self, config: &mut PairConfig) {
+        if let Some(mode) = &self.mode {
+            config.mode = mode.clone();
+            config.strategy = None;
+        }
+        if let Some(strategy) = &self.strategy {
+            config.strategy = Some(strategy.clone());
+        }
+        if let Some(x_amount) = self.x_amount {
+            config.x_amount = x_amount;
+        }
+        if let Some(y_amount) = self.y_amount {
+            config.y_amount = y_amount;
+        }
+    }
+}
+
+impl PairConfig {
+    pub fn get_strategy_name(&self) -> String {
+        match &self.strategy {
+            Some(strategy) => strategy.clone(),
+            None => get_strategy_name_from_mode(&self.mode).to_string(),
+        }
+    }
+
+    pub fn get_target_inventory_ratio(&self) -> f64 {
+        self.target_inventory_ratio
+            .map(|ratio| ratio.clamp(0.0, 1.0))
+            .unwrap_or(0.5)
+    }
+
+    pub fn get_max_price_impact_bps(&self) -> u64 {
+        self.max_price_impact_bps
+            .unwrap_or(DEFAULT_MAX_PRICE_IMPACT_BPS)
+            .min(BASIS_POINT_MAX)
+    }
+
+    pub fn get_paper_balances(&self) -> (u64, u64) {
+        (
+            self.paper_balance_x.unwrap_or(self.x_amount),
+            self.paper_balance_y.unwrap_or(self.y_amount),
+        )
+    }
+
+    pub fn has_risk_limits(&self) -> bool {
+        self.max_rebalances_per_hour.is_some()
+            || self.max_rebalances_per_day.is_some()
+            || self.max_swap_volume_x.is_some()
+            || self.max_swap_volume_y.is_some()
+            || self.max_drawdown_bps.is_some()
+            || self.min_sol_balance.is_some()
+    }
+
+    pub fn get_range_width(&self) -> i32 {
+        self.range_width
+            .map(|range_width| range_width.clamp(1, i32::MAX as u32) as i32)
+            .unwrap_or(MAX_BIN_PER_POSITION as i32)
+    }
 }
 
 pub fn should_market_making(config: &Vec<PairConfig>) -> bool {
     for pair in config.iter() {
-        if pair.mode != MarketMakingMode::ModeView {
+        if pair.get_strategy_name() != VIEW_STRATEGY {
             return true;
         }
     }
@@ -32,6 +168,45 @@ pub fn get_pair_config(config: &Vec<PairConfig>, pair_addr: Pubkey) -> PairConfi
     return PairConfig::default();
 }
 
+/// Config after a reload of the config file. Fields the file changed since its previous read take the value of the
+/// file, the others keep their effective value, so that the changes made through the control API are kept.
+pub fn merge_reloaded_config(
+    effective_config: &Vec<PairConfig>,
+    previous_file_config: &Vec<PairConfig>,
+    new_file_config: &Vec<PairConfig>,
+) -> Result<Vec<PairConfig>> {
+    let mut merged_config = vec![];
+    for new_pair_config in new_file_config.iter() {
+        let find_pair_config = |config: &Vec<PairConfig>| {
+            config
+                .iter()
+                .find(|pair_config| pair_config.pair_address == new_pair_config.pair_address)
+                .map(serde_json::to_value)
+                .transpose()
+        };
+        let (Some(effective), Some(serde_json::Value::Object(previous))) = (
+            find_pair_config(effective_config)?,
+            find_pair_config(previous_file_config)?,
+        ) else {
+            merged_config.push(new_pair_config.clone());
+            continue;
+        };
+        let serde_json::Value::Object(mut merged) = effective else {
+            return Err(Error::msg("Pair config is not an object"));
+        };
+        let serde_json::Value::Object(new) = serde_json::to_value(new_pair_config)? else {
+            return Err(Error::msg("Pair config is not an object"));
+        };
+        for (field, value) in new.into_iter() {
+            if previous.get(&field) != Some(&value) {
+                merged.insert(field, value);
+            }
+        }
+        merged_config.push(serde_json::from_value(serde_json::Value::Object(merged))?);
+    }
+    Ok(merged_config)
+}
+
 pub fn get_config_from_file(path: &str) -> Result<Vec<PairConfig>> {
     // println!("config file {}", env::var("KEEPER_CONFIG_FILE").unwrap());
     let mut file = File::open(path)?;
@@ -59,4 +234,67 @@ mod config_test {
         let config = get_config_from_file(&owned_string).unwrap();
         println!("{:?}", config);
     }
+
+    #[test]
+    fn test_pair_config_update() {
+        let mut config = PairConfig {
+            x_amount: 100,
+            y_amount: 200,
+            strategy: Some("custom".to_string()),
+            ..Default::default()
+        };
+
+        let update: PairConfigUpdate =
+            serde_json::from_str(r#"{"mode": "ModeLeft", "y_amount": 300}"#).unwrap();
+        update.apply(&mut config);
+        assert_eq!(config.mode, MarketMakingMode::ModeLeft);
+        assert_eq!(config.strategy, None);
+        assert_eq!(config.x_amount, 100);
+        assert_eq!(config.y_amount, 300);
+
+        let update: PairConfigUpdate =
+            serde_json::from_str(r#"{"mode": "ModeRight", "strategy": "custom"}"#).unwrap();
+        update.apply(&mut config);
+        assert_eq!(config.mode, MarketMakingMode::ModeRight);
+        assert_eq!(config.strategy, Some("custom".to_string()));
+    }
+
+    #[test]
+    fn test_merge_reloaded_config() {
+        let file_config = vec![PairConfig {
+            pair_address: "pair".to_string(),
+            x_amount: 100,
+            y_amount: 200,
+            ..Default::default()
+        }];
+
+        // Paused and x_amount changed through the control API
+        let mut effective_config = file_config.clone();
+        effective_config[0].paused = true;
+        effective_config[0].x_amount = 150;
+
+        // The file only changes y_amount
+        let mut new_file_config = file_config.clone();
+        new_file_config[0].y_amount = 300;
+        let merged_config =
+            merge_reloaded_config(&effective_config, &file_config, &new_file_config).unwrap();
+        assert!(merged_config[0].paused);
+        assert_eq!(merged_config[0].x_amount, 150);
+        assert_eq!(merged_config[0].y_amount, 300);
+
+        // The file sets paused explicitly
+        let mut next_file_config = new_file_config.clone();
+        next_file_config[0].paused = true;
+        let merged_config =
+            merge_reloaded_config(&merged_config, &new_file_config, &next_file_config).unwrap();
+        assert!(merged_config[0].paused);
+
+        // Unpausing in the file resumes the pair
+        let mut last_file_config = next_file_config.clone();
+        last_file_config[0].paused = false;
+        let merged_config =
+            merge_reloaded_config(&merged_config, &next_file_config, &last_file_config).unwrap();
+        assert!(!merged_config[0].paused);
+        assert_eq!(merged_config[0].x_amount, 150);
+    }
 }
@@ -0,0 +1,591 @@
+use crate::pair_config::PairConfig;
+use crate::state::{
+    get_position_deposit_amounts, get_position_ranges, PositionRaw, SinglePosition,
+};
+use anchor_client::solana_sdk::signature::{Keypair, Signer};
+use anchor_lang::prelude::Pubkey;
+use anyhow::*;
+use lb_clmm::instructions::deposit::*;
+use lb_clmm::math::bin_math::get_liquidity;
+use lb_clmm::math::price_math::get_price_from_id;
+use lb_clmm::state::bin::{get_liquidity_share, Bin, BinArray};
+use lb_clmm::state::lb_pair::LbPair;
+use lb_clmm::state::position::PositionV2;
+use lb_clmm::utils::pda;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// Virtual position opened by a deposit in paper mode.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaperDeposit {
+    pub position: Pubkey,
+    pub amount_x: u64,
+    pub amount_y: u64,
+    /// Amounts and liquidity share deposited into each bin, as (bin_id, amount_x, amount_y, share)
+    pub bins: Vec<(i32, u64, u64, u128)>,
+}
+
+/// Virtual wallet and positions of a pair in paper mode.
+#[derive(Default, Debug, Clone)]
+pub struct PaperPortfolio {
+    pub initial_x: u64,
+    pub initial_y: u64,
+    /// Price of the active bin when the portfolio started, None until the pair is fetched
+    pub initial_price: Option<u128>,
+    pub balance_x: u64,
+    pub balance_y: u64,
+    /// Fees claimed from the virtual positions, on claim or withdraw
+    pub claimed_fee_x: u64,
+    pub claimed_fee_y: u64,
+    pub position_pks: Vec<Pubkey>,
+    pub positions: Vec<PositionV2>,
+}
+
+/// Virtual portfolios following the live pool state. Withdraws, swaps and deposits are applied to them instead of
+/// being sent on chain.
+pub struct PaperTrading {
+    pub portfolios: HashMap<Pubkey, PaperPortfolio>,
+}
+
+impl PaperTrading {
+    pub fn new(config: &Vec<PairConfig>) -> Self {
+        let mut portfolios = HashMap::new();
+        for pair in config.iter() {
+            let pool_pk = Pubkey::from_str(&pair.pair_address).unwrap();
+            let (balance_x, balance_y) = pair.get_paper_balances();
+            portfolios.insert(
+                pool_pk,
+                PaperPortfolio {
+                    initial_x: balance_x,
+                    initial_y: balance_y,
+                    balance_x,
+                    balance_y,
+                    ..Default::default()
+                },
+            );
+        }
+        PaperTrading { portfolios }
+    }
+
+    fn get_portfolio(&self, lb_pair: Pubkey) -> Result<&PaperPortfolio> {
+        self.portfolios
+            .get(&lb_pair)
+            .ok_or_else(|| Error::msg(format!("No paper portfolio for {}", lb_pair)))
+    }
+
+    fn get_portfolio_mut(&mut self, lb_pair: Pubkey) -> Result<&mut PaperPortfolio> {
+        self.portfolios
+            .get_mut(&lb_pair)
+            .ok_or_else(|| Error::msg(format!("No paper portfolio for {}", lb_pair)))
+    }
+
+    /// Record the price the performance is measured from, on the first fetch of the pair.
+    pub fn start(&mut self, lb_pair: Pubkey, lb_pair_state: &LbPair) -> Result<()> {
+        let portfolio = self.get_portfolio_mut(lb_pair)?;
+        if portfolio.initial_price.is_none() {
+            portfolio.initial_price = Some(get_price_from_id(
+                lb_pair_state.active_id,
+                lb_pair_state.bin_step,
+            )?);
+        }
+        Ok(())
+    }
+
+    pub fn get_balances(&self, lb_pair: Pubkey) -> Result<(u64, u64)> {
+        let portfolio = self.get_portfolio(lb_pair)?;
+        Ok((portfolio.balance_x, portfolio.balance_y))
+    }
+
+    /// Virtual positions of the pair, as (position, state).
+    pub fn get_positions(&self, lb_pair: Pubkey) -> Result<Vec<(Pubkey, PositionV2)>> {
+        let portfolio = self.get_portfolio(lb_pair)?;
+        Ok(portfolio
+            .position_pks
+            .iter()
+            .copied()
+            .zip(portfolio.positions.iter().copied())
+            .collect())
+    }
+
+    /// Withdraw the liquidity and the fees of the positions into the virtual wallet, and close them.
+    pub fn withdraw(&mut self, state: &SinglePosition, positions: &[Pubkey]) -> Result<()> {
+        let portfolio = self.get_portfolio_mut(state.lb_pair)?;
+        for position in positions.iter() {
+            let i = portfolio
+                .position_pks
+                .iter()
+                .position(|position_pk| position_pk == position)
+                .ok_or_else(|| Error::msg(format!("Unknown position {}", position)))?;
+            let (amount_x, amount_y, fee_x, fee_y) =
+                state.get_position_amounts(&portfolio.positions[i])?;
+
+            portfolio.balance_x = portfolio.balance_x.saturating_add(amount_x + fee_x);
+            portfolio.balance_y = portfolio.balance_y.saturating_add(amount_y + fee_y);
+            portfolio.claimed_fee_x = portfolio.claimed_fee_x.saturating_add(fee_x);
+            portfolio.claimed_fee_y = portfolio.claimed_fee_y.saturating_add(fee_y);
+            portfolio.position_pks.remove(i);
+            portfolio.positions.remove(i);
+            info!("paper close position {position} {amount_x} {amount_y} {fee_x} {fee_y}");
+        }
+        Ok(())
+    }
+
+    /// Claim the pending fees of all positions into the virtual wallet.
+    pub fn claim_fee(&mut self, state: &SinglePosition) -> Result<()> {
+        let portfolio = self.get_portfolio_mut(state.lb_pair)?;
+        for position in portfolio.positions.iter_mut() {
+            let (_amount_x, _amount_y, fee_x, fee_y) = state.get_position_amounts(position)?;
+            for bin_id in position.lower_bin_id..=position.upper_bin_id {
+                let bin = get_bin(state, bin_id)?;
+                let idx = position.get_idx(bin_id)?;
+                let fee_info = &mut position.fee_infos[idx];
+                fee_info.fee_x_per_token_complete = bin.fee_amount_x_per_token_stored;
+                fee_info.fee_y_per_token_complete = bin.fee_amount_y_per_token_stored;
+                fee_info.fee_x_pending = 0;
+                fee_info.fee_y_pending = 0;
+            }
+
+            portfolio.balance_x = portfolio.balance_x.saturating_add(fee_x);
+            portfolio.balance_y = portfolio.balance_y.saturating_add(fee_y);
+            portfolio.claimed_fee_x = portfolio.claimed_fee_x.saturating_add(fee_x);
+            portfolio.claimed_fee_y = portfolio.claimed_fee_y.saturating_add(fee_y);
+        }
+        Ok(())
+    }
+
+    /// Apply a quoted swap to the virtual wallet.
+    pub fn swap(
+        &mut self,
+        lb_pair: Pubkey,
+        amount_in: u64,
+        amount_out: u64,
+        swap_for_y: bool,
+    ) -> Result<()> {
+        let portfolio = self.get_portfolio_mut(lb_pair)?;
+        let (balance_in, balance_out) = if swap_for_y {
+            (&mut portfolio.balance_x, &mut portfolio.balance_y)
+        } else {
+            (&mut portfolio.balance_y, &mut portfolio.balance_x)
+        };
+        *balance_in = balance_in
+            .checked_sub(amount_in)
+            .context("Insufficient paper balance")?;
+        *balance_out = balance_out.saturating_add(amount_out);
+        Ok(())
+    }
+
+    /// Deposit into new virtual positions covering the bin range. The amounts of each bin follow the strategy like
+    /// the program does, and the shares are computed against the live bins. The bin arrays of the range must be in
+    /// the state, and the composition fee of the active bin is not simulated. The bins are left unchanged. Return the
+    /// new positions with the amounts deposited into them.
+    pub fn deposit(
+        &mut self,
+        state: &SinglePosition,
+        amount_x: u64,
+        amount_y: u64,
+        min_bin_id: i32,
+        max_bin_id: i32,
+        strategy_type: StrategyType,
+    ) -> Result<Vec<PaperDeposit>> {
+        ensure!(min_bin_id <= max_bin_id, "Invalid bin range");
+        let portfolio = self.get_portfolio_mut(state.lb_pair)?;
+        ensure!(
+            amount_x <= portfolio.balance_x && amount_y <= portfolio.balance_y,
+            "Insufficient paper balance"
+        );
+
+        let ranges = get_position_ranges(min_bin_id, max_bin_id);
+        let amounts = get_position_deposit_amounts(
+            &ranges,
+            state.lb_pair_state.active_id,
+            amount_x,
+            amount_y,
+        );
+
+        // Build all positions before touching the portfolio, so a failed deposit leaves it unchanged
+        let mut positions = vec![];
+        for (&(lower_bin_id, upper_bin_id), &(amount_x, amount_y)) in
+            ranges.iter().zip(amounts.iter())
+        {
+            let mut deposited_x = 0u64;
+            let mut deposited_y = 0u64;
+            let mut bins = vec![];
+            let mut position = PositionV2 {
+                lb_pair: state.lb_pair,
+                lower_bin_id,
+                upper_bin_id,
+                ..Default::default()
+            };
+            let amounts_into_bins = get_amounts_into_bins(
+                state,
+                amount_x,
+                amount_y,
+                lower_bin_id,
+                upper_bin_id,
+                strategy_type.clone(),
+            )?;
+            for (bin_id, bin_amount_x, bin_amount_y) in amounts_into_bins {
+                if bin_amount_x == 0 && bin_amount_y == 0 {
+                    continue;
+                }
+                let bin = get_bin(state, bin_id)?;
+                let idx = position.get_idx(bin_id)?;
+                let share = get_deposit_share(
+                    &bin,
+                    bin_id,
+                    state.lb_pair_state.bin_step,
+                    bin_amount_x,
+                    bin_amount_y,
+                )?;
+                position.liquidity_shares[idx].set(share);
+                position.fee_infos[idx].fee_x_per_token_complete =
+                    bin.fee_amount_x_per_token_stored;
+                position.fee_infos[idx].fee_y_per_token_complete =
+                    bin.fee_amount_y_per_token_stored;
+                deposited_x += bin_amount_x;
+                deposited_y += bin_amount_y;
+                bins.push((bin_id, bin_amount_x, bin_amount_y, share));
+            }
+            positions.push((position, deposited_x, deposited_y, bins));
+        }
+
+        let mut deposits = vec![];
+        for (position, deposited_x, deposited_y, bins) in positions {
+            let position_pk = Keypair::new().pubkey();
+            info!(
+                "paper deposit {} {} {position_pk}",
+                position.lower_bin_id, position.upper_bin_id
+            );
+            portfolio.balance_x -= deposited_x;
+            portfolio.balance_y -= deposited_y;
+            portfolio.position_pks.push(position_pk);
+            portfolio.positions.push(position);
+            deposits.push(PaperDeposit {
+                position: position_pk,
+                amount_x: deposited_x,
+                amount_y: deposited_y,
+                bins,
+            });
+        }
+        Ok(deposits)
+    }
+
+    /// Performance of the portfolio, from the amounts and pending fees of its positions in the state.
+    pub fn get_performance(
+        &self,
+        state: &SinglePosition,
+        position: &PositionRaw,
+    ) -> Result<PaperPerformance> {
+        let portfolio = self.get_portfolio(state.lb_pair)?;
+        let price = get_price_from_id(state.lb_pair_state.active_id, state.lb_pair_state.bin_step)?;
+        let initial_price = portfolio.initial_price.unwrap_or(price);
+
+        let amount_x = portfolio
+            .balance_x
+            .saturating_add(position.amount_x)
+            .saturating_add(position.fee_x);
+        let amount_y = portfolio
+            .balance_y
+            .saturating_add(position.amount_y)
+            .saturating_add(position.fee_y);
+
+        let value = get_value(amount_x, amount_y, price)?;
+        let initial_value = get_value(portfolio.initial_x, portfolio.initial_y, initial_price)?;
+        let hold_value = get_value(portfolio.initial_x, portfolio.initial_y, price)?;
+
+        Ok(PaperPerformance {
+            balance_x: portfolio.balance_x,
+            balance_y: portfolio.balance_y,
+            fee_x: portfolio.claimed_fee_x.saturating_add(position.fee_x),
+            fee_y: portfolio.claimed_fee_y.saturating_add(position.fee_y),
+            value,
+            initial_value,
+            hold_value,
+            pnl: value as i128 - initial_value as i128,
+        })
+    }
+}
+
+/// Value of the amounts in token y at the price.
+pub fn get_value(amount_x: u64, amount_y: u64, price: u128) -> Result<u64> {
+    let value_x = Bin::get_amount_out(amount_x, price, true)?;
+    value_x
+        .checked_add(amount_y)
+        .ok_or_else(|| Error::msg("Math is overflow"))
+}
+
+/// Bin of the state, from its bin arrays.
+pub fn get_bin(state: &SinglePosition, bin_id: i32) -> Result<Bin> {
+    let bin_array_idx = BinArray::bin_id_to_bin_array_index(bin_id)?;
+    let (bin_array_pk, _bump) = pda::derive_bin_array_pda(state.lb_pair, bin_array_idx.into());
+    let bin_array = state
+        .bin_arrays
+        .get(&bin_array_pk)
+        .ok_or_else(|| Error::msg(format!("Cannot get binarray {}", bin_array_pk)))?;
+    Ok(*bin_array.get_bin(bin_id)?)
+}
+
+/// Bin array of a pair which does not exist on chain yet.
+pub fn get_empty_bin_array(lb_pair: Pubkey, index: i32) -> Result<BinArray> {
+    let mut bin_array = BinArray {
+        index: 0,
+        version: 0,
+        _padding: [0u8; 7],
+        lb_pair: Pubkey::default(),
+        bins: [Bin::default(); lb_clmm::constants::MAX_BIN_PER_ARRAY],
+    };
+    bin_array.initialize(index.into(), lb_pair)?;
+    Ok(bin_array)
+}
+
+/// Amounts deposited into each bin of a position range by the strategy, as (bin_id, amount_x, amount_y).
+fn get_amounts_into_bins(
+    state: &SinglePosition,
+    amount_x: u64,
+    amount_y: u64,
+    lower_bin_id: i32,
+    upper_bin_id: i32,
+    strategy_type: StrategyType,
+) -> Result<Vec<(i32, u64, u64)>> {
+    if amount_x == 0 && amount_y == 0 {
+        return Ok(vec![]);
+    }
+    let active_id = state.lb_pair_state.active_id;
+    let bin_step = state.lb_pair_state.bin_step;
+    let is_one_side = matches!(
+        strategy_type,
+        StrategyType::SpotOneSide | StrategyType::CurveOneSide | StrategyType::BidAskOneSide
+    );
+    let strategy_parameters = StrategyParameters {
+        min_bin_id: lower_bin_id,
+        max_bin_id: upper_bin_id,
+        strategy_type,
+        parameteres: [0u8; 64],
+    };
+
+    if is_one_side {
+        ensure!(
+            amount_x == 0 || amount_y == 0,
+            "One sided deposit takes a single token"
+        );
+        let deposit_for_y = amount_x == 0;
+        let liquidity_parameter = LiquidityParameterByStrategyOneSide {
+            amount: amount_x.max(amount_y),
+            active_id,
+            max_active_bin_slippage: 0,
+            strategy_parameters,
+        };
+        let amounts =
+            liquidity_parameter.to_amounts_into_bin(active_id, bin_step, deposit_for_y)?;
+        return Ok(amounts
+            .into_iter()
+            .map(|(bin_id, amount)| {
+                if deposit_for_y {
+                    (bin_id, 0, amount)
+                } else {
+                    (bin_id, amount, 0)
+                }
+            })
+            .collect());
+    }
+
+    let active_bin = get_bin(state, active_id).unwrap_or_default();
+    let liquidity_parameter = LiquidityParameterByStrategy {
+        amount_x,
+        amount_y,
+        active_id,
+        max_active_bin_slippage: 0,
+        strategy_parameters,
+    };
+    Ok(liquidity_parameter.to_amounts_into_bin(
+        active_id,
+        bin_step,
+        active_bin.amount_x,
+        active_bin.amount_y,
+    )?)
+}
+
+/// Liquidity share minted by a deposit into the bin. The first deposit into an empty bin gets its liquidity as share.
+fn get_deposit_share(
+    bin: &Bin,
+    bin_id: i32,
+    bin_step: u16,
+    amount_x: u64,
+    amount_y: u64,
+) -> Result<u128> {
+    let price = get_price_from_id(bin_id, bin_step)?;
+    let in_liquidity = get_liquidity(amount_x, amount_y, price)?;
+    if bin.is_zero_liquidity() {
+        return Ok(in_liquidity);
+    }
+    let bin_liquidity = get_liquidity(bin.amount_x, bin.amount_y, price)?;
+    Ok(get_liquidity_share(
+        in_liquidity,
+        bin_liquidity,
+        bin.liquidity_supply.as_u128(),
+    )?)
+}
+
+#[derive(Default, PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct PaperPerformance {
+    /// Virtual wallet balances
+    pub balance_x: u64,
+    pub balance_y: u64,
+    /// Fees earned, claimed and pending
+    pub fee_x: u64,
+    pub fee_y: u64,
+    /// Value of the wallet and the positions with their pending fees, in token y
+    pub value: u64,
+    /// Value of the initial balances at the initial price
+    pub initial_value: u64,
+    /// Value of the initial balances at the current price, as if they were held
+    pub hold_value: u64,
+    /// Value minus initial value
+    pub pnl: i128,
+}
+
+impl PaperPerformance {
+    pub fn to_paper_info(&self, token_x_decimals: u8, token_y_decimals: u8) -> PaperInfo {
+        let x_unit = 10f64.powf(token_x_decimals as f64);
+        let y_unit = 10f64.powf(token_y_decimals as f64);
+        PaperInfo {
+            balance_x: self.balance_x as f64 / x_unit,
+            balance_y: self.balance_y as f64 / y_unit,
+            fee_x: self.fee_x as f64 / x_unit,
+            fee_y: self.fee_y as f64 / y_unit,
+            value: self.value as f64 / y_unit,
+            initial_value: self.initial_value as f64 / y_unit,
+            hold_value: self.hold_value as f64 / y_unit,
+            pnl: self.pnl as f64 / y_unit,
+        }
+    }
+}
+
+#[derive(Default, PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct PaperInfo {
+    pub balance_x: f64,
+    pub balance_y: f64,
+    pub fee_x: f64,
+    pub fee_y: f64,
+    pub value: f64,
+    pub initial_value: f64,
+    pub hold_value: f64,
+    pub pnl: f64,
+}
+
+#[cfg(test)]
+mod paper_test {
+    use super::*;
+    use lb_clmm::math::u64x64_math::SCALE_OFFSET;
+
+    fn get_state(lb_pair: Pubkey) -> SinglePosition {
+        let mut state = SinglePosition::new(lb_pair);
+        state.lb_pair_state.active_id = 0;
+        state.lb_pair_state.bin_step = 10;
+        for index in -1..=1 {
+            let (bin_array_pk, _bump) = pda::derive_bin_array_pda(lb_pair, index.into());
+            state
+                .bin_arrays
+                .insert(bin_array_pk, get_empty_bin_array(lb_pair, index).unwrap());
+        }
+        state
+    }
+
+    fn get_bin_mut(state: &mut SinglePosition, bin_id: i32) -> &mut Bin {
+        let index = BinArray::bin_id_to_bin_array_index(bin_id).unwrap();
+        let (bin_array_pk, _bump) = pda::derive_bin_array_pda(state.lb_pair, index.into());
+        let bin_array = state.bin_arrays.get_mut(&bin_array_pk).unwrap();
+        bin_array.get_bin_mut(bin_id).unwrap()
+    }
+
+    fn sync_positions(paper: &PaperTrading, state: &mut SinglePosition) {
+        let positions = paper.get_positions(state.lb_pair).unwrap();
+        state.position_pks = positions.iter().map(|(pk, _)| *pk).collect();
+        state.positions = positions.iter().map(|(_, position)| *position).collect();
+    }
+
+    #[test]
+    fn test_paper_trading() {
+        let lb_pair = Pubkey::new_unique();
+        let config = vec![PairConfig {
+            pair_address: lb_pair.to_string(),
+            x_amount: 1_000_000,
+            y_amount: 1_000_000,
+            ..Default::default()
+        }];
+        let mut paper = PaperTrading::new(&config);
+        let mut state = get_state(lb_pair);
+        paper.start(lb_pair, &state.lb_pair_state).unwrap();
+
+        // Live liquidity in bin 1, the other bins are empty
+        let price = get_price_from_id(1, 10).unwrap();
+        let bin = get_bin_mut(&mut state, 1);
+        bin.amount_x = 5_000_000;
+        bin.liquidity_supply
+            .set(get_liquidity(5_000_000, 0, price).unwrap());
+
+        paper
+            .deposit(
+                &state,
+                1_000_000,
+                1_000_000,
+                -10,
+                10,
+                StrategyType::SpotBalanced,
+            )
+            .unwrap();
+        sync_positions(&paper, &mut state);
+        assert_eq!(state.positions.len(), 1);
+
+        // Nothing is lost but the rounding, at the initial price
+        let position = state.get_positions().unwrap();
+        let performance = paper.get_performance(&state, &position).unwrap();
+        assert_eq!(performance.initial_value, 2_000_000);
+        assert_eq!(performance.hold_value, 2_000_000);
+        assert!(performance.pnl <= 0 && performance.pnl > -10);
+        assert_eq!(
+            position.amount_x + performance.balance_x + position.amount_y + performance.balance_y,
+            performance.value
+        );
+
+        // Swaps into bin 1 earn one token x per unit of liquidity
+        let share = state.positions[0].liquidity_shares[11].as_u128();
+        assert!(share > 0);
+        let bin = get_bin_mut(&mut state, 1);
+        let fee_stored = bin.fee_amount_x_per_token_stored.as_u128() + (1u128 << SCALE_OFFSET);
+        bin.fee_amount_x_per_token_stored.set(fee_stored);
+        let position = state.get_positions().unwrap();
+        let fee_x = (share >> SCALE_OFFSET) as u64;
+        assert_eq!(position.fee_x, fee_x);
+
+        paper.claim_fee(&state).unwrap();
+        sync_positions(&paper, &mut state);
+        assert_eq!(state.get_positions().unwrap().fee_x, 0);
+        let performance = paper
+            .get_performance(&state, &state.get_positions().unwrap())
+            .unwrap();
+        assert_eq!(performance.fee_x, fee_x);
+        assert!(performance.pnl > 0);
+
+        // Swaps are applied to the virtual wallet
+        let (balance_x, balance_y) = paper.get_balances(lb_pair).unwrap();
+        assert!(paper.swap(lb_pair, balance_x + 1, 0, true).is_err());
+        paper.swap(lb_pair, 1_000, 990, true).unwrap();
+        assert_eq!(
+            paper.get_balances(lb_pair).unwrap(),
+            (balance_x - 1_000, balance_y + 990)
+        );
+
+        // Withdraw everything back into the wallet
+        let (balance_x, balance_y) = paper.get_balances(lb_pair).unwrap();
+        let position = state.get_positions().unwrap();
+        paper.withdraw(&state, &state.position_pks).unwrap();
+        assert!(paper.get_positions(lb_pair).unwrap().is_empty());
+        assert_eq!(
+            paper.get_balances(lb_pair).unwrap(),
+            (balance_x + position.amount_x, balance_y + position.amount_y)
+        );
+    }
+}
@@ -0,0 +1,332 @@
+use crate::journal::{Journal, JournalAction};
+use crate::pair_config::BASIS_POINT_MAX;
+use crate::paper::get_value;
+use crate::strategy::{StrategyAction, StrategySnapshot};
+use anchor_lang::prelude::Pubkey;
+use anyhow::*;
+use lb_clmm::math::price_math::get_price_from_id;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+const HOUR: u64 = 3_600;
+const DAY: u64 = 86_400;
+
+/// Why and when the kill switch was triggered
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KillSwitch {
+    pub timestamp: u64,
+    /// Pair whose limit was breached, none when triggered through the control API
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pair_address: Option<String>,
+    pub reason: String,
+}
+
+#[derive(Debug, Default)]
+pub struct RiskState {
+    /// Time the kill switch was last cleared, from which the swap volume is counted. 0 counts the whole journal.
+    pub start_timestamp: u64,
+    /// Value of the positions and wallet of each pair at its first check, in token Y
+    pub start_values: HashMap<Pubkey, u64>,
+    pub kill_switch: Option<KillSwitch>,
+}
+
+impl RiskState {
+    /// Restore the starting values and the kill switch from the journal, so that a restart does not reset the limits.
+    pub fn from_journal(journal: &Journal) -> Self {
+        let mut risk = RiskState::default();
+        for entry in journal.entries.iter() {
+            match &entry.action {
+                JournalAction::StartValue { value } => {
+                    let Result::Ok(lb_pair) = Pubkey::from_str(&entry.pair_address) else {
+                        continue;
+                    };
+                    risk.start_values.entry(lb_pair).or_insert(*value);
+                }
+                JournalAction::KillSwitch { reason } => {
+                    if risk.kill_switch.is_none() {
+                        risk.kill_switch = Some(KillSwitch {
+                            timestamp: entry.timestamp,
+                            pair_address: (!entry.pair_address.is_empty())
+                                .then(|| entry.pair_address.clone()),
+                            reason: reason.clone(),
+                        });
+                    }
+                }
+                JournalAction::ClearKillSwitch => {
+                    risk = RiskState {
+                        start_timestamp: entry.timestamp,
+                        ..Default::default()
+                    };
+                }
+                _ => {}
+            }
+        }
+        risk
+    }
+}
+
+/// Value of the positions, pending fees and wallet balances of the snapshot in token Y, at the active price.
+pub fn get_snapshot_value(snapshot: &StrategySnapshot) -> Result<u64> {
+    let lb_pair_state = &snapshot.state.lb_pair_state;
+    let price = get_price_from_id(lb_pair_state.active_id, lb_pair_state.bin_step)?;
+    let position = &snapshot.position;
+    let amount_x = position
+        .amount_x
+        .saturating_add(position.fee_x)
+        .saturating_add(snapshot.balance_x);
+    let amount_y = position
+        .amount_y
+        .saturating_add(position.fee_y)
+        .saturating_add(snapshot.balance_y);
+    get_value(amount_x, amount_y, price)
+}
+
+/// First limit of the pair breached by its state or by the actions the strategy is about to take. The rebalance and
+/// swap limits only apply when there are actions. `sol_balance` is none when it is not checked.
+pub fn get_limit_breach(
+    snapshot: &StrategySnapshot,
+    actions: &[StrategyAction],
+    value: u64,
+    sol_balance: Option<u64>,
+    journal: &Journal,
+    risk: &RiskState,
+) -> Option<String> {
+    let config = snapshot.config;
+    let pair_address = snapshot.state.lb_pair.to_string();
+
+    if let (Some(min_sol_balance), Some(sol_balance)) = (config.min_sol_balance, sol_balance) {
+        if sol_balance < min_sol_balance {
+            return Some(format!(
+                "SOL balance {} below the minimum {} lamports",
+                sol_balance, min_sol_balance
+            ));
+        }
+    }
+
+    if let (Some(max_drawdown_bps), Some(&start_value)) = (
+        config.max_drawdown_bps,
+        risk.start_values.get(&snapshot.state.lb_pair),
+    ) {
+        let drawdown_bps = (start_value.saturating_sub(value) as u128 * BASIS_POINT_MAX as u128)
+            .checked_div(start_value as u128)
+            .unwrap_or(0);
+        if drawdown_bps > max_drawdown_bps as u128 {
+            return Some(format!(
+                "Value {} is {} bps below the starting value {}, above the maximum drawdown of {} bps",
+                value, drawdown_bps, start_value, max_drawdown_bps
+            ));
+        }
+    }
+
+    if actions.is_empty() {
+        return None;
+    }
+
+    for (max_rebalances, period, period_name) in [
+        (config.max_rebalances_per_hour, HOUR, "hour"),
+        (config.max_rebalances_per_day, DAY, "day"),
+    ] {
+        let Some(max_rebalances) = max_rebalances else {
+            continue;
+        };
+        let since = snapshot.timestamp.saturating_sub(period);
+        let rebalances = journal.count_rebalances_since(&pair_address, since);
+        if rebalances >= max_rebalances {
+            return Some(format!(
+                "{} rebalances in the last {}, the maximum is {}",
+                rebalances, period_name, max_rebalances
+            ));
+        }
+    }
+
+    let (mut volume_x, mut volume_y) =
+        journal.get_swap_volume_since(&pair_address, risk.start_timestamp);
+    for action in actions.iter() {
+        if let StrategyAction::Swap {
+            amount_in,
+            swap_for_y,
+        } = action
+        {
+            if *swap_for_y {
+                volume_x = volume_x.saturating_add(*amount_in);
+            } else {
+                volume_y = volume_y.saturating_add(*amount_in);
+            }
+        }
+    }
+    for (max_volume, volume, token) in [
+        (config.max_swap_volume_x, volume_x, "x"),
+        (config.max_swap_volume_y, volume_y, "y"),
+    ] {
+        if let Some(max_volume) = max_volume {
+            if volume > max_volume {
+                return Some(format!(
+                    "Swap volume of token {} would reach {}, above the maximum {}",
+                    token, volume, max_volume
+                ));
+            }
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod risk_test {
+    use super::*;
+    use crate::journal::{JournalAction, JournalEntry};
+    use crate::pair_config::PairConfig;
+    use crate::state::{PositionRaw, SinglePosition};
+
+    fn record(journal: &mut Journal, lb_pair: Pubkey, timestamp: u64, action: JournalAction) {
+        journal
+            .record(JournalEntry {
+                timestamp,
+                pair_address: lb_pair.to_string(),
+                signature: None,
+                price: 1.0,
+                action,
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn test_get_limit_breach() {
+        let lb_pair = Pubkey::new_unique();
+        let config = PairConfig {
+            max_rebalances_per_hour: Some(2),
+            max_swap_volume_x: Some(1_000),
+            max_drawdown_bps: Some(1_000),
+            min_sol_balance: Some(10_000_000),
+            ..Default::default()
+        };
+        let mut state = SinglePosition::new(lb_pair);
+        state.lb_pair_state.bin_step = 10;
+        let snapshot = StrategySnapshot {
+            config: &config,
+            state: &state,
+            position: PositionRaw {
+                amount_x: 400,
+                amount_y: 400,
+                ..Default::default()
+            },
+            balance_x: 100,
+            balance_y: 100,
+            timestamp: 10_000,
+        };
+        let swap = vec![StrategyAction::Swap {
+            amount_in: 600,
+            swap_for_y: true,
+        }];
+
+        let mut journal = Journal::default();
+        record(&mut journal, lb_pair, 5_000, JournalAction::Rebalance);
+        record(&mut journal, lb_pair, 9_000, JournalAction::Rebalance);
+        record(
+            &mut journal,
+            lb_pair,
+            9_000,
+            JournalAction::Swap {
+                amount_in: 500,
+                amount_out: 500,
+                swap_for_y: true,
+                start_bin_id: 0,
+                end_bin_id: 0,
+                fee: 0,
+                protocol_fee: 0,
+            },
+        );
+        let mut risk = RiskState::default();
+
+        // Price of 1 at bin 0
+        let value = get_snapshot_value(&snapshot).unwrap();
+        assert_eq!(value, 1_000);
+        risk.start_values.insert(lb_pair, value);
+
+        // Idle within the limits
+        assert_eq!(
+            get_limit_breach(&snapshot, &[], value, Some(10_000_000), &journal, &risk),
+            None
+        );
+        // One rebalance in the last hour, but the swap volume of token x would reach 1100
+        let breach = get_limit_breach(&snapshot, &swap, value, None, &journal, &risk).unwrap();
+        assert!(breach.contains("Swap volume of token x"), "{}", breach);
+
+        record(&mut journal, lb_pair, 9_500, JournalAction::Rebalance);
+        let breach = get_limit_breach(&snapshot, &swap, value, None, &journal, &risk).unwrap();
+        assert!(
+            breach.contains("2 rebalances in the last hour"),
+            "{}",
+            breach
+        );
+
+        let breach = get_limit_breach(&snapshot, &[], 899, None, &journal, &risk).unwrap();
+        assert!(breach.contains("1010 bps below"), "{}", breach);
+
+        let breach = get_limit_breach(&snapshot, &[], value, Some(1), &journal, &risk).unwrap();
+        assert!(breach.contains("SOL balance 1 below"), "{}", breach);
+    }
+
+    #[test]
+    fn test_risk_state_from_journal() {
+        let lb_pair = Pubkey::new_unique();
+        let swap = JournalAction::Swap {
+            amount_in: 500,
+            amount_out: 500,
+            swap_for_y: true,
+            start_bin_id: 0,
+            end_bin_id: 0,
+            fee: 0,
+            protocol_fee: 0,
+        };
+
+        let mut journal = Journal::default();
+        record(
+            &mut journal,
+            lb_pair,
+            1_000,
+            JournalAction::StartValue { value: 1_000 },
+        );
+        record(&mut journal, lb_pair, 2_000, swap.clone());
+        record(
+            &mut journal,
+            lb_pair,
+            3_000,
+            JournalAction::StartValue { value: 900 },
+        );
+        record(
+            &mut journal,
+            lb_pair,
+            4_000,
+            JournalAction::KillSwitch {
+                reason: "drawdown".to_string(),
+            },
+        );
+
+        // The first starting value and the kill switch survive a restart
+        let risk = RiskState::from_journal(&journal);
+        assert_eq!(risk.start_timestamp, 0);
+        assert_eq!(risk.start_values.get(&lb_pair), Some(&1_000));
+        let kill_switch = risk.kill_switch.unwrap();
+        assert_eq!(kill_switch.timestamp, 4_000);
+        assert_eq!(kill_switch.pair_address, Some(lb_pair.to_string()));
+        assert_eq!(kill_switch.reason, "drawdown");
+        assert_eq!(
+            journal.get_swap_volume_since(&lb_pair.to_string(), risk.start_timestamp),
+            (500, 0)
+        );
+
+        // Clearing the kill switch starts the limits again
+        record(&mut journal, lb_pair, 5_000, JournalAction::ClearKillSwitch);
+        record(&mut journal, lb_pair, 6_000, swap);
+        let risk = RiskState::from_journal(&journal);
+        assert_eq!(risk.start_timestamp, 5_000);
+        assert!(risk.start_values.is_empty());
+        assert_eq!(risk.kill_switch, None);
+        assert_eq!(
+            journal.get_swap_volume_since(&lb_pair.to_string(), risk.start_timestamp),
+            (500, 0)
+        );
+    }
+}
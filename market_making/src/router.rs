@@ -1,17 +1,44 @@
 use std::sync::Arc;
 
+use crate::pair_config::{get_config_from_file, PairConfigUpdate};
 use crate::Core;
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use hyper::header::AUTHORIZATION;
 use hyper::{Body, Request, Response, StatusCode};
-use log::debug;
+use log::{debug, error, info};
 use routerify::prelude::*;
 use routerify::{Middleware, RequestInfo, Router};
+use serde::{Deserialize, Serialize};
 use std::convert::Infallible;
+use std::str::FromStr;
 
-pub fn router(core: Arc<Core>) -> Router<Body, Infallible> {
+/// Settings of the control API
+#[derive(Debug, Clone)]
+pub struct ControlConfig {
+    /// Bearer token of the control requests. The control API is disabled without it.
+    pub api_token: Option<String>,
+    /// Config file read on reload
+    pub config_file: String,
+}
+
+pub fn router(core: Arc<Core>, control_config: ControlConfig) -> Router<Body, Infallible> {
     Router::builder()
         .data(core)
+        .data(control_config)
         .middleware(Middleware::pre(logger))
         .get("/check_positions", check_positions)
+        .get("/metrics", metrics)
+        .get("/history", history)
+        .get("/config", get_config)
+        .post("/config/reload", reload_config)
+        .get("/kill_switch", get_kill_switch)
+        .post("/kill_switch", trigger_kill_switch)
+        .delete("/kill_switch", clear_kill_switch)
+        .post("/pairs/:pair/pause", pause_pair)
+        .post("/pairs/:pair/resume", resume_pair)
+        .post("/pairs/:pair/config", update_pair_config)
+        .post("/pairs/:pair/rebalance", rebalance_pair)
+        .post("/pairs/:pair/withdraw", withdraw_pair)
         .err_handler_with_info(error_handler)
         .build()
         .unwrap()
@@ -32,6 +59,216 @@ async fn check_positions(req: Request<Body>) -> Result<Response<Body>, Infallibl
     }
 }
 
+async fn metrics(req: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let core = req.data::<Arc<Core>>().unwrap();
+    match core.get_metrics() {
+        Ok(metrics) => Ok(Response::builder()
+            .header("Content-Type", "text/plain; version=0.0.4")
+            .body(Body::from(metrics))
+            .unwrap()),
+        Err(err) => {
+            error!("cannot get metrics: {err}");
+            Ok(Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body(Body::from("Cannot get metrics"))
+                .unwrap())
+        }
+    }
+}
+
+async fn history(req: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let core = req.data::<Arc<Core>>().unwrap();
+    Ok(json_response(&core.get_history()))
+}
+
+async fn get_config(req: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let core = req.data::<Arc<Core>>().unwrap();
+    Ok(json_response(&core.get_config()))
+}
+
+async fn reload_config(req: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let (core, _lb_pair) = match authorize(&req, false) {
+        Ok(authorized) => authorized,
+        Err(response) => return Ok(response),
+    };
+    let control_config = req.data::<ControlConfig>().unwrap();
+    let result = get_config_from_file(&control_config.config_file)
+        .and_then(|config| core.reload_config(config));
+    match result {
+        Ok(()) => {
+            info!("reloaded config from {}", control_config.config_file);
+            Ok(json_response(&core.get_config()))
+        }
+        Err(err) => Ok(error_response(StatusCode::BAD_REQUEST, err)),
+    }
+}
+
+async fn get_kill_switch(req: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let core = req.data::<Arc<Core>>().unwrap();
+    Ok(json_response(&core.get_kill_switch()))
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct KillSwitchRequest {
+    #[serde(default)]
+    reason: Option<String>,
+}
+
+async fn trigger_kill_switch(req: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let (core, _lb_pair) = match authorize(&req, false) {
+        Ok(authorized) => authorized,
+        Err(response) => return Ok(response),
+    };
+    // The body is optional
+    let request = match hyper::body::to_bytes(req.into_body()).await {
+        Ok(body) if body.is_empty() => KillSwitchRequest::default(),
+        Ok(body) => match serde_json::from_slice::<KillSwitchRequest>(&body) {
+            Ok(request) => request,
+            Err(err) => return Ok(error_response(StatusCode::BAD_REQUEST, err)),
+        },
+        Err(err) => return Ok(error_response(StatusCode::BAD_REQUEST, err)),
+    };
+    let reason = request
+        .reason
+        .unwrap_or_else(|| "Triggered through the control API".to_string());
+    match core.trigger_kill_switch(None, reason).await {
+        Ok(()) => Ok(json_response(&core.get_kill_switch())),
+        Err(err) => Ok(error_response(StatusCode::INTERNAL_SERVER_ERROR, err)),
+    }
+}
+
+async fn clear_kill_switch(req: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let (core, _lb_pair) = match authorize(&req, false) {
+        Ok(authorized) => authorized,
+        Err(response) => return Ok(response),
+    };
+    match core.clear_kill_switch().await {
+        Ok(()) => Ok(json_response(&core.get_kill_switch())),
+        Err(err) => Ok(error_response(StatusCode::BAD_REQUEST, err)),
+    }
+}
+
+async fn pause_pair(req: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let (core, lb_pair) = match authorize(&req, true) {
+        Ok(authorized) => authorized,
+        Err(response) => return Ok(response),
+    };
+    info!("pause {lb_pair}");
+    match core.update_pair_config(lb_pair, |pair_config| pair_config.paused = true) {
+        Ok(pair_config) => Ok(json_response(&pair_config)),
+        Err(err) => Ok(error_response(StatusCode::BAD_REQUEST, err)),
+    }
+}
+
+async fn resume_pair(req: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let (core, lb_pair) = match authorize(&req, true) {
+        Ok(authorized) => authorized,
+        Err(response) => return Ok(response),
+    };
+    info!("resume {lb_pair}");
+    match core.update_pair_config(lb_pair, |pair_config| pair_config.paused = false) {
+        Ok(pair_config) => Ok(json_response(&pair_config)),
+        Err(err) => Ok(error_response(StatusCode::BAD_REQUEST, err)),
+    }
+}
+
+async fn update_pair_config(req: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let (core, lb_pair) = match authorize(&req, true) {
+        Ok(authorized) => authorized,
+        Err(response) => return Ok(response),
+    };
+    let update = match hyper::body::to_bytes(req.into_body()).await {
+        Ok(body) => match serde_json::from_slice::<PairConfigUpdate>(&body) {
+            Ok(update) => update,
+            Err(err) => return Ok(error_response(StatusCode::BAD_REQUEST, err)),
+        },
+        Err(err) => return Ok(error_response(StatusCode::BAD_REQUEST, err)),
+    };
+    info!("update config of {lb_pair} {:?}", update);
+    match core.update_pair_config(lb_pair, |pair_config| update.apply(pair_config)) {
+        Ok(pair_config) => Ok(json_response(&pair_config)),
+        Err(err) => Ok(error_response(StatusCode::BAD_REQUEST, err)),
+    }
+}
+
+async fn rebalance_pair(req: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let (core, lb_pair) = match authorize(&req, true) {
+        Ok(authorized) => authorized,
+        Err(response) => return Ok(response),
+    };
+    if let Err(err) = core.check_force_rebalance(lb_pair) {
+        return Ok(error_response(StatusCode::BAD_REQUEST, err));
+    }
+    info!("force rebalance {lb_pair}");
+    match core.force_rebalance(lb_pair).await {
+        Ok(()) => Ok(json_response(&core.get_pair_config(lb_pair).ok())),
+        Err(err) => Ok(error_response(StatusCode::INTERNAL_SERVER_ERROR, err)),
+    }
+}
+
+async fn withdraw_pair(req: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let (core, lb_pair) = match authorize(&req, true) {
+        Ok(authorized) => authorized,
+        Err(response) => return Ok(response),
+    };
+    info!("withdraw {lb_pair}");
+    match core.withdraw_pair(lb_pair).await {
+        Ok(()) => Ok(json_response(&core.get_pair_config(lb_pair).ok())),
+        Err(err) => Ok(error_response(StatusCode::INTERNAL_SERVER_ERROR, err)),
+    }
+}
+
+/// Check the bearer token of a control request, and the pair of its path when `with_pair` is set.
+fn authorize(req: &Request<Body>, with_pair: bool) -> Result<(Arc<Core>, Pubkey), Response<Body>> {
+    let control_config = req.data::<ControlConfig>().unwrap();
+    let Some(api_token) = &control_config.api_token else {
+        return Err(error_response(
+            StatusCode::FORBIDDEN,
+            "Control API is disabled, set --api-token",
+        ));
+    };
+    let token = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    if !token.is_some_and(|token| is_token_equal(token.as_bytes(), api_token.as_bytes())) {
+        return Err(error_response(StatusCode::UNAUTHORIZED, "Unauthorized"));
+    }
+
+    let core = req.data::<Arc<Core>>().unwrap().clone();
+    if !with_pair {
+        return Ok((core, Pubkey::default()));
+    }
+    let lb_pair = req
+        .param("pair")
+        .and_then(|pair| Pubkey::from_str(pair).ok())
+        .ok_or_else(|| error_response(StatusCode::BAD_REQUEST, "Invalid pair address"))?;
+    if let Err(err) = core.get_pair_config(lb_pair) {
+        return Err(error_response(StatusCode::NOT_FOUND, err));
+    }
+    Ok((core, lb_pair))
+}
+
+/// Compare without stopping at the first difference, so that the time does not tell how much of a token is right.
+fn is_token_equal(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+fn json_response<T: Serialize>(value: &T) -> Response<Body> {
+    match serde_json::to_string(value) {
+        Ok(res) => Response::new(Body::from(res)),
+        Err(err) => error_response(StatusCode::INTERNAL_SERVER_ERROR, err),
+    }
+}
+
+fn error_response(status: StatusCode, err: impl std::fmt::Display) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .body(Body::from(err.to_string()))
+        .unwrap()
+}
+
 async fn error_handler(err: routerify::RouteError, _: RequestInfo) -> Response<Body> {
     debug!("{}", err);
     Response::builder()
@@ -1,13 +1,15 @@
 use crate::bin_array_manager::BinArrayManager;
 use crate::pair_config::PairConfig;
+use crate::paper::PaperInfo;
 use anchor_lang::prelude::Pubkey;
 use anchor_spl::token::Mint;
 use anyhow::*;
+use lb_clmm::constants::MAX_BIN_PER_POSITION;
 use lb_clmm::math::price_math::get_price_from_id;
 use lb_clmm::math::safe_math::SafeMath;
 use lb_clmm::math::u64x64_math::to_decimal;
 use lb_clmm::math::u64x64_math::PRECISION;
-use lb_clmm::state::bin::Bin;
+use lb_clmm::math::u64x64_math::SCALE_OFFSET;
 use lb_clmm::state::bin::BinArray;
 use lb_clmm::state::lb_pair::LbPair;
 use lb_clmm::state::position::PositionV2;
@@ -49,29 +51,85 @@ pub struct SinglePosition {
     pub last_update_timestamp: u64,
 }
 
-const SLIPPAGE_RATE: u64 = 300; // 3%
-const BASIC_POINT_MAX: u64 = 10_000;
-
 impl SinglePosition {
     pub fn inc_rebalance_time(&mut self) {
         self.rebalance_time += 1;
     }
-    pub fn get_min_out_amount_with_slippage_rate(
-        &self,
-        amount_in: u64,
-        swap_for_y: bool,
-    ) -> Result<u64> {
-        let lb_pair_state = self.lb_pair_state;
-        let price = get_price_from_id(lb_pair_state.active_id, lb_pair_state.bin_step)?;
-        let out_amount = Bin::get_amount_out(amount_in, price, swap_for_y)?;
-
-        let min_out_amount = out_amount
-            .checked_mul(BASIC_POINT_MAX - SLIPPAGE_RATE)
-            .unwrap()
-            .checked_div(BASIC_POINT_MAX)
-            .unwrap();
-        Ok(min_out_amount)
+    /// Amounts and pending fees of a position, as (amount_x, amount_y, fee_x, fee_y).
+    pub fn get_position_amounts(&self, position: &PositionV2) -> Result<(u64, u64, u64, u64)> {
+        let lower_bin_array_idx = BinArray::bin_id_to_bin_array_index(position.lower_bin_id)?;
+        let upper_bin_array_idx = lower_bin_array_idx.checked_add(1).context("MathOverflow")?;
+        let mut bin_arrays = vec![];
+        for i in lower_bin_array_idx..=upper_bin_array_idx {
+            let (bin_array_pk, _bump) = pda::derive_bin_array_pda(self.lb_pair, i.into());
+
+            let bin_array_state = self
+                .bin_arrays
+                .get(&bin_array_pk)
+                .ok_or(Error::msg("Cannot get binarray"))?;
+            bin_arrays.push(*bin_array_state);
+        }
+        let bin_array_manager = BinArrayManager {
+            bin_arrays: &bin_arrays,
+        };
+
+        let mut amount_x = 0u64;
+        let mut amount_y = 0u64;
+        for (i, &share) in position.liquidity_shares.iter().enumerate() {
+            let share = share.as_u128();
+            if share == 0 {
+                continue;
+            }
+
+            let bin_id = position.from_idx_to_bin_id(i)?;
+            let bin = bin_array_manager.get_bin(bin_id)?;
+            let (bin_amount_x, bin_amount_y) = if bin.is_zero_liquidity() {
+                // Only virtual positions of the paper mode hold shares of an empty bin
+                get_amounts_of_liquidity(
+                    share,
+                    bin_id,
+                    self.lb_pair_state.active_id,
+                    self.lb_pair_state.bin_step,
+                )?
+            } else {
+                bin.calculate_out_amount(share)?
+            };
+            amount_x = amount_x
+                .safe_add(bin_amount_x)
+                .map_err(|_| Error::msg("Math is overflow"))?;
+            amount_y = amount_y
+                .safe_add(bin_amount_y)
+                .map_err(|_| Error::msg("Math is overflow"))?;
+        }
+
+        let (fee_x, fee_y) = bin_array_manager.get_total_fee_pending(position)?;
+
+        Ok((amount_x, amount_y, fee_x, fee_y))
+    }
+
+    /// Index of the position holding the active bin.
+    pub fn get_active_position_index(&self) -> Option<usize> {
+        let active_id = self.lb_pair_state.active_id;
+        self.positions.iter().position(|position| {
+            active_id >= position.lower_bin_id && active_id <= position.upper_bin_id
+        })
     }
+
+    /// Whether the active bin is out of the range or in its first or last position.
+    pub fn is_active_bin_at_edge(&self) -> bool {
+        match self.get_active_position_index() {
+            Some(index) => index == 0 || index == self.positions.len() - 1,
+            None => true,
+        }
+    }
+
+    /// Whether the positions, sorted by bin id, cover the range without gap.
+    pub fn is_contiguous(&self) -> bool {
+        self.positions
+            .windows(2)
+            .all(|pair| pair[0].upper_bin_id.checked_add(1) == Some(pair[1].lower_bin_id))
+    }
+
     pub fn get_positions(&self) -> Result<PositionRaw> {
         if self.positions.len() == 0 {
             return Ok(PositionRaw::default());
@@ -80,50 +138,23 @@ impl SinglePosition {
         let mut amount_y = 0u64;
         let mut fee_x = 0u64;
         let mut fee_y = 0u64;
+        let mut bin_count = 0usize;
         for position in self.positions.iter() {
-            let lower_bin_array_idx = BinArray::bin_id_to_bin_array_index(position.lower_bin_id)?;
-            let upper_bin_array_idx = lower_bin_array_idx.checked_add(1).context("MathOverflow")?;
-            let mut bin_arrays = vec![];
-            for i in lower_bin_array_idx..=upper_bin_array_idx {
-                let (bin_array_pk, _bump) = pda::derive_bin_array_pda(self.lb_pair, i.into());
-
-                let bin_array_state = self
-                    .bin_arrays
-                    .get(&bin_array_pk)
-                    .ok_or(Error::msg("Cannot get binarray"))?;
-                bin_arrays.push(*bin_array_state);
-            }
-            let bin_array_manager = BinArrayManager {
-                bin_arrays: &bin_arrays,
-            };
-
-            for (i, &share) in position.liquidity_shares.iter().enumerate() {
-                let share = share.as_u128();
-                if share == 0 {
-                    continue;
-                }
-
-                let bin_id = position.from_idx_to_bin_id(i)?;
-                let bin = bin_array_manager.get_bin(bin_id)?;
-                let (bin_amount_x, bin_amount_y) = bin.calculate_out_amount(share)?;
-                amount_x = amount_x
-                    .safe_add(bin_amount_x)
-                    .map_err(|_| Error::msg("Math is overflow"))?;
-                amount_y = amount_y
-                    .safe_add(bin_amount_y)
-                    .map_err(|_| Error::msg("Math is overflow"))?;
-
-                // println!("bin: {bin_id} amount_x: {amount_x} amount_y: {amount_y}");
-            }
-
-            let (fee_x_pending, fee_y_pending) =
-                bin_array_manager.get_total_fee_pending(position)?;
+            let (position_amount_x, position_amount_y, fee_x_pending, fee_y_pending) =
+                self.get_position_amounts(position)?;
+            amount_x = amount_x
+                .safe_add(position_amount_x)
+                .map_err(|_| Error::msg("Math is overflow"))?;
+            amount_y = amount_y
+                .safe_add(position_amount_y)
+                .map_err(|_| Error::msg("Math is overflow"))?;
             fee_x = fee_x
                 .safe_add(fee_x_pending)
                 .map_err(|_| Error::msg("Math is overflow"))?;
             fee_y = fee_y
                 .safe_add(fee_y_pending)
                 .map_err(|_| Error::msg("Math is overflow"))?;
+            bin_count += (position.upper_bin_id - position.lower_bin_id + 1) as usize;
         }
 
         return Ok(PositionRaw {
@@ -133,15 +164,103 @@ impl SinglePosition {
             min_bin_id: self.min_bin_id,
             active_id: self.lb_pair_state.active_id,
             max_bin_id: self.max_bin_id,
+            bin_count,
+            is_contiguous: self.is_contiguous(),
+            active_position_index: self.get_active_position_index(),
             amount_x,
             amount_y,
             fee_x,
             fee_y,
             last_update_timestamp: self.last_update_timestamp,
+            paper: None,
         });
     }
 }
 
+/// Amounts of a liquidity in a bin following the constant sum formula: token x above the active bin, token y from
+/// the active bin down.
+fn get_amounts_of_liquidity(
+    liquidity: u128,
+    bin_id: i32,
+    active_id: i32,
+    bin_step: u16,
+) -> Result<(u64, u64)> {
+    if bin_id > active_id {
+        let price = get_price_from_id(bin_id, bin_step)?;
+        let amount_x = liquidity
+            .checked_div(price)
+            .ok_or_else(|| Error::msg("Math is overflow"))?;
+        Ok((amount_x.try_into()?, 0))
+    } else {
+        let amount_y = liquidity >> SCALE_OFFSET;
+        Ok((0, amount_y.try_into()?))
+    }
+}
+
+/// Split a bin range into the adjacent position ranges covering it, each at most `MAX_BIN_PER_POSITION` wide.
+pub fn get_position_ranges(min_bin_id: i32, max_bin_id: i32) -> Vec<(i32, i32)> {
+    let mut ranges = vec![];
+    let mut lower_bin_id = min_bin_id;
+    while lower_bin_id <= max_bin_id {
+        let upper_bin_id = lower_bin_id
+            .saturating_add(MAX_BIN_PER_POSITION as i32 - 1)
+            .min(max_bin_id);
+        ranges.push((lower_bin_id, upper_bin_id));
+        lower_bin_id = upper_bin_id.saturating_add(1);
+    }
+    ranges
+}
+
+/// Split the deposit amounts of a range between its positions, in proportion of the bins able to hold each token:
+/// token X in the bins from the active bin up, and token Y in the bins from the active bin down. A token without any
+/// such bin in the range is not deposited.
+pub fn get_position_deposit_amounts(
+    ranges: &[(i32, i32)],
+    active_id: i32,
+    amount_x: u64,
+    amount_y: u64,
+) -> Vec<(u64, u64)> {
+    let count_bins = |lower_bin_id: i32, upper_bin_id: i32| -> u128 {
+        if upper_bin_id < lower_bin_id {
+            0
+        } else {
+            (upper_bin_id - lower_bin_id + 1) as u128
+        }
+    };
+    let x_bins = ranges
+        .iter()
+        .map(|&(lower, upper)| count_bins(lower.max(active_id), upper))
+        .collect::<Vec<_>>();
+    let y_bins = ranges
+        .iter()
+        .map(|&(lower, upper)| count_bins(lower, upper.min(active_id)))
+        .collect::<Vec<_>>();
+
+    // Allocate by cumulative bins so the rounding dust ends up in the last position
+    let split = |amount: u64, bins: &[u128]| -> Vec<u64> {
+        let total_bins: u128 = bins.iter().sum();
+        if total_bins == 0 {
+            return vec![0; bins.len()];
+        }
+        let mut cumulative_bins = 0u128;
+        let mut allocated = 0u64;
+        bins.iter()
+            .map(|&bins| {
+                cumulative_bins += bins;
+                let cumulative_amount = (amount as u128 * cumulative_bins / total_bins) as u64;
+                let position_amount = cumulative_amount - allocated;
+                allocated = cumulative_amount;
+                position_amount
+            })
+            .collect()
+    };
+
+    split(amount_x, &x_bins)
+        .into_iter()
+        .zip(split(amount_y, &y_bins))
+        .collect()
+}
+
 #[derive(Default, PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct PositionRaw {
     pub position_len: usize,
@@ -150,6 +269,12 @@ pub struct PositionRaw {
     pub active_id: i32,
     pub min_bin_id: i32,
     pub bin_step: u16,
+    /// Number of bins covered by the positions
+    pub bin_count: usize,
+    /// Whether the positions form one range without gap
+    pub is_contiguous: bool,
+    /// Index of the position holding the active bin, None when the price is out of range
+    pub active_position_index: Option<usize>,
     pub amount_x: u64,
     pub amount_y: u64,
     pub fee_x: u64,
@@ -195,11 +320,18 @@ impl PositionRaw {
             max_price,
             current_price,
             min_price,
+            min_bin_id: self.min_bin_id,
+            max_bin_id: self.max_bin_id,
+            active_id: self.active_id,
+            bin_count: self.bin_count,
+            is_contiguous: self.is_contiguous,
+            active_position_index: self.active_position_index,
             amount_x,
             amount_y,
             fee_x,
             fee_y,
             last_update_timestamp: self.last_update_timestamp,
+            paper: None,
         });
     }
 }
@@ -211,11 +343,20 @@ pub struct PositionInfo {
     pub max_price: f64,
     pub current_price: f64,
     pub min_price: f64,
+    pub min_bin_id: i32,
+    pub max_bin_id: i32,
+    pub active_id: i32,
+    pub bin_count: usize,
+    pub is_contiguous: bool,
+    pub active_position_index: Option<usize>,
     pub amount_x: f64,
     pub amount_y: f64,
     pub fee_x: f64,
     pub fee_y: f64,
     pub last_update_timestamp: u64,
+    /// Virtual wallet, fees earned and PnL in paper mode
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paper: Option<PaperInfo>,
 }
 
 impl SinglePosition {
@@ -240,3 +381,25 @@ pub fn get_decimals(token_mint_pk: Pubkey, all_tokens: &HashMap<Pubkey, Mint>) -
     let token = all_tokens.get(&token_mint_pk).unwrap();
     return token.decimals;
 }
+
+#[cfg(test)]
+mod state_test {
+    use super::*;
+
+    #[test]
+    fn test_position_ranges() {
+        assert_eq!(get_position_ranges(-35, 34), vec![(-35, 34)]);
+        assert_eq!(
+            get_position_ranges(-100, 99),
+            vec![(-100, -31), (-30, 39), (40, 99)]
+        );
+
+        // Active bin 0 holds both tokens: 100 bins can hold x, 101 bins can hold y
+        let amounts = get_position_deposit_amounts(&get_position_ranges(-100, 99), 0, 1_000, 1_010);
+        assert_eq!(amounts, vec![(0, 700), (400, 310), (600, 0)]);
+
+        // Range above the active bin only takes token x
+        let amounts = get_position_deposit_amounts(&[(10, 79)], 0, 1_000, 1_000);
+        assert_eq!(amounts, vec![(1_000, 0)]);
+    }
+}
@@ -0,0 +1,561 @@
+use crate::pair_config::PairConfig;
+use crate::state::{PositionRaw, SinglePosition};
+use crate::MarketMakingMode;
+use anchor_lang::prelude::Pubkey;
+use anyhow::*;
+use lb_clmm::instructions::deposit::StrategyType;
+use lb_clmm::math::price_math::get_price_from_id;
+use lb_clmm::math::u64x64_math::SCALE_OFFSET;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// What a strategy sees of a pair when it is asked to decide.
+pub struct StrategySnapshot<'a> {
+    pub config: &'a PairConfig,
+    /// Pair state, bin arrays and positions of the owner
+    pub state: &'a SinglePosition,
+    /// Amounts and pending fees of all positions of the owner
+    pub position: PositionRaw,
+    /// Wallet balance of token X
+    pub balance_x: u64,
+    /// Wallet balance of token Y
+    pub balance_y: u64,
+    /// Unix timestamp of the snapshot
+    pub timestamp: u64,
+}
+
+/// Intended action of a strategy. Actions are executed in order.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StrategyAction {
+    /// Withdraw all liquidity, claim fees and close all positions of the pair
+    Withdraw,
+    /// Withdraw all liquidity, claim fees and close the given positions of the pair
+    WithdrawPositions {
+        positions: Vec<Pubkey>,
+    },
+    /// Claim fees of all positions of the pair
+    ClaimFee,
+    Swap {
+        amount_in: u64,
+        swap_for_y: bool,
+    },
+    /// Deposit into new adjacent positions covering the bin range. The output of the swaps executed before it is
+    /// added to the amounts, which are capped by the wallet balances.
+    Deposit {
+        amount_x: u64,
+        amount_y: u64,
+        min_bin_id: i32,
+        max_bin_id: i32,
+        strategy_type: StrategyType,
+    },
+}
+
+/// Decide what to do with the liquidity of a pair. Implementations must be pure: they only read the snapshot, and the
+/// bot executes the returned actions.
+pub trait Strategy: Send + Sync {
+    fn decide(&self, snapshot: &StrategySnapshot) -> Result<Vec<StrategyAction>>;
+}
+
+/// Bin range of `range_width` bins centered on the active bin.
+pub fn get_range_around(active_id: i32, range_width: i32) -> (i32, i32) {
+    let min_bin_id = active_id.saturating_sub(range_width / 2);
+    let max_bin_id = min_bin_id.saturating_add(range_width - 1);
+    (min_bin_id, max_bin_id)
+}
+
+/// Only monitor the pair.
+pub struct ViewStrategy;
+
+impl Strategy for ViewStrategy {
+    fn decide(&self, _snapshot: &StrategySnapshot) -> Result<Vec<StrategyAction>> {
+        Ok(vec![])
+    }
+}
+
+/// Move the liquidity to the active bin once the price leaves the range of the positions. The withdrawn token is
+/// swapped up to the target inventory ratio of the pair so the new range holds both tokens, or deposited one sided
+/// without swap with `one_sided_rebalance`.
+///
+/// Ranges of at least `MIN_POSITIONS_FOR_EDGE_REBALANCE` positions are rolled instead while the price is still in
+/// range: once the active bin reaches the edge position, the position at the opposite edge is withdrawn, swapped and
+/// deposited as a new position past the near edge. The other positions are left untouched.
+pub struct ShiftStrategy {
+    pub shift_left: bool,
+    pub shift_right: bool,
+}
+
+/// Minimum number of positions of a range rolled by moving its edge positions. Smaller ranges would roll back and
+/// forth, since the active bin is always in an edge position.
+pub const MIN_POSITIONS_FOR_EDGE_REBALANCE: usize = 3;
+
+/// Price of the active bin, in lamports of token y per lamport of token x.
+pub fn get_active_price(state: &SinglePosition) -> Result<f64> {
+    let lb_pair_state = &state.lb_pair_state;
+    let price = get_price_from_id(lb_pair_state.active_id, lb_pair_state.bin_step)?;
+    Ok(price as f64 / (1u128 << SCALE_OFFSET) as f64)
+}
+
+/// Swap bringing the share of the inventory value held in token x to the target inventory ratio of the pair, capped
+/// by its max swap amounts. Return the amount in and the swap direction, None when there is nothing to swap.
+pub fn get_rebalance_swap(
+    config: &PairConfig,
+    amount_x: u64,
+    amount_y: u64,
+    price: f64,
+) -> Option<(u64, bool)> {
+    let value_x = amount_x as f64 * price;
+    let target_value_x = (value_x + amount_y as f64) * config.get_target_inventory_ratio();
+
+    let (amount_in, swap_for_y) = if value_x < target_value_x {
+        let amount_in = ((target_value_x - value_x) as u64).min(amount_y);
+        (
+            amount_in.min(config.max_swap_amount_y.unwrap_or(u64::MAX)),
+            false,
+        )
+    } else {
+        let amount_in = (((value_x - target_value_x) / price) as u64).min(amount_x);
+        (
+            amount_in.min(config.max_swap_amount_x.unwrap_or(u64::MAX)),
+            true,
+        )
+    };
+
+    if amount_in == 0 {
+        return None;
+    }
+    Some((amount_in, swap_for_y))
+}
+
+impl ShiftStrategy {
+    /// Withdraw all positions and deposit the withdrawn amounts into a new range around the active bin. Either a part
+    /// of the inventory is swapped to reach the target inventory ratio, or the single token withdrawn is deposited on
+    /// its side of the active bin without swap.
+    fn get_redeposit_actions(
+        snapshot: &StrategySnapshot,
+        amount_x: u64,
+        amount_y: u64,
+    ) -> Result<Vec<StrategyAction>> {
+        let config = snapshot.config;
+        let active_id = snapshot.state.lb_pair_state.active_id;
+        let range_width = config.get_range_width();
+        let mut actions = vec![StrategyAction::Withdraw];
+
+        if amount_x == 0 && amount_y == 0 {
+            let (min_bin_id, max_bin_id) = get_range_around(active_id, range_width);
+            actions.push(StrategyAction::Deposit {
+                amount_x: config.x_amount,
+                amount_y: config.y_amount,
+                min_bin_id,
+                max_bin_id,
+                strategy_type: StrategyType::SpotBalanced,
+            });
+            return Ok(actions);
+        }
+
+        if config.one_sided_rebalance && (amount_x == 0 || amount_y == 0) {
+            // token y is deposited in the bins up to the active bin, token x from the active bin up
+            let (min_bin_id, max_bin_id) = if amount_x == 0 {
+                (active_id - range_width + 1, active_id)
+            } else {
+                (active_id, active_id + range_width - 1)
+            };
+            actions.push(StrategyAction::Deposit {
+                amount_x,
+                amount_y,
+                min_bin_id,
+                max_bin_id,
+                strategy_type: StrategyType::SpotOneSide,
+            });
+            return Ok(actions);
+        }
+
+        let (mut amount_x, mut amount_y) = (amount_x, amount_y);
+        let price = get_active_price(snapshot.state)?;
+        if let Some((amount_in, swap_for_y)) = get_rebalance_swap(config, amount_x, amount_y, price)
+        {
+            actions.push(StrategyAction::Swap {
+                amount_in,
+                swap_for_y,
+            });
+            if swap_for_y {
+                amount_x -= amount_in;
+            } else {
+                amount_y -= amount_in;
+            }
+        }
+
+        let (min_bin_id, max_bin_id) = get_range_around(active_id, range_width);
+        actions.push(StrategyAction::Deposit {
+            amount_x,
+            amount_y,
+            min_bin_id,
+            max_bin_id,
+            strategy_type: StrategyType::SpotBalanced,
+        });
+
+        Ok(actions)
+    }
+
+    fn get_shift_right_actions(snapshot: &StrategySnapshot) -> Result<Vec<StrategyAction>> {
+        // validate that x amount is zero
+        let position = &snapshot.position;
+        if position.amount_x != 0 {
+            return Err(Error::msg("Amount x is not zero"));
+        }
+        Self::get_redeposit_actions(snapshot, 0, position.amount_y)
+    }
+
+    fn get_shift_left_actions(snapshot: &StrategySnapshot) -> Result<Vec<StrategyAction>> {
+        // validate that y amount is zero
+        let position = &snapshot.position;
+        if position.amount_y != 0 {
+            return Err(Error::msg("Amount y is not zero"));
+        }
+        Self::get_redeposit_actions(snapshot, position.amount_x, 0)
+    }
+
+    /// Move the lowest position, which only holds token y, above the range.
+    fn get_roll_up_actions(snapshot: &StrategySnapshot) -> Result<Vec<StrategyAction>> {
+        let state = snapshot.state;
+        let position = &state.positions[0];
+        let (amount_x, amount_y, _fee_x, _fee_y) = state.get_position_amounts(position)?;
+
+        let mut actions = vec![StrategyAction::WithdrawPositions {
+            positions: vec![state.position_pks[0]],
+        }];
+        // Whatever is above the max swap amount stays in the wallet
+        let amount_in = amount_y.min(snapshot.config.max_swap_amount_y.unwrap_or(u64::MAX));
+        if amount_in != 0 {
+            actions.push(StrategyAction::Swap {
+                amount_in,
+                swap_for_y: false,
+            });
+        }
+        actions.push(StrategyAction::Deposit {
+            amount_x,
+            amount_y: 0,
+            min_bin_id: state.max_bin_id + 1,
+            max_bin_id: state.max_bin_id + position.upper_bin_id - position.lower_bin_id + 1,
+            strategy_type: StrategyType::SpotBalanced,
+        });
+
+        Ok(actions)
+    }
+
+    /// Move the highest position, which only holds token x, below the range.
+    fn get_roll_down_actions(snapshot: &StrategySnapshot) -> Result<Vec<StrategyAction>> {
+        let state = snapshot.state;
+        let last = state.positions.len() - 1;
+        let position = &state.positions[last];
+        let (amount_x, amount_y, _fee_x, _fee_y) = state.get_position_amounts(position)?;
+
+        let mut actions = vec![StrategyAction::WithdrawPositions {
+            positions: vec![state.position_pks[last]],
+        }];
+        let amount_in = amount_x.min(snapshot.config.max_swap_amount_x.unwrap_or(u64::MAX));
+        if amount_in != 0 {
+            actions.push(StrategyAction::Swap {
+                amount_in,
+                swap_for_y: true,
+            });
+        }
+        actions.push(StrategyAction::Deposit {
+            amount_x: 0,
+            amount_y,
+            min_bin_id: state.min_bin_id - (position.upper_bin_id - position.lower_bin_id + 1),
+            max_bin_id: state.min_bin_id - 1,
+            strategy_type: StrategyType::SpotBalanced,
+        });
+
+        Ok(actions)
+    }
+}
+
+/// Actions of a rebalance forced through the control API, whatever the strategy of the pair: withdraw all positions
+/// and deposit the withdrawn amounts and fees into a new range around the active bin, swapped like a shift. Without
+/// positions, the configured amounts are deposited.
+pub fn get_force_rebalance_actions(snapshot: &StrategySnapshot) -> Result<Vec<StrategyAction>> {
+    let position = &snapshot.position;
+    ShiftStrategy::get_redeposit_actions(
+        snapshot,
+        position.amount_x.saturating_add(position.fee_x),
+        position.amount_y.saturating_add(position.fee_y),
+    )
+}
+
+impl Strategy for ShiftStrategy {
+    fn decide(&self, snapshot: &StrategySnapshot) -> Result<Vec<StrategyAction>> {
+        let active_id = snapshot.state.lb_pair_state.active_id;
+
+        if self.shift_left && active_id < snapshot.state.min_bin_id {
+            return Self::get_shift_left_actions(snapshot);
+        }
+        if self.shift_right && active_id > snapshot.state.max_bin_id {
+            return Self::get_shift_right_actions(snapshot);
+        }
+
+        let positions = &snapshot.state.positions;
+        // Rolling needs a swap, so one sided rebalancing only moves the whole range
+        if !snapshot.config.one_sided_rebalance
+            && positions.len() >= MIN_POSITIONS_FOR_EDGE_REBALANCE
+            && snapshot.state.is_contiguous()
+        {
+            if self.shift_right && active_id >= positions[positions.len() - 1].lower_bin_id {
+                return Self::get_roll_up_actions(snapshot);
+            }
+            if self.shift_left && active_id <= positions[0].upper_bin_id {
+                return Self::get_roll_down_actions(snapshot);
+            }
+        }
+
+        Ok(vec![])
+    }
+}
+
+pub const VIEW_STRATEGY: &str = "view";
+pub const SHIFT_LEFT_STRATEGY: &str = "shift_left";
+pub const SHIFT_RIGHT_STRATEGY: &str = "shift_right";
+pub const SHIFT_BOTH_STRATEGY: &str = "shift_both";
+
+/// Name of the built-in strategy of a legacy market making mode.
+pub fn get_strategy_name_from_mode(mode: &MarketMakingMode) -> &'static str {
+    match mode {
+        MarketMakingMode::ModeRight => SHIFT_RIGHT_STRATEGY,
+        MarketMakingMode::ModeLeft => SHIFT_LEFT_STRATEGY,
+        MarketMakingMode::ModeBoth => SHIFT_BOTH_STRATEGY,
+        MarketMakingMode::ModeView => VIEW_STRATEGY,
+    }
+}
+
+/// Strategies selectable by name in the pair config.
+#[derive(Clone)]
+pub struct StrategyRegistry {
+    strategies: HashMap<String, Arc<dyn Strategy>>,
+}
+
+impl Default for StrategyRegistry {
+    fn default() -> Self {
+        let mut registry = StrategyRegistry {
+            strategies: HashMap::new(),
+        };
+        registry.register(VIEW_STRATEGY, Arc::new(ViewStrategy));
+        registry.register(
+            SHIFT_LEFT_STRATEGY,
+            Arc::new(ShiftStrategy {
+                shift_left: true,
+                shift_right: false,
+            }),
+        );
+        registry.register(
+            SHIFT_RIGHT_STRATEGY,
+            Arc::new(ShiftStrategy {
+                shift_left: false,
+                shift_right: true,
+            }),
+        );
+        registry.register(
+            SHIFT_BOTH_STRATEGY,
+            Arc::new(ShiftStrategy {
+                shift_left: true,
+                shift_right: true,
+            }),
+        );
+        registry
+    }
+}
+
+impl StrategyRegistry {
+    /// Add a strategy, or replace the one with the same name.
+    pub fn register(&mut self, name: &str, strategy: Arc<dyn Strategy>) {
+        self.strategies.insert(name.to_string(), strategy);
+    }
+
+    pub fn get(&self, name: &str) -> Result<Arc<dyn Strategy>> {
+        self.strategies
+            .get(name)
+            .cloned()
+            .ok_or_else(|| Error::msg(format!("Strategy {} not found", name)))
+    }
+
+    /// Check that the strategy of every pair is registered.
+    pub fn validate(&self, config: &Vec<PairConfig>) -> Result<()> {
+        for pair in config.iter() {
+            self.get(&pair.get_strategy_name())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod strategy_test {
+    use super::*;
+
+    fn get_config(strategy_name: &str) -> PairConfig {
+        PairConfig {
+            x_amount: 100,
+            y_amount: 200,
+            strategy: Some(strategy_name.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn decide(strategy_name: &str, active_id: i32, amount_y: u64) -> Vec<StrategyAction> {
+        decide_with_config(get_config(strategy_name), active_id, amount_y)
+    }
+
+    fn decide_with_config(
+        config: PairConfig,
+        active_id: i32,
+        amount_y: u64,
+    ) -> Vec<StrategyAction> {
+        let mut state = SinglePosition::default();
+        state.min_bin_id = -10;
+        state.max_bin_id = 10;
+        state.lb_pair_state.active_id = active_id;
+
+        let snapshot = StrategySnapshot {
+            config: &config,
+            state: &state,
+            position: PositionRaw {
+                amount_y,
+                ..Default::default()
+            },
+            balance_x: 0,
+            balance_y: 0,
+            timestamp: 0,
+        };
+
+        let strategy = StrategyRegistry::default()
+            .get(&config.get_strategy_name())
+            .unwrap();
+        strategy.decide(&snapshot).unwrap()
+    }
+
+    #[test]
+    fn test_force_rebalance_actions() {
+        // In range for a left only strategy, which would not move
+        let config = PairConfig {
+            range_width: Some(5),
+            ..get_config(SHIFT_LEFT_STRATEGY)
+        };
+        let mut state = SinglePosition::default();
+        state.min_bin_id = -10;
+        state.max_bin_id = 10;
+        state.lb_pair_state.active_id = 3;
+        let snapshot = StrategySnapshot {
+            config: &config,
+            state: &state,
+            position: PositionRaw {
+                amount_x: 0,
+                amount_y: 1000,
+                fee_y: 1,
+                ..Default::default()
+            },
+            balance_x: 5_000,
+            balance_y: 5_000,
+            timestamp: 0,
+        };
+        assert!(StrategyRegistry::default()
+            .get(SHIFT_LEFT_STRATEGY)
+            .unwrap()
+            .decide(&snapshot)
+            .unwrap()
+            .is_empty());
+
+        // The withdrawn amount and fees are swapped to the target ratio and deposited around the active bin, the
+        // wallet balances are left out
+        assert_eq!(
+            get_force_rebalance_actions(&snapshot).unwrap(),
+            vec![
+                StrategyAction::Withdraw,
+                StrategyAction::Swap {
+                    amount_in: 500,
+                    swap_for_y: false
+                },
+                StrategyAction::Deposit {
+                    amount_x: 0,
+                    amount_y: 501,
+                    min_bin_id: 1,
+                    max_bin_id: 5,
+                    strategy_type: StrategyType::SpotBalanced
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_shift_strategy() {
+        assert_eq!(
+            decide(SHIFT_BOTH_STRATEGY, 11, 1001),
+            vec![
+                StrategyAction::Withdraw,
+                StrategyAction::Swap {
+                    amount_in: 500,
+                    swap_for_y: false
+                },
+                StrategyAction::Deposit {
+                    amount_x: 0,
+                    amount_y: 501,
+                    min_bin_id: -24,
+                    max_bin_id: 45,
+                    strategy_type: StrategyType::SpotBalanced
+                }
+            ]
+        );
+
+        // Nothing withdrawn, deposit the configured amounts
+        assert_eq!(
+            decide(SHIFT_RIGHT_STRATEGY, 11, 0),
+            vec![
+                StrategyAction::Withdraw,
+                StrategyAction::Deposit {
+                    amount_x: 100,
+                    amount_y: 200,
+                    min_bin_id: -24,
+                    max_bin_id: 45,
+                    strategy_type: StrategyType::SpotBalanced
+                }
+            ]
+        );
+
+        // In range
+        assert!(decide(SHIFT_BOTH_STRATEGY, 0, 1001).is_empty());
+        // Left only strategy ignores the price going up
+        assert!(decide(SHIFT_LEFT_STRATEGY, 11, 1001).is_empty());
+        assert!(decide(VIEW_STRATEGY, 11, 1001).is_empty());
+    }
+
+    #[test]
+    fn test_inventory_rebalance() {
+        // Only a quarter of the value in token x, and at most 200 of token y swapped
+        let config = PairConfig {
+            target_inventory_ratio: Some(0.25),
+            max_swap_amount_y: Some(200),
+            ..get_config(SHIFT_BOTH_STRATEGY)
+        };
+        assert_eq!(
+            get_rebalance_swap(&config, 0, 1000, 1.0),
+            Some((200, false))
+        );
+        assert_eq!(get_rebalance_swap(&config, 1000, 0, 2.0), Some((750, true)));
+        assert_eq!(get_rebalance_swap(&config, 250, 1500, 2.0), None);
+
+        let config = PairConfig {
+            one_sided_rebalance: true,
+            ..get_config(SHIFT_BOTH_STRATEGY)
+        };
+        assert_eq!(
+            decide_with_config(config, 11, 1001),
+            vec![
+                StrategyAction::Withdraw,
+                StrategyAction::Deposit {
+                    amount_x: 0,
+                    amount_y: 1001,
+                    min_bin_id: -58,
+                    max_bin_id: 11,
+                    strategy_type: StrategyType::SpotOneSide
+                }
+            ]
+        );
+    }
+}
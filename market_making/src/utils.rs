@@ -4,7 +4,6 @@ use anchor_client::solana_client::rpc_response::Response;
 use anchor_client::solana_client::rpc_response::RpcSimulateTransactionResult;
 use anchor_client::solana_sdk::commitment_config::CommitmentConfig;
 use anchor_client::solana_sdk::signature::Signature;
-use anchor_client::solana_sdk::signer::keypair::Keypair;
 use anchor_client::solana_sdk::transaction::Transaction;
 use anchor_client::AsSigner;
 use anchor_client::RequestBuilder;
@@ -50,7 +49,7 @@ pub async fn get_or_create_ata<C: Deref<Target = impl Signer> + Clone>(
     program: &Program<C>,
     token_mint: Pubkey,
     wallet_address: Pubkey,
-    payer: &Keypair,
+    payer: &dyn Signer,
 ) -> Result<Pubkey> {
     let user_ata = get_associated_token_address(&wallet_address, &token_mint);
 
@@ -88,7 +87,7 @@ pub fn get_transaction_config() -> RpcSendTransactionConfig {
 }
 
 pub async fn send_tx<C: Clone + std::ops::Deref<Target = impl Signer>, S: AsSigner>(
-    keypairs: Vec<&Keypair>,
+    keypairs: Vec<&dyn Signer>,
     payer: Pubkey,
     program: &Program<C>,
     builder: &RequestBuilder<'_, C, S>,
@@ -107,7 +106,7 @@ pub async fn send_tx<C: Clone + std::ops::Deref<Target = impl Signer>, S: AsSign
 }
 
 pub async fn simulate_transaction<C: Clone + std::ops::Deref<Target = impl Signer>, S: AsSigner>(
-    keypairs: Vec<&Keypair>,
+    keypairs: Vec<&dyn Signer>,
     payer: Pubkey,
     program: &Program<C>,
     builder: &RequestBuilder<'_, C, S>,
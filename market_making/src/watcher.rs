@@ -0,0 +1,313 @@
+use crate::core::Core;
+use crate::pair_config::get_config_from_file;
+use anchor_client::solana_account_decoder::UiAccountEncoding;
+use anchor_client::solana_client::nonblocking::pubsub_client::PubsubClient;
+use anchor_client::solana_client::rpc_config::RpcAccountInfoConfig;
+use anchor_client::solana_sdk::account::Account;
+use anchor_client::solana_sdk::commitment_config::CommitmentConfig;
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anyhow::*;
+use futures::stream::{select_all, StreamExt};
+use std::collections::BTreeSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, Notify};
+use tokio::time::{interval, sleep, sleep_until, Instant};
+
+/// Delay before reconnecting a dropped websocket
+const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+
+#[derive(Debug)]
+pub struct AccountUpdate {
+    pub pubkey: Pubkey,
+    pub data: Vec<u8>,
+    pub slot: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct WatcherConfig {
+    /// Websocket url of the RPC
+    pub ws_url: String,
+    /// Time to wait for more updates after the first one which triggers the strategies
+    pub debounce: Duration,
+    /// Interval of the getMultipleAccounts polling while the websocket is down
+    pub poll_interval: Duration,
+    /// Interval of the full refresh, which also finds new positions, and of the strategy run without trigger
+    pub refresh_interval: Duration,
+    /// Run the strategies, or only keep the state up to date
+    pub run_strategies: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SubscriptionEnd {
+    /// The watched accounts changed
+    Resubscribe,
+    /// The websocket dropped
+    Disconnected,
+}
+
+/// Subscribe to the accounts and forward their updates until the websocket drops or a resubscription is requested.
+pub async fn subscribe_accounts(
+    ws_url: &str,
+    accounts: &[Pubkey],
+    sender: &mpsc::UnboundedSender<AccountUpdate>,
+    resubscribe: &Notify,
+    connected: &AtomicBool,
+) -> Result<SubscriptionEnd> {
+    let client = PubsubClient::new(ws_url).await?;
+    let config = RpcAccountInfoConfig {
+        encoding: Some(UiAccountEncoding::Base64),
+        commitment: Some(CommitmentConfig::processed()),
+        ..Default::default()
+    };
+
+    let mut streams = vec![];
+    for &pubkey in accounts.iter() {
+        let (stream, _unsubscribe) = client
+            .account_subscribe(&pubkey, Some(config.clone()))
+            .await?;
+        streams.push(stream.map(move |response| (pubkey, response)));
+    }
+    connected.store(true, Ordering::Relaxed);
+
+    let mut updates = select_all(streams);
+    let end = loop {
+        tokio::select! {
+            update = updates.next() => {
+                // Every stream ends when the socket drops
+                let Some((pubkey, response)) = update else {
+                    break SubscriptionEnd::Disconnected;
+                };
+                let Some(account) = response.value.decode::<Account>() else {
+                    warn!("cannot decode update of {pubkey}");
+                    continue;
+                };
+                let update = AccountUpdate {
+                    pubkey,
+                    data: account.data,
+                    slot: response.context.slot,
+                };
+                if sender.send(update).is_err() {
+                    break SubscriptionEnd::Disconnected;
+                }
+            }
+            _ = resubscribe.notified() => break SubscriptionEnd::Resubscribe,
+        }
+    };
+    connected.store(false, Ordering::Relaxed);
+
+    drop(updates);
+    if let Err(err) = client.shutdown().await {
+        warn!("websocket shutdown err {}", err);
+    }
+
+    Ok(end)
+}
+
+async fn run_subscriptions(
+    core: Arc<Core>,
+    ws_url: String,
+    sender: mpsc::UnboundedSender<AccountUpdate>,
+    resubscribe: Arc<Notify>,
+    connected: Arc<AtomicBool>,
+) {
+    loop {
+        let accounts = core.get_watched_accounts();
+        info!("subscribe to {} accounts", accounts.len());
+        match subscribe_accounts(&ws_url, &accounts, &sender, &resubscribe, &connected).await {
+            std::result::Result::Ok(SubscriptionEnd::Resubscribe) => continue,
+            std::result::Result::Ok(SubscriptionEnd::Disconnected) => {
+                warn!("websocket disconnected, polling until reconnected")
+            }
+            Err(err) => error!("subscribe err {}, polling until reconnected", err),
+        }
+        sleep(RECONNECT_DELAY).await;
+    }
+}
+
+/// Keep the state up to date from account subscriptions, and run the strategies of a pair once its active bin moves
+/// to an edge position or out of the range. Updates arriving within the debounce window are handled by a single run.
+/// While the websocket is down, the watched accounts are polled instead.
+pub async fn run_watcher(core: Arc<Core>, config: WatcherConfig) {
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    let resubscribe = Arc::new(Notify::new());
+    let connected = Arc::new(AtomicBool::new(false));
+
+    tokio::spawn(run_subscriptions(
+        core.clone(),
+        config.ws_url.clone(),
+        sender,
+        resubscribe.clone(),
+        connected.clone(),
+    ));
+
+    let mut poll_interval = interval(config.poll_interval);
+    let mut refresh_interval = interval(config.refresh_interval);
+    let mut watched_accounts = core.get_watched_accounts();
+    let mut triggered_pairs = BTreeSet::new();
+    let mut deadline: Option<Instant> = None;
+
+    loop {
+        tokio::select! {
+            Some(update) = receiver.recv() => {
+                match core.apply_account_update(update.pubkey, &update.data) {
+                    std::result::Result::Ok(Some(lb_pair)) => {
+                        info!("active bin of {lb_pair} moved at slot {}", update.slot);
+                        triggered_pairs.insert(lb_pair);
+                        deadline.get_or_insert(Instant::now() + config.debounce);
+                    }
+                    std::result::Result::Ok(None) => {}
+                    Err(err) => error!("apply update of {} err {}", update.pubkey, err),
+                }
+            }
+            _ = poll_interval.tick() => {
+                if connected.load(Ordering::Relaxed) {
+                    continue;
+                }
+                match core.poll_accounts().await {
+                    std::result::Result::Ok(lb_pairs) => {
+                        if !lb_pairs.is_empty() {
+                            triggered_pairs.extend(lb_pairs);
+                            deadline.get_or_insert(Instant::now() + config.debounce);
+                        }
+                    }
+                    Err(err) => error!("poll accounts err {}", err),
+                }
+            }
+            _ = refresh_interval.tick() => {
+                info!("refresh state");
+                if let Err(err) = core.refresh_state().await {
+                    error!("refresh_state err {}", err);
+                }
+                if config.run_strategies {
+                    info!("run strategies");
+                    if let Err(err) = core.run_strategies().await {
+                        error!("run strategies err {}", err);
+                    }
+                }
+            }
+            _ = sleep_until(deadline.unwrap_or_else(Instant::now)), if deadline.is_some() => {
+                deadline = None;
+                let lb_pairs = std::mem::take(&mut triggered_pairs).into_iter().collect::<Vec<_>>();
+                if config.run_strategies {
+                    info!("run strategies of {:?}", lb_pairs);
+                    if let Err(err) = core.run_strategies_for_pairs(&lb_pairs).await {
+                        error!("run strategies err {}", err);
+                    }
+                }
+            }
+        }
+
+        // Positions and bin arrays change after a rebalance or a refresh
+        let accounts = core.get_watched_accounts();
+        if accounts != watched_accounts {
+            watched_accounts = accounts;
+            resubscribe.notify_one();
+        }
+    }
+}
+
+/// Reload the config file whenever it is modified. Configs which cannot be applied are logged and ignored.
+pub async fn run_config_watcher(core: Arc<Core>, config_file: String, check_interval: Duration) {
+    let get_modified = || {
+        std::fs::metadata(&config_file)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    };
+    let mut last_modified = get_modified();
+    let mut check_interval = interval(check_interval);
+
+    loop {
+        check_interval.tick().await;
+        let modified = get_modified();
+        if modified == last_modified {
+            continue;
+        }
+        last_modified = modified;
+
+        match get_config_from_file(&config_file).and_then(|config| core.reload_config(config)) {
+            std::result::Result::Ok(()) => info!("reloaded config from {}", config_file),
+            Err(err) => error!("reload config from {} err {}", config_file, err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod watcher_test {
+    use super::*;
+    use futures::SinkExt;
+    use serde_json::{json, Value};
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::tungstenite::Message;
+
+    #[tokio::test]
+    async fn test_subscribe_accounts_with_mock_pubsub() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let pubkey = Pubkey::new_unique();
+
+        // Mock pubsub server answering one accountSubscribe, sending one notification, then dropping the socket
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+
+            let request: Value = loop {
+                if let Message::Text(text) = ws.next().await.unwrap().unwrap() {
+                    break serde_json::from_str(&text).unwrap();
+                }
+            };
+            assert_eq!(request["method"], "accountSubscribe");
+            assert_eq!(request["params"][0], pubkey.to_string());
+
+            let response = json!({"jsonrpc": "2.0", "result": 7, "id": request["id"]});
+            ws.send(Message::Text(response.to_string())).await.unwrap();
+
+            let notification = json!({
+                "jsonrpc": "2.0",
+                "method": "accountNotification",
+                "params": {
+                    "result": {
+                        "context": {"slot": 42},
+                        "value": {
+                            "lamports": 1,
+                            // [1, 2, 3]
+                            "data": ["AQID", "base64"],
+                            "owner": Pubkey::default().to_string(),
+                            "executable": false,
+                            "rentEpoch": 0,
+                            "space": 3
+                        }
+                    },
+                    "subscription": 7
+                }
+            });
+            ws.send(Message::Text(notification.to_string()))
+                .await
+                .unwrap();
+            ws.close(None).await.unwrap();
+        });
+
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let connected = AtomicBool::new(false);
+        let end = subscribe_accounts(
+            &format!("ws://{}", addr),
+            &[pubkey],
+            &sender,
+            &Notify::new(),
+            &connected,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(end, SubscriptionEnd::Disconnected);
+        assert!(!connected.load(Ordering::Relaxed));
+
+        let update = receiver.try_recv().unwrap();
+        assert_eq!(update.pubkey, pubkey);
+        assert_eq!(update.data, vec![1, 2, 3]);
+        assert_eq!(update.slot, 42);
+
+        server.await.unwrap();
+    }
+}
@@ -32,21 +32,55 @@ pub trait LbPairTypeActionAccess {
     fn validate_initialize_bin_array(&self) -> bool;
 }
 
+/// Current point of the pair. Slot or timestamp depending on the activation type.
+pub fn get_current_point(
+    lb_pair: &LbPair,
+    current_slot: u64,
+    current_timestamp: u64,
+) -> Result<u64> {
+    let activation_type = ActivationType::try_from(lb_pair.activation_type)
+        .map_err(|_| LBError::InvalidActivationType)?;
+    match activation_type {
+        ActivationType::Slot => Ok(current_slot),
+        ActivationType::Timestamp => Ok(current_timestamp),
+    }
+}
+
+pub fn get_current_point_from_clock(lb_pair: &LbPair) -> Result<u64> {
+    let clock = Clock::get()?;
+    get_current_point(lb_pair, clock.slot, clock.unix_timestamp as u64)
+}
+
 pub fn get_lb_pair_type_access_validator<'a>(
     lb_pair: &'a LbPair,
+) -> Result<Box<dyn LbPairTypeActionAccess + 'a>> {
+    let current_point = get_current_point_from_clock(lb_pair)?;
+    get_lb_pair_type_access_validator_with_current_point(lb_pair, current_point)
+}
+
+/// Same as `get_lb_pair_type_access_validator`, without reading the clock sysvar. Usable off chain.
+pub fn get_lb_pair_type_access_validator_with_current_point<'a>(
+    lb_pair: &'a LbPair,
+    current_point: u64,
 ) -> Result<Box<dyn LbPairTypeActionAccess + 'a>> {
     let pair_type = PairType::try_from(lb_pair.pair_type).map_err(|_| LBError::InvalidPoolType)?;
     match pair_type {
         PairType::Permissionless => {
-            let pair_access_validator = PermissionlessLbPairActionAccess::new(lb_pair)?;
+            let pair_access_validator =
+                PermissionlessLbPairActionAccess::new_with_current_point(lb_pair, current_point)?;
             Ok(Box::new(pair_access_validator))
         }
         PairType::Permission => {
-            let pair_access_validator = PermissionLbPairActionAccess::new(lb_pair)?;
+            let pair_access_validator =
+                PermissionLbPairActionAccess::new_with_current_point(lb_pair, current_point)?;
             Ok(Box::new(pair_access_validator))
         }
         PairType::CustomizablePermissionless => {
-            let pair_access_validator = CustomizablePermissionlessLbPairActionAccess::new(lb_pair)?;
+            let pair_access_validator =
+                CustomizablePermissionlessLbPairActionAccess::new_with_current_point(
+                    lb_pair,
+                    current_point,
+                )?;
             Ok(Box::new(pair_access_validator))
         }
     }
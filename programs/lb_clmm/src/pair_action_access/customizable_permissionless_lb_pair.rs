@@ -1,10 +1,8 @@
+use crate::errors::LBError;
+use crate::pair_action_access::get_current_point_from_clock;
 use crate::pair_action_access::ActivationType;
 use crate::pair_action_access::LbPairTypeActionAccess;
 use crate::state::lb_pair::{LbPair, PairStatus};
-use crate::{
-    constants::{SLOT_BUFFER, TIME_BUFFER},
-    errors::LBError,
-};
 use anchor_lang::prelude::*;
 use solana_program::pubkey::Pubkey;
 pub struct CustomizablePermissionlessLbPairActionAccess {
@@ -17,12 +15,13 @@ pub struct CustomizablePermissionlessLbPairActionAccess {
 
 impl CustomizablePermissionlessLbPairActionAccess {
     pub fn new(lb_pair: &LbPair) -> Result<Self> {
-        let activation_type = ActivationType::try_from(lb_pair.activation_type)
+        Self::new_with_current_point(lb_pair, get_current_point_from_clock(lb_pair)?)
+    }
+
+    /// `current_point` is the current slot or timestamp, depending on the activation type of the pair.
+    pub fn new_with_current_point(lb_pair: &LbPair, current_point: u64) -> Result<Self> {
+        ActivationType::try_from(lb_pair.activation_type)
             .map_err(|_| LBError::InvalidActivationType)?;
-        let (current_point, _) = match activation_type {
-            ActivationType::Slot => (Clock::get()?.slot, SLOT_BUFFER),
-            ActivationType::Timestamp => (Clock::get()?.unix_timestamp as u64, TIME_BUFFER),
-        };
         Ok(Self {
             is_enabled: lb_pair.status == Into::<u8>::into(PairStatus::Enabled),
             pre_activation_swap_address: lb_pair.pre_activation_swap_address,
@@ -1,6 +1,7 @@
 use crate::constants::FIVE_MINUTES_SLOT_BUFFER;
 use crate::constants::FIVE_MINUTES_TIME_BUFFER;
 use crate::math::safe_math::SafeMath;
+use crate::pair_action_access::get_current_point_from_clock;
 use crate::pair_action_access::validate_activation_point;
 use crate::pair_action_access::ActivationType;
 use crate::pair_action_access::LbPairTypeActionAccess;
@@ -24,23 +25,25 @@ pub struct PermissionLbPairActionAccess {
 
 impl PermissionLbPairActionAccess {
     pub fn new(lb_pair: &LbPair) -> Result<Self> {
+        Self::new_with_current_point(lb_pair, get_current_point_from_clock(lb_pair)?)
+    }
+
+    /// `current_point` is the current slot or timestamp, depending on the activation type of the pair.
+    pub fn new_with_current_point(lb_pair: &LbPair, current_point: u64) -> Result<Self> {
         let activation_type = ActivationType::try_from(lb_pair.activation_type)
             .map_err(|_| LBError::InvalidActivationType)?;
-        let (current_point, time_buffer, deposit_close_idle_duration, last_join_buffer) =
-            match activation_type {
-                ActivationType::Slot => (
-                    Clock::get()?.slot,
-                    SLOT_BUFFER,
-                    FIVE_MINUTES_SLOT_BUFFER,
-                    FIVE_MINUTES_SLOT_BUFFER,
-                ),
-                ActivationType::Timestamp => (
-                    Clock::get()?.unix_timestamp as u64,
-                    TIME_BUFFER,
-                    FIVE_MINUTES_TIME_BUFFER,
-                    FIVE_MINUTES_TIME_BUFFER,
-                ),
-            };
+        let (time_buffer, deposit_close_idle_duration, last_join_buffer) = match activation_type {
+            ActivationType::Slot => (
+                SLOT_BUFFER,
+                FIVE_MINUTES_SLOT_BUFFER,
+                FIVE_MINUTES_SLOT_BUFFER,
+            ),
+            ActivationType::Timestamp => (
+                TIME_BUFFER,
+                FIVE_MINUTES_TIME_BUFFER,
+                FIVE_MINUTES_TIME_BUFFER,
+            ),
+        };
         Ok(Self {
             is_enabled: lb_pair.status == Into::<u8>::into(PairStatus::Enabled),
             pre_activation_swap_address: lb_pair.pre_activation_swap_address,
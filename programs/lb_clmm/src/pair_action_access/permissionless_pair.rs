@@ -1,4 +1,5 @@
 use crate::errors::LBError;
+use crate::pair_action_access::get_current_point_from_clock;
 use crate::pair_action_access::ActivationType;
 use crate::pair_action_access::LbPairTypeActionAccess;
 use crate::state::lb_pair::{LbPair, PairStatus};
@@ -10,12 +11,13 @@ pub struct PermissionlessLbPairActionAccess {
 
 impl PermissionlessLbPairActionAccess {
     pub fn new(lb_pair: &LbPair) -> Result<Self> {
-        let activation_type = ActivationType::try_from(lb_pair.activation_type)
+        Self::new_with_current_point(lb_pair, get_current_point_from_clock(lb_pair)?)
+    }
+
+    /// `current_point` is the current slot or timestamp, depending on the activation type of the pair.
+    pub fn new_with_current_point(lb_pair: &LbPair, current_point: u64) -> Result<Self> {
+        ActivationType::try_from(lb_pair.activation_type)
             .map_err(|_| LBError::InvalidActivationType)?;
-        let current_point = match activation_type {
-            ActivationType::Slot => Clock::get()?.slot,
-            ActivationType::Timestamp => Clock::get()?.unix_timestamp as u64,
-        };
         Ok(Self {
             is_enabled: lb_pair.status == Into::<u8>::into(PairStatus::Enabled),
             current_point,